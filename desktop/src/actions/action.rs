@@ -2,7 +2,8 @@
 
 use std::{
     collections::{HashMap, HashSet},
-    sync::Arc,
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
 };
 
 use anyhow::Result;
@@ -11,26 +12,64 @@ use jukebox_util::{
     input::InputEvent, peripheral::DeviceType, rgb::RgbProfile, screen::ScreenProfile,
 };
 use tokio::sync::{
-    mpsc::{UnboundedReceiver, UnboundedSender},
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
     Mutex,
 };
 
 use crate::{
-    actions::types::{get_icon_bytes, get_icon_cache_async, Action, ActionError},
+    actions::types::{
+        get_action_texts_async, get_icon_bytes, get_icon_cache_async, Action, ActionError,
+    },
     config::{ActionConfig, JukeBoxConfig},
     input::InputKey,
+    mqtt::publish_key_event,
     serial::{SerialCommand, SerialEvent},
+    toast::show_action_error_toast,
 };
 
+// How often action text is checked, and how often a single key's icon may be resent for it
+const LABEL_POLL_INTERVAL: Duration = Duration::from_millis(250);
+const LABEL_RESEND_INTERVAL: Duration = Duration::from_secs(1);
+
+static ICON_STATE_TX: OnceLock<UnboundedSender<()>> = OnceLock::new();
+
+/// Lets the icon state task know that something an action's `icon_state` depends on has changed,
+/// e.g. OBS started streaming. Does nothing before the task is running.
+pub fn notify_icon_states_changed() {
+    if let Some(tx) = ICON_STATE_TX.get() {
+        let _ = tx.send(());
+    }
+}
+
+// Keys whose action picks their LED color (like an MQTT topic state) override the per key colors.
+// True when any key did.
+fn apply_key_colors(rgb_profile: &mut RgbProfile, keys: &HashMap<InputKey, ActionConfig>) -> bool {
+    let RgbProfile::StaticPerKey { colors, .. } = rgb_profile else {
+        return false;
+    };
+
+    let mut applied = false;
+    for (k, a) in keys {
+        if let (Some(color), Some(c)) = (a.action.key_color(), colors.get_mut(k.slot() as usize)) {
+            *c = color;
+            applied = true;
+        }
+    }
+    applied
+}
+
 async fn update_device_configs(
     tx: UnboundedSender<SerialCommand>,
+    device_uid: &String,
     device_type: DeviceType,
     keys: HashMap<InputKey, ActionConfig>,
     profile_name: String,
-    rgb_profile: RgbProfile,
+    mut rgb_profile: RgbProfile,
     screen_profile: ScreenProfile,
 ) {
     if device_type == DeviceType::KeyPad {
+        apply_key_colors(&mut rgb_profile, &keys);
+
         // send profile name
         let _ = tx.send(SerialCommand::SetProfileName(profile_name));
 
@@ -42,7 +81,7 @@ async fn update_device_configs(
 
         // set icons on screen
         for (k, a) in &keys {
-            send_scr_icon(&tx, a, k).await;
+            send_scr_icon(&tx, device_uid, a, k).await;
         }
     }
 
@@ -54,10 +93,21 @@ async fn update_device_configs(
 
 async fn send_scr_icon(
     tx: &UnboundedSender<SerialCommand>,
+    device_uid: &String,
     action_config: &ActionConfig,
     input_key: &InputKey,
 ) {
-    let bytes = get_icon_bytes(action_config, &mut get_icon_cache_async().await);
+    let text = get_action_texts_async()
+        .await
+        .get(&(device_uid.clone(), *input_key))
+        .cloned();
+    let bytes = get_icon_bytes(
+        action_config,
+        device_uid,
+        *input_key,
+        text.as_deref(),
+        &mut get_icon_cache_async().await,
+    );
     let _ = tx.send(SerialCommand::SetScrIcon(input_key.slot(), bytes));
 }
 
@@ -133,6 +183,7 @@ pub async fn action_task(
                     get_profile_info(&config, device_uid).await;
                 update_device_configs(
                     scmd_tx,
+                    device_uid,
                     device_type,
                     keys,
                     profile_name,
@@ -167,6 +218,14 @@ pub async fn action_task(
                     let pressed = keys.difference(&prevkeys);
                     let released = prevkeys.difference(&keys);
 
+                    let key_event_topic = config.lock().await.mqtt_broker.key_event_topic.clone();
+                    for p in pressed.clone() {
+                        publish_key_event(&key_event_topic, &device_uid, *p, true);
+                    }
+                    for p in released.clone() {
+                        publish_key_event(&key_event_topic, &device_uid, *p, false);
+                    }
+
                     let mut pressed_futures = Vec::new();
                     let mut released_futures = Vec::new();
 
@@ -198,11 +257,12 @@ pub async fn action_task(
                             Ok((k, c)) => {
                                 if c {
                                     if let Some(a) = current_profile.get(&k) {
-                                        send_scr_icon(&scmd_tx, a, &k).await;
+                                        send_scr_icon(&scmd_tx, &device_uid, a, &k).await;
                                     }
                                 }
                             }
                             Err(e) => {
+                                show_action_error_toast(&e);
                                 let _ = ae_tx.send(e);
                             }
                         }
@@ -212,11 +272,12 @@ pub async fn action_task(
                             Ok((k, c)) => {
                                 if c {
                                     if let Some(a) = current_profile.get(&k) {
-                                        send_scr_icon(&scmd_tx, a, &k).await;
+                                        send_scr_icon(&scmd_tx, &device_uid, a, &k).await;
                                     }
                                 }
                             }
                             Err(e) => {
+                                show_action_error_toast(&e);
                                 let _ = ae_tx.send(e);
                             }
                         }
@@ -235,6 +296,7 @@ pub async fn action_task(
                     if current_profile_name != new_profile_name {
                         update_device_configs(
                             scmd_tx,
+                            &device_uid,
                             device_type,
                             new_keys,
                             new_profile_name,
@@ -256,3 +318,136 @@ pub async fn action_task(
 
     Ok(())
 }
+
+// Keeps labels that show action text up to date, resending a key's icon when its text changes
+pub async fn icon_label_task(
+    config: Arc<Mutex<JukeBoxConfig>>,
+    scmd_txs: Arc<Mutex<HashMap<String, UnboundedSender<SerialCommand>>>>,
+) -> Result<()> {
+    let mut last_sent: HashMap<(String, InputKey), Instant> = HashMap::new();
+
+    loop {
+        tokio::time::sleep(LABEL_POLL_INTERVAL).await;
+
+        let devices = {
+            let c = config.lock().await;
+            let Some(profile) = c.profiles.get(&c.current_profile) else {
+                continue;
+            };
+            profile
+                .iter()
+                .filter(|(uid, _)| {
+                    c.devices.get(*uid).map(|d| d.device_type) == Some(DeviceType::KeyPad)
+                })
+                .map(|(uid, d)| (uid.clone(), d.key_map.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        for (device_uid, keys) in devices {
+            let Some(tx) = scmd_txs.lock().await.get(&device_uid).cloned() else {
+                continue;
+            };
+
+            for (k, a) in &keys {
+                if !a.label.show_action_text {
+                    continue;
+                }
+
+                let id = (device_uid.clone(), *k);
+                if last_sent
+                    .get(&id)
+                    .is_some_and(|t| t.elapsed() < LABEL_RESEND_INTERVAL)
+                {
+                    continue;
+                }
+
+                let text = a.action.icon_text(&device_uid, *k).await;
+                {
+                    let mut texts = get_action_texts_async().await;
+                    if texts.get(&id) == text.as_ref() {
+                        continue;
+                    }
+                    match text {
+                        Some(t) => texts.insert(id.clone(), t),
+                        None => texts.remove(&id),
+                    };
+                }
+
+                last_sent.insert(id, Instant::now());
+                send_scr_icon(&tx, &device_uid, a, k).await;
+            }
+        }
+    }
+}
+
+// Resends the icons of keys whose icon state changed outside of a key press, and the RGB profile
+// when a key's LED color follows its state
+pub async fn icon_state_task(
+    config: Arc<Mutex<JukeBoxConfig>>,
+    scmd_txs: Arc<Mutex<HashMap<String, UnboundedSender<SerialCommand>>>>,
+) -> Result<()> {
+    let (tx, mut rx) = unbounded_channel::<()>();
+    if ICON_STATE_TX.set(tx).is_err() {
+        return Ok(()); // already running
+    }
+
+    // The action is kept too, so a profile switch that puts a new action on a key is noticed
+    let mut last_sent: HashMap<(String, InputKey), (Action, u8)> = HashMap::new();
+    let mut last_rgb: HashMap<String, RgbProfile> = HashMap::new();
+
+    while rx.recv().await.is_some() {
+        // A burst of changes (a scene switch toggles a few things at once) only needs one pass
+        while rx.try_recv().is_ok() {}
+
+        let devices = {
+            let c = config.lock().await;
+            let Some(profile) = c.profiles.get(&c.current_profile) else {
+                continue;
+            };
+            profile
+                .iter()
+                .filter(|(uid, _)| {
+                    c.devices.get(*uid).map(|d| d.device_type) == Some(DeviceType::KeyPad)
+                })
+                .map(|(uid, d)| (uid.clone(), d.key_map.clone(), d.rgb_profile.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        for (device_uid, keys, rgb_profile) in devices {
+            let Some(tx) = scmd_txs.lock().await.get(&device_uid).cloned() else {
+                continue;
+            };
+
+            // A device whose keys stopped picking colors gets its plain profile back once
+            let mut rgb_profile = rgb_profile.unwrap_or(RgbProfile::default_gui_profile());
+            let applied = apply_key_colors(&mut rgb_profile, &keys);
+            if (applied || last_rgb.contains_key(&device_uid))
+                && last_rgb.get(&device_uid) != Some(&rgb_profile)
+            {
+                if applied {
+                    last_rgb.insert(device_uid.clone(), rgb_profile.clone());
+                } else {
+                    last_rgb.remove(&device_uid);
+                }
+                let _ = tx.send(SerialCommand::SetRgbMode(rgb_profile));
+            }
+
+            for (k, a) in &keys {
+                if a.action.icon_state_count() < 2 {
+                    continue;
+                }
+
+                let id = (device_uid.clone(), *k);
+                let state = (a.action.clone(), a.action.key_icon_state(&device_uid, *k));
+                if last_sent.get(&id) == Some(&state) {
+                    continue;
+                }
+
+                last_sent.insert(id, state);
+                send_scr_icon(&tx, &device_uid, a, k).await;
+            }
+        }
+    }
+
+    Ok(())
+}
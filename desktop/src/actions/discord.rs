@@ -1,31 +1,52 @@
 use std::{
     collections::HashMap,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
         Arc, OnceLock,
     },
+    time::Duration,
 };
 
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use discord_rich_presence::{voice_settings::VoiceSettings, DiscordIpc, DiscordIpcClient};
-use eframe::egui::{include_image, vec2, Button, ImageSource, Ui};
+use eframe::egui::{
+    include_image, vec2, Button, ComboBox, ImageSource, RichText, Slider, TextWrapMode, Ui,
+};
 use egui_phosphor::regular as phos;
+use jukebox_util::toast::{Toast, ToastIcon};
+use rand::{distr::Alphanumeric, prelude::*};
 use serde::{Deserialize, Serialize};
-use tokio::sync::Mutex;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::{
+    sync::{MappedMutexGuard, Mutex, MutexGuard},
+    task::spawn_blocking,
+    time::sleep,
+};
 
 use crate::{
-    config::{DiscordOauthAccess, JukeBoxConfig},
+    actions::action::notify_icon_states_changed,
+    config::{DiscordOauthAccess, JukeBoxConfig, DEFAULT_DISCORD_ACCOUNT},
     get_reqwest_client,
     input::InputKey,
+    single_fire,
+    toast::{show_toast, ToastSource, TOAST_COLOR_INFO},
 };
 
 use super::types::{Action, ActionError};
 
 pub const AID_DISCORD_TOGGLE_MUTE: &str = "DiscordToggleMute";
 pub const AID_DISCORD_TOGGLE_DEAFEN: &str = "DiscordToggleDeafen";
-// pub const AID_DISCORD_TOGGLE_NOISE_SUPPRESSION: &str = "DiscordToggleNoiseSuppression";
+pub const AID_DISCORD_TOGGLE_NOISE_SUPPRESSION: &str = "DiscordToggleNoiseSuppression";
 pub const AID_DISCORD_PUSH_TO_TALK: &str = "DiscordPushToTalk";
 pub const AID_DISCORD_PUSH_TO_MUTE: &str = "DiscordPushToMute";
 pub const AID_DISCORD_PUSH_TO_DEAFEN: &str = "DiscordPushToDeafen";
+pub const AID_DISCORD_TOGGLE_AUTO_GAIN: &str = "DiscordToggleAutoGain";
+pub const AID_DISCORD_TOGGLE_ECHO_CANCELLATION: &str = "DiscordToggleEchoCancellation";
+pub const AID_DISCORD_INPUT_VOLUME: &str = "DiscordInputVolume";
+pub const AID_DISCORD_OUTPUT_VOLUME: &str = "DiscordOutputVolume";
+pub const AID_DISCORD_VOICE_CHANNEL: &str = "DiscordVoiceChannel";
+pub const AID_DISCORD_SELECT_DEVICE: &str = "DiscordSelectDevice";
 
 const ICON_MUTE: ImageSource =
     include_image!("../../../assets/action-icons/discord-microphone-1.bmp");
@@ -35,10 +56,10 @@ const ICON_DEAFEN: ImageSource =
     include_image!("../../../assets/action-icons/discord-headphones-1.bmp");
 const ICON_DEAFENED: ImageSource =
     include_image!("../../../assets/action-icons/discord-headphones-2.bmp");
-// const ICON_NOISE_SUPPRESSION_ON: ImageSource =
-//     include_image!("../../../assets/action-icons/discord-noise-suppression-1.bmp");
-// const ICON_NOISE_SUPPRESSION_OFF: ImageSource =
-//     include_image!("../../../assets/action-icons/discord-noise-suppression-2.bmp");
+const ICON_NOISE_SUPPRESSION_ON: ImageSource =
+    include_image!("../../../assets/action-icons/discord-noise-suppression-1.bmp");
+const ICON_NOISE_SUPPRESSION_OFF: ImageSource =
+    include_image!("../../../assets/action-icons/discord-noise-suppression-2.bmp");
 
 // TODO: make new icons for push actions
 const ICON_PUSH_TO_TALK: ImageSource =
@@ -47,181 +68,591 @@ const ICON_PUSH_TO_MUTE: ImageSource =
     include_image!("../../../assets/action-icons/discord-microphone-1.bmp");
 const ICON_PUSH_TO_DEAFEN: ImageSource =
     include_image!("../../../assets/action-icons/discord-headphones-1.bmp");
+const ICON_VOICE_CHANNEL_LEFT: ImageSource =
+    include_image!("../../../assets/action-icons/discord-talking-1.bmp");
+const ICON_VOICE_CHANNEL_JOINED: ImageSource =
+    include_image!("../../../assets/action-icons/discord-talking-2.bmp");
+const ICON_SPEAKING: ImageSource =
+    include_image!("../../../assets/action-icons/discord-talking-2.bmp");
+
+// Discord's volume ranges, in percent
+const DISCORD_INPUT_VOLUME_MAX: u8 = 100;
+const DISCORD_OUTPUT_VOLUME_MAX: u8 = 200;
 
+const EVENT_RETRY_MIN: Duration = Duration::from_secs(1);
+const EVENT_RETRY_MAX: Duration = Duration::from_secs(30);
+
+const DISCORD_TOKEN_URL: &str = "https://discord.com/api/oauth2/token";
+// Where tests answer token requests in place of Discord
+#[cfg(test)]
+static TEST_TOKEN_URL: OnceLock<String> = OnceLock::new();
+
+// Application used when the user hasn't set up their own, only in builds that were given one. It
+// has to be a public client, authorized with PKCE alone, as nothing secret can ship in a build.
 const DISCORD_CLIENT_ID: Option<&str> = option_env!("DISCORD_CLIENT_ID");
-const DISCORD_CLIENT_SECRET: Option<&str> = option_env!("DISCORD_CLIENT_SECRET");
-static DISCORD_CLIENT: OnceLock<Mutex<DiscordIpcClient>> = OnceLock::new();
+// Connection used by actions, None until we connect and after Discord goes away
+static DISCORD_CLIENT: Mutex<Option<DiscordIpcClient>> = Mutex::const_new(None);
+// Whether DISCORD_CLIENT is Some, for code that can't wait on its lock
+static DISCORD_CONNECTED: AtomicBool = AtomicBool::new(false);
+// Bumped when switching accounts, so the event connection knows to start over
+static DISCORD_SESSION: AtomicU64 = AtomicU64::new(0);
 static DISCORD_MUTED: AtomicBool = AtomicBool::new(false);
 static DISCORD_DEAFENED: AtomicBool = AtomicBool::new(false);
 static DISCORD_NOISE_SUPPRESSION: AtomicBool = AtomicBool::new(false);
+static DISCORD_AUTO_GAIN: AtomicBool = AtomicBool::new(false);
+static DISCORD_ECHO_CANCELLATION: AtomicBool = AtomicBool::new(false);
+static DISCORD_SPEAKING: AtomicBool = AtomicBool::new(false);
+static DISCORD_INPUT_VOLUME: AtomicU8 = AtomicU8::new(100);
+static DISCORD_OUTPUT_VOLUME: AtomicU8 = AtomicU8::new(100);
+// ID of the voice channel we're in
+static DISCORD_VOICE_CHANNEL: std::sync::Mutex<Option<String>> = std::sync::Mutex::new(None);
+static DISCORD_DEVICES: OnceLock<std::sync::Mutex<DiscordDevices>> = OnceLock::new();
+static DISCORD_NONCE: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Default, Clone)]
+struct DiscordDevices {
+    inputs: Vec<(String, String)>, // (ID, Name)
+    outputs: Vec<(String, String)>,
+    input: Option<String>, // ID of the device in use
+    output: Option<String>,
+}
+
+fn discord_devices() -> std::sync::MutexGuard<'static, DiscordDevices> {
+    DISCORD_DEVICES
+        .get_or_init(|| std::sync::Mutex::new(DiscordDevices::default()))
+        .lock()
+        .unwrap()
+}
 
 #[rustfmt::skip]
-#[allow(dead_code)]
 pub fn init_actions_discord(config: Arc<Mutex<JukeBoxConfig>>) -> (String, Vec<(String, Action, String)>) {
-    // init discord connection (if we have a config saved for it)
+    // keeps the discord connection up (once we have a config saved for it) and follows its events
     let _ = tokio::runtime::Handle::current()
-        .spawn(async move { create_client(config, true).await });
+        .spawn(async move { discord_event_task(config).await });
 
     (
         t!("action.discord.title", icon = phos::DISCORD_LOGO).into(),
         vec![
-            (AID_DISCORD_TOGGLE_MUTE.into(),    Action::DiscordToggleMute(DiscordToggleMute::default()),     t!("action.discord.toggle_mute.title").into()),
-            (AID_DISCORD_TOGGLE_DEAFEN.into(),  Action::DiscordToggleDeafen(DiscordToggleDeafen::default()), t!("action.discord.toggle_deafen.title").into()),
-            (AID_DISCORD_PUSH_TO_TALK.into(),   Action::DiscordPushToTalk(DiscordPushToTalk::default()),     t!("action.discord.push_to_talk.title").into()),
-            (AID_DISCORD_PUSH_TO_MUTE.into(),   Action::DiscordPushToMute(DiscordPushToMute::default()),     t!("action.discord.push_to_mute.title").into()),
-            (AID_DISCORD_PUSH_TO_DEAFEN.into(), Action::DiscordPushToDeafen(DiscordPushToDeafen::default()), t!("action.discord.push_to_deafen.title").into()),
-            // (AID_DISCORD_TOGGLE_NOISE_SUPPRESSION.into(), Action::DiscordToggleNoiseSuppression(DiscordToggleNoiseSuppression::default()), t!("action.discord.toggle_noise_suppression.title").into()),
+            (AID_DISCORD_TOGGLE_MUTE.into(),              Action::DiscordToggleMute(DiscordToggleMute::default()),                         t!("action.discord.toggle_mute.title").into()),
+            (AID_DISCORD_TOGGLE_DEAFEN.into(),            Action::DiscordToggleDeafen(DiscordToggleDeafen::default()),                     t!("action.discord.toggle_deafen.title").into()),
+            (AID_DISCORD_PUSH_TO_TALK.into(),             Action::DiscordPushToTalk(DiscordPushToTalk::default()),                         t!("action.discord.push_to_talk.title").into()),
+            (AID_DISCORD_PUSH_TO_MUTE.into(),             Action::DiscordPushToMute(DiscordPushToMute::default()),                         t!("action.discord.push_to_mute.title").into()),
+            (AID_DISCORD_PUSH_TO_DEAFEN.into(),           Action::DiscordPushToDeafen(DiscordPushToDeafen::default()),                     t!("action.discord.push_to_deafen.title").into()),
+            (AID_DISCORD_TOGGLE_NOISE_SUPPRESSION.into(), Action::DiscordToggleNoiseSuppression(DiscordToggleNoiseSuppression::default()), t!("action.discord.toggle_noise_suppression.title").into()),
+            (AID_DISCORD_TOGGLE_AUTO_GAIN.into(),         Action::DiscordToggleAutoGain(DiscordToggleAutoGain::default()),                 t!("action.discord.toggle_auto_gain.title").into()),
+            (AID_DISCORD_TOGGLE_ECHO_CANCELLATION.into(), Action::DiscordToggleEchoCancellation(DiscordToggleEchoCancellation::default()), t!("action.discord.toggle_echo_cancellation.title").into()),
+            (AID_DISCORD_INPUT_VOLUME.into(),             Action::DiscordInputVolume(DiscordInputVolume::default()),                       t!("action.discord.input_volume.title").into()),
+            (AID_DISCORD_OUTPUT_VOLUME.into(),            Action::DiscordOutputVolume(DiscordOutputVolume::default()),                     t!("action.discord.output_volume.title").into()),
+            (AID_DISCORD_SELECT_DEVICE.into(),            Action::DiscordSelectDevice(DiscordSelectDevice::default()),                     t!("action.discord.select_device.title").into()),
+            (AID_DISCORD_VOICE_CHANNEL.into(),            Action::DiscordVoiceChannel(DiscordVoiceChannel::default()),                     t!("action.discord.voice_channel.title").into()),
         ],
     )
 }
 
-async fn discord_access_token_request(
-    code: &str,
-    client_id: &str,
-    client_secret: &str,
-) -> Result<DiscordOauthAccess, ()> {
-    let params = HashMap::from([
-        ("grant_type", "authorization_code"),
-        ("code", code),
-        ("redirect_uri", "http://localhost:61961"),
-        ("client_id", client_id),
-        ("client_secret", client_secret),
-    ]);
+// Why a token request failed
+enum DiscordTokenError {
+    Request,  // Never got an answer we could read
+    Rejected, // Discord turned the grant down, e.g. the user took our access away
+}
+
+async fn discord_token_request(
+    params: HashMap<&str, &str>,
+) -> Result<DiscordOauthAccess, DiscordTokenError> {
+    #[cfg(test)]
+    let url = TEST_TOKEN_URL
+        .get()
+        .map_or(DISCORD_TOKEN_URL, String::as_str);
+    #[cfg(not(test))]
+    let url = DISCORD_TOKEN_URL;
 
     let r = get_reqwest_client()
-        .post("https://discord.com/api/oauth2/token")
+        .post(url)
         .form(&params)
         .send()
         .await
-        .map_err(|_| ())?;
+        .map_err(|_| DiscordTokenError::Request)?;
+
+    if r.status().is_client_error() {
+        return Err(DiscordTokenError::Rejected);
+    }
+
+    r.json().await.map_err(|_| DiscordTokenError::Request)
+}
 
-    r.json().await.map_err(|_| ())
+async fn discord_access_token_request(
+    code: &str,
+    code_verifier: &str,
+    app: &DiscordAppCredentials,
+) -> Result<DiscordOauthAccess, DiscordTokenError> {
+    let mut params = HashMap::from([
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("code_verifier", code_verifier),
+        ("redirect_uri", "http://localhost:61961"),
+        ("client_id", app.client_id.as_str()),
+    ]);
+    if let Some(secret) = &app.client_secret {
+        params.insert("client_secret", secret.as_str());
+    }
+    discord_token_request(params).await
 }
 
 async fn discord_refresh_access_token(
     refresh_token: &str,
-    client_id: &str,
-    client_secret: &str,
-) -> Result<DiscordOauthAccess, ()> {
-    let params = HashMap::from([
+    app: &DiscordAppCredentials,
+) -> Result<DiscordOauthAccess, DiscordTokenError> {
+    let mut params = HashMap::from([
         ("grant_type", "refresh_token"),
         ("refresh_token", refresh_token),
-        ("client_id", client_id),
-        ("client_secret", client_secret),
+        ("client_id", app.client_id.as_str()),
     ]);
+    if let Some(secret) = &app.client_secret {
+        params.insert("client_secret", secret.as_str());
+    }
+    discord_token_request(params).await
+}
 
-    let r = get_reqwest_client()
-        .post("https://discord.com/api/oauth2/token")
-        .form(&params)
-        .send()
-        .await
-        .map_err(|_| ())?;
+// Which Discord application we connect as
+#[derive(Clone)]
+struct DiscordAppCredentials {
+    client_id: String,
+    client_secret: Option<String>,
+}
+
+// The app the user set up in settings, otherwise the one built in
+fn discord_app(config: &JukeBoxConfig) -> Option<DiscordAppCredentials> {
+    let app = &config.discord_app;
+    if !app.client_id.trim().is_empty() {
+        return Some(DiscordAppCredentials {
+            client_id: app.client_id.trim().into(),
+            client_secret: app.client_secret.clone().filter(|s| !s.is_empty()),
+        });
+    }
+
+    Some(DiscordAppCredentials {
+        client_id: DISCORD_CLIENT_ID?.into(),
+        client_secret: None,
+    })
+}
 
-    r.json().await.map_err(|_| ())
+// Asks the user to let us in through the Discord app, returning the code to trade for tokens and
+// the PKCE verifier that goes with it
+fn discord_authorize(
+    client: &mut DiscordIpcClient,
+    app: &DiscordAppCredentials,
+) -> Result<(String, String), String> {
+    let verifier: String = rand::rng()
+        .sample_iter(Alphanumeric)
+        .take(64)
+        .map(char::from)
+        .collect();
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+
+    let data = discord_command(
+        client,
+        "AUTHORIZE",
+        json!({
+            "client_id": app.client_id,
+            "scopes": ["rpc", "rpc.voice.read", "rpc.voice.write"],
+            "code_challenge": challenge,
+            "code_challenge_method": "S256",
+        }),
+    )?;
+    let code = data["code"].as_str().ok_or("no code in reply")?;
+
+    Ok((code.into(), verifier))
 }
 
 async fn auth_client(
     config: Arc<Mutex<JukeBoxConfig>>,
     client: &mut DiscordIpcClient,
+    app: &DiscordAppCredentials,
     skip_if_no_auth: bool,
 ) -> Result<(), ActionError> {
     let mut config = config.lock().await;
-
-    if config.discord_oauth_access.is_none() {
-        if skip_if_no_auth {
-            return Ok(());
+    let current = config.discord_account.clone();
+
+    let mut oauth = None;
+    if let Some(old) = config.discord_oauth() {
+        match discord_refresh_access_token(&old.refresh_token, app).await {
+            Ok(new) => oauth = Some(new),
+            Err(DiscordTokenError::Rejected) => {
+                // The account stays listed, it only needs authorizing again
+                log::warn!("discord: refresh token rejected, authorizing again");
+                if let Some(account) = current
+                    .as_ref()
+                    .and_then(|n| config.discord_accounts.get_mut(n))
+                {
+                    account.oauth = None;
+                }
+                config.save();
+            }
+            Err(DiscordTokenError::Request) => {
+                return Err(ActionError::msg(t!("action.discord.err.oauth_refresh")))
+            }
         }
+    }
 
-        let code = client
-            .authorize(&["rpc", "rpc.voice.read", "rpc.voice.write"])
-            .map_err(|e| {
-                ActionError::msg(t!(
-                    "action.discord.err.authorize",
-                    error = format!("{:?}", e)
-                ))
-            })?;
+    let oauth = match oauth {
+        Some(oauth) => oauth,
+        None => {
+            if skip_if_no_auth {
+                return Ok(());
+            }
 
-        let oauth = discord_access_token_request(
-            &code,
-            DISCORD_CLIENT_ID.unwrap(),
-            DISCORD_CLIENT_SECRET.unwrap(),
-        )
-        .await
-        .map_err(|_| ActionError::msg(t!("action.discord.err.oauth_request")))?;
+            // Authorizes whichever account is logged in to the Discord app
+            let (code, verifier) = discord_authorize(client, app)
+                .map_err(|e| ActionError::msg(t!("action.discord.err.authorize", error = e)))?;
 
-        config.discord_oauth_access = Some(oauth);
-        config.save();
-    } else {
-        let oauth = discord_refresh_access_token(
-            &config.discord_oauth_access.as_ref().unwrap().refresh_token,
-            DISCORD_CLIENT_ID.unwrap(),
-            DISCORD_CLIENT_SECRET.unwrap(),
-        )
-        .await
-        .map_err(|_| ActionError::msg(t!("action.discord.err.oauth_refresh")))?;
+            discord_access_token_request(&code, &verifier, app)
+                .await
+                .map_err(|_| ActionError::msg(t!("action.discord.err.oauth_request")))?
+        }
+    };
 
-        config.discord_oauth_access = Some(oauth);
-        config.save();
+    let auth = discord_command(
+        client,
+        "AUTHENTICATE",
+        json!({ "access_token": oauth.access_token }),
+    )
+    .map_err(|e| ActionError::msg(t!("action.discord.err.authenticate", error = e)))?;
+
+    // Accounts go by the Discord user they belong to, the one carried over from old configs
+    // gets its name now
+    let name = auth["user"]["username"]
+        .as_str()
+        .map(String::from)
+        .or(current.clone())
+        .unwrap_or(DEFAULT_DISCORD_ACCOUNT.into());
+    if current.as_deref() == Some(DEFAULT_DISCORD_ACCOUNT) && name != DEFAULT_DISCORD_ACCOUNT {
+        config.discord_accounts.remove(DEFAULT_DISCORD_ACCOUNT);
     }
-
-    client
-        .authenticate(&config.discord_oauth_access.clone().unwrap().access_token)
-        .map_err(|e| {
-            ActionError::msg(t!(
-                "action.discord.err.authenticate",
-                error = format!("{:?}", e)
-            ))
-        })?;
+    config
+        .discord_accounts
+        .entry(name.clone())
+        .or_default()
+        .oauth = Some(oauth);
+    config.discord_account = Some(name);
+    config.save();
 
     Ok(())
 }
 
+// Connects to the Discord app, which tries each of its discord-ipc-0..9 sockets (or pipes on
+// Windows) until one answers. On Linux and macOS they're looked for in $XDG_RUNTIME_DIR (then
+// $TMPDIR, $TMP, $TEMP and /tmp), so pointing that at a folder with a fake discord-ipc-0 socket
+// stands in for Discord when trying things out.
 async fn create_client(
     config: Arc<Mutex<JukeBoxConfig>>,
     skip_if_no_auth: bool,
-) -> Result<(), ActionError> {
-    if DISCORD_CLIENT_ID.is_none() || DISCORD_CLIENT_SECRET.is_none() {
-        log::error!("discord: missing client id and secret from compile");
-        return Err(ActionError::msg(t!("action.discord.err.compile")));
-    }
+) -> Result<DiscordIpcClient, ActionError> {
+    let Some(app) = discord_app(&*config.lock().await) else {
+        log::error!("discord: no application set up");
+        return Err(ActionError::msg(t!("action.discord.err.no_app")));
+    };
 
-    let mut client = DiscordIpcClient::new(DISCORD_CLIENT_ID.unwrap());
+    let mut client = DiscordIpcClient::new(&app.client_id);
     client.connect().map_err(|e| {
         ActionError::msg(t!("action.discord.err.connect", error = format!("{:?}", e)))
     })?;
 
-    auth_client(config, &mut client, skip_if_no_auth).await?;
+    auth_client(config, &mut client, &app, skip_if_no_auth).await?;
 
-    if let Ok(v) = client.get_voice_settings() {
-        let deaf = if let Some(deaf) = v.deaf {
-            DISCORD_DEAFENED.store(deaf, Ordering::Relaxed);
-            DISCORD_MUTED.store(deaf, Ordering::Relaxed);
-            deaf
-        } else {
-            false
+    refresh_voice_state(&mut client);
+
+    show_toast(
+        ToastSource::Discord,
+        Toast::new(
+            &t!("toast.discord.title"),
+            &t!("toast.discord.connected"),
+            ToastIcon::Info,
+            0,
+            TOAST_COLOR_INFO,
+        ),
+    );
+
+    Ok(client)
+}
+
+/// Locks the connection used by actions, connecting first when there isn't one.
+async fn discord_client(
+    config: Arc<Mutex<JukeBoxConfig>>,
+) -> Result<MappedMutexGuard<'static, DiscordIpcClient>, ActionError> {
+    let mut client = DISCORD_CLIENT.lock().await;
+    if client.is_none() {
+        *client = Some(create_client(config, false).await?);
+        DISCORD_CONNECTED.store(true, Ordering::Relaxed);
+        notify_icon_states_changed();
+    }
+    Ok(MutexGuard::map(client, |c| c.as_mut().unwrap()))
+}
+
+// Drops the connection used by actions, so the next one to need it reconnects
+async fn disconnect_client() {
+    *DISCORD_CLIENT.lock().await = None;
+    DISCORD_CONNECTED.store(false, Ordering::Relaxed);
+    DISCORD_SPEAKING.store(false, Ordering::Relaxed);
+    *DISCORD_VOICE_CHANNEL.lock().unwrap() = None;
+    notify_icon_states_changed();
+}
+
+// Keeps Discord connected and the icons in step with it, including changes made in Discord itself.
+// Reconnects on its own when Discord restarts, backing off while it's gone.
+async fn discord_event_task(config: Arc<Mutex<JukeBoxConfig>>) {
+    let mut retry = EVENT_RETRY_MIN;
+
+    loop {
+        // Nothing to connect with until the user has connected their account
+        let has_oauth = config.lock().await.discord_oauth().is_some();
+        if !has_oauth || discord_client(config.clone()).await.is_err() {
+            sleep(retry).await;
+            retry = (retry * 2).min(EVENT_RETRY_MAX);
+            continue;
+        }
+        retry = EVENT_RETRY_MIN;
+
+        // Reading events blocks, so they come in over a connection of their own. Connecting the
+        // action connection above refreshed the token if it needed it.
+        let (app, token) = {
+            let c = config.lock().await;
+            (
+                discord_app(&c),
+                c.discord_oauth().map(|o| o.access_token.clone()),
+            )
         };
-        if let Some(mute) = v.mute {
-            DISCORD_MUTED.store(mute || deaf, Ordering::Relaxed);
+        let (Some(app), Some(token)) = (app, token) else {
+            continue;
+        };
+        match spawn_blocking(move || discord_listen(&app.client_id, &token)).await {
+            // Switched accounts, the action connection was already redone
+            Ok(Ok(())) => continue,
+            Ok(Err(e)) => log::warn!("discord: event connection closed: {}", e),
+            Err(e) => log::error!("discord: event listener failed: {}", e),
+        }
+
+        // Discord went away (or restarted), start over once it's back
+        disconnect_client().await;
+        sleep(retry).await;
+    }
+}
+
+// Subscribes to (or unsubscribes from) an RPC event, returning the data of Discord's reply
+fn discord_subscribe(
+    client: &mut DiscordIpcClient,
+    subscribe: bool,
+    evt: &str,
+    args: Value,
+) -> Result<Value, String> {
+    let cmd = if subscribe {
+        "SUBSCRIBE"
+    } else {
+        "UNSUBSCRIBE"
+    };
+    discord_request(client, json!({ "cmd": cmd, "evt": evt, "args": args }))
+}
+
+// Follows Discord's events until the connection drops, or until the next one after switching
+// accounts
+fn discord_listen(client_id: &str, token: &str) -> Result<(), String> {
+    let session = DISCORD_SESSION.load(Ordering::Relaxed);
+    let mut client = DiscordIpcClient::new(client_id);
+    client.connect().map_err(|e| format!("{:?}", e))?;
+
+    let auth = discord_command(
+        &mut client,
+        "AUTHENTICATE",
+        json!({ "access_token": token }),
+    )?;
+    let user_id = auth["user"]["id"].as_str().unwrap_or_default().to_string();
+
+    discord_subscribe(&mut client, true, "VOICE_SETTINGS_UPDATE", json!({}))?;
+    discord_subscribe(&mut client, true, "VOICE_CHANNEL_SELECT", json!({}))?;
+
+    // Whatever changed while we weren't listening
+    refresh_voice_state(&mut client);
+    let mut channel = DISCORD_VOICE_CHANNEL.lock().unwrap().clone();
+    speaking_subscribe(&mut client, true, &channel);
+    notify_icon_states_changed();
+
+    loop {
+        let (_, event) = client.recv().map_err(|e| format!("{:?}", e))?;
+        if DISCORD_SESSION.load(Ordering::Relaxed) != session {
+            return Ok(());
+        }
+        let data = &event["data"];
+        match event["evt"].as_str() {
+            Some("VOICE_SETTINGS_UPDATE") => store_voice_settings(data),
+            Some("VOICE_CHANNEL_SELECT") => {
+                // Speaking events are per channel, follow us over to the new one
+                speaking_subscribe(&mut client, false, &channel);
+                channel = data["channel_id"].as_str().map(String::from);
+                speaking_subscribe(&mut client, true, &channel);
+                DISCORD_SPEAKING.store(false, Ordering::Relaxed);
+                *DISCORD_VOICE_CHANNEL.lock().unwrap() = channel.clone();
+            }
+            Some(evt @ ("SPEAKING_START" | "SPEAKING_STOP")) => {
+                if data["user_id"].as_str() != Some(user_id.as_str()) {
+                    continue;
+                }
+                DISCORD_SPEAKING.store(evt == "SPEAKING_START", Ordering::Relaxed);
+            }
+            _ => continue,
         }
-        if let Some(noise_suppression) = v.noise_suppression {
-            DISCORD_NOISE_SUPPRESSION.store(noise_suppression, Ordering::Relaxed);
+        notify_icon_states_changed();
+    }
+}
+
+fn speaking_subscribe(client: &mut DiscordIpcClient, subscribe: bool, channel: &Option<String>) {
+    let Some(channel_id) = channel else {
+        return;
+    };
+    for evt in ["SPEAKING_START", "SPEAKING_STOP"] {
+        let args = json!({ "channel_id": channel_id });
+        if let Err(e) = discord_subscribe(client, subscribe, evt, args) {
+            log::warn!("discord: failed to (un)subscribe {}: {}", evt, e);
         }
     }
+}
+
+// Sends an RPC command the client has no helper for, and returns the data of Discord's reply
+fn discord_command(client: &mut DiscordIpcClient, cmd: &str, args: Value) -> Result<Value, String> {
+    discord_request(client, json!({ "cmd": cmd, "args": args }))
+}
 
-    DISCORD_CLIENT
-        .set(Mutex::new(client))
-        .expect("failed to set DISCORD_CLIENT");
+fn discord_request(client: &mut DiscordIpcClient, mut payload: Value) -> Result<Value, String> {
+    let nonce = DISCORD_NONCE.fetch_add(1, Ordering::Relaxed).to_string();
+    payload["nonce"] = nonce.clone().into();
+    client.send(payload, 1).map_err(|e| format!("{:?}", e))?;
 
-    Ok(())
+    loop {
+        let (_, reply) = client.recv().map_err(|e| format!("{:?}", e))?;
+        // Events and late replies carry no nonce or someone else's
+        if reply["nonce"].as_str() != Some(nonce.as_str()) {
+            continue;
+        }
+        if reply["evt"].as_str() == Some("ERROR") {
+            let message = reply["data"]["message"].as_str().unwrap_or("unknown error");
+            return Err(message.into());
+        }
+        return Ok(reply["data"].clone());
+    }
+}
+
+fn discord_device_list(v: &Value) -> Vec<(String, String)> {
+    v["available_devices"]
+        .as_array()
+        .map(|devices| {
+            devices
+                .iter()
+                .filter_map(|d| Some((d["id"].as_str()?.into(), d["name"].as_str()?.into())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Keeps our copy of Discord's voice settings, from a GET_VOICE_SETTINGS reply
+fn store_voice_settings(v: &Value) {
+    if let Some(deaf) = v["deaf"].as_bool() {
+        DISCORD_DEAFENED.store(deaf, Ordering::Relaxed);
+    }
+    if let Some(mute) = v["mute"].as_bool() {
+        let deaf = DISCORD_DEAFENED.load(Ordering::Relaxed);
+        DISCORD_MUTED.store(mute || deaf, Ordering::Relaxed);
+    }
+
+    for (key, state) in [
+        ("noise_suppression", &DISCORD_NOISE_SUPPRESSION),
+        ("automatic_gain_control", &DISCORD_AUTO_GAIN),
+        ("echo_cancellation", &DISCORD_ECHO_CANCELLATION),
+    ] {
+        if let Some(b) = v[key].as_bool() {
+            state.store(b, Ordering::Relaxed);
+        }
+    }
+
+    if let Some(volume) = v["input"]["volume"].as_f64() {
+        DISCORD_INPUT_VOLUME.store(volume.round() as u8, Ordering::Relaxed);
+    }
+    if let Some(volume) = v["output"]["volume"].as_f64() {
+        DISCORD_OUTPUT_VOLUME.store(volume.round() as u8, Ordering::Relaxed);
+    }
+
+    let mut devices = discord_devices();
+    if v["input"].is_object() {
+        devices.inputs = discord_device_list(&v["input"]);
+        devices.input = v["input"]["device_id"].as_str().map(String::from);
+    }
+    if v["output"].is_object() {
+        devices.outputs = discord_device_list(&v["output"]);
+        devices.output = v["output"]["device_id"].as_str().map(String::from);
+    }
+}
+
+// Reads what the voice actions show from Discord
+fn refresh_voice_state(client: &mut DiscordIpcClient) {
+    match discord_command(client, "GET_VOICE_SETTINGS", json!({})) {
+        Ok(v) => store_voice_settings(&v),
+        Err(e) => log::warn!("discord: failed to get voice settings: {}", e),
+    }
+    match discord_command(client, "GET_SELECTED_VOICE_CHANNEL", json!({})) {
+        Ok(c) => *DISCORD_VOICE_CHANNEL.lock().unwrap() = c["id"].as_str().map(String::from),
+        Err(e) => log::warn!("discord: failed to get voice channel: {}", e),
+    }
 }
 
 fn account_warning(ui: &mut Ui, config: Arc<Mutex<JukeBoxConfig>>) {
-    if DISCORD_CLIENT.get().is_none() {
-        let has_oauth = config.blocking_lock().discord_oauth_access.is_some();
+    let (accounts, current, has_oauth) = {
+        let c = config.blocking_lock();
+        (
+            c.discord_accounts.keys().cloned().collect::<Vec<_>>(),
+            c.discord_account.clone(),
+            c.discord_oauth().is_some(),
+        )
+    };
+
+    if !accounts.is_empty() {
+        ui.label(t!("action.discord.account.select"));
+        ui.horizontal(|ui| {
+            let selected = current.clone().unwrap_or_default();
+            ComboBox::from_id_salt("DiscordAccountSelect")
+                .width(160.0)
+                .selected_text(selected.as_str())
+                .show_ui(ui, |ui| {
+                    for a in &accounts {
+                        if ui.selectable_label(*a == selected, a).clicked() && *a != selected {
+                            switch_account(config.clone(), Some(a.clone()));
+                        }
+                    }
+                });
+
+            if current.is_some()
+                && ui
+                    .button(RichText::new(phos::TRASH))
+                    .on_hover_text_at_pointer(t!("action.discord.account.forget"))
+                    .clicked()
+            {
+                let mut c = config.blocking_lock();
+                c.discord_accounts.remove(&selected);
+                c.save();
+                drop(c);
+                switch_account(config.clone(), None);
+            }
+            if current.is_some()
+                && ui
+                    .button(RichText::new(phos::PLUS))
+                    .on_hover_text_at_pointer(t!("action.discord.account.add"))
+                    .clicked()
+            {
+                switch_account(config.clone(), None);
+            }
+        });
+        ui.label("");
+    }
+
+    if !DISCORD_CONNECTED.load(Ordering::Relaxed) {
         if has_oauth {
-            // TODO: send any error to gui
-            let _ = tokio::runtime::Handle::current()
-                .block_on(async move { create_client(config, false).await });
+            // The event task keeps trying on its own
+            ui.vertical_centered(|ui| ui.label(t!("action.discord.warning.connecting")));
         } else {
             ui.vertical_centered(|ui| ui.label(t!("action.discord.warning.help")));
             ui.label("");
@@ -234,7 +665,7 @@ fn account_warning(ui: &mut Ui, config: Arc<Mutex<JukeBoxConfig>>) {
             {
                 // TODO: send any error to gui
                 tokio::runtime::Handle::current()
-                    .spawn(async move { create_client(config, false).await });
+                    .spawn(async move { discord_client(config).await.map(|_| ()) });
             }
         }
     } else {
@@ -249,16 +680,36 @@ fn account_warning(ui: &mut Ui, config: Arc<Mutex<JukeBoxConfig>>) {
         {
             // TODO: send any error to gui
             tokio::runtime::Handle::current().spawn(async move {
-                let mut client = DISCORD_CLIENT.get().unwrap().lock().await;
-                match client.reconnect() {
-                    Ok(_) => auth_client(config, &mut client, false).await,
-                    Err(_) => Ok(()),
-                }
+                disconnect_client().await;
+                discord_client(config).await.map(|_| ())
             });
         }
     }
 }
 
+// Moves actions over to another saved account, or to none so the next connection authorizes a new
+// one. Only works for the account logged in to the Discord app.
+fn switch_account(config: Arc<Mutex<JukeBoxConfig>>, account: Option<String>) {
+    let mut c = config.blocking_lock();
+    c.discord_account = account;
+    c.save();
+    drop(c);
+
+    reconnect(config);
+}
+
+/// Drops the connection and connects again with the saved app and account, if one is authorized
+pub fn reconnect(config: Arc<Mutex<JukeBoxConfig>>) {
+    DISCORD_SESSION.fetch_add(1, Ordering::Relaxed);
+    tokio::runtime::Handle::current().spawn(async move {
+        disconnect_client().await;
+        if config.lock().await.discord_oauth().is_some() {
+            // TODO: send any error to gui
+            let _ = discord_client(config).await;
+        }
+    });
+}
+
 fn discord_toggle_mute(
     client: &mut DiscordIpcClient,
     muted: bool,
@@ -303,27 +754,52 @@ fn discord_toggle_deafen(
         })
 }
 
-// fn discord_toggle_noise_suppression(
-//     client: &mut DiscordIpcClient,
-//     noise_suppression: bool,
-//     device_uid: &String,
-//     input_key: InputKey,
-// ) -> Result<(InputKey, bool), ActionError> {
-//     client
-//         .set_voice_settings(VoiceSettings::new().noise_suppression(noise_suppression))
-//         .map(|_| (input_key, true))
-//         .map_err(|e| {
-//             ActionError::new(
-//                 device_uid,
-//                 input_key,
-//                 t!(
-//                     "action.discord.err.set_noise_suppression_state",
-//                     state = noise_suppression,
-//                     error = format!("{:?}", e)
-//                 ),
-//             )
-//         })
-// }
+fn discord_toggle_noise_suppression(
+    client: &mut DiscordIpcClient,
+    noise_suppression: bool,
+    device_uid: &String,
+    input_key: InputKey,
+) -> Result<(InputKey, bool), ActionError> {
+    client
+        .set_voice_settings(VoiceSettings::new().noise_suppression(noise_suppression))
+        .map(|_| (input_key, true))
+        .map_err(|e| {
+            ActionError::new(
+                device_uid,
+                input_key,
+                t!(
+                    "action.discord.err.set_noise_suppression_state",
+                    state = noise_suppression,
+                    error = format!("{:?}", e)
+                ),
+            )
+        })
+}
+
+fn discord_set_voice_settings(
+    client: &mut DiscordIpcClient,
+    settings: Value,
+    setting: &str,
+    device_uid: &String,
+    input_key: InputKey,
+) -> Result<(InputKey, bool), ActionError> {
+    discord_command(client, "SET_VOICE_SETTINGS", settings)
+        .map(|v| {
+            store_voice_settings(&v);
+            (input_key, true)
+        })
+        .map_err(|e| {
+            ActionError::new(
+                device_uid,
+                input_key,
+                t!(
+                    "action.discord.err.set_voice_setting",
+                    setting = setting,
+                    error = e
+                ),
+            )
+        })
+}
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct DiscordToggleMute {}
@@ -334,10 +810,7 @@ impl DiscordToggleMute {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        if DISCORD_CLIENT.get().is_none() {
-            create_client(config.clone(), false).await?;
-        }
-        let mut client = DISCORD_CLIENT.get().unwrap().lock().await;
+        let mut client = discord_client(config.clone()).await?;
 
         let muted = !DISCORD_MUTED.load(Ordering::Relaxed);
         DISCORD_MUTED.store(muted, Ordering::Relaxed);
@@ -409,10 +882,7 @@ impl DiscordToggleDeafen {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        if DISCORD_CLIENT.get().is_none() {
-            create_client(config.clone(), false).await?;
-        }
-        let mut client = DISCORD_CLIENT.get().unwrap().lock().await;
+        let mut client = discord_client(config.clone()).await?;
 
         let deafened = !DISCORD_DEAFENED.load(Ordering::Relaxed);
         DISCORD_DEAFENED.store(deafened, Ordering::Relaxed);
@@ -472,76 +942,73 @@ impl DiscordToggleDeafen {
     }
 }
 
-// #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
-// pub struct DiscordToggleNoiseSuppression {}
-// impl DiscordToggleNoiseSuppression {
-//     pub async fn on_press(
-//         &self,
-//         device_uid: &String,
-//         input_key: InputKey,
-//         config: Arc<Mutex<JukeBoxConfig>>,
-//     ) -> Result<(InputKey, bool), ActionError> {
-//         if DISCORD_CLIENT.get().is_none() {
-//             create_client(config.clone(), false).await?;
-//         }
-//         let mut client = DISCORD_CLIENT.get().unwrap().lock().await;
-
-//         let noise_suppression = !DISCORD_NOISE_SUPPRESSION.load(Ordering::Relaxed);
-//         DISCORD_NOISE_SUPPRESSION.store(noise_suppression, Ordering::Relaxed);
-
-//         discord_toggle_noise_suppression(&mut client, noise_suppression, &device_uid, input_key)
-//     }
-
-//     pub async fn on_release(
-//         &self,
-//         _device_uid: &String,
-//         input_key: InputKey,
-//         _config: Arc<Mutex<JukeBoxConfig>>,
-//     ) -> Result<(InputKey, bool), ActionError> {
-//         Ok((input_key, false))
-//     }
-
-//     pub fn get_type(&self) -> String {
-//         AID_DISCORD_TOGGLE_NOISE_SUPPRESSION.into()
-//     }
-
-//     pub fn edit_ui(
-//         &mut self,
-//         ui: &mut Ui,
-//         _device_uid: &String,
-//         _input_key: InputKey,
-//         config: Arc<Mutex<JukeBoxConfig>>,
-//     ) {
-//         account_warning(ui, config)
-//     }
-
-//     pub fn help(&self) -> &str {
-//         "action.discord.toggle_noise_suppression.help"
-//     }
-
-//     pub fn icon_state(&self) -> u8 {
-//         if DISCORD_NOISE_SUPPRESSION.load(Ordering::Relaxed) {
-//             1
-//         } else {
-//             0
-//         }
-//     }
-
-//     pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
-//         &[ICON_NOISE_SUPPRESSION_OFF, ICON_NOISE_SUPPRESSION_ON]
-//     }
-
-//     pub fn icon_state_count(&self) -> u8 {
-//         2
-//     }
-
-//     pub fn icon_state_descriptions(&self) -> &[&str] {
-//         &[
-//             "action.discord.toggle_noise_suppression.icon_state_0",
-//             "action.discord.toggle_noise_suppression.icon_state_1",
-//         ]
-//     }
-// }
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DiscordToggleNoiseSuppression {}
+impl DiscordToggleNoiseSuppression {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        let mut client = discord_client(config.clone()).await?;
+
+        let noise_suppression = !DISCORD_NOISE_SUPPRESSION.load(Ordering::Relaxed);
+        DISCORD_NOISE_SUPPRESSION.store(noise_suppression, Ordering::Relaxed);
+
+        discord_toggle_noise_suppression(&mut client, noise_suppression, &device_uid, input_key)
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_DISCORD_TOGGLE_NOISE_SUPPRESSION.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        account_warning(ui, config)
+    }
+
+    pub fn help(&self) -> &str {
+        "action.discord.toggle_noise_suppression.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        if DISCORD_NOISE_SUPPRESSION.load(Ordering::Relaxed) {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        &[ICON_NOISE_SUPPRESSION_OFF, ICON_NOISE_SUPPRESSION_ON]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        2
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[
+            "action.discord.toggle_noise_suppression.icon_state_0",
+            "action.discord.toggle_noise_suppression.icon_state_1",
+        ]
+    }
+}
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct DiscordPushToTalk {}
@@ -552,10 +1019,7 @@ impl DiscordPushToTalk {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        if DISCORD_CLIENT.get().is_none() {
-            create_client(config.clone(), false).await?;
-        }
-        let mut client = DISCORD_CLIENT.get().unwrap().lock().await;
+        let mut client = discord_client(config.clone()).await?;
 
         discord_toggle_mute(&mut client, false, &device_uid, input_key)
     }
@@ -566,10 +1030,7 @@ impl DiscordPushToTalk {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        if DISCORD_CLIENT.get().is_none() {
-            create_client(config.clone(), false).await?;
-        }
-        let mut client = DISCORD_CLIENT.get().unwrap().lock().await;
+        let mut client = discord_client(config.clone()).await?;
 
         discord_toggle_mute(&mut client, true, &device_uid, input_key)
     }
@@ -593,19 +1054,22 @@ impl DiscordPushToTalk {
     }
 
     pub fn icon_state(&self) -> u8 {
-        0
+        DISCORD_SPEAKING.load(Ordering::Relaxed) as u8
     }
 
     pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
-        &[ICON_PUSH_TO_TALK]
+        &[ICON_PUSH_TO_TALK, ICON_SPEAKING]
     }
 
     pub fn icon_state_count(&self) -> u8 {
-        1
+        2
     }
 
     pub fn icon_state_descriptions(&self) -> &[&str] {
-        &[""]
+        &[
+            "action.discord.push_to_talk.icon_state_0",
+            "action.discord.push_to_talk.icon_state_1",
+        ]
     }
 }
 
@@ -618,10 +1082,7 @@ impl DiscordPushToMute {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        if DISCORD_CLIENT.get().is_none() {
-            create_client(config.clone(), false).await?;
-        }
-        let mut client = DISCORD_CLIENT.get().unwrap().lock().await;
+        let mut client = discord_client(config.clone()).await?;
 
         discord_toggle_mute(&mut client, true, &device_uid, input_key)
     }
@@ -632,10 +1093,7 @@ impl DiscordPushToMute {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        if DISCORD_CLIENT.get().is_none() {
-            create_client(config.clone(), false).await?;
-        }
-        let mut client = DISCORD_CLIENT.get().unwrap().lock().await;
+        let mut client = discord_client(config.clone()).await?;
 
         discord_toggle_mute(&mut client, false, &device_uid, input_key)
     }
@@ -684,10 +1142,7 @@ impl DiscordPushToDeafen {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        if DISCORD_CLIENT.get().is_none() {
-            create_client(config.clone(), false).await?;
-        }
-        let mut client = DISCORD_CLIENT.get().unwrap().lock().await;
+        let mut client = discord_client(config.clone()).await?;
 
         discord_toggle_deafen(&mut client, true, &device_uid, input_key)
     }
@@ -698,10 +1153,7 @@ impl DiscordPushToDeafen {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        if DISCORD_CLIENT.get().is_none() {
-            create_client(config.clone(), false).await?;
-        }
-        let mut client = DISCORD_CLIENT.get().unwrap().lock().await;
+        let mut client = discord_client(config.clone()).await?;
 
         discord_toggle_deafen(&mut client, false, &device_uid, input_key)
     }
@@ -740,3 +1192,831 @@ impl DiscordPushToDeafen {
         &[""]
     }
 }
+
+// Flips one of Discord's on/off voice settings
+async fn discord_toggle_setting(
+    key: &str,
+    state: &AtomicBool,
+    setting: &str,
+    device_uid: &String,
+    input_key: InputKey,
+    config: Arc<Mutex<JukeBoxConfig>>,
+) -> Result<(InputKey, bool), ActionError> {
+    let mut client = discord_client(config.clone()).await?;
+
+    let enabled = !state.load(Ordering::Relaxed);
+    state.store(enabled, Ordering::Relaxed);
+
+    discord_set_voice_settings(
+        &mut client,
+        json!({ key: enabled }),
+        setting,
+        device_uid,
+        input_key,
+    )
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DiscordToggleAutoGain {}
+impl DiscordToggleAutoGain {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        discord_toggle_setting(
+            "automatic_gain_control",
+            &DISCORD_AUTO_GAIN,
+            &t!("action.discord.toggle_auto_gain.title"),
+            device_uid,
+            input_key,
+            config,
+        )
+        .await
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_DISCORD_TOGGLE_AUTO_GAIN.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        account_warning(ui, config)
+    }
+
+    pub fn help(&self) -> &str {
+        "action.discord.toggle_auto_gain.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        if DISCORD_AUTO_GAIN.load(Ordering::Relaxed) {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        // TODO: automatic gain control icons
+        &[ICON_NOISE_SUPPRESSION_OFF, ICON_NOISE_SUPPRESSION_ON]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        2
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[
+            "action.discord.toggle_auto_gain.icon_state_0",
+            "action.discord.toggle_auto_gain.icon_state_1",
+        ]
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DiscordToggleEchoCancellation {}
+impl DiscordToggleEchoCancellation {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        discord_toggle_setting(
+            "echo_cancellation",
+            &DISCORD_ECHO_CANCELLATION,
+            &t!("action.discord.toggle_echo_cancellation.title"),
+            device_uid,
+            input_key,
+            config,
+        )
+        .await
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_DISCORD_TOGGLE_ECHO_CANCELLATION.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        account_warning(ui, config)
+    }
+
+    pub fn help(&self) -> &str {
+        "action.discord.toggle_echo_cancellation.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        if DISCORD_ECHO_CANCELLATION.load(Ordering::Relaxed) {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        // TODO: echo cancellation icons
+        &[ICON_NOISE_SUPPRESSION_OFF, ICON_NOISE_SUPPRESSION_ON]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        2
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[
+            "action.discord.toggle_echo_cancellation.icon_state_0",
+            "action.discord.toggle_echo_cancellation.icon_state_1",
+        ]
+    }
+}
+
+// Moves Discord's input or output volume by `adjust` percent
+async fn discord_adjust_volume(
+    input: bool,
+    adjust: i8,
+    device_uid: &String,
+    input_key: InputKey,
+    config: Arc<Mutex<JukeBoxConfig>>,
+) -> Result<(InputKey, bool), ActionError> {
+    let mut client = discord_client(config.clone()).await?;
+
+    let (state, max, key, setting) = if input {
+        (
+            &DISCORD_INPUT_VOLUME,
+            DISCORD_INPUT_VOLUME_MAX,
+            "input",
+            t!("action.discord.input_volume.title"),
+        )
+    } else {
+        (
+            &DISCORD_OUTPUT_VOLUME,
+            DISCORD_OUTPUT_VOLUME_MAX,
+            "output",
+            t!("action.discord.output_volume.title"),
+        )
+    };
+    let volume = (state.load(Ordering::Relaxed) as i16 + adjust as i16).clamp(0, max as i16) as u8;
+    state.store(volume, Ordering::Relaxed);
+
+    discord_set_voice_settings(
+        &mut client,
+        json!({ key: { "volume": volume } }),
+        &setting,
+        device_uid,
+        input_key,
+    )
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DiscordInputVolume {
+    vol_adjust: i8,
+}
+impl DiscordInputVolume {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        discord_adjust_volume(true, self.vol_adjust, device_uid, input_key, config).await
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_DISCORD_INPUT_VOLUME.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        account_warning(ui, config);
+
+        ui.label(t!("action.discord.input_volume.volume_adjust"));
+        ui.add(Slider::new(&mut self.vol_adjust, -100..=100));
+    }
+
+    pub fn help(&self) -> &str {
+        "action.discord.input_volume.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        0
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        &[ICON_MUTE]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        1
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[""]
+    }
+
+    pub fn icon_text(&self) -> Option<String> {
+        if !DISCORD_CONNECTED.load(Ordering::Relaxed) {
+            return None;
+        }
+        Some(format!("{}%", DISCORD_INPUT_VOLUME.load(Ordering::Relaxed)))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DiscordOutputVolume {
+    vol_adjust: i8,
+}
+impl DiscordOutputVolume {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        discord_adjust_volume(false, self.vol_adjust, device_uid, input_key, config).await
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_DISCORD_OUTPUT_VOLUME.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        account_warning(ui, config);
+
+        ui.label(t!("action.discord.output_volume.volume_adjust"));
+        ui.add(Slider::new(&mut self.vol_adjust, -100..=100));
+    }
+
+    pub fn help(&self) -> &str {
+        "action.discord.output_volume.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        0
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        &[ICON_DEAFEN]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        1
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[""]
+    }
+
+    pub fn icon_text(&self) -> Option<String> {
+        if !DISCORD_CONNECTED.load(Ordering::Relaxed) {
+            return None;
+        }
+        Some(format!(
+            "{}%",
+            DISCORD_OUTPUT_VOLUME.load(Ordering::Relaxed)
+        ))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DiscordSelectDevice {
+    input: bool,
+    device: Option<(String, String)>, // (ID, Name)
+}
+impl DiscordSelectDevice {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        let Some((id, _)) = &self.device else {
+            return Ok((input_key, false));
+        };
+
+        let mut client = discord_client(config.clone()).await?;
+
+        let key = if self.input { "input" } else { "output" };
+        discord_set_voice_settings(
+            &mut client,
+            json!({ key: { "device_id": id } }),
+            &t!("action.discord.select_device.title"),
+            device_uid,
+            input_key,
+        )
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_DISCORD_SELECT_DEVICE.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        account_warning(ui, config);
+
+        ui.horizontal(|ui| {
+            if ui
+                .selectable_label(!self.input, t!("action.discord.select_device.output"))
+                .clicked()
+                && self.input
+            {
+                self.input = false;
+                self.device = None;
+            }
+            if ui
+                .selectable_label(self.input, t!("action.discord.select_device.input"))
+                .clicked()
+                && !self.input
+            {
+                self.input = true;
+                self.device = None;
+            }
+        });
+
+        ui.label(t!("action.discord.select_device.device"));
+        let ir = ComboBox::from_id_salt("DiscordSelectDeviceSelect")
+            .selected_text(self.device.clone().map(|d| d.1).unwrap_or_default())
+            .width(200.0)
+            .wrap_mode(TextWrapMode::Truncate)
+            .show_ui(ui, |ui| {
+                let devices = discord_devices();
+                let list = if self.input {
+                    &devices.inputs
+                } else {
+                    &devices.outputs
+                };
+                if list.is_empty() {
+                    ui.label(t!("action.discord.select_device.loading"));
+                }
+                for (id, name) in list {
+                    let selected = self.device.as_ref().is_some_and(|d| d.0 == *id);
+                    let l = ui.selectable_label(selected, name);
+                    if l.clicked() {
+                        self.device = Some((id.clone(), name.clone()));
+                    }
+                }
+            });
+
+        single_fire!(ComboBox::is_open(ui.ctx(), ir.response.id), {
+            tokio::spawn(async move {
+                if let Some(client) = DISCORD_CLIENT.lock().await.as_mut() {
+                    refresh_voice_state(client);
+                }
+            });
+        });
+    }
+
+    pub fn help(&self) -> &str {
+        "action.discord.select_device.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        let Some((id, _)) = &self.device else {
+            return 0;
+        };
+        let devices = discord_devices();
+        let current = if self.input {
+            &devices.input
+        } else {
+            &devices.output
+        };
+        if current.as_ref() == Some(id) {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        // TODO: selected device icons
+        if self.input {
+            &[ICON_MUTE, ICON_MUTE]
+        } else {
+            &[ICON_DEAFEN, ICON_DEAFEN]
+        }
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        2
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[
+            "action.discord.select_device.icon_state_0",
+            "action.discord.select_device.icon_state_1",
+        ]
+    }
+
+    pub fn icon_text(&self) -> Option<String> {
+        self.device.as_ref().map(|d| d.1.clone())
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DiscordVoiceChannel {
+    channel_id: String,
+}
+impl DiscordVoiceChannel {
+    // Joins the channel, or leaves it when we're already in it
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        let channel_id = self.channel_id.trim();
+        if channel_id.is_empty() {
+            return Err(ActionError::new(
+                device_uid,
+                input_key,
+                t!("action.discord.err.no_voice_channel"),
+            ));
+        }
+
+        let mut client = discord_client(config.clone()).await?;
+
+        let joined = DISCORD_VOICE_CHANNEL.lock().unwrap().as_deref() == Some(channel_id);
+        let args = if joined {
+            json!({ "channel_id": null })
+        } else {
+            // Moves us over from whichever channel we're in
+            json!({ "channel_id": channel_id, "force": true })
+        };
+
+        let channel = discord_command(&mut client, "SELECT_VOICE_CHANNEL", args).map_err(|e| {
+            ActionError::new(
+                device_uid,
+                input_key,
+                t!(
+                    "action.discord.err.voice_channel",
+                    channel = channel_id,
+                    error = e
+                ),
+            )
+        })?;
+        *DISCORD_VOICE_CHANNEL.lock().unwrap() = channel["id"].as_str().map(String::from);
+
+        Ok((input_key, true))
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_DISCORD_VOICE_CHANNEL.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        account_warning(ui, config);
+
+        ui.label(t!("action.discord.voice_channel.channel_id"));
+        ui.text_edit_singleline(&mut self.channel_id)
+            .on_hover_text_at_pointer(t!("action.discord.voice_channel.channel_id_help"));
+    }
+
+    pub fn help(&self) -> &str {
+        "action.discord.voice_channel.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        let channel_id = self.channel_id.trim();
+        if !channel_id.is_empty()
+            && DISCORD_VOICE_CHANNEL.lock().unwrap().as_deref() == Some(channel_id)
+        {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        &[ICON_VOICE_CHANNEL_LEFT, ICON_VOICE_CHANNEL_JOINED]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        2
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[
+            "action.discord.voice_channel.icon_state_0",
+            "action.discord.voice_channel.icon_state_1",
+        ]
+    }
+}
+
+// Stands in for the Discord app with a socket of our own, and for its API with a local server
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        io::{Read, Write},
+        os::unix::net::{UnixListener, UnixStream},
+        sync::mpsc,
+    };
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use crate::config::DiscordAccount;
+
+    use super::*;
+
+    const CLIENT_ID: &str = "1234567890";
+
+    // A frame is its opcode and the length of its JSON, both little endian u32s, then the JSON
+    fn read_frame(stream: &mut UnixStream) -> std::io::Result<(u32, Value)> {
+        let mut header = [0u8; 8];
+        stream.read_exact(&mut header)?;
+        let opcode = u32::from_le_bytes(header[..4].try_into().unwrap());
+        let len = u32::from_le_bytes(header[4..].try_into().unwrap());
+
+        let mut data = vec![0u8; len as usize];
+        stream.read_exact(&mut data)?;
+        Ok((opcode, serde_json::from_slice(&data).unwrap()))
+    }
+
+    fn write_frame(stream: &mut UnixStream, opcode: u32, value: Value) {
+        let data = value.to_string();
+        let mut frame = opcode.to_le_bytes().to_vec();
+        frame.extend((data.len() as u32).to_le_bytes());
+        frame.extend(data.as_bytes());
+        stream.write_all(&frame).unwrap();
+    }
+
+    // Answers like the Discord app would, returning the commands it got and the PKCE challenge
+    fn fake_discord(listener: UnixListener) -> (Vec<String>, String) {
+        let (mut stream, _) = listener.accept().unwrap();
+
+        let (opcode, handshake) = read_frame(&mut stream).unwrap();
+        assert_eq!(opcode, 0);
+        assert_eq!(handshake, json!({ "v": 1, "client_id": CLIENT_ID }));
+        write_frame(
+            &mut stream,
+            1,
+            json!({ "cmd": "DISPATCH", "evt": "READY", "data": { "v": 1 } }),
+        );
+
+        let mut commands = Vec::new();
+        let mut challenge = String::new();
+        while let Ok((opcode, request)) = read_frame(&mut stream) {
+            assert_eq!(opcode, 1);
+            let cmd = request["cmd"].as_str().unwrap().to_string();
+            let args = &request["args"];
+            let data = match cmd.as_str() {
+                "AUTHORIZE" => {
+                    assert_eq!(args["client_id"], CLIENT_ID);
+                    assert_eq!(args["code_challenge_method"], "S256");
+                    challenge = args["code_challenge"].as_str().unwrap().into();
+                    json!({ "code": "fake-code" })
+                }
+                "AUTHENTICATE" => {
+                    assert_eq!(args["access_token"], "new-access");
+                    json!({ "user": { "id": "42", "username": "tester" } })
+                }
+                "GET_VOICE_SETTINGS" => json!({ "mute": true, "deaf": false }),
+                "GET_SELECTED_VOICE_CHANNEL" => Value::Null,
+                _ => panic!("unexpected command {}", cmd),
+            };
+            // Events without a nonce get skipped over while waiting on a reply
+            write_frame(
+                &mut stream,
+                1,
+                json!({ "cmd": "DISPATCH", "evt": "SPEAKING_START", "data": {} }),
+            );
+            write_frame(
+                &mut stream,
+                1,
+                json!({ "cmd": cmd, "evt": null, "nonce": request["nonce"], "data": data }),
+            );
+            commands.push(cmd);
+        }
+
+        (commands, challenge)
+    }
+
+    // Turns refreshes down, like Discord does for a revoked grant, and trades codes for tokens.
+    // Sends on the form of every request.
+    async fn fake_token_api(listener: TcpListener, grants: mpsc::Sender<HashMap<String, String>>) {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            let body = loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                assert!(n > 0, "request cut short");
+                request.extend_from_slice(&buf[..n]);
+
+                let text = String::from_utf8_lossy(&request).to_string();
+                let Some(end) = text.find("\r\n\r\n") else {
+                    continue;
+                };
+                let len = text[..end]
+                    .lines()
+                    .find_map(|l| {
+                        let l = l.to_ascii_lowercase();
+                        Some(l.strip_prefix("content-length:")?.trim().parse().unwrap())
+                    })
+                    .unwrap_or(0);
+                if text.len() >= end + 4 + len {
+                    break text[end + 4..end + 4 + len].to_string();
+                }
+            };
+
+            let form: HashMap<String, String> = body
+                .split('&')
+                .filter_map(|p| p.split_once('='))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            let (status, reply) = if form["grant_type"] == "refresh_token" {
+                ("400 Bad Request", json!({ "error": "invalid_grant" }))
+            } else {
+                (
+                    "200 OK",
+                    json!({ "access_token": "new-access", "refresh_token": "new-refresh" }),
+                )
+            };
+            grants.send(form).unwrap();
+
+            let reply = reply.to_string();
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                reply.len(),
+                reply
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    }
+
+    #[test]
+    fn rejected_refresh_authorizes_again() {
+        let dir = std::env::temp_dir().join(format!("jukebox-discord-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // Discord's socket is looked for here, and the config gets saved here
+        std::env::set_var("XDG_RUNTIME_DIR", &dir);
+        std::env::set_var("XDG_CONFIG_HOME", &dir);
+        std::env::set_var("NO_PROXY", "127.0.0.1");
+        std::env::set_var("JUKEBOX_SECRET_STORE", "memory");
+        let _store = crate::secrets::TEST_STORE
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+
+        let socket = dir.join("discord-ipc-0");
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+        let discord = std::thread::spawn(move || fake_discord(listener));
+
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let (grants_tx, grants_rx) = mpsc::channel();
+        rt.block_on(async {
+            let api = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}/api/oauth2/token", api.local_addr().unwrap());
+            TEST_TOKEN_URL.set(url).unwrap();
+            tokio::spawn(fake_token_api(api, grants_tx));
+        });
+
+        let config = Arc::new(Mutex::new(JukeBoxConfig {
+            discord_app: crate::config::DiscordApp {
+                client_id: CLIENT_ID.into(),
+                client_secret: None,
+            },
+            discord_accounts: BTreeMap::from([(
+                "tester".to_string(),
+                DiscordAccount {
+                    oauth: Some(DiscordOauthAccess {
+                        access_token: "old-access".into(),
+                        refresh_token: "old-refresh".into(),
+                    }),
+                },
+            )]),
+            discord_account: Some("tester".into()),
+            ..Default::default()
+        }));
+
+        let client = rt
+            .block_on(create_client(config.clone(), false))
+            .expect("failed to connect to the fake Discord");
+        drop(client);
+        let (commands, challenge) = discord.join().unwrap();
+
+        assert_eq!(
+            commands,
+            [
+                "AUTHORIZE",
+                "AUTHENTICATE",
+                "GET_VOICE_SETTINGS",
+                "GET_SELECTED_VOICE_CHANNEL"
+            ]
+        );
+        assert!(DISCORD_MUTED.load(Ordering::Relaxed));
+
+        // The refresh was turned down, then the code from authorizing was traded with its verifier
+        let refresh = grants_rx.recv().unwrap();
+        assert_eq!(refresh["refresh_token"], "old-refresh");
+        let grant = grants_rx.recv().unwrap();
+        assert_eq!(grant["grant_type"], "authorization_code");
+        assert_eq!(grant["code"], "fake-code");
+        assert_eq!(grant["client_id"], CLIENT_ID);
+        assert_eq!(
+            URL_SAFE_NO_PAD.encode(Sha256::digest(grant["code_verifier"].as_bytes())),
+            challenge
+        );
+
+        let config = rt.block_on(config.lock()).clone();
+        let oauth = config.discord_oauth().unwrap();
+        assert_eq!(oauth.access_token, "new-access");
+        assert_eq!(oauth.refresh_token, "new-refresh");
+        assert_eq!(config.discord_accounts.len(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
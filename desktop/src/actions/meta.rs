@@ -191,6 +191,10 @@ impl MetaSwitchProfile {
     pub fn icon_state_descriptions(&self) -> &[&str] {
         &[""]
     }
+
+    pub fn icon_text(&self) -> Option<String> {
+        Some(self.profile.clone()).filter(|p| !p.is_empty())
+    }
 }
 
 // #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
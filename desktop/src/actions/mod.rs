@@ -2,6 +2,7 @@ pub mod action;
 pub mod discord;
 pub mod input;
 pub mod meta;
+pub mod mqtt;
 pub mod obs;
 pub mod system;
 pub mod types;
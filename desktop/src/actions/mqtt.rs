@@ -0,0 +1,348 @@
+// MQTT actions, for home automation through the broker set up in settings
+
+use std::sync::Arc;
+
+use eframe::egui::{include_image, ComboBox, ImageSource, TextEdit, Ui};
+use egui_phosphor::regular as phos;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::mqtt::{self, MqttError};
+use crate::{config::JukeBoxConfig, input::InputKey};
+
+use super::types::{Action, ActionError};
+
+pub const AID_MQTT_PUBLISH: &str = "MqttPublish";
+pub const AID_MQTT_TOPIC_STATE: &str = "MqttTopicState";
+
+// TODO: make icons for MQTT
+const ICON_PUBLISH: ImageSource = include_image!("../../../assets/action-icons/system-webopen.bmp");
+const ICON_TOPIC_STATE: ImageSource =
+    include_image!("../../../assets/action-icons/system-webopen.bmp");
+
+#[rustfmt::skip]
+pub fn init_actions_mqtt(config: Arc<Mutex<JukeBoxConfig>>) -> (String, Vec<(String, Action, String)>) {
+    // keeps the broker connection up (once one is set up in settings) and follows watched topics
+    mqtt::start_mqtt_service(config);
+
+    (
+        t!("action.mqtt.title", icon = phos::BROADCAST).into(),
+        vec![
+            (AID_MQTT_PUBLISH.into(),     Action::MqttPublish(MqttPublish::default()),       t!("action.mqtt.publish.title").into()),
+            (AID_MQTT_TOPIC_STATE.into(), Action::MqttTopicState(MqttTopicState::default()), t!("action.mqtt.topic_state.title").into()),
+        ],
+    )
+}
+
+fn mqtt_error(device_uid: &str, input_key: InputKey, e: MqttError) -> ActionError {
+    ActionError::new(device_uid, input_key, e.describe())
+}
+
+fn check_topic(device_uid: &str, input_key: InputKey, topic: &str) -> Result<(), ActionError> {
+    if topic.trim().is_empty() {
+        return Err(ActionError::new(
+            device_uid,
+            input_key,
+            t!("action.mqtt.err.no_topic"),
+        ));
+    }
+    Ok(())
+}
+
+// Takes #RRGGBB or r,g,b
+fn parse_color(s: &str) -> Option<(u8, u8, u8)> {
+    let s = s.trim();
+    if let Some(hex) = s.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let c = u32::from_str_radix(hex, 16).ok()?;
+        return Some(((c >> 16) as u8, (c >> 8) as u8, c as u8));
+    }
+
+    let mut parts = s.split(',').map(|p| p.trim().parse::<u8>());
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(r)), Some(Ok(g)), Some(Ok(b)), None) => Some((r, g, b)),
+        _ => None,
+    }
+}
+
+fn color_edit(ui: &mut Ui, color: &mut (u8, u8, u8)) {
+    let mut c = [color.0, color.1, color.2];
+    ui.color_edit_button_srgb(&mut c);
+    *color = (c[0], c[1], c[2]);
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct MqttPublish {
+    topic: String,
+    press_payload: String,
+    release_payload: Option<String>, // None publishes nothing on release
+    retain: bool,
+}
+impl MqttPublish {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        check_topic(device_uid, input_key, &self.topic)?;
+        mqtt::publish(
+            self.topic.trim(),
+            self.press_payload.as_bytes(),
+            self.retain,
+        )
+        .map_err(|e| mqtt_error(device_uid, input_key, e))?;
+        Ok((input_key, false))
+    }
+
+    pub async fn on_release(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        let Some(payload) = &self.release_payload else {
+            return Ok((input_key, false));
+        };
+        check_topic(device_uid, input_key, &self.topic)?;
+        mqtt::publish(self.topic.trim(), payload.as_bytes(), self.retain)
+            .map_err(|e| mqtt_error(device_uid, input_key, e))?;
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_MQTT_PUBLISH.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        ui.label(t!("action.mqtt.topic"));
+        ui.add(TextEdit::singleline(&mut self.topic).hint_text("home/desk/light/set"));
+
+        ui.label(t!("action.mqtt.publish.press_payload"));
+        ui.add(TextEdit::singleline(&mut self.press_payload).hint_text("ON"));
+
+        let mut on_release = self.release_payload.is_some();
+        ui.checkbox(&mut on_release, t!("action.mqtt.publish.on_release"))
+            .on_hover_text_at_pointer(t!("action.mqtt.publish.on_release_help"));
+        if on_release != self.release_payload.is_some() {
+            self.release_payload = on_release.then(String::new);
+        }
+        if let Some(payload) = &mut self.release_payload {
+            ui.add(TextEdit::singleline(payload).hint_text("OFF"));
+        }
+
+        ui.checkbox(&mut self.retain, t!("action.mqtt.publish.retain"))
+            .on_hover_text_at_pointer(t!("action.mqtt.publish.retain_help"));
+    }
+
+    pub fn help(&self) -> &str {
+        "action.mqtt.publish.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        0
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        &[ICON_PUBLISH]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        1
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[]
+    }
+}
+
+// What a topic state does with the key's LED. Only keypads with the Static Per Key RGB mode have
+// LEDs of their own to change.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MqttKeyColor {
+    #[default]
+    Profile, // Left to the RGB profile
+    OnOff {
+        on: (u8, u8, u8),
+        off: (u8, u8, u8),
+    },
+    Payload, // The message is the color, like #FF8000 or 255,128,0
+}
+impl MqttKeyColor {
+    const ALL: [Self; 3] = [
+        Self::Profile,
+        Self::OnOff {
+            on: (0, 255, 0),
+            off: (255, 0, 0),
+        },
+        Self::Payload,
+    ];
+
+    fn title(&self) -> String {
+        match self {
+            Self::Profile => t!("action.mqtt.topic_state.color_profile").into(),
+            Self::OnOff { .. } => t!("action.mqtt.topic_state.color_on_off").into(),
+            Self::Payload => t!("action.mqtt.topic_state.color_payload").into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct MqttTopicState {
+    topic: String, // Can have + and # wildcards
+    on_value: String,
+    off_value: String,
+    command_topic: String, // Empty only shows the state, otherwise a press toggles it through here
+    show_payload: bool,
+    key_color: MqttKeyColor,
+}
+impl Default for MqttTopicState {
+    fn default() -> Self {
+        Self {
+            topic: String::new(),
+            on_value: "ON".into(),
+            off_value: "OFF".into(),
+            command_topic: String::new(),
+            show_payload: false,
+            key_color: MqttKeyColor::default(),
+        }
+    }
+}
+impl MqttTopicState {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        if self.command_topic.trim().is_empty() {
+            return Ok((input_key, false));
+        }
+
+        let payload = if self.is_on() {
+            &self.off_value
+        } else {
+            &self.on_value
+        };
+        mqtt::publish(self.command_topic.trim(), payload.as_bytes(), false)
+            .map_err(|e| mqtt_error(device_uid, input_key, e))?;
+
+        // The icon changes once the state topic answers
+        Ok((input_key, false))
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_MQTT_TOPIC_STATE.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        ui.label(t!("action.mqtt.topic_state.topic"))
+            .on_hover_text_at_pointer(t!("action.mqtt.topic_state.topic_help"));
+        ui.add(TextEdit::singleline(&mut self.topic).hint_text("home/desk/light/state"));
+
+        ui.label(t!("action.mqtt.topic_state.on_value"));
+        ui.add(TextEdit::singleline(&mut self.on_value).hint_text("ON"));
+
+        ui.label(t!("action.mqtt.topic_state.command_topic"))
+            .on_hover_text_at_pointer(t!("action.mqtt.topic_state.command_topic_help"));
+        ui.add(TextEdit::singleline(&mut self.command_topic).hint_text("home/desk/light/set"));
+        if !self.command_topic.trim().is_empty() {
+            ui.label(t!("action.mqtt.topic_state.off_value"));
+            ui.add(TextEdit::singleline(&mut self.off_value).hint_text("OFF"));
+        }
+
+        ui.checkbox(
+            &mut self.show_payload,
+            t!("action.mqtt.topic_state.show_payload"),
+        );
+
+        ui.label(t!("action.mqtt.topic_state.key_color"))
+            .on_hover_text_at_pointer(t!("action.mqtt.topic_state.key_color_help"));
+        ComboBox::from_id_salt("MqttTopicStateKeyColor")
+            .width(200.0)
+            .selected_text(self.key_color.title())
+            .show_ui(ui, |ui| {
+                for c in MqttKeyColor::ALL {
+                    let selected =
+                        std::mem::discriminant(&self.key_color) == std::mem::discriminant(&c);
+                    if ui.selectable_label(selected, c.title()).clicked() && !selected {
+                        self.key_color = c;
+                    }
+                }
+            });
+        if let MqttKeyColor::OnOff { on, off } = &mut self.key_color {
+            ui.horizontal(|ui| {
+                ui.label(t!("action.mqtt.topic_state.color_on"));
+                color_edit(ui, on);
+                ui.label(t!("action.mqtt.topic_state.color_off"));
+                color_edit(ui, off);
+            });
+        }
+    }
+
+    pub fn help(&self) -> &str {
+        "action.mqtt.topic_state.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        self.is_on() as u8
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        // TODO: make icons for on and off
+        &[ICON_TOPIC_STATE, ICON_TOPIC_STATE]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        2
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[
+            "action.mqtt.topic_state.icon_state_0",
+            "action.mqtt.topic_state.icon_state_1",
+        ]
+    }
+
+    pub fn icon_text(&self) -> Option<String> {
+        if !self.show_payload {
+            return None;
+        }
+        mqtt::watch(self.topic.trim())
+    }
+
+    pub fn key_color(&self) -> Option<(u8, u8, u8)> {
+        match self.key_color {
+            MqttKeyColor::Profile => None,
+            MqttKeyColor::OnOff { on, off } => Some(if self.is_on() { on } else { off }),
+            MqttKeyColor::Payload => mqtt::watch(self.topic.trim()).and_then(|p| parse_color(&p)),
+        }
+    }
+
+    fn is_on(&self) -> bool {
+        mqtt::watch(self.topic.trim()).is_some_and(|p| p.trim() == self.on_value.trim())
+    }
+}
@@ -1,30 +1,54 @@
 use std::{
+    collections::HashMap,
+    future::Future,
+    net::Ipv4Addr,
     sync::{Arc, OnceLock},
     time::Duration,
 };
 
-use eframe::egui::{include_image, ComboBox, ImageSource, RichText, TextEdit, Ui};
+use eframe::egui::{
+    include_image, Button, ComboBox, Context, ImageSource, InnerResponse, RichText, Slider,
+    TextEdit, Ui,
+};
 use egui_phosphor::regular as phos;
+use futures::{pin_mut, StreamExt};
+use jukebox_util::toast::{Toast, ToastIcon};
 use obws::{
     client::{ConnectConfig, DEFAULT_BROADCAST_CAPACITY},
-    requests::{inputs::InputId, scene_items::SetEnabled, scenes::SceneId},
-    responses::{inputs::Input, scene_items::SceneItem, scenes::Scene},
+    common::MediaAction,
+    events::{Event, OutputState},
+    requests::{
+        filters::SetEnabled as SetFilterEnabled,
+        inputs::{InputId, Volume},
+        scene_items::SetEnabled,
+        scenes::SceneId,
+        sources::{SaveScreenshot, SourceId},
+        EventSubscription,
+    },
+    responses::{inputs::Input, media_inputs::MediaState, scene_items::SceneItem, scenes::Scene},
     Client,
 };
+use rfd::FileDialog;
 use serde::{Deserialize, Serialize};
 use tokio::{
-    runtime::Handle,
-    sync::{Mutex, MutexGuard},
+    sync::{Mutex, OwnedMutexGuard},
+    task::JoinHandle,
+    time::{sleep, timeout},
 };
 use uuid::Uuid;
 
 use crate::{
-    config::{JukeBoxConfig, ObsAccess},
+    config::{JukeBoxConfig, ObsAccess, TlsTrust, DEFAULT_OBS_CONNECTION, DEFAULT_OBS_TIMEOUT},
     input::InputKey,
     single_fire,
+    tls::{start_relay, tcp_connect, TlsError},
+    toast::{show_toast, ToastSource, TOAST_COLOR_INFO, TOAST_COLOR_WARNING},
 };
 
-use super::types::{Action, ActionError};
+use super::{
+    action::notify_icon_states_changed,
+    types::{Action, ActionError},
+};
 
 pub const AID_OBS_STREAM: &str = "ObsStream";
 pub const AID_OBS_RECORD: &str = "ObsRecord";
@@ -38,7 +62,17 @@ pub const AID_OBS_PREVIEW_SWITCH: &str = "ObsPreviewSwitch";
 pub const AID_OBS_PREVIEW_PUSH: &str = "ObsPreviewPush";
 pub const AID_OBS_COLLECTION_SWITCH: &str = "ObsCollectionSwitch";
 pub const AID_OBS_CHAPTER_MARKER: &str = "ObsChapterMarker";
-
+pub const AID_OBS_TOGGLE_FILTER: &str = "ObsFilter";
+pub const AID_OBS_TRANSITION_SWITCH: &str = "ObsTransition";
+pub const AID_OBS_STUDIO_TRANSITION: &str = "ObsStudioTransition";
+pub const AID_OBS_STUDIO_MODE: &str = "ObsStudioMode";
+pub const AID_OBS_INPUT_VOLUME: &str = "ObsInputVolume";
+pub const AID_OBS_SCREENSHOT: &str = "ObsScreenshot";
+pub const AID_OBS_VIRTUAL_CAM: &str = "ObsVirtualCam";
+pub const AID_OBS_HOTKEY: &str = "ObsHotkey";
+pub const AID_OBS_MEDIA: &str = "ObsMedia";
+
+// TODO: make "active" variants, the two-state actions use the same icon for both for now
 const ICON_STREAM: ImageSource = include_image!("../../../assets/action-icons/obs-stream.bmp");
 const ICON_RECORD: ImageSource = include_image!("../../../assets/action-icons/obs-record.bmp");
 const ICON_PAUSE_RECORD: ImageSource =
@@ -59,38 +93,212 @@ const ICON_SWITCH_COLLECTION: ImageSource =
     include_image!("../../../assets/action-icons/obs-collectionswitch.bmp");
 const ICON_CHAPTER_MARKER: ImageSource =
     include_image!("../../../assets/action-icons/obs-chaptermarker.bmp");
+// TODO: make new icons for filters, screenshots, the virtual camera, hotkeys and media
+const ICON_BASE: ImageSource = include_image!("../../../assets/action-icons/obs-base.bmp");
 
-static OBS_HOST_ADDRESS: OnceLock<Mutex<String>> = OnceLock::new();
-static OBS_HOST_PORT: OnceLock<Mutex<String>> = OnceLock::new();
-static OBS_PASSWORD: OnceLock<Mutex<String>> = OnceLock::new();
-static OBS_CLIENT: OnceLock<Mutex<Option<Client>>> = OnceLock::new();
+// Connection Name -> Client, None while that OBS is disconnected. Each connection has its own lock,
+// so one slow OBS doesn't hold up actions on the others.
+static OBS_CLIENTS: OnceLock<Mutex<HashMap<String, Arc<Mutex<Option<Client>>>>>> = OnceLock::new();
+static OBS_SETUP: OnceLock<Mutex<ObsSetup>> = OnceLock::new();
 
 static OBS_SCENES: OnceLock<Mutex<Option<Vec<Scene>>>> = OnceLock::new();
 static OBS_SOURCES: OnceLock<Mutex<Option<Vec<SceneItem>>>> = OnceLock::new();
 static OBS_INPUTS: OnceLock<Mutex<Option<Vec<Input>>>> = OnceLock::new();
 static OBS_SCENE_COLLECTIONS: OnceLock<Mutex<Option<Vec<String>>>> = OnceLock::new();
+static OBS_FILTERS: OnceLock<Mutex<Option<Vec<String>>>> = OnceLock::new();
+static OBS_TRANSITIONS: OnceLock<Mutex<Option<Vec<String>>>> = OnceLock::new();
+static OBS_HOTKEYS: OnceLock<Mutex<Option<Vec<String>>>> = OnceLock::new();
+
+// How long the event listener waits before trying OBS again, doubling while it stays away
+const EVENT_RETRY_MIN: Duration = Duration::from_secs(1);
+const EVENT_RETRY_MAX: Duration = Duration::from_secs(30);
+// How often connected clients get pinged, the ones that don't answer are dropped and reconnected
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+// What an OBS is currently doing, kept up to date by its event listener. Read by `icon_state`,
+// which isn't async, hence the std mutex.
+#[derive(Default)]
+struct ObsState {
+    streaming: bool,
+    recording: bool,
+    record_paused: bool,
+    replay_buffer: bool,
+    virtual_cam: bool,
+    studio_mode: bool,
+    program_scene: Option<Uuid>,
+    preview_scene: Option<Uuid>,
+    scene_items: HashMap<(Uuid, i64), bool>, // (scene, item) -> visible
+    inputs_muted: HashMap<Uuid, bool>,
+}
+
+// Connection Name -> State
+static OBS_STATES: OnceLock<std::sync::Mutex<HashMap<String, ObsState>>> = OnceLock::new();
+
+fn obs_states<'a>() -> std::sync::MutexGuard<'a, HashMap<String, ObsState>> {
+    OBS_STATES
+        .get_or_init(|| std::sync::Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap()
+}
+
+// Reads part of a connection's state, or the default while nothing is known about it
+fn obs_state<R: Default>(connection: &Option<String>, f: impl FnOnce(&ObsState) -> R) -> R {
+    obs_states()
+        .get(connection_name(connection))
+        .map(f)
+        .unwrap_or_default()
+}
+
+// What the connection form in the action editor is showing
+struct ObsSetup {
+    connection: String,
+    new_connection: String,
+    host: String,
+    port: String,
+    password: String,
+    tls: Option<TlsTrust>,
+    timeout_ms: u32,
+    status: Option<String>, // Outcome of the last connect or test
+    busy: bool,             // A connect or test is still waiting on OBS
+}
+impl ObsSetup {
+    fn new(connection: &str, access: Option<&ObsAccess>) -> Self {
+        match access {
+            Some(a) => Self {
+                connection: connection.into(),
+                new_connection: String::new(),
+                host: a.host.clone(),
+                port: a.port.to_string(),
+                password: a.password.clone().unwrap_or_default(),
+                tls: a.tls.clone(),
+                timeout_ms: a.timeout_ms,
+                status: None,
+                busy: false,
+            },
+            None => Self {
+                connection: connection.into(),
+                new_connection: String::new(),
+                host: "localhost".into(),
+                port: "4455".into(),
+                password: String::new(),
+                tls: None,
+                timeout_ms: DEFAULT_OBS_TIMEOUT,
+                status: None,
+                busy: false,
+            },
+        }
+    }
+
+    fn access(&self) -> Option<ObsAccess> {
+        Some(ObsAccess {
+            host: self.host.clone(),
+            port: self.port.parse().ok()?,
+            password: if self.password.is_empty() {
+                None
+            } else {
+                Some(self.password.clone())
+            },
+            tls: self.tls.clone(),
+            timeout_ms: self.timeout_ms,
+        })
+    }
+}
+
+// Why a connection to OBS couldn't be made, worded so the user knows what to fix
+#[derive(Debug)]
+enum ObsConnectError {
+    NotSetUp,
+    Network(TlsError),
+    Obs(obws::error::Error),
+}
+impl ObsConnectError {
+    fn describe(&self, connection: &str) -> String {
+        match self {
+            Self::NotSetUp => t!("action.obs.err.not_set_up", connection = connection).into(),
+            Self::Network(TlsError::Timeout) => {
+                t!("action.obs.err.timeout", connection = connection).into()
+            }
+            Self::Network(TlsError::Refused) => {
+                t!("action.obs.err.refused", connection = connection).into()
+            }
+            Self::Network(TlsError::Unreachable(e)) => t!(
+                "action.obs.err.unreachable",
+                connection = connection,
+                error = e
+            )
+            .into(),
+            Self::Network(TlsError::CaFile(e)) => t!("action.obs.err.ca_file", error = e).into(),
+            Self::Network(TlsError::Untrusted(e)) => t!(
+                "action.obs.err.untrusted",
+                connection = connection,
+                error = e
+            )
+            .into(),
+            Self::Network(TlsError::FingerprintChanged { expected, actual }) => t!(
+                "action.obs.err.fingerprint_changed",
+                connection = connection,
+                expected = expected,
+                actual = actual
+            )
+            .into(),
+            Self::Network(TlsError::Handshake(e)) => {
+                t!("action.obs.err.tls", connection = connection, error = e).into()
+            }
+            Self::Obs(e) => t!(
+                "action.obs.err.handshake",
+                connection = connection,
+                error = e.to_string()
+            )
+            .into(),
+        }
+    }
+}
+
+// Actions without a connection use the default one
+fn connection_name(connection: &Option<String>) -> &str {
+    connection.as_deref().unwrap_or(DEFAULT_OBS_CONNECTION)
+}
+
+async fn client_slot(connection: &str) -> Arc<Mutex<Option<Client>>> {
+    OBS_CLIENTS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .await
+        .entry(connection.into())
+        .or_insert_with(|| Arc::new(Mutex::new(None)))
+        .clone()
+}
+
+fn client_slot_blocking(connection: &str) -> Arc<Mutex<Option<Client>>> {
+    OBS_CLIENTS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .blocking_lock()
+        .entry(connection.into())
+        .or_insert_with(|| Arc::new(Mutex::new(None)))
+        .clone()
+}
 
 #[rustfmt::skip]
 pub fn init_actions_obs(config: Arc<Mutex<JukeBoxConfig>>) -> (String, Vec<(String, Action, String)>) {
-    let c = config.blocking_lock().obs_access.clone();
-    let (host, port, password) = if let Some(obs_access) = &c {
-        (obs_access.host.clone(), obs_access.port.to_string(), obs_access.password.clone().unwrap_or_default())
-    } else {
-        ("localhost".to_string(), "4455".to_string(), "".to_string())
-    };
+    {
+        let c = config.blocking_lock();
+        let access = c.obs_connections.get(DEFAULT_OBS_CONNECTION);
+        OBS_SETUP.get_or_init(|| Mutex::new(ObsSetup::new(DEFAULT_OBS_CONNECTION, access)));
+    }
 
-    OBS_HOST_ADDRESS.get_or_init(|| Mutex::new(host));
-    OBS_HOST_PORT.get_or_init(|| Mutex::new(port));
-    OBS_PASSWORD.get_or_init(|| Mutex::new(password));
-    
     OBS_SCENES.get_or_init(|| Mutex::new(None));
     OBS_SOURCES.get_or_init(|| Mutex::new(None));
     OBS_INPUTS.get_or_init(|| Mutex::new(None));
     OBS_SCENE_COLLECTIONS.get_or_init(|| Mutex::new(None));
+    OBS_FILTERS.get_or_init(|| Mutex::new(None));
+    OBS_TRANSITIONS.get_or_init(|| Mutex::new(None));
+    OBS_HOTKEYS.get_or_init(|| Mutex::new(None));
 
-    // init obs websocket (if we have a saved config for it)
+    // init obs websockets (and look for a local OBS if none are saved yet)
+    tokio::runtime::Handle::current()
+        .spawn(async move { obs_connection_task(config).await });
     tokio::runtime::Handle::current()
-        .spawn(async move { create_client(config).await });
+        .spawn(async move { stream_toast_task().await });
 
     (
         t!("action.obs.title", icon = phos::VINYL_RECORD).into(),
@@ -106,176 +314,840 @@ pub fn init_actions_obs(config: Arc<Mutex<JukeBoxConfig>>) -> (String, Vec<(Stri
             (AID_OBS_PREVIEW_SWITCH.into(),     Action::ObsPreviewSceneSwitch(ObsPreviewSceneSwitch::default()),       t!("action.obs.switch_preview_scene.title").into()),
             (AID_OBS_PREVIEW_PUSH.into(),       Action::ObsPreviewScenePush(ObsPreviewScenePush::default()),           t!("action.obs.push_preview_scene.title").into()),
             (AID_OBS_COLLECTION_SWITCH.into(),  Action::ObsSceneCollectionSwitch(ObsSceneCollectionSwitch::default()), t!("action.obs.switch_scene_collection.title").into()),
-            // ("ObsFilter".into(),                Action::ObsFilter(ObsFilter::default()),                               t!("action.obs.toggle_filter.title").into()),
-            // ("ObsTransition".into(),            Action::ObsTransition(ObsTransition::default()),                       t!("action.obs.switch_transition.title").into()),
-            // TODO: Source Screenshot?
+            (AID_OBS_TOGGLE_FILTER.into(),      Action::ObsFilter(ObsFilter::default()),                               t!("action.obs.toggle_filter.title").into()),
+            (AID_OBS_TRANSITION_SWITCH.into(),  Action::ObsTransition(ObsTransition::default()),                       t!("action.obs.switch_transition.title").into()),
+            (AID_OBS_STUDIO_TRANSITION.into(),  Action::ObsStudioTransition(ObsStudioTransition::default()),           t!("action.obs.studio_transition.title").into()),
+            (AID_OBS_STUDIO_MODE.into(),        Action::ObsStudioMode(ObsStudioMode::default()),                       t!("action.obs.toggle_studio_mode.title").into()),
+            (AID_OBS_INPUT_VOLUME.into(),       Action::ObsInputVolume(ObsInputVolume::default()),                     t!("action.obs.input_volume.title").into()),
+            (AID_OBS_SCREENSHOT.into(),         Action::ObsScreenshot(ObsScreenshot::default()),                       t!("action.obs.save_screenshot.title").into()),
+            (AID_OBS_VIRTUAL_CAM.into(),        Action::ObsVirtualCam(ObsVirtualCam::default()),                       t!("action.obs.toggle_virtual_cam.title").into()),
+            (AID_OBS_HOTKEY.into(),             Action::ObsHotkey(ObsHotkey::default()),                               t!("action.obs.trigger_hotkey.title").into()),
+            (AID_OBS_MEDIA.into(),              Action::ObsMedia(ObsMedia::default()),                                 t!("action.obs.media_control.title").into()),
             (AID_OBS_CHAPTER_MARKER.into(),     Action::ObsChapterMarker(ObsChapterMarker::default()),                 t!("action.obs.add_chapter_marker.title").into()),
         ],
     )
 }
 
-async fn create_client<'a>(
+// Connects with a connection's saved access
+async fn create_client(
     config: Arc<Mutex<JukeBoxConfig>>,
-) -> Result<MutexGuard<'a, Option<Client>>, ()> {
-    let client_config = {
-        let c = config.lock().await.clone();
+    connection: &str,
+) -> Result<OwnedMutexGuard<Option<Client>>, ObsConnectError> {
+    let access = config
+        .lock()
+        .await
+        .obs_connections
+        .get(connection)
+        .cloned()
+        .ok_or(ObsConnectError::NotSetUp)?;
+    connect_client(config, connection, access).await
+}
 
-        let config = if let Some(o) = c.obs_access {
-            o
-        } else {
-            let pw = OBS_PASSWORD.get().unwrap().lock().await.clone();
-            let password = if pw.len() == 0 { None } else { Some(pw) };
-            ObsAccess {
-                host: OBS_HOST_ADDRESS.get().unwrap().lock().await.clone(),
-                port: OBS_HOST_PORT
-                    .get()
-                    .unwrap()
-                    .lock()
-                    .await
-                    .clone()
-                    .parse()
-                    .expect("cannot parse port"),
-                password,
+// Opens a client without storing it anywhere. A first TLS connection pins the certificate it sees.
+async fn open_client(access: &mut ObsAccess) -> Result<Client, ObsConnectError> {
+    let wait = Duration::from_millis(access.timeout_ms as u64);
+
+    let (host, port) = match &access.tls {
+        None => {
+            // obws only says that it failed, this finds out why
+            tcp_connect(&access.host, access.port, wait)
+                .await
+                .map_err(ObsConnectError::Network)?;
+            (access.host.clone(), access.port)
+        }
+        Some(trust) => {
+            let pin = *trust == TlsTrust::Pinned(None);
+            let relay = start_relay(&access.host, access.port, trust, wait)
+                .await
+                .map_err(ObsConnectError::Network)?;
+            if pin {
+                access.tls = Some(TlsTrust::Pinned(Some(relay.fingerprint)));
             }
-        };
-
-        ConnectConfig {
-            host: config.host,
-            port: config.port,
-            dangerous: None,
-            password: config.password,
-            event_subscriptions: None, // TODO: subscribe for kicked/disconnected events?
-            // tls: false,
-            broadcast_capacity: DEFAULT_BROADCAST_CAPACITY,
-            connect_timeout: Duration::from_millis(250),
-            // NOTE: we're using a pretty low connection timeout time here because of UI reasons.
-            // In the future, we should increase this for high latency environments.
-            // (At the cost of frames)
+            (Ipv4Addr::LOCALHOST.to_string(), relay.port)
         }
     };
 
-    let obs_access = ObsAccess {
-        host: client_config.host.clone(),
-        port: client_config.port,
-        password: client_config.password.clone(),
+    let client_config = ConnectConfig {
+        host,
+        port,
+        dangerous: None,
+        password: access.password.clone(),
+        event_subscriptions: Some(EventSubscription::ALL),
+        broadcast_capacity: DEFAULT_BROADCAST_CAPACITY,
+        connect_timeout: wait,
     };
 
-    let client = Client::connect_with_config(client_config)
+    Client::connect_with_config(client_config)
         .await
-        .map_err(|_| ())?;
+        .map_err(ObsConnectError::Obs)
+}
+
+// Connects and saves the access under the connection's name
+async fn connect_client(
+    config: Arc<Mutex<JukeBoxConfig>>,
+    connection: &str,
+    mut access: ObsAccess,
+) -> Result<OwnedMutexGuard<Option<Client>>, ObsConnectError> {
+    let client = open_client(&mut access).await?;
 
     {
         let mut config = config.lock().await;
-        config.obs_access = Some(obs_access);
-        config.save();
+        if config.obs_connections.get(connection) != Some(&access) {
+            config.obs_connections.insert(connection.into(), access);
+            config.save();
+        }
     }
 
-    if OBS_CLIENT.get().is_none() {
-        let _ = OBS_CLIENT.set(Mutex::new(Some(client)));
-    } else {
-        let mut c = OBS_CLIENT.get().unwrap().lock().await;
-        *c = Some(client);
+    let mut slot = client_slot(connection).await.lock_owned().await;
+    *slot = Some(client);
+
+    Ok(slot)
+}
+
+// Connects without saving anything and reports the OBS version, for the test button
+async fn test_connection(mut access: ObsAccess) -> Result<String, ObsConnectError> {
+    let mut client = open_client(&mut access).await?;
+    let version = client
+        .general()
+        .version()
+        .await
+        .map_err(ObsConnectError::Obs)?;
+    client.disconnect().await;
+
+    let mut res = t!(
+        "action.obs.setup.test_success",
+        obs = version.obs_version.to_string(),
+        websocket = version.obs_web_socket_version.to_string()
+    )
+    .to_string();
+    if let Some(TlsTrust::Pinned(Some(fingerprint))) = access.tls {
+        res.push('\n');
+        res.push_str(&t!("action.obs.setup.pinned", fingerprint = fingerprint));
+    }
+    Ok(res)
+}
+
+// Keeps an event listener running for every saved connection and drops clients that stop answering
+async fn obs_connection_task(config: Arc<Mutex<JukeBoxConfig>>) {
+    let mut listeners: HashMap<String, JoinHandle<()>> = HashMap::new();
+
+    // Nothing saved yet, see if there's an OBS running locally with the default settings
+    if config.lock().await.obs_connections.is_empty() {
+        let access = ObsSetup::new(DEFAULT_OBS_CONNECTION, None)
+            .access()
+            .unwrap();
+        let _ = connect_client(config.clone(), DEFAULT_OBS_CONNECTION, access).await;
+    }
+
+    loop {
+        let connections = config
+            .lock()
+            .await
+            .obs_connections
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for connection in &connections {
+            if listeners.get(connection).is_none_or(|l| l.is_finished()) {
+                let config = config.clone();
+                let name = connection.clone();
+                listeners.insert(
+                    connection.clone(),
+                    tokio::spawn(async move { obs_event_task(config, name).await }),
+                );
+            }
+        }
+
+        // Forgotten connections
+        let mut forgotten = Vec::new();
+        listeners.retain(|name, listener| {
+            let keep = connections.contains(name);
+            if !keep {
+                listener.abort();
+                forgotten.push(name.clone());
+            }
+            keep
+        });
+        if !forgotten.is_empty() {
+            let mut clients = OBS_CLIENTS
+                .get_or_init(|| Mutex::new(HashMap::new()))
+                .lock()
+                .await;
+            let mut states = obs_states();
+            for name in forgotten {
+                clients.remove(&name);
+                states.remove(&name);
+            }
+            drop(states);
+            notify_icon_states_changed();
+        }
+
+        for connection in &connections {
+            let wait = config
+                .lock()
+                .await
+                .obs_connections
+                .get(connection)
+                .map_or(DEFAULT_OBS_TIMEOUT, |a| a.timeout_ms);
+            let slot = client_slot(connection).await;
+            let mut client = slot.lock().await;
+            // An OBS that hangs instead of hanging up would otherwise hold the slot forever
+            let healthy = match client.as_ref() {
+                Some(c) => timeout(Duration::from_millis(wait as u64), c.general().version())
+                    .await
+                    .is_ok_and(|r| r.is_ok()),
+                None => true,
+            };
+            if !healthy {
+                log::warn!("obs connection {} stopped responding", connection);
+                *client = None; // ends the event stream, so the listener reconnects
+            }
+        }
+
+        sleep(HEALTH_CHECK_INTERVAL).await;
     }
+}
+
+// Follows one OBS's events so keys show what it's doing, including changes made in OBS itself.
+// Reconnects on its own when OBS restarts, backing off while it's gone.
+async fn obs_event_task(config: Arc<Mutex<JukeBoxConfig>>, connection: String) {
+    let mut retry = EVENT_RETRY_MIN;
+    let slot = client_slot(&connection).await;
+
+    loop {
+        let connected = slot.lock().await.is_some();
+        if !connected && create_client(config.clone(), &connection).await.is_err() {
+            sleep(retry).await;
+            retry = (retry * 2).min(EVENT_RETRY_MAX);
+            continue;
+        }
+
+        let events = {
+            let client = slot.lock().await;
+            let Some(events) = client.as_ref().and_then(|c| c.events().ok()) else {
+                drop(client);
+                sleep(retry).await;
+                retry = (retry * 2).min(EVENT_RETRY_MAX);
+                continue;
+            };
+            sync_obs_state(client.as_ref().unwrap(), &connection, &config).await;
+            events
+        };
+        retry = EVENT_RETRY_MIN;
+        notify_icon_states_changed();
+
+        pin_mut!(events);
+        while let Some(event) = events.next().await {
+            if update_obs_state(&connection, event) {
+                notify_icon_states_changed();
+            }
+        }
 
-    Ok(OBS_CLIENT.get().unwrap().lock().await)
+        // The stream ends when the connection does, unless the client was swapped out under us
+        {
+            let mut client = slot.lock().await;
+            let alive = match client.as_ref() {
+                Some(c) => c.general().version().await.is_ok(),
+                None => false,
+            };
+            if !alive {
+                *client = None;
+            }
+        }
+        obs_states().remove(&connection);
+        notify_icon_states_changed();
+    }
 }
 
-fn account_warning(ui: &mut Ui, config: Arc<Mutex<JukeBoxConfig>>) -> Option<()> {
-    if OBS_HOST_ADDRESS.get().is_none()
-        && OBS_HOST_PORT.get().is_none()
-        && OBS_PASSWORD.get().is_none()
-    {
-        let c = config.blocking_lock().clone();
-        if let Some(c) = c.obs_access {
-            OBS_HOST_ADDRESS.get_or_init(|| Mutex::new(c.host));
-            OBS_HOST_PORT.get_or_init(|| Mutex::new(c.port.to_string()));
-            OBS_PASSWORD.get_or_init(|| Mutex::new(c.password.unwrap_or("".into())));
-        } else {
-            OBS_HOST_ADDRESS.get_or_init(|| Mutex::new("localhost".into()));
-            OBS_HOST_PORT.get_or_init(|| Mutex::new("4455".into()));
-            OBS_PASSWORD.get_or_init(|| Mutex::new("".into()));
+// Asks OBS for everything the events would tell us, for when we've just (re)connected
+async fn sync_obs_state(client: &Client, connection: &str, config: &Arc<Mutex<JukeBoxConfig>>) {
+    // Every profile, so switching profiles doesn't leave keys without a state
+    let actions = {
+        let c = config.lock().await;
+        c.profiles
+            .values()
+            .flat_map(|p| p.values())
+            .flat_map(|d| d.key_map.values())
+            .map(|a| a.action.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let mut state = ObsState::default();
+
+    if let Ok(s) = client.streaming().status().await {
+        state.streaming = s.active;
+    }
+    if let Ok(s) = client.recording().status().await {
+        state.recording = s.active;
+        state.record_paused = s.paused;
+    }
+    if let Ok(active) = client.replay_buffer().status().await {
+        state.replay_buffer = active;
+    }
+    if let Ok(active) = client.virtual_cam().status().await {
+        state.virtual_cam = active;
+    }
+    if let Ok(enabled) = client.ui().studio_mode_enabled().await {
+        state.studio_mode = enabled;
+    }
+    if let Ok(scenes) = client.scenes().list().await {
+        state.program_scene = scenes.current_program_scene.map(|s| s.uuid);
+        state.preview_scene = scenes.current_preview_scene.map(|s| s.uuid);
+    }
+
+    for action in actions {
+        match action {
+            Action::ObsSource(ObsSource {
+                connection: c,
+                scene: Some(scene),
+                source: Some(source),
+            }) if connection_name(&c) == connection => {
+                let scene_id = SceneId::Uuid(scene.0);
+                if let Ok(e) = client.scene_items().enabled(scene_id, source.0).await {
+                    state.scene_items.insert((scene.0, source.0), e);
+                }
+            }
+            Action::ObsMute(ObsMute {
+                connection: c,
+                input: Some(input),
+            }) if connection_name(&c) == connection => {
+                if let Ok(m) = client.inputs().muted(InputId::Uuid(input.0)).await {
+                    state.inputs_muted.insert(input.0, m);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    obs_states().insert(connection.into(), state);
+}
+
+// True if the event changed anything an icon depends on
+fn update_obs_state(connection: &str, event: Event) -> bool {
+    let mut states = obs_states();
+    let state = states.entry(connection.into()).or_default();
+    match event {
+        Event::StreamStateChanged { active, .. } => state.streaming = active,
+        Event::RecordStateChanged {
+            active,
+            state: output_state,
+            ..
+        } => {
+            state.recording = active;
+            state.record_paused = matches!(output_state, OutputState::Paused);
+        }
+        Event::ReplayBufferStateChanged { active, .. } => state.replay_buffer = active,
+        Event::VirtualcamStateChanged { active, .. } => state.virtual_cam = active,
+        Event::StudioModeStateChanged { enabled } => state.studio_mode = enabled,
+        Event::CurrentProgramSceneChanged { id } => state.program_scene = Some(id.uuid),
+        Event::CurrentPreviewSceneChanged { id } => state.preview_scene = Some(id.uuid),
+        Event::SceneItemEnableStateChanged {
+            scene,
+            item_id,
+            enabled,
+        } => {
+            state
+                .scene_items
+                .insert((scene.uuid, item_id as i64), enabled);
+        }
+        Event::InputMuteStateChanged { id, muted } => {
+            state.inputs_muted.insert(id.uuid, muted);
+        }
+        _ => return false,
+    }
+    true
+}
+
+fn obs_toast(connection: &str, body: &str, icon: ToastIcon, color: u16) {
+    // Only worth saying which OBS it was when there's more than one
+    let title = if connection == DEFAULT_OBS_CONNECTION {
+        t!("toast.obs.title")
+    } else {
+        t!("toast.obs.title_connection", connection = connection)
+    };
+    show_toast(ToastSource::Obs, Toast::new(&title, body, icon, 0, color));
+}
+
+#[derive(Default)]
+struct StreamToastState {
+    active: Option<bool>,
+    reconnecting: bool,
+    skipped_frames: u32,
+}
+
+// Polls stream statuses so the keypad can say when a stream starts, stops or drops frames
+async fn stream_toast_task() {
+    let mut states: HashMap<String, StreamToastState> = HashMap::new();
+
+    loop {
+        sleep(Duration::from_secs(5)).await;
+
+        let slots = match OBS_CLIENTS.get() {
+            Some(clients) => clients.lock().await.clone(),
+            None => continue,
+        };
+
+        for (connection, slot) in slots {
+            let status = match slot.lock().await.as_ref() {
+                Some(client) => client.streaming().status().await,
+                None => continue,
+            };
+            let last = states.entry(connection.clone()).or_default();
+            let Ok(status) = status else {
+                // OBS went away, don't toast about whatever it's doing when it comes back
+                last.active = None;
+                continue;
+            };
+
+            if last.active.is_some_and(|a| a != status.active) {
+                if status.active {
+                    obs_toast(
+                        &connection,
+                        &t!("toast.obs.stream_started"),
+                        ToastIcon::Info,
+                        TOAST_COLOR_INFO,
+                    );
+                } else {
+                    obs_toast(
+                        &connection,
+                        &t!("toast.obs.stream_stopped"),
+                        ToastIcon::Info,
+                        TOAST_COLOR_INFO,
+                    );
+                }
+                last.skipped_frames = 0;
+            }
+
+            if status.reconnecting && !last.reconnecting {
+                obs_toast(
+                    &connection,
+                    &t!("toast.obs.stream_reconnecting"),
+                    ToastIcon::Warning,
+                    TOAST_COLOR_WARNING,
+                );
+            }
+
+            if status.active && status.skipped_frames > last.skipped_frames {
+                obs_toast(
+                    &connection,
+                    &t!(
+                        "toast.obs.dropped_frames",
+                        count = status.skipped_frames - last.skipped_frames
+                    ),
+                    ToastIcon::Warning,
+                    TOAST_COLOR_WARNING,
+                );
+            }
+
+            last.active = Some(status.active);
+            last.reconnecting = status.reconnecting;
+            last.skipped_frames = status.skipped_frames;
         }
     }
+}
+
+fn account_warning(
+    ui: &mut Ui,
+    connection: &mut Option<String>,
+    config: Arc<Mutex<JukeBoxConfig>>,
+) -> Option<()> {
+    let connections = config
+        .blocking_lock()
+        .obs_connections
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    let name = connection_name(connection).to_string();
+
+    ui.label(t!("action.obs.connection.select"));
+    ui.horizontal(|ui| {
+        ComboBox::from_id_salt("ObsConnectionSelect")
+            .width(160.0)
+            .selected_text(name.as_str())
+            .show_ui(ui, |ui| {
+                // The default connection is always there to pick, even before it's set up
+                let names = std::iter::once(DEFAULT_OBS_CONNECTION).chain(
+                    connections
+                        .iter()
+                        .map(|c| c.as_str())
+                        .filter(|c| *c != DEFAULT_OBS_CONNECTION),
+                );
+                for n in names {
+                    if ui.selectable_label(n == name, n).clicked() {
+                        *connection = if n == DEFAULT_OBS_CONNECTION {
+                            None
+                        } else {
+                            Some(n.into())
+                        };
+                    }
+                }
+            });
 
-    let o = config.blocking_lock().obs_access.clone();
-    if OBS_CLIENT.get().is_none() && o.is_some() {
-        let c = config.clone();
-        let res = Handle::current().block_on(async { create_client(c).await });
-        if let Err(_) = res {
-            let config = config.clone();
+        if name != DEFAULT_OBS_CONNECTION
+            && connections.contains(&name)
+            && ui
+                .button(RichText::new(phos::TRASH))
+                .on_hover_text_at_pointer(t!("action.obs.connection.forget"))
+                .clicked()
+        {
             let mut c = config.blocking_lock();
-            c.obs_access = None;
+            c.obs_connections.remove(&name);
             c.save();
+            *connection = None;
         }
-    }
+    });
+    ui.horizontal(|ui| {
+        let mut setup = OBS_SETUP.get().unwrap().blocking_lock();
+        ui.add(
+            TextEdit::singleline(&mut setup.new_connection)
+                .hint_text(t!("action.obs.connection.new_hint"))
+                .desired_width(160.0),
+        );
+        let new_connection = setup.new_connection.trim().to_string();
+        if ui
+            .add_enabled(
+                !new_connection.is_empty(),
+                Button::new(RichText::new(phos::PLUS)),
+            )
+            .on_hover_text_at_pointer(t!("action.obs.connection.add"))
+            .clicked()
+        {
+            *connection = if new_connection == DEFAULT_OBS_CONNECTION {
+                None
+            } else {
+                Some(new_connection)
+            };
+            setup.new_connection.clear();
+        }
+    });
+    ui.label("");
 
-    if OBS_CLIENT.get().is_none() || OBS_CLIENT.get().unwrap().blocking_lock().is_none() {
+    let name = connection_name(connection).to_string();
+    let slot = client_slot_blocking(&name);
+    if slot.blocking_lock().is_some() {
         ui.vertical_centered(|ui| {
-            ui.label(RichText::new(t!("action.obs.setup.help_connect")).size(11.0));
-            ui.label(RichText::new(t!("action.obs.setup.help_instructions")).size(9.0));
+            ui.label(t!("action.obs.setup.success"));
         });
-        ui.label("");
-        if ui.button(t!("action.obs.setup.button")).clicked() {
-            let res = Handle::current().block_on(async { create_client(config).await });
-            match res {
-                Ok(_) => log::error!("connected to obs"),
-                Err(e) => log::error!("failed to connect to obs: {:?}", e),
-            }
 
-            // TODO: error handle
+        return Some(());
+    }
+
+    {
+        // Show what's saved for the connection when it changes
+        let mut setup = OBS_SETUP.get().unwrap().blocking_lock();
+        if setup.connection != name {
+            let c = config.blocking_lock();
+            let new_connection = std::mem::take(&mut setup.new_connection);
+            *setup = ObsSetup::new(&name, c.obs_connections.get(&name));
+            setup.new_connection = new_connection;
         }
-        ui.label("");
+    }
+
+    ui.vertical_centered(|ui| {
+        ui.label(RichText::new(t!("action.obs.setup.help_connect")).size(11.0));
+        ui.label(RichText::new(t!("action.obs.setup.help_instructions")).size(9.0));
+    });
+    ui.label("");
+    let busy = OBS_SETUP.get().unwrap().blocking_lock().busy;
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(!busy, Button::new(t!("action.obs.setup.button")))
+            .clicked()
         {
-            ui.label(t!("action.obs.setup.host_address"));
-            let mut obs_host_address = OBS_HOST_ADDRESS.get().unwrap().blocking_lock();
-            ui.add(TextEdit::singleline(&mut *obs_host_address).hint_text("localhost"));
+            let access = OBS_SETUP.get().unwrap().blocking_lock().access();
+            if let Some(access) = access {
+                let name = name.clone();
+                run_setup_job(ui.ctx().clone(), name.clone(), async move {
+                    match connect_client(config, &name, access).await {
+                        Ok(_) => {
+                            log::info!("connected to obs {}", name);
+                            None
+                        }
+                        Err(e) => {
+                            log::error!("failed to connect to obs {}: {:?}", name, e);
+                            Some(e.describe(&name))
+                        }
+                    }
+                });
+            }
         }
+        if ui
+            .add_enabled(!busy, Button::new(t!("action.obs.setup.test")))
+            .clicked()
         {
-            ui.label(t!("action.obs.setup.host_port"));
-            let mut obs_host_port = OBS_HOST_PORT.get().unwrap().blocking_lock();
-            let old_port = obs_host_port.clone();
-            ui.add(TextEdit::singleline(&mut *obs_host_port).hint_text("4455"));
-            if let Err(_) = obs_host_port.parse::<u16>() {
-                *obs_host_port = old_port;
+            let access = OBS_SETUP.get().unwrap().blocking_lock().access();
+            if let Some(access) = access {
+                let name = name.clone();
+                run_setup_job(ui.ctx().clone(), name.clone(), async move {
+                    match test_connection(access).await {
+                        Ok(s) => Some(s),
+                        Err(e) => Some(e.describe(&name)),
+                    }
+                });
             }
         }
+    });
+    {
+        let setup = OBS_SETUP.get().unwrap().blocking_lock();
+        if let Some(status) = &setup.status {
+            ui.label(RichText::new(status).size(9.0));
+        }
+    }
+    ui.label("");
+    {
+        let mut setup = OBS_SETUP.get().unwrap().blocking_lock();
+
+        ui.label(t!("action.obs.setup.host_address"));
+        ui.add(TextEdit::singleline(&mut setup.host).hint_text("localhost"));
+
+        ui.label(t!("action.obs.setup.host_port"));
+        let old_port = setup.port.clone();
+        ui.add(TextEdit::singleline(&mut setup.port).hint_text("4455"));
+        if let Err(_) = setup.port.parse::<u16>() {
+            setup.port = old_port;
+        }
+
+        ui.label(t!("action.obs.setup.password"));
+        ui.add(
+            TextEdit::singleline(&mut setup.password)
+                .hint_text("password")
+                .password(true),
+        );
+
+        ui.label(t!("action.obs.setup.timeout"));
+        ui.add(
+            Slider::new(&mut setup.timeout_ms, DEFAULT_OBS_TIMEOUT..=10000)
+                .step_by(250.0)
+                .suffix(" ms"),
+        )
+        .on_hover_text_at_pointer(t!("action.obs.setup.timeout_help"));
+
+        let mut use_tls = setup.tls.is_some();
+        if ui
+            .checkbox(&mut use_tls, t!("action.obs.setup.tls"))
+            .on_hover_text_at_pointer(t!("action.obs.setup.tls_help"))
+            .changed()
         {
-            ui.label(t!("action.obs.setup.password"));
-            let mut obs_password = OBS_PASSWORD.get().unwrap().blocking_lock();
-            ui.add(
-                TextEdit::singleline(&mut *obs_password)
-                    .hint_text("password")
-                    .password(true),
-            );
+            setup.tls = if use_tls {
+                Some(TlsTrust::SystemRoots)
+            } else {
+                None
+            };
         }
+        if let Some(trust) = &mut setup.tls {
+            tls_trust_ui(ui, trust);
+        }
+    }
 
-        None
-    } else {
-        ui.vertical_centered(|ui| {
-            ui.label(t!("action.obs.setup.success"));
+    None
+}
+
+// Connecting can take as long as the timeout, so it happens off the GUI thread. The outcome shows
+// up in the form once it's done, if it's still showing the same connection.
+fn run_setup_job(
+    ctx: Context,
+    connection: String,
+    job: impl Future<Output = Option<String>> + Send + 'static,
+) {
+    {
+        let mut setup = OBS_SETUP.get().unwrap().blocking_lock();
+        setup.busy = true;
+        setup.status = Some(t!("action.obs.setup.waiting").into());
+    }
+
+    tokio::spawn(async move {
+        let status = job.await;
+        let mut setup = OBS_SETUP.get().unwrap().lock().await;
+        if setup.connection == connection {
+            setup.busy = false;
+            setup.status = status;
+        }
+        ctx.request_repaint();
+    });
+}
+
+// Which certificates a TLS connection accepts, and the details for each choice
+pub fn tls_trust_ui(ui: &mut Ui, trust: &mut TlsTrust) {
+    let title = |t: &TlsTrust| match t {
+        TlsTrust::SystemRoots => t!("action.obs.setup.trust_system"),
+        TlsTrust::CustomCa(_) => t!("action.obs.setup.trust_custom_ca"),
+        TlsTrust::Pinned(_) => t!("action.obs.setup.trust_pinned"),
+    };
+
+    ui.label(t!("action.obs.setup.trust"));
+    ComboBox::from_id_salt("ObsTlsTrust")
+        .width(200.0)
+        .selected_text(title(trust))
+        .show_ui(ui, |ui| {
+            for option in [
+                TlsTrust::SystemRoots,
+                TlsTrust::CustomCa(String::new()),
+                TlsTrust::Pinned(None),
+            ] {
+                let selected = std::mem::discriminant(trust) == std::mem::discriminant(&option);
+                if ui.selectable_label(selected, title(&option)).clicked() && !selected {
+                    *trust = option;
+                }
+            }
         });
 
-        Some(())
+    match trust {
+        TlsTrust::SystemRoots => {}
+        TlsTrust::CustomCa(path) => {
+            ui.horizontal(|ui| {
+                if ui.button(t!("action.obs.setup.choose_ca")).clicked() {
+                    if let Some(file) = FileDialog::new()
+                        .add_filter("PEM", &["pem", "crt", "cer"])
+                        .pick_file()
+                    {
+                        *path = file.to_string_lossy().into();
+                    }
+                }
+                ui.add(TextEdit::singleline(path).hint_text("ca.pem"));
+            });
+        }
+        TlsTrust::Pinned(fingerprint) => match fingerprint.clone() {
+            Some(f) => {
+                ui.label(RichText::new(t!("action.obs.setup.pinned", fingerprint = f)).size(9.0));
+                if ui.button(t!("action.obs.setup.forget_pin")).clicked() {
+                    *fingerprint = None;
+                }
+            }
+            None => {
+                ui.label(RichText::new(t!("action.obs.setup.pin_on_connect")).size(9.0));
+            }
+        },
     }
 }
 
-async fn check_client<'a>(
+async fn check_client(
+    connection: &Option<String>,
     device_uid: &String,
     input_key: InputKey,
     config: Arc<Mutex<JukeBoxConfig>>,
-) -> Result<MutexGuard<'a, Option<Client>>, ActionError> {
-    let c = config.clone();
-    if OBS_CLIENT.get().is_none() || OBS_CLIENT.get().unwrap().lock().await.is_none() {
-        create_client(c)
-            .await
-            .map_err(|_| ActionError::new(device_uid, input_key, t!("action.obs.err.client")))
+) -> Result<OwnedMutexGuard<Option<Client>>, ActionError> {
+    let name = connection_name(connection);
+    let client = client_slot(name).await.lock_owned().await;
+    if client.is_some() {
+        return Ok(client);
+    }
+    drop(client);
+
+    create_client(config, name)
+        .await
+        .map_err(|e| ActionError::new(device_uid, input_key, e.describe(name)))
+}
+
+// The input picker shared by actions that work on a single input. True when the pick changed.
+fn input_select(
+    ui: &mut Ui,
+    connection: &Option<String>,
+    input: &mut Option<(Uuid, String)>,
+) -> bool {
+    let mut changed = false;
+
+    ui.label(t!("action.obs.options.select_input"));
+    let ir = ComboBox::from_id_salt("ObsInputSelect")
+        .width(200.0)
+        .selected_text(input.clone().map(|s| s.1).unwrap_or("".into()))
+        .show_ui(ui, |ui| {
+            let inputs = OBS_INPUTS.get().unwrap().blocking_lock();
+            if let Some(inputs) = &*inputs {
+                for i in inputs {
+                    let selected = if let Some(selected_input) = &input {
+                        selected_input.0 == i.id.uuid
+                    } else {
+                        false
+                    };
+                    let l = ui.selectable_label(selected, i.id.name.clone());
+                    if l.clicked() {
+                        *input = Some((i.id.uuid, i.id.name.clone()));
+                        changed = true;
+                    }
+                }
+            } else {
+                ui.label(t!("action.obs.options.loading"));
+            }
+        });
+
+    single_fire!(ComboBox::is_open(ui.ctx(), ir.response.id), {
+        *OBS_INPUTS.get().unwrap().blocking_lock() = None;
+        let connection = connection_name(connection).to_string();
+        tokio::spawn(async move {
+            let slot = client_slot(&connection).await;
+            let client = slot.lock().await;
+            if let Ok(input_list) = client.as_ref().unwrap().inputs().list(None).await {
+                *OBS_INPUTS.get().unwrap().lock().await = Some(input_list);
+            }
+        });
+    });
+
+    changed
+}
+
+// A picker over names fetched from OBS, like filters or hotkeys. The caller fills the list in.
+fn name_select(
+    ui: &mut Ui,
+    salt: &str,
+    names: &OnceLock<Mutex<Option<Vec<String>>>>,
+    name: &mut Option<String>,
+) -> InnerResponse<Option<()>> {
+    ComboBox::from_id_salt(salt)
+        .width(200.0)
+        .selected_text(name.clone().unwrap_or("".into()))
+        .show_ui(ui, |ui| {
+            let names = names.get().unwrap().blocking_lock();
+            if let Some(names) = &*names {
+                for n in names {
+                    let selected = name.as_ref() == Some(n);
+                    if ui.selectable_label(selected, n.clone()).clicked() {
+                        *name = Some(n.clone());
+                    }
+                }
+            } else {
+                ui.label(t!("action.obs.options.loading"));
+            }
+        })
+}
+
+// Labels only read from an existing connection, polling them shouldn't be what brings OBS up
+async fn output_timecode(connection: &Option<String>, recording: bool) -> Option<String> {
+    let slot = OBS_CLIENTS
+        .get()?
+        .lock()
+        .await
+        .get(connection_name(connection))?
+        .clone();
+    let client = slot.lock().await;
+    let client = client.as_ref()?;
+
+    let (active, timecode) = if recording {
+        let s = client.recording().status().await.ok()?;
+        (s.active, s.timecode)
     } else {
-        Ok(OBS_CLIENT.get().unwrap().lock().await)
+        let s = client.streaming().status().await.ok()?;
+        (s.active, s.timecode)
+    };
+
+    if !active {
+        return None;
     }
+
+    let secs = timecode.whole_seconds();
+    Some(format!(
+        "{}:{:02}:{:02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    ))
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
-pub struct ObsStream {}
+pub struct ObsStream {
+    #[serde(default)]
+    connection: Option<String>,
+}
 impl ObsStream {
     pub async fn on_press(
         &self,
@@ -283,7 +1155,8 @@ impl ObsStream {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        let mut client = check_client(device_uid, input_key, config.clone()).await?;
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
 
         let res = client
             .as_ref()
@@ -326,7 +1199,7 @@ impl ObsStream {
         _input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) {
-        account_warning(ui, config);
+        account_warning(ui, &mut self.connection, config);
     }
 
     pub fn help(&self) -> &str {
@@ -334,24 +1207,38 @@ impl ObsStream {
     }
 
     pub fn icon_state(&self) -> u8 {
-        0
+        if obs_state(&self.connection, |s| s.streaming) {
+            1
+        } else {
+            0
+        }
     }
 
     pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
-        &[ICON_STREAM]
+        &[ICON_STREAM, ICON_STREAM]
     }
 
     pub fn icon_state_count(&self) -> u8 {
-        1
+        2
     }
 
     pub fn icon_state_descriptions(&self) -> &[&str] {
-        &[""]
+        &[
+            "action.obs.toggle_stream.icon_state_0",
+            "action.obs.toggle_stream.icon_state_1",
+        ]
+    }
+
+    pub async fn icon_text(&self) -> Option<String> {
+        output_timecode(&self.connection, false).await
     }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
-pub struct ObsRecord {}
+pub struct ObsRecord {
+    #[serde(default)]
+    connection: Option<String>,
+}
 impl ObsRecord {
     pub async fn on_press(
         &self,
@@ -359,7 +1246,8 @@ impl ObsRecord {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        let mut client = check_client(device_uid, input_key, config.clone()).await?;
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
 
         let res = client
             .as_ref()
@@ -402,7 +1290,7 @@ impl ObsRecord {
         _input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) {
-        account_warning(ui, config);
+        account_warning(ui, &mut self.connection, config);
     }
 
     pub fn help(&self) -> &str {
@@ -410,24 +1298,38 @@ impl ObsRecord {
     }
 
     pub fn icon_state(&self) -> u8 {
-        0
+        if obs_state(&self.connection, |s| s.recording) {
+            1
+        } else {
+            0
+        }
     }
 
     pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
-        &[ICON_RECORD]
+        &[ICON_RECORD, ICON_RECORD]
     }
 
     pub fn icon_state_count(&self) -> u8 {
-        1
+        2
     }
 
     pub fn icon_state_descriptions(&self) -> &[&str] {
-        &[""]
+        &[
+            "action.obs.toggle_record.icon_state_0",
+            "action.obs.toggle_record.icon_state_1",
+        ]
+    }
+
+    pub async fn icon_text(&self) -> Option<String> {
+        output_timecode(&self.connection, true).await
     }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
-pub struct ObsPauseRecord {}
+pub struct ObsPauseRecord {
+    #[serde(default)]
+    connection: Option<String>,
+}
 impl ObsPauseRecord {
     pub async fn on_press(
         &self,
@@ -435,7 +1337,8 @@ impl ObsPauseRecord {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        let mut client = check_client(device_uid, input_key, config.clone()).await?;
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
 
         let res = client
             .as_ref()
@@ -478,7 +1381,7 @@ impl ObsPauseRecord {
         _input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) {
-        account_warning(ui, config);
+        account_warning(ui, &mut self.connection, config);
     }
 
     pub fn help(&self) -> &str {
@@ -486,24 +1389,34 @@ impl ObsPauseRecord {
     }
 
     pub fn icon_state(&self) -> u8 {
-        0
+        if obs_state(&self.connection, |s| s.record_paused) {
+            1
+        } else {
+            0
+        }
     }
 
     pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
-        &[ICON_PAUSE_RECORD]
+        &[ICON_PAUSE_RECORD, ICON_PAUSE_RECORD]
     }
 
     pub fn icon_state_count(&self) -> u8 {
-        1
+        2
     }
 
     pub fn icon_state_descriptions(&self) -> &[&str] {
-        &[""]
+        &[
+            "action.obs.pause_record.icon_state_0",
+            "action.obs.pause_record.icon_state_1",
+        ]
     }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
-pub struct ObsReplayBuffer {}
+pub struct ObsReplayBuffer {
+    #[serde(default)]
+    connection: Option<String>,
+}
 impl ObsReplayBuffer {
     pub async fn on_press(
         &self,
@@ -511,7 +1424,8 @@ impl ObsReplayBuffer {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        let mut client = check_client(device_uid, input_key, config.clone()).await?;
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
 
         let res = client
             .as_ref()
@@ -558,7 +1472,7 @@ impl ObsReplayBuffer {
         _input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) {
-        account_warning(ui, config);
+        account_warning(ui, &mut self.connection, config);
     }
 
     pub fn help(&self) -> &str {
@@ -566,24 +1480,34 @@ impl ObsReplayBuffer {
     }
 
     pub fn icon_state(&self) -> u8 {
-        0
+        if obs_state(&self.connection, |s| s.replay_buffer) {
+            1
+        } else {
+            0
+        }
     }
 
     pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
-        &[ICON_REPLAY_BUFFER]
+        &[ICON_REPLAY_BUFFER, ICON_REPLAY_BUFFER]
     }
 
     pub fn icon_state_count(&self) -> u8 {
-        1
+        2
     }
 
     pub fn icon_state_descriptions(&self) -> &[&str] {
-        &[""]
+        &[
+            "action.obs.toggle_replay_buffer.icon_state_0",
+            "action.obs.toggle_replay_buffer.icon_state_1",
+        ]
     }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
-pub struct ObsSaveReplay {}
+pub struct ObsSaveReplay {
+    #[serde(default)]
+    connection: Option<String>,
+}
 impl ObsSaveReplay {
     pub async fn on_press(
         &self,
@@ -591,7 +1515,8 @@ impl ObsSaveReplay {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        let mut client = check_client(device_uid, input_key, config.clone()).await?;
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
 
         let res = client
             .as_ref()
@@ -638,7 +1563,7 @@ impl ObsSaveReplay {
         _input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) {
-        account_warning(ui, config);
+        account_warning(ui, &mut self.connection, config);
     }
 
     pub fn help(&self) -> &str {
@@ -664,6 +1589,8 @@ impl ObsSaveReplay {
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct ObsSource {
+    #[serde(default)]
+    connection: Option<String>,
     scene: Option<(Uuid, String)>,
     source: Option<(i64, String)>,
 }
@@ -674,7 +1601,8 @@ impl ObsSource {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        let mut client = check_client(device_uid, input_key, config.clone()).await?;
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
 
         let Some(scene) = &self.scene else {
             return Err(ActionError::new(
@@ -774,7 +1702,7 @@ impl ObsSource {
         _input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) {
-        if account_warning(ui, config).is_none() {
+        if account_warning(ui, &mut self.connection, config).is_none() {
             return;
         }
 
@@ -806,8 +1734,10 @@ impl ObsSource {
 
         single_fire!(ComboBox::is_open(ui.ctx(), ir.response.id), {
             *OBS_SCENES.get().unwrap().blocking_lock() = None;
-            tokio::spawn(async {
-                let client = OBS_CLIENT.get().unwrap().lock().await;
+            let connection = connection_name(&self.connection).to_string();
+            tokio::spawn(async move {
+                let slot = client_slot(&connection).await;
+                let client = slot.lock().await;
                 if let Ok(scene_list) = client.as_ref().unwrap().scenes().list().await {
                     *OBS_SCENES.get().unwrap().lock().await = Some(scene_list.scenes);
                 }
@@ -844,8 +1774,10 @@ impl ObsSource {
         single_fire!(ComboBox::is_open(ui.ctx(), ir.response.id), {
             *OBS_SOURCES.get().unwrap().blocking_lock() = None;
             let scene_id = SceneId::Uuid(self.scene.clone().unwrap().0);
+            let connection = connection_name(&self.connection).to_string();
             tokio::spawn(async move {
-                let client = OBS_CLIENT.get().unwrap().lock().await;
+                let slot = client_slot(&connection).await;
+                let client = slot.lock().await;
                 if let Ok(items) = client.as_ref().unwrap().scene_items().list(scene_id).await {
                     *OBS_SOURCES.get().unwrap().lock().await = Some(items);
                 }
@@ -858,24 +1790,39 @@ impl ObsSource {
     }
 
     pub fn icon_state(&self) -> u8 {
-        0
+        let (Some(scene), Some(source)) = (&self.scene, &self.source) else {
+            return 0;
+        };
+        let visible = obs_state(&self.connection, |s| {
+            s.scene_items.get(&(scene.0, source.0)).copied()
+        });
+        if visible == Some(false) {
+            1
+        } else {
+            0
+        }
     }
 
     pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
-        &[ICON_SOURCE]
+        &[ICON_SOURCE, ICON_SOURCE]
     }
 
     pub fn icon_state_count(&self) -> u8 {
-        1
+        2
     }
 
     pub fn icon_state_descriptions(&self) -> &[&str] {
-        &[""]
+        &[
+            "action.obs.toggle_source.icon_state_0",
+            "action.obs.toggle_source.icon_state_1",
+        ]
     }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct ObsMute {
+    #[serde(default)]
+    connection: Option<String>,
     input: Option<(Uuid, String)>,
 }
 impl ObsMute {
@@ -885,7 +1832,8 @@ impl ObsMute {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        let mut client = check_client(device_uid, input_key, config.clone()).await?;
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
 
         let Some(input) = &self.input else {
             return Err(ActionError::new(
@@ -940,7 +1888,7 @@ impl ObsMute {
         _input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) {
-        if account_warning(ui, config).is_none() {
+        if account_warning(ui, &mut self.connection, config).is_none() {
             return;
         }
 
@@ -971,8 +1919,10 @@ impl ObsMute {
 
         single_fire!(ComboBox::is_open(ui.ctx(), ir.response.id), {
             *OBS_INPUTS.get().unwrap().blocking_lock() = None;
-            tokio::spawn(async {
-                let client = OBS_CLIENT.get().unwrap().lock().await;
+            let connection = connection_name(&self.connection).to_string();
+            tokio::spawn(async move {
+                let slot = client_slot(&connection).await;
+                let client = slot.lock().await;
                 if let Ok(input_list) = client.as_ref().unwrap().inputs().list(None).await {
                     // TODO: filter out non-audio sources
                     *OBS_INPUTS.get().unwrap().lock().await = Some(input_list);
@@ -986,24 +1936,38 @@ impl ObsMute {
     }
 
     pub fn icon_state(&self) -> u8 {
-        0
+        let Some(input) = &self.input else {
+            return 0;
+        };
+        if obs_state(&self.connection, |s| {
+            s.inputs_muted.get(&input.0) == Some(&true)
+        }) {
+            1
+        } else {
+            0
+        }
     }
 
     pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
-        &[ICON_MUTE]
+        &[ICON_MUTE, ICON_MUTE]
     }
 
     pub fn icon_state_count(&self) -> u8 {
-        1
+        2
     }
 
     pub fn icon_state_descriptions(&self) -> &[&str] {
-        &[""]
+        &[
+            "action.obs.toggle_mute.icon_state_0",
+            "action.obs.toggle_mute.icon_state_1",
+        ]
     }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct ObsSceneSwitch {
+    #[serde(default)]
+    connection: Option<String>,
     scene: Option<(Uuid, String)>,
 }
 impl ObsSceneSwitch {
@@ -1013,7 +1977,8 @@ impl ObsSceneSwitch {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        let mut client = check_client(device_uid, input_key, config.clone()).await?;
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
 
         let Some(scene) = &self.scene else {
             return Err(ActionError::new(
@@ -1068,7 +2033,7 @@ impl ObsSceneSwitch {
         _input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) {
-        if account_warning(ui, config).is_none() {
+        if account_warning(ui, &mut self.connection, config).is_none() {
             return;
         }
 
@@ -1099,8 +2064,10 @@ impl ObsSceneSwitch {
 
         single_fire!(ComboBox::is_open(ui.ctx(), ir.response.id), {
             *OBS_SCENES.get().unwrap().blocking_lock() = None;
-            tokio::spawn(async {
-                let client = OBS_CLIENT.get().unwrap().lock().await;
+            let connection = connection_name(&self.connection).to_string();
+            tokio::spawn(async move {
+                let slot = client_slot(&connection).await;
+                let client = slot.lock().await;
                 if let Ok(scene_list) = client.as_ref().unwrap().scenes().list().await {
                     *OBS_SCENES.get().unwrap().lock().await = Some(scene_list.scenes);
                 }
@@ -1113,24 +2080,38 @@ impl ObsSceneSwitch {
     }
 
     pub fn icon_state(&self) -> u8 {
-        0
+        let scene = self.scene.as_ref().map(|s| s.0);
+        if scene.is_some() && obs_state(&self.connection, |s| s.program_scene) == scene {
+            1
+        } else {
+            0
+        }
     }
 
     pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
-        &[ICON_SWITCH_SCENE]
+        &[ICON_SWITCH_SCENE, ICON_SWITCH_SCENE]
     }
 
     pub fn icon_state_count(&self) -> u8 {
-        1
+        2
     }
 
     pub fn icon_state_descriptions(&self) -> &[&str] {
-        &[""]
+        &[
+            "action.obs.switch_scene.icon_state_0",
+            "action.obs.switch_scene.icon_state_1",
+        ]
+    }
+
+    pub fn icon_text(&self) -> Option<String> {
+        self.scene.as_ref().map(|s| s.1.clone())
     }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct ObsPreviewSceneSwitch {
+    #[serde(default)]
+    connection: Option<String>,
     scene: Option<(Uuid, String)>,
 }
 impl ObsPreviewSceneSwitch {
@@ -1140,7 +2121,8 @@ impl ObsPreviewSceneSwitch {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        let mut client = check_client(device_uid, input_key, config.clone()).await?;
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
 
         let Some(scene) = &self.scene else {
             return Err(ActionError::new(
@@ -1198,7 +2180,7 @@ impl ObsPreviewSceneSwitch {
         _input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) {
-        if account_warning(ui, config).is_none() {
+        if account_warning(ui, &mut self.connection, config).is_none() {
             return;
         }
 
@@ -1229,8 +2211,10 @@ impl ObsPreviewSceneSwitch {
 
         single_fire!(ComboBox::is_open(ui.ctx(), ir.response.id), {
             *OBS_SCENES.get().unwrap().blocking_lock() = None;
-            tokio::spawn(async {
-                let client = OBS_CLIENT.get().unwrap().lock().await;
+            let connection = connection_name(&self.connection).to_string();
+            tokio::spawn(async move {
+                let slot = client_slot(&connection).await;
+                let client = slot.lock().await;
                 if let Ok(scene_list) = client.as_ref().unwrap().scenes().list().await {
                     *OBS_SCENES.get().unwrap().lock().await = Some(scene_list.scenes);
                 }
@@ -1243,24 +2227,43 @@ impl ObsPreviewSceneSwitch {
     }
 
     pub fn icon_state(&self) -> u8 {
-        0
+        let scene = self.scene.as_ref().map(|s| s.0);
+        if scene.is_some()
+            && obs_state(&self.connection, |s| {
+                s.studio_mode && s.preview_scene == scene
+            })
+        {
+            1
+        } else {
+            0
+        }
     }
 
     pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
-        &[ICON_SWITCH_PREVIEW]
+        &[ICON_SWITCH_PREVIEW, ICON_SWITCH_PREVIEW]
     }
 
     pub fn icon_state_count(&self) -> u8 {
-        1
+        2
     }
 
     pub fn icon_state_descriptions(&self) -> &[&str] {
-        &[""]
+        &[
+            "action.obs.switch_preview_scene.icon_state_0",
+            "action.obs.switch_preview_scene.icon_state_1",
+        ]
+    }
+
+    pub fn icon_text(&self) -> Option<String> {
+        self.scene.as_ref().map(|s| s.1.clone())
     }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
-pub struct ObsPreviewScenePush {}
+pub struct ObsPreviewScenePush {
+    #[serde(default)]
+    connection: Option<String>,
+}
 impl ObsPreviewScenePush {
     pub async fn on_press(
         &self,
@@ -1268,7 +2271,8 @@ impl ObsPreviewScenePush {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        let mut client = check_client(device_uid, input_key, config.clone()).await?;
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
 
         let res = client
             .as_ref()
@@ -1315,7 +2319,7 @@ impl ObsPreviewScenePush {
         _input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) {
-        account_warning(ui, config);
+        account_warning(ui, &mut self.connection, config);
     }
 
     pub fn help(&self) -> &str {
@@ -1341,6 +2345,8 @@ impl ObsPreviewScenePush {
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct ObsSceneCollectionSwitch {
+    #[serde(default)]
+    connection: Option<String>,
     scene_collection: Option<String>,
 }
 impl ObsSceneCollectionSwitch {
@@ -1350,7 +2356,8 @@ impl ObsSceneCollectionSwitch {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        let mut client = check_client(device_uid, input_key, config.clone()).await?;
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
 
         let Some(scene_collection) = &self.scene_collection else {
             return Err(ActionError::new(
@@ -1408,7 +2415,7 @@ impl ObsSceneCollectionSwitch {
         _input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) {
-        if account_warning(ui, config).is_none() {
+        if account_warning(ui, &mut self.connection, config).is_none() {
             return;
         }
 
@@ -1439,8 +2446,10 @@ impl ObsSceneCollectionSwitch {
 
         single_fire!(ComboBox::is_open(ui.ctx(), ir.response.id), {
             *OBS_SCENE_COLLECTIONS.get().unwrap().blocking_lock() = None;
-            tokio::spawn(async {
-                let client = OBS_CLIENT.get().unwrap().lock().await;
+            let connection = connection_name(&self.connection).to_string();
+            tokio::spawn(async move {
+                let slot = client_slot(&connection).await;
+                let client = slot.lock().await;
                 if let Ok(collection_list) =
                     client.as_ref().unwrap().scene_collections().list().await
                 {
@@ -1473,7 +2482,10 @@ impl ObsSceneCollectionSwitch {
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
-pub struct ObsChapterMarker {}
+pub struct ObsChapterMarker {
+    #[serde(default)]
+    connection: Option<String>,
+}
 impl ObsChapterMarker {
     pub async fn on_press(
         &self,
@@ -1481,7 +2493,8 @@ impl ObsChapterMarker {
         input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        let mut client = check_client(device_uid, input_key, config.clone()).await?;
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
 
         let res = client
             .as_ref()
@@ -1528,7 +2541,7 @@ impl ObsChapterMarker {
         _input_key: InputKey,
         config: Arc<Mutex<JukeBoxConfig>>,
     ) {
-        account_warning(ui, config);
+        account_warning(ui, &mut self.connection, config);
     }
 
     pub fn help(&self) -> &str {
@@ -1551,3 +2564,1099 @@ impl ObsChapterMarker {
         &[""]
     }
 }
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ObsFilter {
+    #[serde(default)]
+    connection: Option<String>,
+    input: Option<(Uuid, String)>,
+    filter: Option<String>,
+}
+impl ObsFilter {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
+
+        let Some(input) = &self.input else {
+            return Err(ActionError::new(
+                device_uid,
+                input_key,
+                t!("action.obs.toggle_filter.err.input_not_configured"),
+            ));
+        };
+
+        let Some(filter) = &self.filter else {
+            return Err(ActionError::new(
+                device_uid,
+                input_key,
+                t!("action.obs.toggle_filter.err.filter_not_configured"),
+            ));
+        };
+
+        let source = SourceId::Uuid(input.0);
+
+        let res = client
+            .as_ref()
+            .unwrap()
+            .filters()
+            .get(source, filter)
+            .await
+            .map_err(|_| {
+                ActionError::new(
+                    device_uid,
+                    input_key,
+                    t!(
+                        "action.obs.toggle_filter.err.get_enabled",
+                        input = input.1,
+                        filter = filter
+                    ),
+                )
+            });
+
+        let enabled = match res {
+            Ok(f) => f.enabled,
+            Err(e) => {
+                client.as_mut().unwrap().disconnect().await;
+                *client = None;
+                return Err(e);
+            }
+        };
+
+        let res = client
+            .as_ref()
+            .unwrap()
+            .filters()
+            .set_enabled(SetFilterEnabled {
+                source,
+                filter,
+                enabled: !enabled,
+            })
+            .await
+            .map(|_| (input_key, false))
+            .map_err(|_| {
+                ActionError::new(
+                    device_uid,
+                    input_key,
+                    t!(
+                        "action.obs.toggle_filter.err.set_enabled",
+                        input = input.1,
+                        filter = filter
+                    ),
+                )
+            });
+
+        match res {
+            Ok(o) => Ok(o),
+            Err(e) => {
+                client.as_mut().unwrap().disconnect().await;
+                *client = None;
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_OBS_TOGGLE_FILTER.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        if account_warning(ui, &mut self.connection, config).is_none() {
+            return;
+        }
+
+        ui.label("");
+
+        if input_select(ui, &self.connection, &mut self.input) {
+            self.filter = None;
+        }
+
+        ui.label(t!("action.obs.options.select_filter"));
+        let ir = ui
+            .add_enabled_ui(self.input.is_some(), |ui| {
+                name_select(ui, "ObsFilterSelect", &OBS_FILTERS, &mut self.filter)
+            })
+            .inner;
+
+        single_fire!(ComboBox::is_open(ui.ctx(), ir.response.id), {
+            *OBS_FILTERS.get().unwrap().blocking_lock() = None;
+            let source = SourceId::Uuid(self.input.clone().unwrap().0);
+            let connection = connection_name(&self.connection).to_string();
+            tokio::spawn(async move {
+                let slot = client_slot(&connection).await;
+                let client = slot.lock().await;
+                if let Ok(filters) = client.as_ref().unwrap().filters().list(source).await {
+                    *OBS_FILTERS.get().unwrap().lock().await =
+                        Some(filters.into_iter().map(|f| f.name).collect());
+                }
+            });
+        });
+    }
+
+    pub fn help(&self) -> &str {
+        "action.obs.toggle_filter.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        0
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        &[ICON_BASE]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        1
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[""]
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ObsTransition {
+    #[serde(default)]
+    connection: Option<String>,
+    transition: Option<String>,
+    duration_ms: u32, // 0 leaves the duration alone
+}
+impl ObsTransition {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
+
+        let Some(transition) = &self.transition else {
+            return Err(ActionError::new(
+                device_uid,
+                input_key,
+                t!("action.obs.switch_transition.err.transition_not_configured"),
+            ));
+        };
+
+        let mut res = client
+            .as_ref()
+            .unwrap()
+            .transitions()
+            .set_current(transition)
+            .await;
+        if res.is_ok() && self.duration_ms > 0 {
+            res = client
+                .as_ref()
+                .unwrap()
+                .transitions()
+                .set_current_duration(
+                    Duration::from_millis(self.duration_ms as u64)
+                        .try_into()
+                        .unwrap(),
+                )
+                .await;
+        }
+
+        let res = res.map(|_| (input_key, false)).map_err(|_| {
+            ActionError::new(
+                device_uid,
+                input_key,
+                t!(
+                    "action.obs.switch_transition.err.failure",
+                    transition = transition
+                ),
+            )
+        });
+
+        match res {
+            Ok(o) => Ok(o),
+            Err(e) => {
+                client.as_mut().unwrap().disconnect().await;
+                *client = None;
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_OBS_TRANSITION_SWITCH.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        if account_warning(ui, &mut self.connection, config).is_none() {
+            return;
+        }
+
+        ui.label("");
+
+        ui.label(t!("action.obs.options.select_transition"));
+        let ir = name_select(
+            ui,
+            "ObsTransitionSelect",
+            &OBS_TRANSITIONS,
+            &mut self.transition,
+        );
+
+        single_fire!(ComboBox::is_open(ui.ctx(), ir.response.id), {
+            *OBS_TRANSITIONS.get().unwrap().blocking_lock() = None;
+            let connection = connection_name(&self.connection).to_string();
+            tokio::spawn(async move {
+                let slot = client_slot(&connection).await;
+                let client = slot.lock().await;
+                if let Ok(list) = client.as_ref().unwrap().transitions().list().await {
+                    *OBS_TRANSITIONS.get().unwrap().lock().await =
+                        Some(list.transitions.into_iter().map(|t| t.name).collect());
+                }
+            });
+        });
+
+        ui.label(t!("action.obs.switch_transition.duration"));
+        ui.add(
+            Slider::new(&mut self.duration_ms, 0..=5000)
+                .step_by(50.0)
+                .suffix(" ms"),
+        )
+        .on_hover_text_at_pointer(t!("action.obs.switch_transition.duration_help"));
+    }
+
+    pub fn help(&self) -> &str {
+        "action.obs.switch_transition.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        0
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        &[ICON_SWITCH_SCENE]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        1
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[""]
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ObsStudioTransition {
+    #[serde(default)]
+    connection: Option<String>,
+}
+impl ObsStudioTransition {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
+
+        let res = client
+            .as_ref()
+            .unwrap()
+            .transitions()
+            .trigger()
+            .await
+            .map(|_| (input_key, false))
+            .map_err(|_| {
+                ActionError::new(
+                    device_uid,
+                    input_key,
+                    t!("action.obs.studio_transition.err"),
+                )
+            });
+
+        match res {
+            Ok(o) => Ok(o),
+            Err(e) => {
+                client.as_mut().unwrap().disconnect().await;
+                *client = None;
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_OBS_STUDIO_TRANSITION.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        account_warning(ui, &mut self.connection, config);
+    }
+
+    pub fn help(&self) -> &str {
+        "action.obs.studio_transition.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        0
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        &[ICON_PUSH_PREVIEW]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        1
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[""]
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ObsStudioMode {
+    #[serde(default)]
+    connection: Option<String>,
+}
+impl ObsStudioMode {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
+
+        let res = match client.as_ref().unwrap().ui().studio_mode_enabled().await {
+            Ok(enabled) => {
+                client
+                    .as_ref()
+                    .unwrap()
+                    .ui()
+                    .set_studio_mode_enabled(!enabled)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+
+        let res = res.map(|_| (input_key, false)).map_err(|_| {
+            ActionError::new(
+                device_uid,
+                input_key,
+                t!("action.obs.toggle_studio_mode.err"),
+            )
+        });
+
+        match res {
+            Ok(o) => Ok(o),
+            Err(e) => {
+                client.as_mut().unwrap().disconnect().await;
+                *client = None;
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_OBS_STUDIO_MODE.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        account_warning(ui, &mut self.connection, config);
+    }
+
+    pub fn help(&self) -> &str {
+        "action.obs.toggle_studio_mode.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        if obs_state(&self.connection, |s| s.studio_mode) {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        &[ICON_SWITCH_PREVIEW, ICON_SWITCH_PREVIEW]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        2
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[
+            "action.obs.toggle_studio_mode.icon_state_0",
+            "action.obs.toggle_studio_mode.icon_state_1",
+        ]
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ObsInputVolume {
+    #[serde(default)]
+    connection: Option<String>,
+    input: Option<(Uuid, String)>,
+    db_adjust: i8,
+}
+impl ObsInputVolume {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
+
+        let Some(input) = &self.input else {
+            return Err(ActionError::new(
+                device_uid,
+                input_key,
+                t!("action.obs.input_volume.err.input_not_configured"),
+            ));
+        };
+
+        let input_id = InputId::Uuid(input.0);
+
+        let res = match client.as_ref().unwrap().inputs().volume(input_id).await {
+            Ok(volume) => {
+                // OBS faders go from -100 dB (silent) up to +26 dB
+                let db = (volume.db + self.db_adjust as f32).clamp(-100.0, 26.0);
+                client
+                    .as_ref()
+                    .unwrap()
+                    .inputs()
+                    .set_volume(input_id, Volume::Db(db))
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+
+        let res = res.map(|_| (input_key, false)).map_err(|_| {
+            ActionError::new(
+                device_uid,
+                input_key,
+                t!("action.obs.input_volume.err.failure", input = input.1),
+            )
+        });
+
+        match res {
+            Ok(o) => Ok(o),
+            Err(e) => {
+                client.as_mut().unwrap().disconnect().await;
+                *client = None;
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_OBS_INPUT_VOLUME.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        if account_warning(ui, &mut self.connection, config).is_none() {
+            return;
+        }
+
+        ui.label("");
+
+        input_select(ui, &self.connection, &mut self.input);
+
+        ui.label(t!("action.obs.input_volume.volume_adjust"));
+        ui.add(Slider::new(&mut self.db_adjust, -20..=20).suffix(" dB"));
+    }
+
+    pub fn help(&self) -> &str {
+        "action.obs.input_volume.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        0
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        &[ICON_MUTE]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        1
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[""]
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ObsScreenshot {
+    #[serde(default)]
+    connection: Option<String>,
+    input: Option<(Uuid, String)>,
+    folder: String,
+}
+impl ObsScreenshot {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
+
+        let Some(input) = &self.input else {
+            return Err(ActionError::new(
+                device_uid,
+                input_key,
+                t!("action.obs.save_screenshot.err.input_not_configured"),
+            ));
+        };
+
+        if self.folder.trim().is_empty() {
+            return Err(ActionError::new(
+                device_uid,
+                input_key,
+                t!("action.obs.save_screenshot.err.folder_not_configured"),
+            ));
+        }
+
+        // OBS writes the file itself, so the path has to be absolute
+        let file_path = std::path::Path::new(&self.folder).join(format!(
+            "{}_{}.png",
+            input.1,
+            chrono::Local::now().format("%Y-%m-%d_%H-%M-%S")
+        ));
+
+        let res = client
+            .as_ref()
+            .unwrap()
+            .sources()
+            .save_screenshot(SaveScreenshot {
+                source: SourceId::Uuid(input.0),
+                format: "png",
+                file_path: &file_path,
+                width: None,
+                height: None,
+                compression_quality: None,
+            })
+            .await
+            .map(|_| (input_key, false))
+            .map_err(|_| {
+                ActionError::new(
+                    device_uid,
+                    input_key,
+                    t!(
+                        "action.obs.save_screenshot.err.failure",
+                        input = input.1,
+                        path = file_path.display()
+                    ),
+                )
+            });
+
+        match res {
+            Ok(o) => Ok(o),
+            Err(e) => {
+                client.as_mut().unwrap().disconnect().await;
+                *client = None;
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_OBS_SCREENSHOT.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        if account_warning(ui, &mut self.connection, config).is_none() {
+            return;
+        }
+
+        ui.label("");
+
+        input_select(ui, &self.connection, &mut self.input);
+
+        ui.label(t!("action.obs.save_screenshot.folder"));
+        if ui
+            .button(t!("action.obs.save_screenshot.choose_folder"))
+            .clicked()
+        {
+            if let Some(f) = FileDialog::new().pick_folder() {
+                self.folder = f.to_str().unwrap().to_owned();
+            }
+        }
+        ui.text_edit_singleline(&mut self.folder);
+    }
+
+    pub fn help(&self) -> &str {
+        "action.obs.save_screenshot.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        0
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        &[ICON_BASE]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        1
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[""]
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ObsVirtualCam {
+    #[serde(default)]
+    connection: Option<String>,
+}
+impl ObsVirtualCam {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
+
+        let res = client
+            .as_ref()
+            .unwrap()
+            .virtual_cam()
+            .toggle()
+            .await
+            .map(|_| (input_key, false))
+            .map_err(|_| {
+                ActionError::new(
+                    device_uid,
+                    input_key,
+                    t!("action.obs.toggle_virtual_cam.err"),
+                )
+            });
+
+        match res {
+            Ok(o) => Ok(o),
+            Err(e) => {
+                client.as_mut().unwrap().disconnect().await;
+                *client = None;
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_OBS_VIRTUAL_CAM.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        account_warning(ui, &mut self.connection, config);
+    }
+
+    pub fn help(&self) -> &str {
+        "action.obs.toggle_virtual_cam.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        if obs_state(&self.connection, |s| s.virtual_cam) {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        &[ICON_BASE, ICON_BASE]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        2
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[
+            "action.obs.toggle_virtual_cam.icon_state_0",
+            "action.obs.toggle_virtual_cam.icon_state_1",
+        ]
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ObsHotkey {
+    #[serde(default)]
+    connection: Option<String>,
+    hotkey: Option<String>,
+}
+impl ObsHotkey {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
+
+        let Some(hotkey) = &self.hotkey else {
+            return Err(ActionError::new(
+                device_uid,
+                input_key,
+                t!("action.obs.trigger_hotkey.err.hotkey_not_configured"),
+            ));
+        };
+
+        let res = client
+            .as_ref()
+            .unwrap()
+            .hotkeys()
+            .trigger_by_name(hotkey, None)
+            .await
+            .map(|_| (input_key, false))
+            .map_err(|_| {
+                ActionError::new(
+                    device_uid,
+                    input_key,
+                    t!("action.obs.trigger_hotkey.err.failure", hotkey = hotkey),
+                )
+            });
+
+        match res {
+            Ok(o) => Ok(o),
+            Err(e) => {
+                client.as_mut().unwrap().disconnect().await;
+                *client = None;
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_OBS_HOTKEY.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        if account_warning(ui, &mut self.connection, config).is_none() {
+            return;
+        }
+
+        ui.label("");
+
+        ui.label(t!("action.obs.options.select_hotkey"));
+        let ir = name_select(ui, "ObsHotkeySelect", &OBS_HOTKEYS, &mut self.hotkey);
+
+        single_fire!(ComboBox::is_open(ui.ctx(), ir.response.id), {
+            *OBS_HOTKEYS.get().unwrap().blocking_lock() = None;
+            let connection = connection_name(&self.connection).to_string();
+            tokio::spawn(async move {
+                let slot = client_slot(&connection).await;
+                let client = slot.lock().await;
+                if let Ok(hotkeys) = client.as_ref().unwrap().hotkeys().list().await {
+                    *OBS_HOTKEYS.get().unwrap().lock().await = Some(hotkeys);
+                }
+            });
+        });
+    }
+
+    pub fn help(&self) -> &str {
+        "action.obs.trigger_hotkey.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        0
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        &[ICON_BASE]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        1
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[""]
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ObsMediaControl {
+    #[default]
+    PlayPause,
+    Restart,
+    Stop,
+}
+impl ObsMediaControl {
+    const ALL: [Self; 3] = [Self::PlayPause, Self::Restart, Self::Stop];
+
+    fn title(&self) -> String {
+        match self {
+            Self::PlayPause => t!("action.obs.media_control.play_pause").into(),
+            Self::Restart => t!("action.obs.media_control.restart").into(),
+            Self::Stop => t!("action.obs.media_control.stop").into(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ObsMedia {
+    #[serde(default)]
+    connection: Option<String>,
+    input: Option<(Uuid, String)>,
+    control: ObsMediaControl,
+}
+impl ObsMedia {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        let mut client =
+            check_client(&self.connection, device_uid, input_key, config.clone()).await?;
+
+        let Some(input) = &self.input else {
+            return Err(ActionError::new(
+                device_uid,
+                input_key,
+                t!("action.obs.media_control.err.input_not_configured"),
+            ));
+        };
+
+        let input_id = InputId::Uuid(input.0);
+
+        let action = match self.control {
+            ObsMediaControl::PlayPause => {
+                match client
+                    .as_ref()
+                    .unwrap()
+                    .media_inputs()
+                    .status(input_id)
+                    .await
+                {
+                    Ok(s) if matches!(s.state, MediaState::Playing) => Ok(MediaAction::Pause),
+                    Ok(_) => Ok(MediaAction::Play),
+                    Err(e) => Err(e),
+                }
+            }
+            ObsMediaControl::Restart => Ok(MediaAction::Restart),
+            ObsMediaControl::Stop => Ok(MediaAction::Stop),
+        };
+
+        let res = match action {
+            Ok(action) => {
+                client
+                    .as_ref()
+                    .unwrap()
+                    .media_inputs()
+                    .trigger_action(input_id, action)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+
+        let res = res.map(|_| (input_key, false)).map_err(|_| {
+            ActionError::new(
+                device_uid,
+                input_key,
+                t!("action.obs.media_control.err.failure", input = input.1),
+            )
+        });
+
+        match res {
+            Ok(o) => Ok(o),
+            Err(e) => {
+                client.as_mut().unwrap().disconnect().await;
+                *client = None;
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_OBS_MEDIA.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        if account_warning(ui, &mut self.connection, config).is_none() {
+            return;
+        }
+
+        ui.label("");
+
+        input_select(ui, &self.connection, &mut self.input);
+
+        ui.label(t!("action.obs.media_control.select_control"));
+        ComboBox::from_id_salt("ObsMediaControlSelect")
+            .width(200.0)
+            .selected_text(self.control.title())
+            .show_ui(ui, |ui| {
+                for c in ObsMediaControl::ALL {
+                    ui.selectable_value(&mut self.control, c, c.title());
+                }
+            });
+    }
+
+    pub fn help(&self) -> &str {
+        "action.obs.media_control.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        0
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        &[ICON_BASE]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        1
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[""]
+    }
+}
@@ -1,409 +1,201 @@
-use std::sync::OnceLock;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::io::{BufRead, BufReader, Read};
+use std::process::Stdio;
+use std::time::Duration;
 use std::{process::Command, sync::Arc};
 
-use eframe::egui::{include_image, ComboBox, ImageSource, Slider, TextWrapMode, Ui};
+use eframe::egui::{
+    include_image, ComboBox, Context, DragValue, Id, ImageSource, Slider, TextEdit, TextWrapMode,
+    Ui,
+};
 use egui_phosphor::regular as phos;
+use jukebox_util::toast::{Toast, ToastIcon};
+use reqwest::{header::CONTENT_TYPE, Method};
 use rfd::FileDialog;
 use serde::{Deserialize, Serialize};
-use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use serde_json::Value;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, Signal, System};
 use tokio::{sync::Mutex, task::spawn_blocking};
 
+use crate::audio::{self, AppStreamTarget, AudioError};
+use crate::get_reqwest_client;
 use crate::single_fire;
+use crate::toast::{show_device_toast, ToastSource, TOAST_COLOR_ERROR, TOAST_COLOR_INFO};
 use crate::{config::JukeBoxConfig, input::InputKey};
 
 use super::types::{Action, ActionError};
 
 pub const AID_SYSTEM_OPEN_APP: &str = "SystemOpenApp";
 pub const AID_SYSTEM_OPEN_WEB: &str = "SystemOpenWeb";
+pub const AID_SYSTEM_HTTP_REQUEST: &str = "SystemHttpRequest";
 pub const AID_SYSTEM_SND_IN_CTRL: &str = "SystemSndInCtrl";
 pub const AID_SYSTEM_SND_OUT_CTRL: &str = "SystemSndOutCtrl";
+pub const AID_SYSTEM_SND_APP_VOLUME: &str = "SystemSndAppVolume";
+pub const AID_SYSTEM_SND_APP_MUTE: &str = "SystemSndAppMute";
+pub const AID_SYSTEM_SND_APP_MOVE: &str = "SystemSndAppMove";
+pub const AID_SYSTEM_SND_MUTE_TOGGLE: &str = "SystemSndMuteToggle";
+pub const AID_SYSTEM_SND_SET_DEFAULT: &str = "SystemSndSetDefaultDevice";
+pub const AID_SYSTEM_SND_SET_VOLUME: &str = "SystemSndSetVolume";
 
 const ICON_OPEN_APP: ImageSource =
     include_image!("../../../assets/action-icons/system-appopen.bmp");
 const ICON_OPEN_WEB: ImageSource =
     include_image!("../../../assets/action-icons/system-webopen.bmp");
+// TODO: make an icon for requests
+const ICON_HTTP_REQUEST: ImageSource =
+    include_image!("../../../assets/action-icons/system-webopen.bmp");
 const ICON_INPUT_CONTROL: ImageSource =
     include_image!("../../../assets/action-icons/system-inputcontrol.bmp");
 const ICON_OUTPUT_CONTROL: ImageSource =
     include_image!("../../../assets/action-icons/system-outputcontrol.bmp");
 
-enum AudioCommand {
-    GetInputDevices,
-    GetOutputDevices,
-    AdjustInputDevice(String, i8),
-    AdjustOutputDevice(String, i8),
-}
-
-#[cfg(target_os = "linux")]
-use pulse::{
-    callbacks::ListResult,
-    context::{Context, FlagSet},
-    mainloop::standard::{IterateResult, Mainloop},
-    operation::Operation,
-    operation::State,
-    proplist::{properties::APPLICATION_NAME, Proplist},
-    volume::{ChannelVolumes, Volume},
-};
-#[cfg(target_os = "linux")]
-use std::cell::RefCell;
-#[cfg(target_os = "linux")]
-use std::rc::Rc;
-#[cfg(target_os = "windows")]
-use windows::Win32::{
-    Devices::FunctionDiscovery::*,
-    Media::{
-        Audio::{Endpoints::IAudioEndpointVolume, *},
-        KernelStreaming::GUID_NULL,
-    },
-    System::{Com::*, Variant::VT_EMPTY},
-};
-
-static SYSTEM_AUDIO_CMD_TX: OnceLock<UnboundedSender<AudioCommand>> = OnceLock::new();
-static SYSTEM_SOURCES: OnceLock<Mutex<Option<Vec<String>>>> = OnceLock::new();
-static SYSTEM_SINKS: OnceLock<Mutex<Option<Vec<String>>>> = OnceLock::new();
-
-#[cfg(target_os = "linux")]
-fn wait_for_operation<O: ?Sized>(mainloop: &mut Mainloop, op: Operation<O>) {
-    loop {
-        match mainloop.iterate(false) {
-            IterateResult::Err(_) | IterateResult::Quit(_) => panic!(),
-            IterateResult::Success(_) => (),
-        }
-        match op.get_state() {
-            State::Running => (),
-            State::Cancelled => panic!(),
-            State::Done => break,
-        }
-    }
-}
-
-#[cfg(target_os = "linux")]
-fn get_input_devices(mainloop: &mut Mainloop, context: &mut Context) -> Vec<String> {
-    let devices = Rc::new(RefCell::new(Vec::new()));
-    let i_devices = devices.clone();
-    let op = context.introspect().get_source_info_list(move |l| match l {
-        ListResult::Item(i) => i_devices
-            .borrow_mut()
-            .push(i.description.clone().unwrap_or_default().to_string()),
-        _ => (),
-    });
-    wait_for_operation(mainloop, op);
-    devices.take()
-}
-
-#[cfg(target_os = "linux")]
-fn get_output_devices(mainloop: &mut Mainloop, context: &mut Context) -> Vec<String> {
-    let devices = Rc::new(RefCell::new(Vec::new()));
-    let i_devices = devices.clone();
-    let op = context.introspect().get_sink_info_list(move |l| match l {
-        ListResult::Item(i) => i_devices
-            .borrow_mut()
-            .push(i.description.clone().unwrap_or_default().to_string()),
-        _ => (),
-    });
-    wait_for_operation(mainloop, op);
-    devices.take()
+fn audio_error(device_uid: &str, input_key: InputKey, e: AudioError) -> ActionError {
+    ActionError::new(device_uid, input_key, e.describe())
 }
 
-#[cfg(target_os = "linux")]
-fn adjust_input_device_volume(
-    mainloop: &mut Mainloop,
-    context: &mut Context,
-    source: String,
-    adjust: i8,
-) {
-    let (volume, source) = {
-        let channel_volumes = Rc::new(RefCell::new(None));
-        let c = channel_volumes.clone();
-        let full_source = Rc::new(RefCell::new(None));
-        let f = full_source.clone();
-        let op = context.introspect().get_source_info_list(move |s| match s {
-            ListResult::Item(i) => {
-                if i.description.clone().unwrap().to_string() == source {
-                    *c.borrow_mut() = Some(i.volume.clone());
-                    *f.borrow_mut() = Some(i.name.clone().unwrap().to_string());
-                }
-            }
-            _ => (),
-        });
-        wait_for_operation(mainloop, op);
-        (channel_volumes.take().unwrap(), full_source.take().unwrap())
-    };
-
-    let volume = adjust_volume(adjust, volume);
+// Options for a picker, kept in egui's memory and reloaded in the background each time it opens.
+// None until the first load is done.
+type PickerList<T> = Arc<std::sync::Mutex<Option<Result<Vec<T>, String>>>>;
 
-    let op = context
-        .introspect()
-        .set_source_volume_by_name(&source, &volume, None);
-    wait_for_operation(mainloop, op);
+fn picker_list<T: Send + 'static>(ctx: &Context, id: Id) -> PickerList<T> {
+    ctx.data_mut(|d| d.get_temp_mut_or_default::<PickerList<T>>(id).clone())
 }
 
-#[cfg(target_os = "linux")]
-fn adjust_output_device_volume(
-    mainloop: &mut Mainloop,
-    context: &mut Context,
-    sink: String,
-    adjust: i8,
+fn load_picker_list<T: Send + 'static>(
+    ctx: &Context,
+    list: PickerList<T>,
+    load: impl Future<Output = Result<Vec<T>, AudioError>> + Send + 'static,
 ) {
-    let (volume, sink) = {
-        let channel_volumes = Rc::new(RefCell::new(None));
-        let c = channel_volumes.clone();
-        let full_sink = Rc::new(RefCell::new(None));
-        let f = full_sink.clone();
-        let op = context.introspect().get_sink_info_list(move |s| match s {
-            ListResult::Item(i) => {
-                if i.description.clone().unwrap().to_string() == sink {
-                    *c.borrow_mut() = Some(i.volume.clone());
-                    *f.borrow_mut() = Some(i.name.clone().unwrap().to_string());
-                }
-            }
-            _ => (),
-        });
-        wait_for_operation(mainloop, op);
-        (channel_volumes.take().unwrap(), full_sink.take().unwrap())
-    };
-
-    let volume = adjust_volume(adjust, volume);
-
-    let op = context
-        .introspect()
-        .set_sink_volume_by_name(&sink, &volume, None);
-    wait_for_operation(mainloop, op);
-}
-
-#[cfg(target_os = "linux")]
-fn adjust_volume(a: i8, mut v: ChannelVolumes) -> ChannelVolumes {
-    if a < 0 {
-        *v.decrease(Volume(
-            ((-a as f64 / 100.0) * (Volume::NORMAL.0 as f64)) as u32,
-        ))
-        .unwrap()
-    } else {
-        *v.increase(Volume(
-            ((a as f64 / 100.0) * (Volume::NORMAL.0 as f64)) as u32,
-        ))
-        .unwrap()
-    }
-}
-
-#[cfg(target_os = "windows")]
-fn get_devices(dir: EDataFlow) -> IMMDeviceCollection {
-    unsafe {
-        let device_enumerator: IMMDeviceEnumerator =
-            CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL).unwrap();
-
-        device_enumerator
-            .EnumAudioEndpoints(dir, DEVICE_STATE_ACTIVE)
-            .unwrap()
-    }
-}
-
-#[cfg(target_os = "windows")]
-fn get_device_names(dir: EDataFlow) -> Vec<String> {
-    unsafe {
-        let devices = get_devices(dir);
-
-        let mut output = Vec::new();
-        let count = devices.GetCount().unwrap();
-        for i in 0..count {
-            let item = devices.Item(i).unwrap();
-
-            let properties = item.OpenPropertyStore(STGM_READ).unwrap();
-            let friendly_name = properties.GetValue(&PKEY_Device_FriendlyName).unwrap();
-            assert_ne!(friendly_name.vt(), VT_EMPTY);
-            let name = friendly_name
-                .Anonymous
-                .Anonymous
-                .Anonymous
-                .pwszVal
-                .to_string()
-                .unwrap();
-
-            // let endpoint: IAudioEndpointVolume = item.Activate(CLSCTX_ALL, None)?;
-
-            output.push(name);
-        }
-
-        output
-    }
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        *list.lock().unwrap() = Some(load.await.map_err(|e| e.describe()));
+        ctx.request_repaint();
+    });
 }
 
-#[cfg(target_os = "windows")]
-fn adjust_device_volume(dir: EDataFlow, device_name: String, adjust: i8) {
-    unsafe {
-        let devices = get_devices(dir);
-
-        let count = devices.GetCount().unwrap();
-        for i in 0..count {
-            let item = devices.Item(i).unwrap();
-
-            let properties = item.OpenPropertyStore(STGM_READ).unwrap();
-            let friendly_name = properties.GetValue(&PKEY_Device_FriendlyName).unwrap();
-            assert_ne!(friendly_name.vt(), VT_EMPTY);
-            let name = friendly_name
-                .Anonymous
-                .Anonymous
-                .Anonymous
-                .pwszVal
-                .to_string()
-                .unwrap();
-
-            if name == device_name {
-                let endpoint: IAudioEndpointVolume = item.Activate(CLSCTX_ALL, None).unwrap();
-
-                let current_volume = endpoint.GetMasterVolumeLevelScalar().unwrap();
-                let new_volume = current_volume + (adjust as f32) / 100.0;
-                endpoint
-                    .SetMasterVolumeLevelScalar(new_volume, &GUID_NULL)
-                    .unwrap();
-
-                break;
+// Picks one of the input or output devices the system has right now
+fn device_picker(ui: &mut Ui, id: &str, input: bool, device: &mut Option<String>, loading: &str) {
+    let list = picker_list::<String>(ui.ctx(), Id::new((id, input)));
+    let ir = ComboBox::from_id_salt(id)
+        .selected_text(device.clone().unwrap_or_default())
+        .width(200.0)
+        .wrap_mode(TextWrapMode::Truncate)
+        .show_ui(ui, |ui| match &*list.lock().unwrap() {
+            Some(Ok(devices)) => {
+                for d in devices {
+                    let selected = device.as_ref() == Some(d);
+                    let l = ui.selectable_label(selected, d);
+                    if l.clicked() {
+                        *device = Some(d.clone());
+                    }
+                }
             }
-        }
-    }
-}
-
-fn system_audio_control_loop(mut cmd_rx: UnboundedReceiver<AudioCommand>) {
-    #[cfg(target_os = "linux")]
-    use std::cell::RefCell;
-    #[cfg(target_os = "linux")]
-    use std::rc::Rc;
-    #[cfg(target_os = "linux")]
-    let (mainloop, _proplist, context) = {
-        use std::ops::Deref as _;
-
-        let mut proplist = Proplist::new().unwrap();
-        proplist
-            .set_str(APPLICATION_NAME, "JukeBoxDesktop")
-            .unwrap();
-
-        let mainloop = Rc::new(RefCell::new(
-            Mainloop::new().expect("Failed to create PulseAudio mainloop"),
-        ));
-
-        let context = Rc::new(RefCell::new(
-            Context::new_with_proplist(mainloop.borrow().deref(), "JukeBoxDesktop", &proplist)
-                .expect("Failed to create new context"),
-        ));
-
-        context
-            .borrow_mut()
-            .connect(None, FlagSet::NOFLAGS, None)
-            .expect("Failed to connect context");
-
-        // Wait for context to be ready
-        loop {
-            match mainloop.borrow_mut().iterate(false) {
-                IterateResult::Err(_) | IterateResult::Quit(_) => panic!(),
-                IterateResult::Success(_) => {}
+            Some(Err(e)) => {
+                ui.label(e);
             }
-            match context.borrow().get_state() {
-                pulse::context::State::Ready => break,
-                pulse::context::State::Failed | pulse::context::State::Terminated => panic!(),
-                _ => {}
+            None => {
+                ui.label(loading);
             }
-        }
-
-        (mainloop, proplist, context)
-    };
-
-    #[cfg(target_os = "windows")]
-    unsafe {
-        let _ = CoInitializeEx(None, COINIT_SPEED_OVER_MEMORY);
-    }
-
-    while let Some(cmd) = cmd_rx.blocking_recv() {
-        match cmd {
-            AudioCommand::GetInputDevices => {
-                #[cfg(target_os = "linux")]
-                let devices =
-                    get_input_devices(&mut mainloop.borrow_mut(), &mut context.borrow_mut());
-                #[cfg(target_os = "windows")]
-                let devices = get_device_names(eCapture);
+        });
 
-                let mut system_sources = SYSTEM_SOURCES.get().unwrap().blocking_lock();
-                *system_sources = Some(devices);
-            }
-            AudioCommand::GetOutputDevices => {
-                #[cfg(target_os = "linux")]
-                let devices =
-                    get_output_devices(&mut mainloop.borrow_mut(), &mut context.borrow_mut());
-                #[cfg(target_os = "windows")]
-                let devices = get_device_names(eRender);
-
-                let mut system_sinks = SYSTEM_SINKS.get().unwrap().blocking_lock();
-                *system_sinks = Some(devices);
-            }
-            AudioCommand::AdjustInputDevice(source, adjust) => {
-                #[cfg(target_os = "linux")]
-                adjust_input_device_volume(
-                    &mut mainloop.borrow_mut(),
-                    &mut context.borrow_mut(),
-                    source,
-                    adjust,
-                );
-                #[cfg(target_os = "windows")]
-                adjust_device_volume(eCapture, source, adjust);
-            }
-            AudioCommand::AdjustOutputDevice(sink, adjust) => {
-                #[cfg(target_os = "linux")]
-                adjust_output_device_volume(
-                    &mut mainloop.borrow_mut(),
-                    &mut context.borrow_mut(),
-                    sink,
-                    adjust,
-                );
-                #[cfg(target_os = "windows")]
-                adjust_device_volume(eRender, sink, adjust);
-            }
-        }
-    }
+    single_fire!(ComboBox::is_open(ui.ctx(), ir.response.id), {
+        load_picker_list(ui.ctx(), list, audio::devices(input));
+    });
 }
 
 #[rustfmt::skip]
 pub fn init_actions_system(_config: Arc<Mutex<JukeBoxConfig>>) -> (String, Vec<(String, Action, String)>) {
-    let (cmd_tx, cmd_rx) = unbounded_channel();
-    SYSTEM_AUDIO_CMD_TX.get_or_init(|| cmd_tx);
-    SYSTEM_SOURCES.get_or_init(|| Mutex::new(None));
-    SYSTEM_SINKS.get_or_init(|| Mutex::new(None));
-
-    tokio::spawn(async move {
-        spawn_blocking(move || {
-            system_audio_control_loop(cmd_rx);
-        });
-    });
+    audio::start_audio_service();
 
     (
         t!("action.system.title", icon = phos::DESKTOP_TOWER).into(),
         vec![
-            (AID_SYSTEM_OPEN_APP.into(),     Action::SystemOpenApp(SystemOpenApp::default()),       t!("action.system.open_app.title").into()),
-            (AID_SYSTEM_OPEN_WEB.into(),     Action::SystemOpenWeb(SystemOpenWeb::default()),       t!("action.system.open_web.title").into()),
-            (AID_SYSTEM_SND_IN_CTRL.into(),  Action::SystemSndInCtrl(SystemSndInCtrl::default()),   t!("action.system.snd_in_ctrl.title").into()),
-            (AID_SYSTEM_SND_OUT_CTRL.into(), Action::SystemSndOutCtrl(SystemSndOutCtrl::default()), t!("action.system.snd_out_ctrl.title").into()),
+            (AID_SYSTEM_OPEN_APP.into(),        Action::SystemOpenApp(SystemOpenApp::default()),                         t!("action.system.open_app.title").into()),
+            (AID_SYSTEM_OPEN_WEB.into(),        Action::SystemOpenWeb(SystemOpenWeb::default()),                         t!("action.system.open_web.title").into()),
+            (AID_SYSTEM_HTTP_REQUEST.into(),    Action::SystemHttpRequest(SystemHttpRequest::default()),                 t!("action.system.http_request.title").into()),
+            (AID_SYSTEM_SND_IN_CTRL.into(),     Action::SystemSndInCtrl(SystemSndInCtrl::default()),                     t!("action.system.snd_in_ctrl.title").into()),
+            (AID_SYSTEM_SND_OUT_CTRL.into(),    Action::SystemSndOutCtrl(SystemSndOutCtrl::default()),                   t!("action.system.snd_out_ctrl.title").into()),
+            (AID_SYSTEM_SND_APP_VOLUME.into(),  Action::SystemSndAppVolume(SystemSndAppVolume::default()),               t!("action.system.snd_app_volume.title").into()),
+            (AID_SYSTEM_SND_APP_MUTE.into(),    Action::SystemSndAppMute(SystemSndAppMute::default()),                   t!("action.system.snd_app_mute.title").into()),
+            (AID_SYSTEM_SND_APP_MOVE.into(),    Action::SystemSndAppMove(SystemSndAppMove::default()),                   t!("action.system.snd_app_move.title").into()),
+            (AID_SYSTEM_SND_MUTE_TOGGLE.into(), Action::SystemSndMuteToggle(SystemSndMuteToggle::default()),             t!("action.system.snd_mute_toggle.title").into()),
+            (AID_SYSTEM_SND_SET_DEFAULT.into(), Action::SystemSndSetDefaultDevice(SystemSndSetDefaultDevice::default()), t!("action.system.snd_set_default.title").into()),
+            (AID_SYSTEM_SND_SET_VOLUME.into(),  Action::SystemSndSetVolume(SystemSndSetVolume::default()),               t!("action.system.snd_set_volume.title").into()),
         ],
     )
 }
 
+// What pressing a Launch Application action does
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum OpenAppMode {
+    #[default]
+    Launch, // Starts another copy on every press
+    Toggle, // Starts it when it isn't running, otherwise closes it
+    Shell,  // Runs a shell command and shows what it printed as a toast
+}
+impl OpenAppMode {
+    const ALL: [Self; 3] = [Self::Launch, Self::Toggle, Self::Shell];
+
+    fn title(&self) -> String {
+        match self {
+            Self::Launch => t!("action.system.open_app.mode_launch").into(),
+            Self::Toggle => t!("action.system.open_app.mode_toggle").into(),
+            Self::Shell => t!("action.system.open_app.mode_shell").into(),
+        }
+    }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct SystemOpenApp {
     filepath: String,
     arguments: Vec<String>,
+    #[serde(default)]
+    mode: OpenAppMode,
+    #[serde(default)]
+    command: String, // Shell mode only
+    #[serde(default)]
+    working_dir: String, // Empty starts it where we are
+    #[serde(default)]
+    env: Vec<(String, String)>, // (Variable Name, Value)
+    #[serde(default)]
+    log_output: bool,
 }
 impl SystemOpenApp {
     pub async fn on_press(
         &self,
-        _device_uid: &String,
+        device_uid: &String,
         input_key: InputKey,
         _config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        // spin off the process, drop its handle since we don't care about it completing
-        let _ = Command::new(self.filepath.clone())
-            .args(self.arguments.clone())
-            .spawn();
-
-        // error handling?
-
-        Ok((input_key, false))
+        let res = match self.mode {
+            OpenAppMode::Launch => self.launch(),
+            OpenAppMode::Toggle => {
+                let filepath = self.filepath.clone();
+                match spawn_blocking(move || close_app(&filepath)).await {
+                    Ok(true) => Ok(()),
+                    _ => self.launch(),
+                }
+            }
+            OpenAppMode::Shell => self.run_shell(device_uid),
+        };
+
+        res.map(|_| (input_key, false)).map_err(|e| {
+            let app = match self.mode {
+                OpenAppMode::Shell => &self.command,
+                _ => &self.filepath,
+            };
+            ActionError::new(
+                device_uid,
+                input_key,
+                t!(
+                    "action.system.open_app.err",
+                    app = app,
+                    error = e.to_string()
+                ),
+            )
+        })
     }
 
     pub async fn on_release(
@@ -426,34 +218,94 @@ impl SystemOpenApp {
         _input_key: InputKey,
         _config: Arc<Mutex<JukeBoxConfig>>,
     ) {
-        if ui
-            .button(t!("action.system.open_app.choose_file"))
-            .clicked()
-        {
-            if let Some(f) = FileDialog::new().pick_file() {
-                self.filepath = f.to_str().unwrap().to_owned();
+        ui.label(t!("action.system.open_app.mode"));
+        ComboBox::from_id_salt("SystemOpenAppMode")
+            .width(200.0)
+            .selected_text(self.mode.title())
+            .show_ui(ui, |ui| {
+                for m in OpenAppMode::ALL {
+                    ui.selectable_value(&mut self.mode, m, m.title());
+                }
+            });
+
+        if self.mode == OpenAppMode::Shell {
+            ui.label(t!("action.system.open_app.command"));
+            ui.text_edit_multiline(&mut self.command);
+        } else {
+            if ui
+                .button(t!("action.system.open_app.choose_file"))
+                .clicked()
+            {
+                if let Some(f) = FileDialog::new().pick_file() {
+                    self.filepath = f.to_str().unwrap().to_owned();
+                }
+            }
+            ui.text_edit_singleline(&mut self.filepath);
+            ui.horizontal(|ui| {
+                ui.label(t!("action.system.open_app.add_arguments"));
+                if ui.button("+").clicked() {
+                    self.arguments.push(String::new());
+                }
+            });
+            let mut delete = Vec::new();
+            for (i, a) in self.arguments.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.button(phos::TRASH).clicked() {
+                        delete.push(i);
+                    }
+                    ui.text_edit_singleline(a);
+                });
+            }
+            delete.reverse();
+            for i in delete {
+                self.arguments.remove(i);
             }
         }
-        ui.text_edit_singleline(&mut self.filepath);
+
+        ui.horizontal(|ui| {
+            ui.label(t!("action.system.open_app.working_dir"));
+            if ui.button(t!("action.system.open_app.choose_dir")).clicked() {
+                if let Some(f) = FileDialog::new().pick_folder() {
+                    self.working_dir = f.to_str().unwrap().to_owned();
+                }
+            }
+        });
+        ui.text_edit_singleline(&mut self.working_dir);
+
         ui.horizontal(|ui| {
-            ui.label(t!("action.system.open_app.add_arguments"));
+            ui.label(t!("action.system.open_app.add_env"));
             if ui.button("+").clicked() {
-                self.arguments.push(String::new());
+                self.env.push((String::new(), String::new()));
             }
         });
         let mut delete = Vec::new();
-        for (i, a) in self.arguments.iter_mut().enumerate() {
+        for (i, (k, v)) in self.env.iter_mut().enumerate() {
             ui.horizontal(|ui| {
                 if ui.button(phos::TRASH).clicked() {
                     delete.push(i);
                 }
-                ui.text_edit_singleline(a);
+                ui.add(
+                    TextEdit::singleline(k)
+                        .hint_text(t!("action.system.open_app.env_name"))
+                        .desired_width(80.0),
+                );
+                ui.label("=");
+                ui.add(
+                    TextEdit::singleline(v)
+                        .hint_text(t!("action.system.open_app.env_value"))
+                        .desired_width(120.0),
+                );
             });
         }
         delete.reverse();
         for i in delete {
-            self.arguments.remove(i);
+            self.env.remove(i);
         }
+
+        ui.checkbox(
+            &mut self.log_output,
+            t!("action.system.open_app.log_output"),
+        );
     }
 
     pub fn help(&self) -> &str {
@@ -477,6 +329,162 @@ impl SystemOpenApp {
     }
 }
 
+impl SystemOpenApp {
+    fn command(&self, program: &str, args: &[String]) -> Command {
+        let mut cmd = Command::new(program);
+        cmd.args(args)
+            .envs(self.env.iter().filter(|(k, _)| !k.is_empty()).cloned());
+        if !self.working_dir.is_empty() {
+            cmd.current_dir(&self.working_dir);
+        }
+        cmd
+    }
+
+    fn launch(&self) -> std::io::Result<()> {
+        let mut cmd = self.command(&self.filepath, &self.arguments);
+        if self.log_output {
+            cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+        let mut child = cmd.spawn()?;
+        let pid = child.id();
+        launched_apps()
+            .entry(self.filepath.clone())
+            .or_default()
+            .push(pid);
+
+        log_lines(&self.filepath, child.stdout.take(), log::Level::Info);
+        log_lines(&self.filepath, child.stderr.take(), log::Level::Warn);
+
+        // Reaps it once it exits, so it doesn't hang around as a zombie
+        let filepath = self.filepath.clone();
+        std::thread::spawn(move || {
+            let _ = child.wait();
+            let mut apps = launched_apps();
+            if let Some(pids) = apps.get_mut(&filepath) {
+                pids.retain(|p| *p != pid);
+                if pids.is_empty() {
+                    apps.remove(&filepath);
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn run_shell(&self, device_uid: &str) -> std::io::Result<()> {
+        // cmd.exe doesn't follow the quoting Command::arg uses, so the command goes in as it is
+        #[cfg(target_os = "windows")]
+        let mut cmd = {
+            use std::os::windows::process::CommandExt;
+            let mut cmd = self.command("cmd", &["/C".into()]);
+            cmd.raw_arg(&self.command);
+            cmd
+        };
+        #[cfg(not(target_os = "windows"))]
+        let mut cmd = self.command("sh", &["-c".into(), self.command.clone()]);
+
+        let child = cmd
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let device_uid = device_uid.to_string();
+        let log_output = self.log_output;
+        std::thread::spawn(move || {
+            let Ok(output) = child.wait_with_output() else {
+                return;
+            };
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if log_output {
+                stdout.lines().for_each(|l| log::info!("sh: {}", l));
+                stderr.lines().for_each(|l| log::warn!("sh: {}", l));
+            }
+
+            let (title, text, icon, color) = if output.status.success() {
+                (
+                    t!("toast.command.done"),
+                    &stdout,
+                    ToastIcon::Info,
+                    TOAST_COLOR_INFO,
+                )
+            } else {
+                let code = output.status.code().map(|c| c.to_string());
+                let text = if stderr.trim().is_empty() {
+                    &stdout
+                } else {
+                    &stderr
+                };
+                (
+                    t!("toast.command.failed", code = code.unwrap_or("?".into())),
+                    text,
+                    ToastIcon::Error,
+                    TOAST_COLOR_ERROR,
+                )
+            };
+            // Only a line fits on screen, the last one is usually the answer
+            let body = text.lines().rev().find(|l| !l.trim().is_empty());
+            show_device_toast(
+                ToastSource::Command,
+                &device_uid,
+                Toast::new(&title, body.unwrap_or_default(), icon, 0, color),
+            );
+        });
+
+        Ok(())
+    }
+}
+
+// Passes what an app prints on to our log, line by line
+fn log_lines(name: &str, out: Option<impl Read + Send + 'static>, level: log::Level) {
+    let Some(out) = out else {
+        return;
+    };
+    let name = name.to_string();
+    std::thread::spawn(move || {
+        for line in BufReader::new(out).lines().map_while(Result::ok) {
+            log::log!(level, "{}: {}", name, line);
+        }
+    });
+}
+
+// App Path -> IDs of the processes we started that are still running
+static LAUNCHED_APPS: std::sync::Mutex<BTreeMap<String, Vec<u32>>> =
+    std::sync::Mutex::new(BTreeMap::new());
+
+fn launched_apps() -> std::sync::MutexGuard<'static, BTreeMap<String, Vec<u32>>> {
+    LAUNCHED_APPS.lock().unwrap()
+}
+
+// Closes the copies of an app we started, returning whether there were any. Copies started some
+// other way are left alone.
+fn close_app(filepath: &str) -> bool {
+    let pids: Vec<_> = match launched_apps().get(filepath) {
+        Some(pids) => pids.iter().map(|p| Pid::from_u32(*p)).collect(),
+        None => return false,
+    };
+
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&pids),
+        true,
+        ProcessRefreshKind::nothing(),
+    );
+
+    let mut found = false;
+    for pid in pids {
+        if let Some(p) = sys.process(pid) {
+            found = true;
+            // Give it a chance to close cleanly where we can
+            if p.kill_with(Signal::Term).is_none() {
+                p.kill();
+            }
+        }
+    }
+    found
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct SystemOpenWeb {
     url: String,
@@ -548,28 +556,200 @@ impl SystemOpenWeb {
     }
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
-pub struct SystemSndInCtrl {
-    input_device: Option<String>,
-    vol_adjust: i8,
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    #[default]
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
 }
-impl SystemSndInCtrl {
+impl HttpMethod {
+    const ALL: [Self; 5] = [Self::Get, Self::Post, Self::Put, Self::Patch, Self::Delete];
+
+    fn method(&self) -> Method {
+        match self {
+            Self::Get => Method::GET,
+            Self::Post => Method::POST,
+            Self::Put => Method::PUT,
+            Self::Patch => Method::PATCH,
+            Self::Delete => Method::DELETE,
+        }
+    }
+}
+
+// What a request's answer is used for, through a field of its JSON
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum HttpResponseUse {
+    #[default]
+    Nothing,
+    IconState, // On when the field matches
+    Label,     // Shows the field
+}
+impl HttpResponseUse {
+    const ALL: [Self; 3] = [Self::Nothing, Self::IconState, Self::Label];
+
+    fn title(&self) -> String {
+        match self {
+            Self::Nothing => t!("action.system.http_request.response_nothing").into(),
+            Self::IconState => t!("action.system.http_request.response_icon_state").into(),
+            Self::Label => t!("action.system.http_request.response_label").into(),
+        }
+    }
+}
+
+const HTTP_DEFAULT_TIMEOUT: u32 = 5000;
+
+// Device UID, Key and Request -> Response field, as text
+static HTTP_RESPONSES: std::sync::Mutex<BTreeMap<String, String>> =
+    std::sync::Mutex::new(BTreeMap::new());
+
+// Fills in the variables a request can use
+fn fill_template<V: AsRef<str>>(s: &str, vars: &[(&str, V)]) -> String {
+    vars.iter().fold(s.to_string(), |s, (k, v)| {
+        s.replace(&format!("{{{}}}", k), v.as_ref())
+    })
+}
+
+// The variables escaped for the inside of a JSON string, so a quote in a profile name can't end it
+fn json_escaped<'a>(vars: &[(&'a str, &str)]) -> Vec<(&'a str, String)> {
+    vars.iter()
+        .map(|(k, v)| {
+            let quoted = Value::from(*v).to_string();
+            (*k, quoted[1..quoted.len() - 1].to_string())
+        })
+        .collect()
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SystemHttpRequest {
+    method: HttpMethod,
+    url: String,
+    headers: Vec<(String, String)>, // (Header Name, Value)
+    body: String,
+    timeout_ms: u32,
+    expected_status: Option<u16>, // None takes any success
+    response_use: HttpResponseUse,
+    response_field: String, // JSON pointer, like /state
+    on_value: String,       // Field value that shows the on icon
+}
+impl Default for SystemHttpRequest {
+    fn default() -> Self {
+        Self {
+            method: HttpMethod::default(),
+            url: String::new(),
+            headers: Vec::new(),
+            body: String::new(),
+            timeout_ms: HTTP_DEFAULT_TIMEOUT,
+            expected_status: None,
+            response_use: HttpResponseUse::default(),
+            response_field: String::new(),
+            on_value: String::new(),
+        }
+    }
+}
+impl SystemHttpRequest {
     pub async fn on_press(
         &self,
-        _device_uid: &String,
+        device_uid: &String,
         input_key: InputKey,
-        _config: Arc<Mutex<JukeBoxConfig>>,
+        config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        // TODO: error handling
-        if let Some(input_device) = self.input_device.clone() {
-            let adjust = self.vol_adjust;
-            let _ = SYSTEM_AUDIO_CMD_TX
-                .get()
-                .unwrap()
-                .send(AudioCommand::AdjustInputDevice(input_device, adjust));
+        let profile = config.lock().await.current_profile.clone();
+        let key = input_key.to_string();
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        let vars = [
+            ("device_uid", device_uid.as_str()),
+            ("key", key.as_str()),
+            ("profile", profile.as_str()),
+            ("timestamp", timestamp.as_str()),
+        ];
+        let url = fill_template(&self.url, &vars);
+        let err = |msg: Cow<str>| ActionError::new(device_uid, input_key, msg);
+
+        let mut req = get_reqwest_client()
+            .request(self.method.method(), &url)
+            .timeout(Duration::from_millis(self.timeout_ms as u64));
+        for (name, value) in &self.headers {
+            if !name.trim().is_empty() {
+                req = req.header(name.trim(), fill_template(value, &vars));
+            }
+        }
+        if !self.body.is_empty() {
+            let content_type = self
+                .headers
+                .iter()
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case(CONTENT_TYPE.as_str()))
+                .map(|(_, value)| value.to_ascii_lowercase());
+            let json = match &content_type {
+                Some(t) => t.contains("json"),
+                None => self.body.trim_start().starts_with(['{', '[']),
+            };
+
+            if content_type.is_none() {
+                let t = if json {
+                    "application/json"
+                } else {
+                    "text/plain; charset=utf-8"
+                };
+                req = req.header(CONTENT_TYPE, t);
+            }
+            req = req.body(if json {
+                fill_template(&self.body, &json_escaped(&vars))
+            } else {
+                fill_template(&self.body, &vars)
+            });
         }
 
-        Ok((input_key, false))
+        let r = req.send().await.map_err(|e| {
+            err(t!(
+                "action.system.http_request.err.request",
+                url = url,
+                error = e.to_string()
+            ))
+        })?;
+
+        let status = r.status();
+        let expected = match self.expected_status {
+            Some(s) => status.as_u16() == s,
+            None => status.is_success(),
+        };
+        if !expected {
+            return Err(err(t!(
+                "action.system.http_request.err.status",
+                url = url,
+                status = status.as_u16()
+            )));
+        }
+
+        if self.response_use == HttpResponseUse::Nothing {
+            return Ok((input_key, false));
+        }
+
+        let json: Value = r.json().await.map_err(|e| {
+            err(t!(
+                "action.system.http_request.err.json",
+                url = url,
+                error = e.to_string()
+            ))
+        })?;
+        let value = json.pointer(&self.pointer()).ok_or_else(|| {
+            err(t!(
+                "action.system.http_request.err.field",
+                field = self.response_field
+            ))
+        })?;
+        let value = match value {
+            Value::String(s) => s.clone(),
+            v => v.to_string(),
+        };
+        HTTP_RESPONSES
+            .lock()
+            .unwrap()
+            .insert(self.response_key(device_uid, input_key), value);
+
+        Ok((input_key, true))
     }
 
     pub async fn on_release(
@@ -582,7 +762,7 @@ impl SystemSndInCtrl {
     }
 
     pub fn get_type(&self) -> String {
-        AID_SYSTEM_SND_IN_CTRL.into()
+        AID_SYSTEM_HTTP_REQUEST.into()
     }
 
     pub fn edit_ui(
@@ -592,78 +772,255 @@ impl SystemSndInCtrl {
         _input_key: InputKey,
         _config: Arc<Mutex<JukeBoxConfig>>,
     ) {
-        ui.label(t!("action.system.snd_in_ctrl.input_device"));
-        let ir = ComboBox::from_id_salt("SystemAudioInputControlDeviceSelect")
-            .selected_text(self.input_device.clone().unwrap_or_default())
-            .width(200.0)
-            .wrap_mode(TextWrapMode::Truncate)
-            .show_ui(ui, |ui| {
-                let sources = SYSTEM_SOURCES.get().unwrap().blocking_lock();
-                if let Some(sources) = &*sources {
-                    for source in sources {
-                        let selected = *source == self.input_device.clone().unwrap_or_default();
-                        let l = ui.selectable_label(selected, source);
-                        if l.clicked() {
-                            self.input_device = Some(source.clone());
-                        }
+        ui.label(t!("action.system.http_request.url"));
+        ui.horizontal(|ui| {
+            ComboBox::from_id_salt("SystemHttpRequestMethod")
+                .width(70.0)
+                .selected_text(self.method.method().as_str())
+                .show_ui(ui, |ui| {
+                    for m in HttpMethod::ALL {
+                        ui.selectable_value(&mut self.method, m, m.method().as_str());
                     }
-                } else {
-                    ui.label(t!("action.system.snd_in_ctrl.loading"));
+                });
+            ui.text_edit_singleline(&mut self.url);
+        });
+
+        ui.horizontal(|ui| {
+            ui.label(t!("action.system.http_request.add_headers"));
+            if ui.button("+").clicked() {
+                self.headers.push((String::new(), String::new()));
+            }
+        });
+        let mut delete = Vec::new();
+        for (i, (k, v)) in self.headers.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.button(phos::TRASH).clicked() {
+                    delete.push(i);
                 }
+                ui.add(
+                    TextEdit::singleline(k)
+                        .hint_text(t!("action.system.http_request.header_name"))
+                        .desired_width(100.0),
+                );
+                ui.add(
+                    TextEdit::singleline(v)
+                        .hint_text(t!("action.system.http_request.header_value"))
+                        .desired_width(120.0),
+                );
             });
+        }
+        delete.reverse();
+        for i in delete {
+            self.headers.remove(i);
+        }
 
-        single_fire!(ComboBox::is_open(ui.ctx(), ir.response.id), {
-            *SYSTEM_SOURCES.get().unwrap().blocking_lock() = None;
-            let _ = SYSTEM_AUDIO_CMD_TX
-                .get()
-                .unwrap()
-                .send(AudioCommand::GetInputDevices);
-        });
+        ui.label(t!("action.system.http_request.body"));
+        ui.add(
+            TextEdit::multiline(&mut self.body)
+                .hint_text(t!("action.system.http_request.body_hint")),
+        );
 
-        ui.label(t!("action.system.snd_in_ctrl.volume_adjust"));
-        ui.add(Slider::new(&mut self.vol_adjust, -100..=100));
+        ui.label(t!("action.system.http_request.timeout"));
+        ui.add(
+            Slider::new(&mut self.timeout_ms, 250..=30000)
+                .step_by(250.0)
+                .suffix(" ms"),
+        );
+
+        let mut expect = self.expected_status.is_some();
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut expect, t!("action.system.http_request.expect_status"));
+            if let Some(status) = &mut self.expected_status {
+                ui.add(DragValue::new(status).range(100..=599));
+            }
+        })
+        .response
+        .on_hover_text_at_pointer(t!("action.system.http_request.expect_status_help"));
+        if expect != self.expected_status.is_some() {
+            self.expected_status = expect.then_some(200);
+        }
+
+        ui.label(t!("action.system.http_request.response"));
+        ComboBox::from_id_salt("SystemHttpRequestResponse")
+            .width(200.0)
+            .selected_text(self.response_use.title())
+            .show_ui(ui, |ui| {
+                for r in HttpResponseUse::ALL {
+                    ui.selectable_value(&mut self.response_use, r, r.title());
+                }
+            });
+        if self.response_use != HttpResponseUse::Nothing {
+            ui.label(t!("action.system.http_request.response_field"));
+            ui.add(TextEdit::singleline(&mut self.response_field).hint_text("/state"));
+        }
+        if self.response_use == HttpResponseUse::IconState {
+            ui.label(t!("action.system.http_request.on_value"));
+            ui.add(TextEdit::singleline(&mut self.on_value).hint_text("on"));
+        }
     }
 
     pub fn help(&self) -> &str {
-        "action.system.snd_in_ctrl.help"
+        "action.system.http_request.help"
     }
 
+    // Off until it's known which key it's on, see key_icon_state
     pub fn icon_state(&self) -> u8 {
         0
     }
 
+    pub fn key_icon_state(&self, device_uid: &str, input_key: InputKey) -> u8 {
+        if self.response_use != HttpResponseUse::IconState {
+            return 0;
+        }
+        let responses = HTTP_RESPONSES.lock().unwrap();
+        (responses.get(&self.response_key(device_uid, input_key)) == Some(&self.on_value)) as u8
+    }
+
     pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
-        &[ICON_INPUT_CONTROL]
+        // TODO: make icons for on and off
+        &[ICON_HTTP_REQUEST, ICON_HTTP_REQUEST]
     }
 
     pub fn icon_state_count(&self) -> u8 {
-        1
+        match self.response_use {
+            HttpResponseUse::IconState => 2,
+            _ => 1,
+        }
     }
 
     pub fn icon_state_descriptions(&self) -> &[&str] {
-        &[""]
+        &[
+            "action.system.http_request.icon_state_0",
+            "action.system.http_request.icon_state_1",
+        ]
+    }
+
+    pub fn icon_text(&self, device_uid: &str, input_key: InputKey) -> Option<String> {
+        if self.response_use != HttpResponseUse::Label {
+            return None;
+        }
+        HTTP_RESPONSES
+            .lock()
+            .unwrap()
+            .get(&self.response_key(device_uid, input_key))
+            .cloned()
+    }
+
+    // Where the answer is kept. Each key has its own, as the variables filled in can differ by
+    // key, and the request is part of it so an edited one doesn't show the old answer.
+    fn response_key(&self, device_uid: &str, input_key: InputKey) -> String {
+        format!(
+            "{} {} {:?} {} {}",
+            device_uid, input_key, self.method, self.url, self.response_field
+        )
+    }
+
+    // Takes fields with or without the leading slash
+    fn pointer(&self) -> String {
+        if self.response_field.starts_with('/') {
+            self.response_field.clone()
+        } else {
+            format!("/{}", self.response_field)
+        }
     }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
-pub struct SystemSndOutCtrl {
-    output_device: Option<String>,
+pub struct SystemSndInCtrl {
+    input_device: Option<String>,
     vol_adjust: i8,
 }
-impl SystemSndOutCtrl {
+impl SystemSndInCtrl {
     pub async fn on_press(
         &self,
-        _device_uid: &String,
+        device_uid: &String,
         input_key: InputKey,
         _config: Arc<Mutex<JukeBoxConfig>>,
     ) -> Result<(InputKey, bool), ActionError> {
-        // TODO: error handling
-        if let Some(output_device) = self.output_device.clone() {
-            let adjust = self.vol_adjust;
-            let _ = SYSTEM_AUDIO_CMD_TX
-                .get()
-                .unwrap()
-                .send(AudioCommand::AdjustOutputDevice(output_device, adjust));
+        if let Some(input_device) = self.input_device.clone() {
+            audio::adjust_device_volume(true, input_device, self.vol_adjust)
+                .await
+                .map_err(|e| audio_error(device_uid, input_key, e))?;
+        }
+
+        Ok((input_key, false))
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_SYSTEM_SND_IN_CTRL.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        ui.label(t!("action.system.snd_in_ctrl.input_device"));
+        device_picker(
+            ui,
+            "SystemAudioInputControlDeviceSelect",
+            true,
+            &mut self.input_device,
+            &t!("action.system.snd_in_ctrl.loading"),
+        );
+
+        ui.label(t!("action.system.snd_in_ctrl.volume_adjust"));
+        ui.add(Slider::new(&mut self.vol_adjust, -100..=100));
+    }
+
+    pub fn help(&self) -> &str {
+        "action.system.snd_in_ctrl.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        0
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        &[ICON_INPUT_CONTROL]
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        1
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[""]
+    }
+
+    pub fn icon_text(&self) -> Option<String> {
+        let device = self.input_device.as_ref()?;
+        audio::device_state(true, device).map(|s| format!("{}%", s.volume))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SystemSndOutCtrl {
+    output_device: Option<String>,
+    vol_adjust: i8,
+}
+impl SystemSndOutCtrl {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        if let Some(output_device) = self.output_device.clone() {
+            audio::adjust_device_volume(false, output_device, self.vol_adjust)
+                .await
+                .map_err(|e| audio_error(device_uid, input_key, e))?;
         }
 
         Ok((input_key, false))
@@ -690,32 +1047,13 @@ impl SystemSndOutCtrl {
         _config: Arc<Mutex<JukeBoxConfig>>,
     ) {
         ui.label(t!("action.system.snd_out_ctrl.output_device"));
-        let ir = ComboBox::from_id_salt("SystemAudioOutputControlDeviceSelect")
-            .selected_text(self.output_device.clone().unwrap_or_default())
-            .width(200.0)
-            .wrap_mode(TextWrapMode::Truncate)
-            .show_ui(ui, |ui| {
-                let sinks = SYSTEM_SINKS.get().unwrap().blocking_lock();
-                if let Some(sinks) = &*sinks {
-                    for sink in sinks {
-                        let selected = *sink == self.output_device.clone().unwrap_or_default();
-                        let l = ui.selectable_label(selected, sink);
-                        if l.clicked() {
-                            self.output_device = Some(sink.clone());
-                        }
-                    }
-                } else {
-                    ui.label(t!("action.system.snd_out_ctrl.loading"));
-                }
-            });
-
-        single_fire!(ComboBox::is_open(ui.ctx(), ir.response.id), {
-            *SYSTEM_SINKS.get().unwrap().blocking_lock() = None;
-            let _ = SYSTEM_AUDIO_CMD_TX
-                .get()
-                .unwrap()
-                .send(AudioCommand::GetOutputDevices);
-        });
+        device_picker(
+            ui,
+            "SystemAudioOutputControlDeviceSelect",
+            false,
+            &mut self.output_device,
+            &t!("action.system.snd_out_ctrl.loading"),
+        );
 
         ui.label(t!("action.system.snd_out_ctrl.volume_adjust"));
         ui.add(Slider::new(&mut self.vol_adjust, -100..=100));
@@ -740,4 +1078,604 @@ impl SystemSndOutCtrl {
     pub fn icon_state_descriptions(&self) -> &[&str] {
         &[""]
     }
+
+    pub fn icon_text(&self) -> Option<String> {
+        let device = self.output_device.as_ref()?;
+        audio::device_state(false, device).map(|s| format!("{}%", s.volume))
+    }
+}
+
+// Picks an application from the streams playing or recording right now
+fn app_stream_select(ui: &mut Ui, id: &str, target: &mut Option<AppStreamTarget>) {
+    ui.label(t!("action.system.app_stream.stream"));
+    let streams = picker_list::<audio::AppStream>(ui.ctx(), Id::new(id));
+    let ir = ComboBox::from_id_salt(id)
+        .selected_text(target.as_ref().map(|t| t.title()).unwrap_or_default())
+        .width(200.0)
+        .wrap_mode(TextWrapMode::Truncate)
+        .show_ui(ui, |ui| match &*streams.lock().unwrap() {
+            Some(Ok(streams)) if streams.is_empty() => {
+                ui.label(t!("action.system.app_stream.none"));
+            }
+            Some(Ok(streams)) => {
+                let by_binary = target.as_ref().is_some_and(|t| t.by_binary);
+                for stream in streams {
+                    let mut t = stream.target.clone();
+                    t.by_binary = by_binary;
+
+                    let icon = match (t.capture, stream.muted) {
+                        (false, false) => phos::SPEAKER_HIGH,
+                        (false, true) => phos::SPEAKER_SLASH,
+                        (true, false) => phos::MICROPHONE,
+                        (true, true) => phos::MICROPHONE_SLASH,
+                    };
+                    let selected = target.as_ref() == Some(&t);
+                    let l = ui.selectable_label(selected, format!("{} {}", icon, t.app()));
+                    if l.clicked() {
+                        *target = Some(t);
+                    }
+                }
+            }
+            Some(Err(e)) => {
+                ui.label(e);
+            }
+            None => {
+                ui.label(t!("action.system.app_stream.loading"));
+            }
+        });
+
+    single_fire!(ComboBox::is_open(ui.ctx(), ir.response.id), {
+        load_picker_list(ui.ctx(), streams, audio::app_streams());
+    });
+
+    if let Some(target) = target {
+        ui.checkbox(
+            &mut target.by_binary,
+            t!("action.system.app_stream.by_binary"),
+        )
+        .on_hover_text_at_pointer(t!("action.system.app_stream.by_binary_help"));
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SystemSndAppVolume {
+    target: Option<AppStreamTarget>,
+    vol_adjust: i8,
+}
+impl SystemSndAppVolume {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        if let Some(target) = self.target.clone() {
+            audio::adjust_app_volume(target, self.vol_adjust)
+                .await
+                .map_err(|e| audio_error(device_uid, input_key, e))?;
+        }
+
+        Ok((input_key, false))
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_SYSTEM_SND_APP_VOLUME.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        app_stream_select(ui, "SystemAudioAppVolumeStreamSelect", &mut self.target);
+
+        ui.label(t!("action.system.snd_app_volume.volume_adjust"));
+        ui.add(Slider::new(&mut self.vol_adjust, -100..=100));
+    }
+
+    pub fn help(&self) -> &str {
+        "action.system.snd_app_volume.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        0
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        if self.target.as_ref().is_some_and(|t| t.capture) {
+            &[ICON_INPUT_CONTROL]
+        } else {
+            &[ICON_OUTPUT_CONTROL]
+        }
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        1
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[""]
+    }
+
+    pub fn icon_text(&self) -> Option<String> {
+        let target = self.target.as_ref()?;
+        audio::app_volume(target).map(|v| format!("{}%", v))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SystemSndAppMute {
+    target: Option<AppStreamTarget>,
+}
+impl SystemSndAppMute {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        if let Some(target) = self.target.clone() {
+            audio::toggle_app_mute(target)
+                .await
+                .map_err(|e| audio_error(device_uid, input_key, e))?;
+        }
+
+        Ok((input_key, false))
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_SYSTEM_SND_APP_MUTE.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        app_stream_select(ui, "SystemAudioAppMuteStreamSelect", &mut self.target);
+    }
+
+    pub fn help(&self) -> &str {
+        "action.system.snd_app_mute.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        let Some(target) = &self.target else {
+            return 0;
+        };
+        if audio::app_muted(target) {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        // TODO: muted icons
+        if self.target.as_ref().is_some_and(|t| t.capture) {
+            &[ICON_INPUT_CONTROL, ICON_INPUT_CONTROL]
+        } else {
+            &[ICON_OUTPUT_CONTROL, ICON_OUTPUT_CONTROL]
+        }
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        2
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[
+            "action.system.snd_app_mute.icon_state_0",
+            "action.system.snd_app_mute.icon_state_1",
+        ]
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SystemSndAppMove {
+    target: Option<AppStreamTarget>,
+    device: Option<String>,
+}
+impl SystemSndAppMove {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        if let (Some(target), Some(device)) = (self.target.clone(), self.device.clone()) {
+            audio::move_app(target, device)
+                .await
+                .map_err(|e| audio_error(device_uid, input_key, e))?;
+        }
+
+        Ok((input_key, false))
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_SYSTEM_SND_APP_MOVE.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        let capture = self.target.as_ref().is_some_and(|t| t.capture);
+        app_stream_select(ui, "SystemAudioAppMoveStreamSelect", &mut self.target);
+        if self.target.as_ref().is_some_and(|t| t.capture) != capture {
+            // Playback goes to outputs and recording comes from inputs, the old pick doesn't fit
+            self.device = None;
+        }
+        let capture = self.target.as_ref().is_some_and(|t| t.capture);
+
+        ui.label(t!("action.system.snd_app_move.device"));
+        device_picker(
+            ui,
+            "SystemAudioAppMoveDeviceSelect",
+            capture,
+            &mut self.device,
+            &t!("action.system.snd_app_move.loading"),
+        );
+    }
+
+    pub fn help(&self) -> &str {
+        "action.system.snd_app_move.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        0
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        if self.target.as_ref().is_some_and(|t| t.capture) {
+            &[ICON_INPUT_CONTROL]
+        } else {
+            &[ICON_OUTPUT_CONTROL]
+        }
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        1
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[""]
+    }
+}
+
+// Picks an input or output device, for actions that work on either
+fn audio_device_select(ui: &mut Ui, id: &str, input: &mut bool, device: &mut Option<String>) {
+    ui.horizontal(|ui| {
+        if ui
+            .selectable_label(!*input, t!("action.system.audio_device.output"))
+            .clicked()
+            && *input
+        {
+            *input = false;
+            *device = None;
+        }
+        if ui
+            .selectable_label(*input, t!("action.system.audio_device.input"))
+            .clicked()
+            && !*input
+        {
+            *input = true;
+            *device = None;
+        }
+    });
+
+    ui.label(t!("action.system.audio_device.device"));
+    device_picker(
+        ui,
+        id,
+        *input,
+        device,
+        &t!("action.system.audio_device.loading"),
+    );
+}
+
+fn audio_device_state(input: bool, device: &Option<String>) -> Option<audio::AudioDeviceState> {
+    audio::device_state(input, device.as_ref()?)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SystemSndMuteToggle {
+    input: bool,
+    device: Option<String>,
+}
+impl SystemSndMuteToggle {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        if let Some(device) = self.device.clone() {
+            audio::toggle_device_mute(self.input, device)
+                .await
+                .map_err(|e| audio_error(device_uid, input_key, e))?;
+        }
+
+        Ok((input_key, false))
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_SYSTEM_SND_MUTE_TOGGLE.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        audio_device_select(
+            ui,
+            "SystemAudioMuteToggleDeviceSelect",
+            &mut self.input,
+            &mut self.device,
+        );
+    }
+
+    pub fn help(&self) -> &str {
+        "action.system.snd_mute_toggle.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        if audio_device_state(self.input, &self.device).is_some_and(|s| s.muted) {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        // TODO: muted icons
+        if self.input {
+            &[ICON_INPUT_CONTROL, ICON_INPUT_CONTROL]
+        } else {
+            &[ICON_OUTPUT_CONTROL, ICON_OUTPUT_CONTROL]
+        }
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        2
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[
+            "action.system.snd_mute_toggle.icon_state_0",
+            "action.system.snd_mute_toggle.icon_state_1",
+        ]
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SystemSndSetDefaultDevice {
+    input: bool,
+    device: Option<String>,
+}
+impl SystemSndSetDefaultDevice {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        if let Some(device) = self.device.clone() {
+            audio::set_default_device(self.input, device)
+                .await
+                .map_err(|e| audio_error(device_uid, input_key, e))?;
+        }
+
+        Ok((input_key, false))
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_SYSTEM_SND_SET_DEFAULT.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        audio_device_select(
+            ui,
+            "SystemAudioSetDefaultDeviceSelect",
+            &mut self.input,
+            &mut self.device,
+        );
+    }
+
+    pub fn help(&self) -> &str {
+        "action.system.snd_set_default.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        if audio_device_state(self.input, &self.device).is_some_and(|s| s.default) {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        // TODO: default device icons
+        if self.input {
+            &[ICON_INPUT_CONTROL, ICON_INPUT_CONTROL]
+        } else {
+            &[ICON_OUTPUT_CONTROL, ICON_OUTPUT_CONTROL]
+        }
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        2
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[
+            "action.system.snd_set_default.icon_state_0",
+            "action.system.snd_set_default.icon_state_1",
+        ]
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SystemSndSetVolume {
+    input: bool,
+    device: Option<String>,
+    volume: u8, // Percent
+}
+impl SystemSndSetVolume {
+    pub async fn on_press(
+        &self,
+        device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        if let Some(device) = self.device.clone() {
+            audio::set_device_volume(self.input, device, self.volume)
+                .await
+                .map_err(|e| audio_error(device_uid, input_key, e))?;
+        }
+
+        Ok((input_key, false))
+    }
+
+    pub async fn on_release(
+        &self,
+        _device_uid: &String,
+        input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) -> Result<(InputKey, bool), ActionError> {
+        Ok((input_key, false))
+    }
+
+    pub fn get_type(&self) -> String {
+        AID_SYSTEM_SND_SET_VOLUME.into()
+    }
+
+    pub fn edit_ui(
+        &mut self,
+        ui: &mut Ui,
+        _device_uid: &String,
+        _input_key: InputKey,
+        _config: Arc<Mutex<JukeBoxConfig>>,
+    ) {
+        audio_device_select(
+            ui,
+            "SystemAudioSetVolumeDeviceSelect",
+            &mut self.input,
+            &mut self.device,
+        );
+
+        ui.label(t!("action.system.snd_set_volume.volume"));
+        ui.add(Slider::new(&mut self.volume, 0..=100).suffix("%"));
+    }
+
+    pub fn help(&self) -> &str {
+        "action.system.snd_set_volume.help"
+    }
+
+    pub fn icon_state(&self) -> u8 {
+        0
+    }
+
+    pub fn icon_state_icons(&'_ self) -> &[ImageSource<'_>] {
+        if self.input {
+            &[ICON_INPUT_CONTROL]
+        } else {
+            &[ICON_OUTPUT_CONTROL]
+        }
+    }
+
+    pub fn icon_state_count(&self) -> u8 {
+        1
+    }
+
+    pub fn icon_state_descriptions(&self) -> &[&str] {
+        &[""]
+    }
+
+    pub fn icon_text(&self) -> Option<String> {
+        audio_device_state(self.input, &self.device).map(|s| format!("{}%", s.volume))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_escapes_json() {
+        let vars = [("profile", "My \"Best\" Profile\\"), ("key", "KeySwitch1")];
+        let body = r#"{"profile": "{profile}", "key": "{key}"}"#;
+
+        let filled = fill_template(body, &json_escaped(&vars));
+        let json: Value = serde_json::from_str(&filled).unwrap();
+        assert_eq!(json["profile"], "My \"Best\" Profile\\");
+        assert_eq!(json["key"], "KeySwitch1");
+
+        // Anywhere else they go in as they are
+        assert_eq!(
+            fill_template("/{key}/{profile}", &vars),
+            "/KeySwitch1/My \"Best\" Profile\\"
+        );
+    }
 }
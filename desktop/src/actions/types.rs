@@ -7,19 +7,22 @@ use std::{
 };
 
 use eframe::egui::{
-    load::Bytes, Image, ImageSource, TextureFilter, TextureOptions, TextureWrapMode, Ui,
+    load::Bytes, pos2, Image, ImageSource, Rect, TextureFilter, TextureOptions, TextureWrapMode, Ui,
 };
-use jukebox_util::peripheral::DeviceType;
+use jukebox_util::{peripheral::DeviceType, screen::ICON_DATA_SIZE};
 use serde::{Deserialize, Serialize};
 use tokio::sync::{Mutex, MutexGuard};
 
 use crate::{
-    actions::{discord::*, input::*, meta::*, obs::*, system::*},
+    actions::{discord::*, input::*, meta::*, mqtt::*, obs::*, system::*},
     config::{ActionConfig, ActionIcon, JukeBoxConfig},
+    icon::{compose_icon_label, decode_icon_frames, icon_frame_count, IconFrame},
     input::InputKey,
 };
 
 pub static ICON_CACHE: OnceLock<Mutex<HashMap<String, Vec<u8>>>> = OnceLock::new();
+// The last text each key's action reported, so icons sent from anywhere show the same label
+pub static ACTION_TEXTS: OnceLock<Mutex<HashMap<(String, InputKey), String>>> = OnceLock::new();
 
 #[derive(Debug, Clone)]
 pub struct ActionError {
@@ -163,8 +166,15 @@ create_actions! {
 
     SystemOpenApp,
     SystemOpenWeb,
+    SystemHttpRequest,
     SystemSndInCtrl,
     SystemSndOutCtrl,
+    SystemSndAppVolume,
+    SystemSndAppMute,
+    SystemSndAppMove,
+    SystemSndMuteToggle,
+    SystemSndSetDefaultDevice,
+    SystemSndSetVolume,
 
     InputKeyboard,
     InputMouse,
@@ -181,16 +191,71 @@ create_actions! {
     ObsPreviewSceneSwitch,
     ObsPreviewScenePush,
     ObsSceneCollectionSwitch,
-    // ObsFilter,
-    // ObsTransition,
+    ObsFilter,
+    ObsTransition,
+    ObsStudioTransition,
+    ObsStudioMode,
+    ObsInputVolume,
+    ObsScreenshot,
+    ObsVirtualCam,
+    ObsHotkey,
+    ObsMedia,
     ObsChapterMarker,
 
     DiscordToggleMute,
     DiscordToggleDeafen,
-    // DiscordToggleNoiseSuppression,
+    DiscordToggleNoiseSuppression,
     DiscordPushToTalk,
     DiscordPushToMute,
-    DiscordPushToDeafen
+    DiscordPushToDeafen,
+    DiscordToggleAutoGain,
+    DiscordToggleEchoCancellation,
+    DiscordInputVolume,
+    DiscordOutputVolume,
+    DiscordSelectDevice,
+    DiscordVoiceChannel,
+
+    MqttPublish,
+    MqttTopicState
+}
+
+impl Action {
+    // Text for the key's label, like a scene name or a running timer
+    pub async fn icon_text(&self, device_uid: &str, input_key: InputKey) -> Option<String> {
+        match self {
+            Self::MetaSwitchProfile(x) => x.icon_text(),
+            Self::SystemHttpRequest(x) => x.icon_text(device_uid, input_key),
+            Self::SystemSndInCtrl(x) => x.icon_text(),
+            Self::SystemSndOutCtrl(x) => x.icon_text(),
+            Self::SystemSndAppVolume(x) => x.icon_text(),
+            Self::SystemSndSetVolume(x) => x.icon_text(),
+            Self::ObsStream(x) => x.icon_text().await,
+            Self::ObsRecord(x) => x.icon_text().await,
+            Self::ObsSceneSwitch(x) => x.icon_text(),
+            Self::ObsPreviewSceneSwitch(x) => x.icon_text(),
+            Self::DiscordInputVolume(x) => x.icon_text(),
+            Self::DiscordOutputVolume(x) => x.icon_text(),
+            Self::DiscordSelectDevice(x) => x.icon_text(),
+            Self::MqttTopicState(x) => x.icon_text(),
+            _ => None,
+        }
+    }
+
+    // The icon state on a particular key, for actions that answer differently from key to key
+    pub fn key_icon_state(&self, device_uid: &str, input_key: InputKey) -> u8 {
+        match self {
+            Self::SystemHttpRequest(x) => x.key_icon_state(device_uid, input_key),
+            _ => self.icon_state(),
+        }
+    }
+
+    // Color for the key's LED in place of the RGB profile's, like from an MQTT topic
+    pub fn key_color(&self) -> Option<(u8, u8, u8)> {
+        match self {
+            Self::MqttTopicState(x) => x.key_color(),
+            _ => None,
+        }
+    }
 }
 
 pub struct ActionMap {
@@ -206,9 +271,9 @@ impl ActionMap {
             init_actions_meta(config.clone()),
             init_actions_input(config.clone()),
             init_actions_system(config.clone()),
-            #[cfg(feature = "discord")]
             init_actions_discord(config.clone()),
             init_actions_obs(config.clone()),
+            init_actions_mqtt(config.clone()),
         ];
 
         let ui_list = l
@@ -243,6 +308,7 @@ impl ActionMap {
         ActionConfig {
             action: Action::InputKeyboard(InputKeyboard { keys: vec![key] }),
             icons: vec![ActionIcon::DefaultActionIcon],
+            label: Default::default(),
         }
     }
 
@@ -295,45 +361,89 @@ pub async fn get_icon_cache_async<'a>() -> MutexGuard<'a, HashMap<String, Vec<u8
         .await
 }
 
-pub fn get_icon_bytes<'a>(
-    action_config: &ActionConfig,
-    icon_cache: &mut MutexGuard<'a, HashMap<String, Vec<u8>>>,
-) -> [u8; 32 * 32 * 2] {
-    let icon_state = action_config.action.icon_state();
-    let icon = &action_config.icons[icon_state as usize];
+pub fn get_action_texts<'a>() -> MutexGuard<'a, HashMap<(String, InputKey), String>> {
+    ACTION_TEXTS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .blocking_lock()
+}
+
+pub async fn get_action_texts_async<'a>() -> MutexGuard<'a, HashMap<(String, InputKey), String>> {
+    ACTION_TEXTS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .await
+}
 
-    let b = match icon {
+fn get_icon_data(
+    action_config: &ActionConfig,
+    device_uid: &str,
+    input_key: InputKey,
+    icon_cache: &mut MutexGuard<'_, HashMap<String, Vec<u8>>>,
+) -> Option<Vec<u8>> {
+    match action_config.current_icon(device_uid, input_key) {
         ActionIcon::ImageIcon(i) => {
             if !icon_cache.contains_key(i) {
-                // TODO: use fallback in cases where we can't read icon?
-                icon_cache.insert(
-                    i.into(),
-                    std::fs::read(i).expect("failed to read icon data"),
-                );
+                match std::fs::read(i) {
+                    Ok(b) => {
+                        icon_cache.insert(i.into(), b);
+                    }
+                    Err(e) => {
+                        log::error!("failed to read icon data from {}: {}", i, e);
+                        return None;
+                    }
+                }
             }
 
-            icon_cache.get(i).unwrap().clone()
+            icon_cache.get(i).cloned()
         }
         ActionIcon::DefaultActionIcon => match action_config.action.icon_source() {
-            ImageSource::Uri(_) => panic!(),
-            ImageSource::Texture(_) => panic!(),
             ImageSource::Bytes { uri: _, bytes } => match bytes {
-                Bytes::Static(items) => items.to_vec(),
-                Bytes::Shared(items) => items.to_vec(),
+                Bytes::Static(items) => Some(items.to_vec()),
+                Bytes::Shared(items) => Some(items.to_vec()),
             },
+            _ => None,
         },
-    };
-
-    let (_, b) = b.split_at(0x7A);
+    }
+}
 
-    if b.len() != (32 * 32 * 2) {
-        panic!();
+/// Every frame of an action's current icon, with its label drawn on top. Icons that can't be read
+/// or decoded come out blank.
+pub fn get_icon_bytes<'a>(
+    action_config: &ActionConfig,
+    device_uid: &str,
+    input_key: InputKey,
+    action_text: Option<&str>,
+    icon_cache: &mut MutexGuard<'a, HashMap<String, Vec<u8>>>,
+) -> Vec<IconFrame> {
+    let mut frames = get_icon_data(action_config, device_uid, input_key, icon_cache)
+        .and_then(|b| {
+            decode_icon_frames(&b)
+                .inspect_err(|e| log::error!("failed to decode icon: {}", e))
+                .ok()
+        })
+        .unwrap_or(vec![[0u8; ICON_DATA_SIZE]]);
+
+    if let Some(text) = action_config.label.resolve(action_text) {
+        compose_icon_label(&mut frames, &action_config.label, &text);
     }
 
-    let mut bytes = [0u8; 32 * 32 * 2];
-    bytes.copy_from_slice(b);
+    frames
+}
+
+/// Animated icons are stored as a strip of frames, this picks out the first one for display.
+pub fn get_icon_uv(path: &String) -> Rect {
+    let mut icon_cache = get_icon_cache();
+    if !icon_cache.contains_key(path) {
+        if let Ok(b) = std::fs::read(path) {
+            icon_cache.insert(path.clone(), b);
+        }
+    }
 
-    bytes
+    let frames = icon_cache
+        .get(path)
+        .map(|b| icon_frame_count(b))
+        .unwrap_or(1);
+    Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0 / frames as f32))
 }
 
 #[macro_export]
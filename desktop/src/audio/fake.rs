@@ -0,0 +1,285 @@
+// Made up devices and apps kept in memory, for trying out the audio actions without a sound server
+
+use std::collections::HashMap;
+
+use super::{AppStream, AppStreamTarget, AudioBackend, AudioDeviceState, AudioError};
+
+struct FakeApp {
+    target: AppStreamTarget,
+    volume: u8,
+    muted: bool,
+}
+
+pub struct FakeBackend {
+    // (Input?, Device Name) -> State
+    devices: HashMap<(bool, String), AudioDeviceState>,
+    apps: Vec<FakeApp>,
+}
+impl FakeBackend {
+    pub fn new() -> Self {
+        let device = |default| AudioDeviceState {
+            muted: false,
+            default,
+            volume: 50,
+        };
+        let app = |name: &str, capture| FakeApp {
+            target: AppStreamTarget {
+                name: name.into(),
+                binary: name.to_lowercase(),
+                by_binary: false,
+                capture,
+            },
+            volume: 100,
+            muted: false,
+        };
+
+        Self {
+            devices: HashMap::from([
+                ((true, "Fake Microphone".into()), device(true)),
+                ((true, "Fake Headset Microphone".into()), device(false)),
+                ((false, "Fake Speakers".into()), device(true)),
+                ((false, "Fake Headphones".into()), device(false)),
+            ]),
+            apps: vec![
+                app("Music Player", false),
+                app("Voice Chat", false),
+                app("Voice Chat", true),
+            ],
+        }
+    }
+
+    fn device(&mut self, input: bool, device: &str) -> Result<&mut AudioDeviceState, AudioError> {
+        self.devices
+            .get_mut(&(input, device.to_string()))
+            .ok_or_else(|| AudioError::DeviceNotFound(device.into()))
+    }
+
+    fn apps(&mut self, target: &AppStreamTarget) -> Result<Vec<&mut FakeApp>, AudioError> {
+        let apps: Vec<_> = self
+            .apps
+            .iter_mut()
+            .filter(|a| a.target.capture == target.capture)
+            .filter(|a| {
+                let app = if target.by_binary {
+                    &a.target.binary
+                } else {
+                    &a.target.name
+                };
+                app == target.app()
+            })
+            .collect();
+        if apps.is_empty() {
+            return Err(AudioError::StreamNotFound(target.app().into()));
+        }
+        Ok(apps)
+    }
+}
+impl AudioBackend for FakeBackend {
+    fn devices(&mut self, input: bool) -> Result<Vec<String>, AudioError> {
+        let mut devices: Vec<_> = self
+            .devices
+            .keys()
+            .filter(|(i, _)| *i == input)
+            .map(|(_, name)| name.clone())
+            .collect();
+        devices.sort();
+        Ok(devices)
+    }
+
+    fn device_states(&mut self) -> Result<HashMap<(bool, String), AudioDeviceState>, AudioError> {
+        Ok(self.devices.clone())
+    }
+
+    fn adjust_device_volume(
+        &mut self,
+        input: bool,
+        device: &str,
+        adjust: i8,
+    ) -> Result<u8, AudioError> {
+        let state = self.device(input, device)?;
+        state.volume = (state.volume as i16 + adjust as i16).clamp(0, 100) as u8;
+        Ok(state.volume)
+    }
+
+    fn set_device_volume(
+        &mut self,
+        input: bool,
+        device: &str,
+        percent: u8,
+    ) -> Result<(), AudioError> {
+        self.device(input, device)?.volume = percent.min(100);
+        Ok(())
+    }
+
+    fn toggle_device_mute(&mut self, input: bool, device: &str) -> Result<bool, AudioError> {
+        let state = self.device(input, device)?;
+        state.muted = !state.muted;
+        Ok(state.muted)
+    }
+
+    fn set_default_device(&mut self, input: bool, device: &str) -> Result<(), AudioError> {
+        self.device(input, device)?;
+        for ((i, name), state) in self.devices.iter_mut() {
+            if *i == input {
+                state.default = name == device;
+            }
+        }
+        Ok(())
+    }
+
+    fn app_streams(&mut self) -> Result<Vec<AppStream>, AudioError> {
+        Ok(self
+            .apps
+            .iter()
+            .map(|a| AppStream {
+                target: a.target.clone(),
+                muted: a.muted,
+            })
+            .collect())
+    }
+
+    fn adjust_app_volume(
+        &mut self,
+        target: &AppStreamTarget,
+        adjust: i8,
+    ) -> Result<u8, AudioError> {
+        let mut percent = 0;
+        for app in self.apps(target)? {
+            app.volume = (app.volume as i16 + adjust as i16).clamp(0, 100) as u8;
+            percent = percent.max(app.volume);
+        }
+        Ok(percent)
+    }
+
+    fn toggle_app_mute(&mut self, target: &AppStreamTarget) -> Result<bool, AudioError> {
+        let apps = self.apps(target)?;
+        let mute = !apps.iter().all(|a| a.muted);
+        for app in apps {
+            app.muted = mute;
+        }
+        Ok(mute)
+    }
+
+    fn app_state(&mut self, target: &AppStreamTarget) -> Result<(bool, u8), AudioError> {
+        let apps = self.apps(target)?;
+        let volume = apps.iter().map(|a| a.volume).max().unwrap_or(0);
+        Ok((apps.iter().all(|a| a.muted), volume))
+    }
+
+    fn move_app(&mut self, target: &AppStreamTarget, device: &str) -> Result<(), AudioError> {
+        // Nothing tracks where fake streams play, only check both ends exist
+        self.device(target.capture, device)?;
+        self.apps(target).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(name: &str, capture: bool) -> AppStreamTarget {
+        AppStreamTarget {
+            name: name.into(),
+            capture,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn lists_devices() {
+        let mut b = FakeBackend::new();
+        assert_eq!(
+            b.devices(true).unwrap(),
+            ["Fake Headset Microphone", "Fake Microphone"]
+        );
+        assert_eq!(
+            b.devices(false).unwrap(),
+            ["Fake Headphones", "Fake Speakers"]
+        );
+        assert_eq!(b.device_states().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn sets_and_reads_volume() {
+        let mut b = FakeBackend::new();
+        b.set_device_volume(false, "Fake Speakers", 80).unwrap();
+        assert_eq!(
+            b.adjust_device_volume(false, "Fake Speakers", 30).unwrap(),
+            100
+        );
+        assert_eq!(
+            b.adjust_device_volume(false, "Fake Speakers", -120)
+                .unwrap(),
+            0
+        );
+        b.set_device_volume(false, "Fake Speakers", 150).unwrap();
+
+        let states = b.device_states().unwrap();
+        assert_eq!(states[&(false, "Fake Speakers".into())].volume, 100);
+        // Other devices are left alone
+        assert_eq!(states[&(true, "Fake Microphone".into())].volume, 50);
+
+        assert!(matches!(
+            b.set_device_volume(true, "Fake Speakers", 10),
+            Err(AudioError::DeviceNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn toggles_mute() {
+        let mut b = FakeBackend::new();
+        assert!(b.toggle_device_mute(true, "Fake Microphone").unwrap());
+        assert!(b.device_states().unwrap()[&(true, "Fake Microphone".into())].muted);
+        assert!(!b.toggle_device_mute(true, "Fake Microphone").unwrap());
+        assert!(!b.device_states().unwrap()[&(true, "Fake Microphone".into())].muted);
+
+        // Both Voice Chat playback streams follow, the recording one doesn't
+        assert!(b.toggle_app_mute(&target("Voice Chat", false)).unwrap());
+        let muted: Vec<_> = b
+            .app_streams()
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.target.app().to_string(), s.target.capture, s.muted))
+            .collect();
+        assert_eq!(
+            muted,
+            [
+                ("Music Player".to_string(), false, false),
+                ("Voice Chat".to_string(), false, true),
+                ("Voice Chat".to_string(), true, false),
+            ]
+        );
+        assert_eq!(
+            b.app_state(&target("Voice Chat", false)).unwrap(),
+            (true, 100)
+        );
+        assert_eq!(
+            b.app_state(&target("Voice Chat", true)).unwrap(),
+            (false, 100)
+        );
+
+        assert!(matches!(
+            b.toggle_app_mute(&target("Music Player", true)),
+            Err(AudioError::StreamNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn switches_default_device() {
+        let mut b = FakeBackend::new();
+        b.set_default_device(false, "Fake Headphones").unwrap();
+
+        let states = b.device_states().unwrap();
+        assert!(states[&(false, "Fake Headphones".into())].default);
+        assert!(!states[&(false, "Fake Speakers".into())].default);
+        // Inputs keep their own default
+        assert!(states[&(true, "Fake Microphone".into())].default);
+
+        // An unknown device changes nothing
+        assert!(matches!(
+            b.set_default_device(false, "Nowhere"),
+            Err(AudioError::DeviceNotFound(_))
+        ));
+        assert!(b.device_states().unwrap()[&(false, "Fake Headphones".into())].default);
+    }
+}
@@ -0,0 +1,420 @@
+// Audio devices and application streams, for the system actions. A service thread owns the
+// platform backend, reconnects it when the sound server goes away (like a PipeWire restart), and
+// answers requests sent to it over a channel.
+
+mod fake;
+#[cfg(target_os = "linux")]
+mod pulse;
+#[cfg(target_os = "windows")]
+mod windows;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Mutex, MutexGuard, OnceLock},
+    time::{Duration, Instant},
+};
+
+use egui_phosphor::regular as phos;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{
+    mpsc::{error::TryRecvError, unbounded_channel, UnboundedReceiver, UnboundedSender},
+    oneshot,
+};
+
+use crate::actions::action::notify_icon_states_changed;
+
+// Set JUKEBOX_AUDIO_BACKEND to "fake" to use made up devices instead of the system's
+const AUDIO_BACKEND_ENV: &str = "JUKEBOX_AUDIO_BACKEND";
+// How long the service naps between checks for requests and device changes
+const AUDIO_IDLE_INTERVAL: Duration = Duration::from_millis(20);
+// Backends without change notifications get their device states polled
+const AUDIO_STATE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const RECONNECT_MIN_INTERVAL: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub enum AudioError {
+    Unavailable(String), // The sound server can't be reached, the service reconnects on its own
+    DeviceNotFound(String),
+    StreamNotFound(String),
+    Unsupported,
+    Failed(String),
+}
+impl AudioError {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Unavailable(e) => t!("action.system.audio_err.unavailable", error = e).into(),
+            Self::DeviceNotFound(d) => {
+                t!("action.system.audio_err.device_not_found", device = d).into()
+            }
+            Self::StreamNotFound(a) => {
+                t!("action.system.audio_err.stream_not_found", app = a).into()
+            }
+            Self::Unsupported => t!("action.system.audio_err.unsupported").into(),
+            Self::Failed(e) => t!("action.system.audio_err.failed", error = e).into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioDeviceState {
+    pub muted: bool,
+    pub default: bool,
+    pub volume: u8, // Percent
+}
+
+// Which application's streams an action works on. Apps can have several streams open at once,
+// actions apply to all of them.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+pub struct AppStreamTarget {
+    pub name: String,   // application.name
+    pub binary: String, // application.process.binary
+    pub by_binary: bool,
+    pub capture: bool, // A recording stream (source-output) instead of playback (sink-input)
+}
+impl AppStreamTarget {
+    pub fn app(&self) -> &str {
+        if self.by_binary {
+            &self.binary
+        } else {
+            &self.name
+        }
+    }
+
+    pub fn title(&self) -> String {
+        let icon = if self.capture {
+            phos::MICROPHONE
+        } else {
+            phos::SPEAKER_HIGH
+        };
+        format!("{} {}", icon, self.app())
+    }
+}
+
+// A live application stream, for the stream picker
+#[derive(Debug, Clone)]
+pub struct AppStream {
+    pub target: AppStreamTarget,
+    pub muted: bool,
+}
+
+/// What a platform's sound system has to offer. `input` picks input devices (sources) over output
+/// devices (sinks). Devices go by their description, the name users see.
+pub trait AudioBackend {
+    fn devices(&mut self, input: bool) -> Result<Vec<String>, AudioError>;
+    fn device_states(&mut self) -> Result<HashMap<(bool, String), AudioDeviceState>, AudioError>;
+    fn adjust_device_volume(
+        &mut self,
+        input: bool,
+        device: &str,
+        adjust: i8,
+    ) -> Result<u8, AudioError>;
+    fn set_device_volume(
+        &mut self,
+        input: bool,
+        device: &str,
+        percent: u8,
+    ) -> Result<(), AudioError>;
+    fn toggle_device_mute(&mut self, input: bool, device: &str) -> Result<bool, AudioError>;
+    fn set_default_device(&mut self, input: bool, device: &str) -> Result<(), AudioError>;
+
+    fn app_streams(&mut self) -> Result<Vec<AppStream>, AudioError>;
+    fn adjust_app_volume(&mut self, target: &AppStreamTarget, adjust: i8)
+        -> Result<u8, AudioError>;
+    fn toggle_app_mute(&mut self, target: &AppStreamTarget) -> Result<bool, AudioError>;
+    /// Whether all of the app's streams are muted, and the loudest one's volume.
+    fn app_state(&mut self, target: &AppStreamTarget) -> Result<(bool, u8), AudioError>;
+    fn move_app(&mut self, target: &AppStreamTarget, device: &str) -> Result<(), AudioError>;
+
+    /// Handles whatever the sound server sent. True when device or app states may have changed.
+    fn poll_changes(&mut self) -> Result<bool, AudioError> {
+        Ok(false)
+    }
+
+    /// False when changes made outside of JukeBox go unnoticed, and states have to be polled.
+    fn notifies_changes(&self) -> bool {
+        true
+    }
+}
+
+// Runs against the backend, or gets None while it's disconnected. True when the connection was lost.
+type AudioJob = Box<dyn FnOnce(Option<&mut dyn AudioBackend>) -> bool + Send>;
+
+static AUDIO_TX: OnceLock<UnboundedSender<AudioJob>> = OnceLock::new();
+
+// What key icons and labels show, read without waiting on the service
+#[derive(Default)]
+struct AudioState {
+    devices: HashMap<(bool, String), AudioDeviceState>,
+    apps: HashSet<AppStreamTarget>, // Apps keys show, kept up to date by the service
+    app_mutes: HashMap<AppStreamTarget, bool>,
+    app_volumes: HashMap<AppStreamTarget, u8>,
+}
+
+static AUDIO_STATE: OnceLock<Mutex<AudioState>> = OnceLock::new();
+
+fn audio_state() -> MutexGuard<'static, AudioState> {
+    AUDIO_STATE
+        .get_or_init(|| Mutex::new(AudioState::default()))
+        .lock()
+        .unwrap()
+}
+
+pub fn start_audio_service() {
+    let (tx, rx) = unbounded_channel::<AudioJob>();
+    if AUDIO_TX.set(tx).is_err() {
+        return; // already running
+    }
+
+    // Backends block and aren't Send (PulseAudio), so they get a thread of their own
+    std::thread::spawn(move || audio_service(rx));
+}
+
+fn connect() -> Result<Box<dyn AudioBackend>, AudioError> {
+    if std::env::var(AUDIO_BACKEND_ENV).as_deref() == Ok("fake") {
+        return Ok(Box::new(fake::FakeBackend::new()));
+    }
+
+    #[cfg(target_os = "linux")]
+    let backend = pulse::PulseBackend::connect().map(|b| Box::new(b) as Box<dyn AudioBackend>);
+    #[cfg(target_os = "windows")]
+    let backend = windows::WindowsBackend::connect().map(|b| Box::new(b) as Box<dyn AudioBackend>);
+
+    backend
+}
+
+fn audio_service(mut rx: UnboundedReceiver<AudioJob>) {
+    let mut backoff = RECONNECT_MIN_INTERVAL;
+
+    loop {
+        let mut backend = match connect() {
+            Ok(b) => {
+                log::info!("audio: connected to the sound server");
+                backoff = RECONNECT_MIN_INTERVAL;
+                b
+            }
+            Err(e) => {
+                log::warn!(
+                    "audio: failed to connect, retrying in {:?}: {:?}",
+                    backoff,
+                    e
+                );
+
+                // Keep answering while down, so nothing sits waiting on us
+                let retry = Instant::now() + backoff;
+                while Instant::now() < retry {
+                    match rx.try_recv() {
+                        Ok(job) => {
+                            job(None);
+                        }
+                        Err(TryRecvError::Empty) => std::thread::sleep(AUDIO_IDLE_INTERVAL),
+                        Err(TryRecvError::Disconnected) => return,
+                    }
+                }
+                backoff = (backoff * 2).min(RECONNECT_MAX_INTERVAL);
+                continue;
+            }
+        };
+
+        let mut refresh = true;
+        let mut last_refresh = Instant::now();
+        loop {
+            match rx.try_recv() {
+                Ok(job) => {
+                    if job(Some(backend.as_mut())) {
+                        break;
+                    }
+                    refresh = true;
+                }
+                Err(TryRecvError::Disconnected) => return,
+                Err(TryRecvError::Empty) => {
+                    match backend.poll_changes() {
+                        Ok(changed) => refresh |= changed,
+                        Err(e) => {
+                            log::warn!("audio: {:?}", e);
+                            break;
+                        }
+                    }
+                    if !backend.notifies_changes()
+                        && last_refresh.elapsed() >= AUDIO_STATE_POLL_INTERVAL
+                    {
+                        refresh = true;
+                    }
+
+                    // Apps keys just started showing need their states too
+                    let unknown_apps = {
+                        let state = audio_state();
+                        state.apps.iter().any(|a| !state.app_mutes.contains_key(a))
+                    };
+
+                    if refresh || unknown_apps {
+                        refresh = false;
+                        last_refresh = Instant::now();
+                        match backend.device_states() {
+                            Ok(states) => update_device_states(states),
+                            Err(AudioError::Unavailable(e)) => {
+                                log::warn!("audio: {}", e);
+                                break;
+                            }
+                            Err(e) => log::warn!("audio: failed to get device states: {:?}", e),
+                        }
+                        match app_states(backend.as_mut()) {
+                            Ok(states) => update_app_states(states),
+                            Err(e) => {
+                                log::warn!("audio: {:?}", e);
+                                break;
+                            }
+                        }
+                    }
+
+                    std::thread::sleep(AUDIO_IDLE_INTERVAL);
+                }
+            }
+        }
+
+        log::warn!("audio: lost the sound server, reconnecting");
+    }
+}
+
+// Stores the latest device states, and refreshes key icons when any changed
+fn update_device_states(states: HashMap<(bool, String), AudioDeviceState>) {
+    let mut state = audio_state();
+    if state.devices != states {
+        state.devices = states;
+        notify_icon_states_changed();
+    }
+}
+
+// Mute and volume of every app keys show. Apps that aren't running count as unmuted with no volume.
+fn app_states(
+    backend: &mut dyn AudioBackend,
+) -> Result<HashMap<AppStreamTarget, Option<(bool, u8)>>, AudioError> {
+    let apps: Vec<_> = audio_state().apps.iter().cloned().collect();
+    let mut states = HashMap::new();
+    for app in apps {
+        let state = match backend.app_state(&app) {
+            Ok(s) => Some(s),
+            Err(AudioError::StreamNotFound(_)) | Err(AudioError::Unsupported) => None,
+            Err(AudioError::Unavailable(e)) => return Err(AudioError::Unavailable(e)),
+            Err(e) => {
+                log::warn!("audio: failed to get the state of {}: {:?}", app.app(), e);
+                None
+            }
+        };
+        states.insert(app, state);
+    }
+    Ok(states)
+}
+
+// Stores the latest app states, and refreshes key icons when any mute changed
+fn update_app_states(states: HashMap<AppStreamTarget, Option<(bool, u8)>>) {
+    let mut state = audio_state();
+    let mut changed = false;
+    for (app, s) in states {
+        let muted = s.is_some_and(|(m, _)| m);
+        changed |= state.app_mutes.insert(app.clone(), muted) != Some(muted);
+        match s {
+            Some((_, volume)) => state.app_volumes.insert(app, volume),
+            None => state.app_volumes.remove(&app),
+        };
+    }
+    if changed {
+        notify_icon_states_changed();
+    }
+}
+
+async fn request<T: Send + 'static>(
+    f: impl FnOnce(&mut dyn AudioBackend) -> Result<T, AudioError> + Send + 'static,
+) -> Result<T, AudioError> {
+    let (tx, rx) = oneshot::channel();
+    let job: AudioJob = Box::new(move |backend| {
+        let res = match backend {
+            Some(b) => f(b),
+            None => Err(AudioError::Unavailable(
+                "not connected to the sound server".into(),
+            )),
+        };
+        let lost = matches!(res, Err(AudioError::Unavailable(_)));
+        let _ = tx.send(res);
+        lost
+    });
+
+    let stopped = || AudioError::Unavailable("the audio service is not running".into());
+    AUDIO_TX
+        .get()
+        .ok_or_else(stopped)?
+        .send(job)
+        .map_err(|_| stopped())?;
+    rx.await.map_err(|_| stopped())?
+}
+
+pub async fn devices(input: bool) -> Result<Vec<String>, AudioError> {
+    request(move |b| b.devices(input)).await
+}
+
+pub async fn adjust_device_volume(
+    input: bool,
+    device: String,
+    adjust: i8,
+) -> Result<u8, AudioError> {
+    let d = device.clone();
+    let volume = request(move |b| b.adjust_device_volume(input, &d, adjust)).await?;
+    if let Some(s) = audio_state().devices.get_mut(&(input, device)) {
+        s.volume = volume;
+    }
+    Ok(volume)
+}
+
+pub async fn set_device_volume(input: bool, device: String, percent: u8) -> Result<(), AudioError> {
+    request(move |b| b.set_device_volume(input, &device, percent)).await
+}
+
+pub async fn toggle_device_mute(input: bool, device: String) -> Result<bool, AudioError> {
+    request(move |b| b.toggle_device_mute(input, &device)).await
+}
+
+pub async fn set_default_device(input: bool, device: String) -> Result<(), AudioError> {
+    request(move |b| b.set_default_device(input, &device)).await
+}
+
+pub async fn app_streams() -> Result<Vec<AppStream>, AudioError> {
+    request(|b| b.app_streams()).await
+}
+
+pub async fn adjust_app_volume(target: AppStreamTarget, adjust: i8) -> Result<u8, AudioError> {
+    let t = target.clone();
+    let volume = request(move |b| b.adjust_app_volume(&t, adjust)).await?;
+    audio_state().app_volumes.insert(target, volume);
+    Ok(volume)
+}
+
+pub async fn toggle_app_mute(target: AppStreamTarget) -> Result<bool, AudioError> {
+    let t = target.clone();
+    let muted = request(move |b| b.toggle_app_mute(&t)).await?;
+    audio_state().app_mutes.insert(target, muted);
+    notify_icon_states_changed();
+    Ok(muted)
+}
+
+pub async fn move_app(target: AppStreamTarget, device: String) -> Result<(), AudioError> {
+    request(move |b| b.move_app(&target, &device)).await
+}
+
+pub fn device_state(input: bool, device: &str) -> Option<AudioDeviceState> {
+    audio_state()
+        .devices
+        .get(&(input, device.to_string()))
+        .copied()
+}
+
+pub fn app_muted(target: &AppStreamTarget) -> bool {
+    let mut state = audio_state();
+    state.apps.insert(target.clone());
+    state.app_mutes.get(target).copied().unwrap_or(false)
+}
+
+pub fn app_volume(target: &AppStreamTarget) -> Option<u8> {
+    let mut state = audio_state();
+    state.apps.insert(target.clone());
+    state.app_volumes.get(target).copied()
+}
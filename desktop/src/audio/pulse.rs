@@ -0,0 +1,473 @@
+// PulseAudio, and PipeWire through its PulseAudio server
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
+
+use pulse::{
+    callbacks::ListResult,
+    context::{subscribe::InterestMaskSet, Context, FlagSet, State as ContextState},
+    mainloop::standard::{IterateResult, Mainloop},
+    operation::{Operation, State},
+    proplist::{
+        properties::{APPLICATION_NAME, APPLICATION_PROCESS_BINARY},
+        Proplist,
+    },
+    volume::{ChannelVolumes, Volume},
+};
+
+use super::{AppStream, AppStreamTarget, AudioBackend, AudioDeviceState, AudioError};
+
+fn unavailable(e: impl ToString) -> AudioError {
+    AudioError::Unavailable(e.to_string())
+}
+
+fn iterate(mainloop: &mut Mainloop, block: bool) -> Result<(), AudioError> {
+    match mainloop.iterate(block) {
+        IterateResult::Success(_) => Ok(()),
+        IterateResult::Quit(_) => Err(unavailable("mainloop quit")),
+        IterateResult::Err(e) => Err(unavailable(e)),
+    }
+}
+
+fn wait_for_operation<O: ?Sized>(
+    mainloop: &mut Mainloop,
+    op: Operation<O>,
+) -> Result<(), AudioError> {
+    loop {
+        match op.get_state() {
+            State::Done => return Ok(()),
+            State::Cancelled => return Err(AudioError::Failed("operation cancelled".into())),
+            State::Running => iterate(mainloop, true)?,
+        }
+    }
+}
+
+fn volume_percent(v: &ChannelVolumes) -> u8 {
+    (v.avg().0 as f64 / Volume::NORMAL.0 as f64 * 100.0)
+        .round()
+        .min(u8::MAX as f64) as u8
+}
+
+fn percent_volume(p: f64) -> Volume {
+    Volume(((p / 100.0) * (Volume::NORMAL.0 as f64)) as u32)
+}
+
+fn adjust_volume(a: i8, mut v: ChannelVolumes) -> ChannelVolumes {
+    if a < 0 {
+        v.decrease(percent_volume(-a as f64));
+    } else {
+        v.increase(percent_volume(a as f64));
+    }
+    v
+}
+
+fn app_stream_target(proplist: &Proplist, capture: bool) -> AppStreamTarget {
+    AppStreamTarget {
+        name: proplist.get_str(APPLICATION_NAME).unwrap_or_default(),
+        binary: proplist
+            .get_str(APPLICATION_PROCESS_BINARY)
+            .unwrap_or_default(),
+        by_binary: false,
+        capture,
+    }
+}
+
+fn app_stream_matches(target: &AppStreamTarget, proplist: &Proplist) -> bool {
+    let key = if target.by_binary {
+        APPLICATION_PROCESS_BINARY
+    } else {
+        APPLICATION_NAME
+    };
+    !target.app().is_empty() && proplist.get_str(key).as_deref() == Some(target.app())
+}
+
+pub struct PulseBackend {
+    // Dropped before the mainloop it runs on
+    context: Context,
+    mainloop: Mainloop,
+    _proplist: Proplist,
+    changed: Rc<Cell<bool>>, // Set by the subscription when devices or app streams change
+}
+impl PulseBackend {
+    pub fn connect() -> Result<Self, AudioError> {
+        let mut proplist = Proplist::new().ok_or_else(|| unavailable("no proplist"))?;
+        proplist
+            .set_str(APPLICATION_NAME, "JukeBoxDesktop")
+            .map_err(|_| unavailable("failed to set application name"))?;
+
+        let mut mainloop = Mainloop::new().ok_or_else(|| unavailable("no mainloop"))?;
+        let mut context = Context::new_with_proplist(&mainloop, "JukeBoxDesktop", &proplist)
+            .ok_or_else(|| unavailable("no context"))?;
+        context
+            .connect(None, FlagSet::NOFLAGS, None)
+            .map_err(unavailable)?;
+
+        loop {
+            iterate(&mut mainloop, true)?;
+            match context.get_state() {
+                ContextState::Ready => break,
+                ContextState::Failed | ContextState::Terminated => {
+                    return Err(unavailable("context failed to connect"))
+                }
+                _ => {}
+            }
+        }
+
+        // Devices and app streams changed by other apps or the system mark this, so their states
+        // get refreshed
+        let changed = Rc::new(Cell::new(true));
+        let c = changed.clone();
+        context.set_subscribe_callback(Some(Box::new(move |_, _, _| c.set(true))));
+        let op = context.subscribe(
+            InterestMaskSet::SINK
+                | InterestMaskSet::SOURCE
+                | InterestMaskSet::SINK_INPUT
+                | InterestMaskSet::SOURCE_OUTPUT
+                | InterestMaskSet::SERVER,
+            |_| {},
+        );
+        wait_for_operation(&mut mainloop, op)?;
+
+        Ok(Self {
+            context,
+            mainloop,
+            _proplist: proplist,
+            changed,
+        })
+    }
+
+    // Name, volume and mute state of the device with this description
+    fn find_device(
+        &mut self,
+        input: bool,
+        description: &str,
+    ) -> Result<(String, ChannelVolumes, bool), AudioError> {
+        let device = Rc::new(RefCell::new(None));
+        let d = device.clone();
+        let desc = description.to_string();
+        if input {
+            let op = self.context.introspect().get_source_info_list(move |l| {
+                if let ListResult::Item(i) = l {
+                    if i.description.as_deref() == Some(desc.as_str()) {
+                        if let Some(name) = &i.name {
+                            *d.borrow_mut() = Some((name.to_string(), i.volume, i.mute));
+                        }
+                    }
+                }
+            });
+            wait_for_operation(&mut self.mainloop, op)?;
+        } else {
+            let op = self.context.introspect().get_sink_info_list(move |l| {
+                if let ListResult::Item(i) = l {
+                    if i.description.as_deref() == Some(desc.as_str()) {
+                        if let Some(name) = &i.name {
+                            *d.borrow_mut() = Some((name.to_string(), i.volume, i.mute));
+                        }
+                    }
+                }
+            });
+            wait_for_operation(&mut self.mainloop, op)?;
+        }
+
+        device
+            .take()
+            .ok_or_else(|| AudioError::DeviceNotFound(description.into()))
+    }
+
+    fn set_volume_by_name(
+        &mut self,
+        input: bool,
+        name: &str,
+        volume: &ChannelVolumes,
+    ) -> Result<(), AudioError> {
+        let op = if input {
+            self.context
+                .introspect()
+                .set_source_volume_by_name(name, volume, None)
+        } else {
+            self.context
+                .introspect()
+                .set_sink_volume_by_name(name, volume, None)
+        };
+        wait_for_operation(&mut self.mainloop, op)
+    }
+
+    // Index, volume and mute state of every stream the target matches
+    fn find_app_streams(
+        &mut self,
+        target: &AppStreamTarget,
+    ) -> Result<Vec<(u32, ChannelVolumes, bool)>, AudioError> {
+        let streams = Rc::new(RefCell::new(Vec::new()));
+        let s = streams.clone();
+        let t = target.clone();
+        if target.capture {
+            let op = self
+                .context
+                .introspect()
+                .get_source_output_info_list(move |l| match l {
+                    ListResult::Item(i) if app_stream_matches(&t, &i.proplist) => {
+                        s.borrow_mut().push((i.index, i.volume, i.mute))
+                    }
+                    _ => (),
+                });
+            wait_for_operation(&mut self.mainloop, op)?;
+        } else {
+            let op = self
+                .context
+                .introspect()
+                .get_sink_input_info_list(move |l| match l {
+                    ListResult::Item(i) if app_stream_matches(&t, &i.proplist) => {
+                        s.borrow_mut().push((i.index, i.volume, i.mute))
+                    }
+                    _ => (),
+                });
+            wait_for_operation(&mut self.mainloop, op)?;
+        }
+
+        let streams = streams.take();
+        if streams.is_empty() {
+            return Err(AudioError::StreamNotFound(target.app().into()));
+        }
+        Ok(streams)
+    }
+}
+impl AudioBackend for PulseBackend {
+    fn devices(&mut self, input: bool) -> Result<Vec<String>, AudioError> {
+        let devices = Rc::new(RefCell::new(Vec::new()));
+        let d = devices.clone();
+        if input {
+            let op = self.context.introspect().get_source_info_list(move |l| {
+                if let ListResult::Item(i) = l {
+                    d.borrow_mut()
+                        .push(i.description.clone().unwrap_or_default().to_string())
+                }
+            });
+            wait_for_operation(&mut self.mainloop, op)?;
+        } else {
+            let op = self.context.introspect().get_sink_info_list(move |l| {
+                if let ListResult::Item(i) = l {
+                    d.borrow_mut()
+                        .push(i.description.clone().unwrap_or_default().to_string())
+                }
+            });
+            wait_for_operation(&mut self.mainloop, op)?;
+        }
+        Ok(devices.take())
+    }
+
+    fn device_states(&mut self) -> Result<HashMap<(bool, String), AudioDeviceState>, AudioError> {
+        let defaults = Rc::new(RefCell::new((None, None)));
+        let d = defaults.clone();
+        let op = self.context.introspect().get_server_info(move |i| {
+            *d.borrow_mut() = (
+                i.default_source_name.as_ref().map(|n| n.to_string()),
+                i.default_sink_name.as_ref().map(|n| n.to_string()),
+            )
+        });
+        wait_for_operation(&mut self.mainloop, op)?;
+        let (default_source, default_sink) = defaults.take();
+
+        let states = Rc::new(RefCell::new(HashMap::new()));
+        let s = states.clone();
+        let op = self.context.introspect().get_source_info_list(move |l| {
+            if let ListResult::Item(i) = l {
+                let state = AudioDeviceState {
+                    muted: i.mute,
+                    default: default_source.is_some()
+                        && i.name.as_deref() == default_source.as_deref(),
+                    volume: volume_percent(&i.volume),
+                };
+                let description = i.description.clone().unwrap_or_default().to_string();
+                s.borrow_mut().insert((true, description), state);
+            }
+        });
+        wait_for_operation(&mut self.mainloop, op)?;
+
+        let s = states.clone();
+        let op = self.context.introspect().get_sink_info_list(move |l| {
+            if let ListResult::Item(i) = l {
+                let state = AudioDeviceState {
+                    muted: i.mute,
+                    default: default_sink.is_some() && i.name.as_deref() == default_sink.as_deref(),
+                    volume: volume_percent(&i.volume),
+                };
+                let description = i.description.clone().unwrap_or_default().to_string();
+                s.borrow_mut().insert((false, description), state);
+            }
+        });
+        wait_for_operation(&mut self.mainloop, op)?;
+
+        Ok(states.take())
+    }
+
+    fn adjust_device_volume(
+        &mut self,
+        input: bool,
+        device: &str,
+        adjust: i8,
+    ) -> Result<u8, AudioError> {
+        let (name, volume, _) = self.find_device(input, device)?;
+        let volume = adjust_volume(adjust, volume);
+        self.set_volume_by_name(input, &name, &volume)?;
+        Ok(volume_percent(&volume))
+    }
+
+    fn set_device_volume(
+        &mut self,
+        input: bool,
+        device: &str,
+        percent: u8,
+    ) -> Result<(), AudioError> {
+        let (name, mut volume, _) = self.find_device(input, device)?;
+        let channels = volume.len();
+        volume.set(channels, percent_volume(percent as f64));
+        self.set_volume_by_name(input, &name, &volume)
+    }
+
+    fn toggle_device_mute(&mut self, input: bool, device: &str) -> Result<bool, AudioError> {
+        let (name, _, muted) = self.find_device(input, device)?;
+        let op = if input {
+            self.context
+                .introspect()
+                .set_source_mute_by_name(&name, !muted, None)
+        } else {
+            self.context
+                .introspect()
+                .set_sink_mute_by_name(&name, !muted, None)
+        };
+        wait_for_operation(&mut self.mainloop, op)?;
+        Ok(!muted)
+    }
+
+    fn set_default_device(&mut self, input: bool, device: &str) -> Result<(), AudioError> {
+        let (name, _, _) = self.find_device(input, device)?;
+        let op = if input {
+            self.context.set_default_source(&name, |_| {})
+        } else {
+            self.context.set_default_sink(&name, |_| {})
+        };
+        wait_for_operation(&mut self.mainloop, op)
+    }
+
+    fn app_streams(&mut self) -> Result<Vec<AppStream>, AudioError> {
+        let streams = Rc::new(RefCell::new(Vec::new()));
+
+        let s = streams.clone();
+        let op = self
+            .context
+            .introspect()
+            .get_sink_input_info_list(move |l| {
+                if let ListResult::Item(i) = l {
+                    s.borrow_mut().push(AppStream {
+                        target: app_stream_target(&i.proplist, false),
+                        muted: i.mute,
+                    })
+                }
+            });
+        wait_for_operation(&mut self.mainloop, op)?;
+
+        let s = streams.clone();
+        let op = self
+            .context
+            .introspect()
+            .get_source_output_info_list(move |l| {
+                if let ListResult::Item(i) = l {
+                    s.borrow_mut().push(AppStream {
+                        target: app_stream_target(&i.proplist, true),
+                        muted: i.mute,
+                    })
+                }
+            });
+        wait_for_operation(&mut self.mainloop, op)?;
+
+        // One entry per app, muted only when all of its streams are
+        let mut apps: Vec<AppStream> = Vec::new();
+        for stream in streams.take() {
+            match apps.iter_mut().find(|a| a.target == stream.target) {
+                Some(a) => a.muted &= stream.muted,
+                None => apps.push(stream),
+            }
+        }
+        Ok(apps)
+    }
+
+    fn adjust_app_volume(
+        &mut self,
+        target: &AppStreamTarget,
+        adjust: i8,
+    ) -> Result<u8, AudioError> {
+        let mut percent = 0;
+        for (index, volume, _) in self.find_app_streams(target)? {
+            let volume = adjust_volume(adjust, volume);
+            let op = if target.capture {
+                self.context
+                    .introspect()
+                    .set_source_output_volume(index, &volume, None)
+            } else {
+                self.context
+                    .introspect()
+                    .set_sink_input_volume(index, &volume, None)
+            };
+            wait_for_operation(&mut self.mainloop, op)?;
+            percent = percent.max(volume_percent(&volume));
+        }
+        Ok(percent)
+    }
+
+    // Mutes every stream of the app, or unmutes them all when they already are
+    fn toggle_app_mute(&mut self, target: &AppStreamTarget) -> Result<bool, AudioError> {
+        let streams = self.find_app_streams(target)?;
+        let mute = !streams.iter().all(|(_, _, muted)| *muted);
+        for (index, _, _) in streams {
+            let op = if target.capture {
+                self.context
+                    .introspect()
+                    .set_source_output_mute(index, mute, None)
+            } else {
+                self.context
+                    .introspect()
+                    .set_sink_input_mute(index, mute, None)
+            };
+            wait_for_operation(&mut self.mainloop, op)?;
+        }
+        Ok(mute)
+    }
+
+    fn app_state(&mut self, target: &AppStreamTarget) -> Result<(bool, u8), AudioError> {
+        let streams = self.find_app_streams(target)?;
+        let muted = streams.iter().all(|(_, _, muted)| *muted);
+        let volume = streams.iter().map(|(_, v, _)| volume_percent(v)).max();
+        Ok((muted, volume.unwrap_or(0)))
+    }
+
+    fn move_app(&mut self, target: &AppStreamTarget, device: &str) -> Result<(), AudioError> {
+        // Playback goes to outputs, recording comes from inputs
+        let (name, _, _) = self.find_device(target.capture, device)?;
+        for (index, _, _) in self.find_app_streams(target)? {
+            let op = if target.capture {
+                self.context
+                    .introspect()
+                    .move_source_output_by_name(index, &name, None)
+            } else {
+                self.context
+                    .introspect()
+                    .move_sink_input_by_name(index, &name, None)
+            };
+            wait_for_operation(&mut self.mainloop, op)?;
+        }
+        Ok(())
+    }
+
+    fn poll_changes(&mut self) -> Result<bool, AudioError> {
+        iterate(&mut self.mainloop, false)?;
+        match self.context.get_state() {
+            ContextState::Failed | ContextState::Terminated => {
+                Err(unavailable("the sound server went away"))
+            }
+            _ => Ok(self.changed.replace(false)),
+        }
+    }
+}
@@ -0,0 +1,206 @@
+// Windows Core Audio
+
+use std::collections::HashMap;
+
+use windows::Win32::{
+    Devices::FunctionDiscovery::*,
+    Media::{
+        Audio::{Endpoints::IAudioEndpointVolume, *},
+        KernelStreaming::GUID_NULL,
+    },
+    System::{Com::*, Variant::VT_EMPTY},
+};
+
+use super::{AppStream, AppStreamTarget, AudioBackend, AudioDeviceState, AudioError};
+
+fn failed(e: windows::core::Error) -> AudioError {
+    AudioError::Failed(e.to_string())
+}
+
+fn direction(input: bool) -> EDataFlow {
+    if input {
+        eCapture
+    } else {
+        eRender
+    }
+}
+
+fn device_friendly_name(device: &IMMDevice) -> Option<String> {
+    unsafe {
+        let properties = device.OpenPropertyStore(STGM_READ).ok()?;
+        let friendly_name = properties.GetValue(&PKEY_Device_FriendlyName).ok()?;
+        if friendly_name.vt() == VT_EMPTY {
+            return None;
+        }
+        friendly_name
+            .Anonymous
+            .Anonymous
+            .Anonymous
+            .pwszVal
+            .to_string()
+            .ok()
+    }
+}
+
+pub struct WindowsBackend {
+    enumerator: IMMDeviceEnumerator,
+}
+impl WindowsBackend {
+    pub fn connect() -> Result<Self, AudioError> {
+        unsafe {
+            // Fails harmlessly when this thread already initialized COM
+            let _ = CoInitializeEx(None, COINIT_SPEED_OVER_MEMORY);
+            let enumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)
+                .map_err(|e| AudioError::Unavailable(e.to_string()))?;
+            Ok(Self { enumerator })
+        }
+    }
+
+    fn devices_of(&self, input: bool) -> Result<IMMDeviceCollection, AudioError> {
+        unsafe {
+            self.enumerator
+                .EnumAudioEndpoints(direction(input), DEVICE_STATE_ACTIVE)
+                .map_err(|e| AudioError::Unavailable(e.to_string()))
+        }
+    }
+
+    fn find_endpoint(&self, input: bool, device: &str) -> Result<IAudioEndpointVolume, AudioError> {
+        unsafe {
+            let devices = self.devices_of(input)?;
+            for i in 0..devices.GetCount().map_err(failed)? {
+                let item = devices.Item(i).map_err(failed)?;
+                if device_friendly_name(&item).as_deref() == Some(device) {
+                    return item.Activate(CLSCTX_ALL, None).map_err(failed);
+                }
+            }
+            Err(AudioError::DeviceNotFound(device.into()))
+        }
+    }
+}
+impl AudioBackend for WindowsBackend {
+    fn devices(&mut self, input: bool) -> Result<Vec<String>, AudioError> {
+        unsafe {
+            let devices = self.devices_of(input)?;
+            let mut names = Vec::new();
+            for i in 0..devices.GetCount().map_err(failed)? {
+                let item = devices.Item(i).map_err(failed)?;
+                if let Some(name) = device_friendly_name(&item) {
+                    names.push(name);
+                }
+            }
+            Ok(names)
+        }
+    }
+
+    fn device_states(&mut self) -> Result<HashMap<(bool, String), AudioDeviceState>, AudioError> {
+        let mut states = HashMap::new();
+        for input in [true, false] {
+            unsafe {
+                let default = self
+                    .enumerator
+                    .GetDefaultAudioEndpoint(direction(input), eConsole)
+                    .ok()
+                    .and_then(|d| device_friendly_name(&d));
+
+                let devices = self.devices_of(input)?;
+                for i in 0..devices.GetCount().unwrap_or(0) {
+                    let Ok(item) = devices.Item(i) else {
+                        continue;
+                    };
+                    let Some(name) = device_friendly_name(&item) else {
+                        continue;
+                    };
+                    let Ok(endpoint) = item.Activate::<IAudioEndpointVolume>(CLSCTX_ALL, None)
+                    else {
+                        continue;
+                    };
+
+                    let state = AudioDeviceState {
+                        muted: endpoint.GetMute().is_ok_and(|m| m.as_bool()),
+                        default: default.as_ref() == Some(&name),
+                        volume: endpoint
+                            .GetMasterVolumeLevelScalar()
+                            .map(|v| (v * 100.0).round() as u8)
+                            .unwrap_or(0),
+                    };
+                    states.insert((input, name), state);
+                }
+            }
+        }
+        Ok(states)
+    }
+
+    fn adjust_device_volume(
+        &mut self,
+        input: bool,
+        device: &str,
+        adjust: i8,
+    ) -> Result<u8, AudioError> {
+        let endpoint = self.find_endpoint(input, device)?;
+        unsafe {
+            let current_volume = endpoint.GetMasterVolumeLevelScalar().map_err(failed)?;
+            let new_volume = (current_volume + (adjust as f32) / 100.0).clamp(0.0, 1.0);
+            endpoint
+                .SetMasterVolumeLevelScalar(new_volume, &GUID_NULL)
+                .map_err(failed)?;
+            Ok((new_volume * 100.0).round() as u8)
+        }
+    }
+
+    fn set_device_volume(
+        &mut self,
+        input: bool,
+        device: &str,
+        percent: u8,
+    ) -> Result<(), AudioError> {
+        let endpoint = self.find_endpoint(input, device)?;
+        unsafe {
+            endpoint
+                .SetMasterVolumeLevelScalar((percent as f32 / 100.0).min(1.0), &GUID_NULL)
+                .map_err(failed)
+        }
+    }
+
+    fn toggle_device_mute(&mut self, input: bool, device: &str) -> Result<bool, AudioError> {
+        let endpoint = self.find_endpoint(input, device)?;
+        unsafe {
+            let muted = !endpoint.GetMute().map_err(failed)?.as_bool();
+            endpoint.SetMute(muted, &GUID_NULL).map_err(failed)?;
+            Ok(muted)
+        }
+    }
+
+    // TODO: windows only allows this through the undocumented IPolicyConfig
+    fn set_default_device(&mut self, _input: bool, _device: &str) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported)
+    }
+
+    // TODO: per app audio sessions on windows
+    fn app_streams(&mut self) -> Result<Vec<AppStream>, AudioError> {
+        Ok(Vec::new())
+    }
+
+    fn adjust_app_volume(
+        &mut self,
+        _target: &AppStreamTarget,
+        _adjust: i8,
+    ) -> Result<u8, AudioError> {
+        Err(AudioError::Unsupported)
+    }
+
+    fn toggle_app_mute(&mut self, _target: &AppStreamTarget) -> Result<bool, AudioError> {
+        Err(AudioError::Unsupported)
+    }
+
+    fn app_state(&mut self, _target: &AppStreamTarget) -> Result<(bool, u8), AudioError> {
+        Err(AudioError::Unsupported)
+    }
+
+    fn move_app(&mut self, _target: &AppStreamTarget, _device: &str) -> Result<(), AudioError> {
+        Err(AudioError::Unsupported)
+    }
+
+    fn notifies_changes(&self) -> bool {
+        false
+    }
+}
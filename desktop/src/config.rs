@@ -1,7 +1,7 @@
 // Config
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs::{create_dir_all, File},
     path::PathBuf,
 };
@@ -12,6 +12,8 @@ use serde::{Deserialize, Serialize};
 use crate::{
     actions::{meta::MetaNoAction, types::Action},
     input::InputKey,
+    secrets,
+    system::DEFAULT_SYSTEM_STATS,
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -20,11 +22,90 @@ pub struct DiscordOauthAccess {
     pub refresh_token: String,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct DiscordAccount {
+    // Left out once the secret store has it. None until the account is authorized again.
+    #[serde(default, skip_serializing_if = "secrets::is_stored")]
+    pub oauth: Option<DiscordOauthAccess>,
+}
+
+// Discord application to connect as, in place of the one built in (if any)
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct DiscordApp {
+    pub client_id: String,
+    // Only needed for apps that aren't public clients, the rest authorize with PKCE alone.
+    // Left out once the secret store has it.
+    #[serde(default, skip_serializing_if = "secrets::is_stored")]
+    pub client_secret: Option<String>,
+}
+
+pub const DEFAULT_MQTT_PORT: u16 = 1883;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MqttBroker {
+    pub host: String, // Empty until one is set up
+    pub port: u16,
+    pub client_id: String,
+    #[serde(default)]
+    pub username: String, // Empty connects without logging in
+    // Left out once the secret store has it
+    #[serde(default, skip_serializing_if = "secrets::is_stored")]
+    pub password: Option<String>,
+    // None connects without TLS
+    #[serde(default)]
+    pub tls: Option<TlsTrust>,
+    // Key presses and releases go to <topic>/<device UID>/<key>, empty keeps them to ourselves
+    #[serde(default)]
+    pub key_event_topic: String,
+}
+impl Default for MqttBroker {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            port: DEFAULT_MQTT_PORT,
+            client_id: "jukebox-desktop".into(),
+            username: String::new(),
+            password: None,
+            tls: None,
+            key_event_topic: String::new(),
+        }
+    }
+}
+
+// Name of the account carried over from configs from before there could be more than one, renamed
+// after the Discord user once it connects
+pub const DEFAULT_DISCORD_ACCOUNT: &str = "Default";
+
+// Name of the connection used by actions that haven't picked one, and by configs from before
+// there could be more than one
+pub const DEFAULT_OBS_CONNECTION: &str = "Default";
+
+// Which certificates a TLS connection accepts
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum TlsTrust {
+    SystemRoots,
+    CustomCa(String),       // Path to a PEM file
+    Pinned(Option<String>), // Certificate fingerprint, None until the first connection pins one
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct ObsAccess {
     pub host: String,
     pub port: u16,
+    // Left out once the secret store has it
+    #[serde(default, skip_serializing_if = "secrets::is_stored")]
     pub password: Option<String>,
+    // None connects without TLS
+    #[serde(default)]
+    pub tls: Option<TlsTrust>,
+    #[serde(default = "default_obs_timeout")]
+    pub timeout_ms: u32,
+}
+
+pub const DEFAULT_OBS_TIMEOUT: u32 = 250;
+
+fn default_obs_timeout() -> u32 {
+    DEFAULT_OBS_TIMEOUT
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq)]
@@ -33,19 +114,72 @@ pub enum ActionIcon {
     DefaultActionIcon,
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LabelPosition {
+    Top,
+    Middle,
+    Bottom,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct IconLabel {
+    pub text: String,
+    pub show_action_text: bool,
+    pub position: LabelPosition,
+    pub large: bool,
+    pub color: u16,
+}
+impl Default for IconLabel {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            show_action_text: false,
+            position: LabelPosition::Bottom,
+            large: false,
+            color: 0xFFFF,
+        }
+    }
+}
+impl IconLabel {
+    /// The text to draw, preferring what the action reports when asked to.
+    pub fn resolve(&self, action_text: Option<&str>) -> Option<String> {
+        let text = match action_text {
+            Some(t) if self.show_action_text => t,
+            _ => self.text.as_str(),
+        };
+        if text.trim().is_empty() {
+            None
+        } else {
+            Some(text.to_string())
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct ActionConfig {
     pub action: Action,
     pub icons: Vec<ActionIcon>,
+    #[serde(default)]
+    pub label: IconLabel,
 }
 impl Default for ActionConfig {
     fn default() -> Self {
         Self {
             action: Action::MetaNoAction(MetaNoAction::default()),
             icons: Default::default(),
+            label: Default::default(),
         }
     }
 }
+impl ActionConfig {
+    /// The icon for the action's current state on a key. Configs saved before an action gained
+    /// more icon states don't have icons for the new ones, those fall back to the default icon.
+    pub fn current_icon(&self, device_uid: &str, input_key: InputKey) -> &ActionIcon {
+        self.icons
+            .get(self.action.key_icon_state(device_uid, input_key) as usize)
+            .unwrap_or(&ActionIcon::DefaultActionIcon)
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct DeviceConfig {
@@ -60,6 +194,29 @@ pub struct DeviceInfo {
     pub nickname: String,
 }
 
+fn default_system_stats() -> Vec<String> {
+    DEFAULT_SYSTEM_STATS.iter().map(|s| s.to_string()).collect()
+}
+
+// Which events show up as toasts on keypad screens
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ToastConfig {
+    pub action_errors: bool,
+    pub obs: bool,
+    pub discord: bool,
+    pub desktop: bool,
+}
+impl Default for ToastConfig {
+    fn default() -> Self {
+        Self {
+            action_errors: true,
+            obs: true,
+            discord: true,
+            desktop: false, // other apps' notifications can be private
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct JukeBoxConfig {
     // Profile Name
@@ -70,14 +227,40 @@ pub struct JukeBoxConfig {
     // Device UID -> (Device Type, Device Nickname)
     pub devices: HashMap<String, DeviceInfo>,
 
-    pub discord_oauth_access: Option<DiscordOauthAccess>,
-    pub obs_access: Option<ObsAccess>,
+    // Only read from old configs, moved into `discord_accounts` on load
+    #[serde(default, skip_serializing)]
+    discord_oauth_access: Option<DiscordOauthAccess>,
+    // Discord Username -> Discord Account
+    #[serde(default)]
+    pub discord_accounts: BTreeMap<String, DiscordAccount>,
+    // Account actions use, None until one is authorized
+    #[serde(default)]
+    pub discord_account: Option<String>,
+    #[serde(default)]
+    pub discord_app: DiscordApp,
+
+    #[serde(default)]
+    pub mqtt_broker: MqttBroker,
+
+    // Only read from old configs, moved into `obs_connections` on load
+    #[serde(default, skip_serializing)]
+    obs_access: Option<ObsAccess>,
+    // Connection Name -> OBS Access
+    #[serde(default)]
+    pub obs_connections: BTreeMap<String, ObsAccess>,
 
     pub enable_splash: bool,
     pub always_save_on_exit: bool,
     pub ignore_update_notifications: bool,
 
     pub seen_intro_messages: bool,
+
+    // Sensor keys shown on the device, the index of each is the stat ID
+    #[serde(default = "default_system_stats")]
+    pub system_stats: Vec<String>,
+
+    #[serde(default)]
+    pub toasts: ToastConfig,
 }
 impl Default for JukeBoxConfig {
     fn default() -> Self {
@@ -87,13 +270,22 @@ impl Default for JukeBoxConfig {
             devices: HashMap::new(),
 
             discord_oauth_access: None,
+            discord_accounts: BTreeMap::new(),
+            discord_account: None,
+            discord_app: DiscordApp::default(),
+            mqtt_broker: MqttBroker::default(),
             obs_access: None,
+            obs_connections: BTreeMap::new(),
 
             enable_splash: true,
             always_save_on_exit: false,
             ignore_update_notifications: false,
 
             seen_intro_messages: true,
+
+            system_stats: default_system_stats(),
+
+            toasts: ToastConfig::default(),
         }
     }
 }
@@ -118,6 +310,17 @@ impl JukeBoxConfig {
     }
 
     pub fn load() -> (Self, bool) {
+        let (mut conf, failed_to_parse) = Self::load_file();
+
+        // Plaintext credentials from older configs stay put here, and move to the store on save
+        if let Some(secrets) = secrets::load() {
+            conf.fill_secrets(&secrets);
+        }
+
+        (conf, failed_to_parse)
+    }
+
+    fn load_file() -> (Self, bool) {
         let path = Self::get_path();
 
         let file = match File::open(path) {
@@ -128,7 +331,7 @@ impl JukeBoxConfig {
             Ok(f) => f,
         };
 
-        let conf = match serde_json::from_reader(file) {
+        let mut conf: JukeBoxConfig = match serde_json::from_reader(file) {
             Err(e) => {
                 log::error!("failed to parse config file: {}", e);
 
@@ -153,14 +356,154 @@ impl JukeBoxConfig {
             Ok(c) => c,
         };
 
+        conf.migrate();
+
         // TODO: serde_validate the config?
 
         (conf, false)
     }
 
+    // Moves fields of older configs to where they are now
+    fn migrate(&mut self) {
+        if let Some(obs_access) = self.obs_access.take() {
+            self.obs_connections
+                .entry(DEFAULT_OBS_CONNECTION.into())
+                .or_insert(obs_access);
+        }
+        if let Some(oauth) = self.discord_oauth_access.take() {
+            self.add_legacy_discord_account(oauth);
+        }
+    }
+
+    // Secret Store Key -> Secret, for every credential in the config
+    fn secrets(&self) -> BTreeMap<String, String> {
+        let mut secrets = BTreeMap::new();
+        for (name, access) in &self.obs_connections {
+            if let Some(password) = &access.password {
+                secrets.insert(format!("obs/{}", name), password.clone());
+            }
+        }
+        if let Some(password) = &self.mqtt_broker.password {
+            secrets.insert("mqtt".into(), password.clone());
+        }
+        if let Some(secret) = &self.discord_app.client_secret {
+            secrets.insert("discord_app".into(), secret.clone());
+        }
+        for (name, account) in &self.discord_accounts {
+            if let Some(oauth) = &account.oauth {
+                secrets.insert(
+                    format!("discord/{}", name),
+                    serde_json::to_string(oauth).expect("failed to serialize discord oauth"),
+                );
+            }
+        }
+        secrets
+    }
+
+    /// Fills in credentials from the secret store, keeping any already in the config.
+    pub fn fill_secrets(&mut self, secrets: &HashMap<String, String>) {
+        for (name, access) in self.obs_connections.iter_mut() {
+            if access.password.is_none() {
+                access.password = secrets.get(&format!("obs/{}", name)).cloned();
+            }
+        }
+        if self.mqtt_broker.password.is_none() {
+            self.mqtt_broker.password = secrets.get("mqtt").cloned();
+        }
+        if self.discord_app.client_secret.is_none() {
+            self.discord_app.client_secret = secrets.get("discord_app").cloned();
+        }
+        for (name, account) in self.discord_accounts.iter_mut() {
+            if account.oauth.is_none() {
+                account.oauth = secrets
+                    .get(&format!("discord/{}", name))
+                    .and_then(|s| serde_json::from_str(s).ok());
+            }
+        }
+        // Stored before there could be more than one account
+        if self.discord_accounts.is_empty() {
+            if let Some(oauth) = secrets
+                .get("discord")
+                .and_then(|s| serde_json::from_str(s).ok())
+            {
+                self.add_legacy_discord_account(oauth);
+            }
+        }
+    }
+
+    fn add_legacy_discord_account(&mut self, oauth: DiscordOauthAccess) {
+        self.discord_accounts
+            .entry(DEFAULT_DISCORD_ACCOUNT.into())
+            .or_default()
+            .oauth = Some(oauth);
+        self.discord_account
+            .get_or_insert_with(|| DEFAULT_DISCORD_ACCOUNT.into());
+    }
+
+    /// Tokens of the Discord account actions use, if it's authorized.
+    pub fn discord_oauth(&self) -> Option<&DiscordOauthAccess> {
+        let name = self.discord_account.as_ref()?;
+        self.discord_accounts.get(name)?.oauth.as_ref()
+    }
+
     pub fn save(&self) {
+        secrets::store(self.secrets());
+
         let path = Self::get_path();
         let file = File::create(path).expect("failed to create config file");
         serde_json::to_writer(file, &self).expect("failed to write config file");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plaintext_credentials_move_to_the_store() {
+        let _store = secrets::TEST_STORE
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        std::env::set_var("JUKEBOX_SECRET_STORE", "memory");
+
+        // Written before there was a secret store
+        let mut legacy = serde_json::to_value(JukeBoxConfig::default()).unwrap();
+        legacy["mqtt_broker"]["password"] = "hunter2".into();
+        legacy["discord_app"] = serde_json::json!({ "client_id": "1234", "client_secret": "shh" });
+        legacy["obs_access"] =
+            serde_json::json!({ "host": "localhost", "port": 4455, "password": "swordfish" });
+        let legacy = legacy.to_string();
+
+        let mut conf: JukeBoxConfig = serde_json::from_str(&legacy).unwrap();
+        conf.migrate();
+        conf.fill_secrets(&secrets::load().expect("memory store is always usable"));
+        let stored: HashMap<_, _> = conf.secrets().into_iter().collect();
+        assert_eq!(stored["mqtt"], "hunter2");
+        assert_eq!(stored["discord_app"], "shh");
+        assert_eq!(
+            stored[&format!("obs/{}", DEFAULT_OBS_CONNECTION)],
+            "swordfish"
+        );
+
+        // Saving moves them out of the file
+        secrets::store(conf.secrets());
+        assert!(secrets::is_ready());
+        let saved = serde_json::to_string(&conf).unwrap();
+        for plain in ["hunter2", "shh", "swordfish"] {
+            assert!(!saved.contains(plain), "{} left in {}", plain, saved);
+        }
+
+        // And loading puts them back
+        let mut conf: JukeBoxConfig = serde_json::from_str(&saved).unwrap();
+        assert_eq!(conf.mqtt_broker.password, None);
+        conf.fill_secrets(&secrets::load().unwrap());
+        assert_eq!(conf.mqtt_broker.password.as_deref(), Some("hunter2"));
+        assert_eq!(conf.discord_app.client_secret.as_deref(), Some("shh"));
+        assert_eq!(
+            conf.obs_connections[DEFAULT_OBS_CONNECTION]
+                .password
+                .as_deref(),
+            Some("swordfish")
+        );
+    }
+}
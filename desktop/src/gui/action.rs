@@ -1,12 +1,11 @@
 use std::path::PathBuf;
 
 use eframe::egui::{
-    scroll_area::ScrollBarVisibility, vec2, Align, Button, CollapsingHeader, Grid, Image,
-    ImageSource, Layout, Popup, RichText, ScrollArea, TextureFilter, TextureOptions,
+    scroll_area::ScrollBarVisibility, vec2, Align, Button, CollapsingHeader, ComboBox, Grid, Image,
+    ImageSource, Layout, Popup, RichText, ScrollArea, TextEdit, TextureFilter, TextureOptions,
     TextureWrapMode, Ui,
 };
 use egui_phosphor::regular as phos;
-use image::EncodableLayout;
 use jukebox_util::peripheral::DeviceType;
 use rfd::FileDialog;
 use tokio::runtime::Handle;
@@ -15,23 +14,16 @@ use crate::{
     actions::{
         action::send_input_event,
         meta::AID_META_NO_ACTION,
-        types::{get_icon_bytes, get_icon_cache, ActionError},
+        types::{get_action_texts, get_icon_bytes, get_icon_cache, get_icon_uv, ActionError},
     },
-    config::{ActionConfig, ActionIcon, JukeBoxConfig},
+    config::{ActionConfig, ActionIcon, JukeBoxConfig, LabelPosition},
+    icon::{encode_icon_bmp, load_icon_frames},
     input::InputKey,
     serial::SerialCommand,
 };
 
 use super::gui::{GuiTab, JukeBoxGui};
 
-const BMP_HEADER: &[u8] = &[
-    66, 77, 122, 8, 0, 0, 0, 0, 0, 0, 122, 0, 0, 0, 108, 0, 0, 0, 32, 0, 0, 0, 32, 0, 0, 0, 1, 0,
-    16, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 248, 0, 0,
-    224, 7, 0, 0, 31, 0, 0, 0, 0, 0, 0, 0, 66, 71, 82, 115, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-    0, 0, 0,
-];
-
 impl JukeBoxGui {
     pub fn enter_action_editor(&mut self, key: InputKey) {
         self.device_renaming = false;
@@ -47,12 +39,19 @@ impl JukeBoxGui {
                 .and_then(|d| d.key_map.get(&self.editing_key))
             {
                 self.editing_action_icons = r.icons.clone();
+                // Saved before the action gained its current icon states
+                self.editing_action_icons.resize(
+                    r.action.icon_state_count() as usize,
+                    ActionIcon::DefaultActionIcon,
+                );
+                self.editing_action_label = r.label.clone();
                 self.editing_action_type = r.action.get_type();
                 self.editing_action = r.action.clone();
             } else {
                 self.editing_action_type = AID_META_NO_ACTION.into();
                 self.editing_action = self.action_map.enum_new(self.editing_action_type.clone());
                 self.editing_action_icons = vec![ActionIcon::DefaultActionIcon];
+                self.editing_action_label = Default::default();
             }
         };
     }
@@ -82,7 +81,9 @@ impl JukeBoxGui {
                 let txs = self.scmd_txs.blocking_lock();
                 if let Some(tx) = txs.get(device_uid) {
                     let slot = k.slot();
-                    let icon = get_icon_bytes(&a, &mut get_icon_cache());
+                    let text = get_action_texts().get(&(device_uid.clone(), *k)).cloned();
+                    let icon =
+                        get_icon_bytes(a, device_uid, *k, text.as_deref(), &mut get_icon_cache());
                     let _ = tx.send(SerialCommand::SetScrIcon(slot, icon));
                 }
             }
@@ -139,7 +140,16 @@ impl JukeBoxGui {
                 .and_then(|d| d.get(device_uid))
                 .and_then(|p| p.key_map.get(&self.editing_key))
         } {
-            get_icon_bytes(action_config, &mut get_icon_cache())
+            let text = get_action_texts()
+                .get(&(device_uid.clone(), self.editing_key))
+                .cloned();
+            get_icon_bytes(
+                action_config,
+                device_uid,
+                self.editing_key,
+                text.as_deref(),
+                &mut get_icon_cache(),
+            )
         } else {
             return;
         };
@@ -199,6 +209,7 @@ impl JukeBoxGui {
             let new_action = ActionConfig {
                 action: self.editing_action.clone(),
                 icons: self.editing_action_icons.clone(),
+                label: self.editing_action_label.clone(),
             };
             new_action != *old_action
         } else {
@@ -219,6 +230,7 @@ impl JukeBoxGui {
                 ActionConfig {
                     action: self.editing_action.clone(),
                     icons: self.editing_action_icons.clone(),
+                    label: self.editing_action_label.clone(),
                 },
             );
             c.save();
@@ -232,22 +244,25 @@ impl JukeBoxGui {
     pub fn draw_edit_action(&mut self, ui: &mut Ui) {
         ui.columns_const(|[c1, c2]| {
             c1.horizontal(|ui| {
-                let test_btn =
-                    match &self.editing_action_icons[self.editing_action.icon_state() as usize] {
-                        ActionIcon::ImageIcon(s) => {
-                            let p = String::new() + "file://" + s;
-                            let i = Image::new(ImageSource::Uri(p.into()))
-                                .texture_options(TextureOptions {
-                                    magnification: TextureFilter::Nearest,
-                                    minification: TextureFilter::Nearest,
-                                    wrap_mode: TextureWrapMode::ClampToEdge,
-                                    mipmap_mode: None,
-                                })
-                                .corner_radius(2.0);
-                            Button::new(i)
-                        }
-                        ActionIcon::DefaultActionIcon => Button::new(self.editing_action.icon()),
-                    };
+                let state = self
+                    .editing_action
+                    .key_icon_state(&self.current_device, self.editing_key);
+                let test_btn = match &self.editing_action_icons[state as usize] {
+                    ActionIcon::ImageIcon(s) => {
+                        let p = String::new() + "file://" + s;
+                        let i = Image::new(ImageSource::Uri(p.into()))
+                            .uv(get_icon_uv(s))
+                            .texture_options(TextureOptions {
+                                magnification: TextureFilter::Nearest,
+                                minification: TextureFilter::Nearest,
+                                wrap_mode: TextureWrapMode::ClampToEdge,
+                                mipmap_mode: None,
+                            })
+                            .corner_radius(2.0);
+                        Button::new(i)
+                    }
+                    ActionIcon::DefaultActionIcon => Button::new(self.editing_action.icon()),
+                };
                 let test_btn = ui
                     .add_sized([60.0, 60.0], test_btn)
                     .on_hover_text_at_pointer(t!("help.action.test_input"));
@@ -334,6 +349,7 @@ impl JukeBoxGui {
                                 self.editing_key,
                                 self.config.clone(),
                             );
+                            self.draw_action_label_editor(ui);
                             ui.allocate_space(ui.available_size_before_wrap());
                         });
                     });
@@ -357,6 +373,54 @@ impl JukeBoxGui {
         });
     }
 
+    fn draw_action_label_editor(&mut self, ui: &mut Ui) {
+        let device_type = self
+            .devices
+            .get(&self.current_device)
+            .map(|d| d.device_info.device_type)
+            .unwrap_or(DeviceType::Unknown);
+        if device_type != DeviceType::KeyPad {
+            return;
+        }
+
+        let label = &mut self.editing_action_label;
+
+        ui.separator();
+        CollapsingHeader::new(t!("action.label.title"))
+            .id_salt("ActionLabelEdit")
+            .show(ui, |ui| {
+                ui.label(t!("action.label.text"));
+                ui.add(TextEdit::singleline(&mut label.text).desired_width(200.0));
+                ui.checkbox(
+                    &mut label.show_action_text,
+                    t!("action.label.show_action_text"),
+                )
+                .on_hover_text_at_pointer(t!("help.action.label.show_action_text"));
+                ui.checkbox(&mut label.large, t!("action.label.large"));
+
+                let position_name = |p: LabelPosition| match p {
+                    LabelPosition::Top => t!("action.label.position.top"),
+                    LabelPosition::Middle => t!("action.label.position.middle"),
+                    LabelPosition::Bottom => t!("action.label.position.bottom"),
+                };
+                ui.label(t!("action.label.position.title"));
+                ComboBox::from_id_salt("ActionLabelPosition")
+                    .selected_text(position_name(label.position))
+                    .width(200.0)
+                    .show_ui(ui, |ui| {
+                        for p in [
+                            LabelPosition::Top,
+                            LabelPosition::Middle,
+                            LabelPosition::Bottom,
+                        ] {
+                            ui.selectable_value(&mut label.position, p, position_name(p));
+                        }
+                    });
+
+                Self::draw_widget_color_editor(ui, t!("action.label.color"), &mut label.color);
+            });
+    }
+
     fn draw_action_list(&mut self, ui: &mut Ui) {
         for (header, options) in self.action_map.ui_list() {
             CollapsingHeader::new(RichText::new(header).strong())
@@ -387,7 +451,7 @@ impl JukeBoxGui {
 
     fn store_editing_action_icon(&mut self, slot: usize) {
         if let Some(f) = FileDialog::new()
-            .add_filter("PNG Image", &["png"])
+            .add_filter("Image", &["png", "jpg", "jpeg", "gif", "bmp"])
             .pick_file()
         {
             match self.load_custom_icon(f) {
@@ -404,35 +468,16 @@ impl JukeBoxGui {
     }
 
     fn load_custom_icon(&mut self, f: PathBuf) -> Result<PathBuf, ActionError> {
-        let image = image::open(f)
-            .map_err(|_| {
-                ActionError::new(
-                    self.current_device.clone(),
-                    self.editing_key,
-                    t!("help.action.err.not_an_image"),
-                )
-            })?
-            .into_rgb8();
-
-        let image = image::imageops::resize(&image, 32, 32, image::imageops::FilterType::Nearest);
-
-        let rgb = image.into_raw();
-        let mut icon = [0u16; 32 * 32];
-        for i in 0..(32 * 32) {
-            let r = (((rgb[i * 3 + 0] as f64) / 255.0 * 31.0).round() as u16) & 0b00000000_00011111;
-            let g = (((rgb[i * 3 + 1] as f64) / 255.0 * 63.0).round() as u16) & 0b00000000_00111111;
-            let b = (((rgb[i * 3 + 2] as f64) / 255.0 * 31.0).round() as u16) & 0b00000000_00011111;
-
-            let c = (r << 11) | (g << 5) | b;
-
-            let x = i % 32;
-            let y = 31 - i / 32;
-            icon[y * 32 + x] = c;
-        }
+        let frames = load_icon_frames(&f).map_err(|e| {
+            log::error!("failed to load icon {:?}: {}", f, e);
+            ActionError::new(
+                self.current_device.clone(),
+                self.editing_key,
+                t!("help.action.err.not_an_image"),
+            )
+        })?;
 
-        let mut data = Vec::new();
-        data.extend_from_slice(BMP_HEADER);
-        data.extend_from_slice(icon.as_bytes());
+        let data = encode_icon_bmp(&frames);
 
         std::fs::create_dir_all(JukeBoxConfig::get_icon_dir()).map_err(|_| {
             ActionError::new(
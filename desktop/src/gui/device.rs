@@ -9,6 +9,7 @@ use jukebox_util::peripheral::DeviceType;
 use jukebox_util::rgb::RgbProfile;
 use jukebox_util::screen::ScreenProfile;
 
+use crate::actions::types::get_icon_uv;
 use crate::firmware_update::FirmwareUpdateStatus;
 use crate::serial::SerialCommand;
 use crate::{config::ActionIcon, input::InputKey};
@@ -101,13 +102,13 @@ impl JukeBoxGui {
                 for k in keys.iter() {
                     for k in k.iter() {
                         let ac = c.key_map.get(&k).map(|c| c.clone()).unwrap_or_default();
-                        let a = ac.action;
-                        let i = ac.icons;
+                        let a = &ac.action;
 
-                        let mut b = match &i[a.icon_state() as usize] {
+                        let mut b = match ac.current_icon(&self.current_device, *k) {
                             ActionIcon::ImageIcon(s) => {
                                 let p = String::new() + "file://" + &s;
                                 let i = Image::new(ImageSource::Uri(p.into()))
+                                    .uv(get_icon_uv(s))
                                     .texture_options(TextureOptions {
                                         magnification: TextureFilter::Nearest,
                                         minification: TextureFilter::Nearest,
@@ -177,13 +178,13 @@ impl JukeBoxGui {
 
                     let mut i = |ui: &mut Ui, b| {
                         let ac = c.key_map.get(&b).map(|c| c.clone()).unwrap_or_default();
-                        let a = ac.action;
-                        let i = ac.icons;
+                        let a = &ac.action;
 
-                        let mut p = match &i[a.icon_state() as usize] {
+                        let mut p = match ac.current_icon(&self.current_device, b) {
                             ActionIcon::ImageIcon(s) => {
                                 let p = String::new() + "file://" + &s;
                                 let i = Image::new(ImageSource::Uri(p.into()))
+                                    .uv(get_icon_uv(s))
                                     .texture_options(TextureOptions {
                                         magnification: TextureFilter::Nearest,
                                         minification: TextureFilter::Nearest,
@@ -325,6 +326,7 @@ impl JukeBoxGui {
                                 .and_then(|d| d.screen_profile.clone())
                                 .unwrap_or(ScreenProfile::default_profile())
                         };
+                        self.editing_screen_widget = 0;
                         self.gui_tab = GuiTab::EditingScreen;
                     }
                 });
@@ -6,7 +6,7 @@ use std::sync::{atomic::AtomicBool, Arc};
 use std::time::{Duration, Instant};
 
 use eframe::egui::{
-    vec2, Align, CentralPanel, Context, Id, Layout, Modal, RichText, ScrollArea, Ui,
+    vec2, Align, CentralPanel, Context, Id, Layout, Modal, RichText, ScrollArea, TextureHandle, Ui,
     UserAttentionType, ViewportBuilder, ViewportCommand,
 };
 use eframe::Frame;
@@ -33,17 +33,18 @@ use tray_icon::{TrayIcon, TrayIconBuilder, TrayIconEvent};
 use crate::actions::meta::AID_META_NO_ACTION;
 use crate::actions::types::ActionError;
 use crate::actions::{
-    action::action_task,
+    action::{action_task, icon_label_task, icon_state_task},
     meta::MetaNoAction,
     types::{Action, ActionMap},
 };
-use crate::config::{ActionIcon, DeviceInfo, JukeBoxConfig};
+use crate::config::{ActionIcon, DeviceInfo, DiscordApp, IconLabel, JukeBoxConfig, MqttBroker};
 use crate::firmware_update::{FirmwareUpdateStatus, UpdateError};
 use crate::input::InputKey;
 use crate::serial::{serial_task, SerialCommand, SerialEvent};
 use crate::software_update::software_update_task;
 use crate::splash::SPLASH_MESSAGES;
 use crate::system::system_task;
+use crate::toast::toast_task;
 
 const APP_ICON: &[u8] = include_bytes!("../../../assets/applogo.png");
 static QUIT_APP: OnceLock<Mutex<bool>> = OnceLock::new();
@@ -83,6 +84,13 @@ pub struct JukeBoxGui {
     pub config_always_save_on_exit: bool,
     pub config_ignore_update_notifications: bool,
 
+    pub secrets_passphrase: String,
+    pub secrets_error: Option<String>,
+
+    // Being edited in settings, saved to the config on request
+    pub discord_app: DiscordApp,
+    pub mqtt_broker: MqttBroker,
+
     pub profile_renaming: bool,
     pub profile_name_entry: String,
 
@@ -91,6 +99,7 @@ pub struct JukeBoxGui {
 
     pub editing_key: InputKey,
     pub editing_action_icons: Vec<ActionIcon>,
+    pub editing_action_label: IconLabel,
     pub editing_action_type: String,
     pub editing_action: Action,
 
@@ -98,6 +107,10 @@ pub struct JukeBoxGui {
     pub editing_rgb_key_index: usize,
 
     pub editing_screen: ScreenProfile,
+    pub editing_screen_widget: usize,
+    pub screen_preview: Option<TextureHandle>,
+
+    pub system_stats: Arc<Mutex<SystemStats>>,
 
     pub exit_save_modal: bool,
 
@@ -200,6 +213,8 @@ impl JukeBoxGui {
         let config_always_save_on_exit = config.always_save_on_exit;
         let config_ignore_update_notifications = config.ignore_update_notifications;
         let config_seen_intro_messages = config.seen_intro_messages;
+        let discord_app = config.discord_app.clone();
+        let mqtt_broker = config.mqtt_broker.clone();
         let config = Arc::new(Mutex::new(config));
 
         // when gui exits, we use these to signal the other threads to stop
@@ -223,9 +238,17 @@ impl JukeBoxGui {
         let serial_scmd_txs = scmd_txs.clone();
         let action_config = config.clone();
         let action_scmd_txs = scmd_txs.clone();
+        let label_config = config.clone();
+        let label_scmd_txs = scmd_txs.clone();
+        let icon_state_config = config.clone();
+        let icon_state_scmd_txs = scmd_txs.clone();
+        let system_config = config.clone();
+        let toast_config = config.clone();
+        let toast_scmd_txs = scmd_txs.clone();
 
         let system_stats: Arc<Mutex<SystemStats>> = Arc::new(Mutex::new(SystemStats::default()));
         let serial_ss = system_stats.clone();
+        let system_ss = system_stats.clone();
 
         spawn(async move {
             serial_task(
@@ -239,8 +262,11 @@ impl JukeBoxGui {
             .await
         });
         spawn(async move { action_task(sr_rx, action_config, action_scmd_txs, ae_tx).await });
-        spawn(async move { spawn_blocking(|| system_task(system_stats)) });
+        spawn(async move { icon_label_task(label_config, label_scmd_txs).await });
+        spawn(async move { icon_state_task(icon_state_config, icon_state_scmd_txs).await });
+        spawn(async move { spawn_blocking(|| system_task(system_config, system_ss)) });
         spawn(async move { software_update_task(gu_tx).await });
+        spawn(async move { toast_task(toast_config, toast_scmd_txs).await });
 
         let mut generic_errors = VecDeque::new();
 
@@ -287,6 +313,12 @@ impl JukeBoxGui {
             config_always_save_on_exit: config_always_save_on_exit,
             config_ignore_update_notifications: config_ignore_update_notifications,
 
+            secrets_passphrase: String::new(),
+            secrets_error: None,
+
+            discord_app: discord_app,
+            mqtt_broker: mqtt_broker,
+
             profile_renaming: false,
             profile_name_entry: String::new(),
 
@@ -295,6 +327,7 @@ impl JukeBoxGui {
 
             editing_key: InputKey::UnknownKey,
             editing_action_icons: Vec::new(),
+            editing_action_label: IconLabel::default(),
             editing_action_type: AID_META_NO_ACTION.into(),
             editing_action: Action::MetaNoAction(MetaNoAction::default()),
 
@@ -302,6 +335,10 @@ impl JukeBoxGui {
             editing_rgb_key_index: 0,
 
             editing_screen: ScreenProfile::default_profile(),
+            editing_screen_widget: 0,
+            screen_preview: None,
+
+            system_stats: system_stats,
 
             exit_save_modal: false,
 
@@ -154,6 +154,7 @@ impl JukeBoxGui {
                         RgbProfile::StaticSolid {
                             mut brightness,
                             mut color,
+                            idle,
                         } => {
                             ui.label(t!("rgb.brightness"));
                             ui.add(Slider::new(&mut brightness, 0..=100));
@@ -161,11 +162,16 @@ impl JukeBoxGui {
                             ui.label(t!("rgb.static_solid.select_color"));
                             Self::draw_rgb888_editor(ui, &mut color);
 
-                            self.editing_rgb = RgbProfile::StaticSolid { brightness, color };
+                            self.editing_rgb = RgbProfile::StaticSolid {
+                                brightness,
+                                color,
+                                idle,
+                            };
                         }
                         RgbProfile::StaticPerKey {
                             mut brightness,
                             mut colors,
+                            idle,
                         } => {
                             ui.label(t!("rgb.brightness"));
                             ui.add(Slider::new(&mut brightness, 0..=100));
@@ -201,7 +207,11 @@ impl JukeBoxGui {
 
                             Self::draw_rgb888_editor(ui, &mut colors[self.editing_rgb_key_index]);
 
-                            self.editing_rgb = RgbProfile::StaticPerKey { brightness, colors }
+                            self.editing_rgb = RgbProfile::StaticPerKey {
+                                brightness,
+                                colors,
+                                idle,
+                            }
                         }
                         RgbProfile::Wave {
                             mut brightness,
@@ -210,6 +220,7 @@ impl JukeBoxGui {
                             mut speed_y,
                             mut color_count,
                             mut colors,
+                            idle,
                         } => {
                             ui.label(t!("rgb.brightness"));
                             ui.add(Slider::new(&mut brightness, 0..=100));
@@ -267,6 +278,7 @@ impl JukeBoxGui {
                                 speed_y,
                                 color_count,
                                 colors,
+                                idle,
                             };
                         }
                         RgbProfile::Breathe {
@@ -275,6 +287,7 @@ impl JukeBoxGui {
                             mut trans_time,
                             mut color_count,
                             mut colors,
+                            idle,
                         } => {
                             ui.label(t!("rgb.brightness"));
                             ui.add(Slider::new(&mut brightness, 0..=100));
@@ -333,6 +346,7 @@ impl JukeBoxGui {
                                 trans_time,
                                 color_count,
                                 colors,
+                                idle,
                             };
                         }
                         RgbProfile::RainbowSolid {
@@ -340,6 +354,7 @@ impl JukeBoxGui {
                             mut speed,
                             mut saturation,
                             mut value,
+                            idle,
                         } => {
                             ui.label(t!("rgb.brightness"));
                             ui.add(Slider::new(&mut brightness, 0..=100));
@@ -358,6 +373,7 @@ impl JukeBoxGui {
                                 speed,
                                 saturation,
                                 value,
+                                idle,
                             };
                         }
                         RgbProfile::RainbowWave {
@@ -367,6 +383,7 @@ impl JukeBoxGui {
                             mut speed_y,
                             mut saturation,
                             mut value,
+                            idle,
                         } => {
                             ui.label(t!("rgb.brightness"));
                             ui.add(Slider::new(&mut brightness, 0..=100));
@@ -393,9 +410,15 @@ impl JukeBoxGui {
                                 speed_y,
                                 saturation,
                                 value,
+                                idle,
                             };
                         }
                     }
+
+                    if let Some(idle) = self.editing_rgb.idle_mut() {
+                        ui.label("");
+                        Self::draw_idle_editor(ui, idle);
+                    }
                 });
             });
 
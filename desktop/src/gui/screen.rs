@@ -1,17 +1,129 @@
+use std::{borrow::Cow, convert::Infallible, ops::RangeInclusive};
+
 use eframe::egui::{
-    vec2, Align, Color32, ComboBox, Layout, RichText, ScrollArea, Sense, Slider, TextEdit, Ui,
+    pos2, vec2, Align, Color32, ColorImage, ComboBox, Layout, Rect, RichText, ScrollArea, Sense,
+    Slider, Stroke, StrokeKind, TextEdit, TextureOptions, Ui,
 };
 use egui_phosphor::regular as phos;
+use embedded_graphics::{
+    pixelcolor::{
+        raw::{RawData, RawU16},
+        Bgr565,
+    },
+    prelude::{DrawTarget, OriginDimensions, Point, Size},
+    primitives::Rectangle,
+    Pixel,
+};
 use jukebox_util::{
     color::{combine_to_rgb565, map_565_to_888, split_to_rgb565},
+    idle::IdleTimeout,
     peripheral::DeviceType,
-    screen::ScreenProfile,
+    render::{self, widget_font, widget_text_style},
+    screen::{
+        ScreenProfile, ScreenSaver, ScreenWidget, WidgetAlign, WidgetFont, WidgetLabel,
+        ICON_ANIMATION_FPS, ICON_SIZE, SCREEN_LAYOUT_MAX_WIDGETS, SCREEN_WIDGET_LABEL_LEN,
+    },
+    time::{ClockFormat, DateTime},
 };
 
-use crate::serial::SerialCommand;
+use crate::{
+    actions::types::{get_action_texts, get_icon_bytes, get_icon_cache},
+    serial::SerialCommand,
+    system::get_system_stat_names,
+};
 
 use super::gui::JukeBoxGui;
 
+const SCREEN_W: u16 = 320;
+const SCREEN_H: u16 = 240;
+// The GUI is zoomed 2x, so half scale in points is one to one with the device's pixels
+const PREVIEW_SCALE: f32 = 0.5;
+
+fn rgb565_to_color32(c: u16) -> Color32 {
+    let (r, g, b) = map_565_to_888(split_to_rgb565(c));
+    Color32::from_rgb(r, g, b)
+}
+
+// The keypad's screen, held in memory for the preview to be drawn into
+struct PreviewScreen {
+    pixels: Vec<u16>,
+}
+
+impl PreviewScreen {
+    fn new(background_color: u16) -> Self {
+        Self {
+            pixels: vec![background_color; SCREEN_W as usize * SCREEN_H as usize],
+        }
+    }
+
+    fn image(&self) -> ColorImage {
+        ColorImage::new(
+            [SCREEN_W as usize, SCREEN_H as usize],
+            self.pixels.iter().map(|c| rgb565_to_color32(*c)).collect(),
+        )
+    }
+}
+
+impl OriginDimensions for PreviewScreen {
+    fn size(&self) -> Size {
+        Size::new(SCREEN_W as u32, SCREEN_H as u32)
+    }
+}
+
+impl DrawTarget for PreviewScreen {
+    type Color = Bgr565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(p, c) in pixels {
+            if p.x < 0 || p.y < 0 || p.x >= SCREEN_W as i32 || p.y >= SCREEN_H as i32 {
+                continue;
+            }
+            self.pixels[p.y as usize * SCREEN_W as usize + p.x as usize] =
+                RawU16::from(c).into_inner();
+        }
+        Ok(())
+    }
+}
+
+fn widget_title(w: &ScreenWidget) -> Cow<'static, str> {
+    match w {
+        ScreenWidget::Empty => t!("screen.custom_layout.widget.empty"),
+        ScreenWidget::Label { .. } => t!("screen.custom_layout.widget.label"),
+        ScreenWidget::Stat { .. } => t!("screen.custom_layout.widget.stat"),
+        ScreenWidget::IconGrid { .. } => t!("screen.custom_layout.widget.icon_grid"),
+        ScreenWidget::Clock { .. } => t!("screen.custom_layout.widget.clock"),
+        ScreenWidget::ProgressBar { .. } => t!("screen.custom_layout.widget.progress_bar"),
+        ScreenWidget::Image { .. } => t!("screen.custom_layout.widget.image"),
+        ScreenWidget::ProfileName { .. } => t!("screen.custom_layout.widget.profile_name"),
+    }
+}
+
+fn clock_format_title(f: &ClockFormat) -> Cow<'static, str> {
+    match f {
+        ClockFormat::Time24 => t!("screen.custom_layout.clock.time_24"),
+        ClockFormat::Time24Seconds => t!("screen.custom_layout.clock.time_24_seconds"),
+        ClockFormat::Time12 => t!("screen.custom_layout.clock.time_12"),
+        ClockFormat::Time12Seconds => t!("screen.custom_layout.clock.time_12_seconds"),
+        ClockFormat::DateIso => t!("screen.custom_layout.clock.date_iso"),
+        ClockFormat::DateDmy => t!("screen.custom_layout.clock.date_dmy"),
+        ClockFormat::DateMdy => t!("screen.custom_layout.clock.date_mdy"),
+        ClockFormat::DateLong => t!("screen.custom_layout.clock.date_long"),
+    }
+}
+
+fn screen_saver_title(s: ScreenSaver) -> Cow<'static, str> {
+    match s {
+        ScreenSaver::Blank => t!("screen.screen_saver.blank"),
+        ScreenSaver::Clock => t!("screen.screen_saver.clock"),
+        ScreenSaver::BouncingLogo => t!("screen.screen_saver.bouncing_logo"),
+        ScreenSaver::PixelShift => t!("screen.screen_saver.pixel_shift"),
+    }
+}
+
 impl JukeBoxGui {
     fn calculate_rgb565_from_hex_string(s: String) -> Option<(u8, u8, u8)> {
         let s = s.trim().trim_start_matches('#');
@@ -49,6 +161,596 @@ impl JukeBoxGui {
         });
     }
 
+    pub fn draw_widget_color_editor(ui: &mut Ui, label: Cow<'static, str>, color: &mut u16) {
+        ui.label(label);
+        let mut c = split_to_rgb565(*color);
+        Self::draw_rgb565_editor(ui, &mut c);
+        *color = combine_to_rgb565(c.0, c.1, c.2);
+    }
+
+    fn draw_widget_position_editor(ui: &mut Ui, x: &mut u16, y: &mut u16) {
+        ui.label(t!("screen.custom_layout.position"));
+        ui.add(Slider::new(x, 0..=SCREEN_W - 1).prefix("X: "));
+        ui.add(Slider::new(y, 0..=SCREEN_H - 1).prefix("Y: "));
+    }
+
+    fn draw_widget_font_editor(ui: &mut Ui, font: &mut WidgetFont, align: &mut WidgetAlign) {
+        ui.horizontal(|ui| {
+            ui.label(t!("screen.custom_layout.font"));
+            ComboBox::from_id_salt("ScreenWidgetFontSelect")
+                .selected_text(match font {
+                    WidgetFont::Small => t!("screen.custom_layout.font_small"),
+                    WidgetFont::Large => t!("screen.custom_layout.font_large"),
+                })
+                .width(75.0)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        font,
+                        WidgetFont::Small,
+                        t!("screen.custom_layout.font_small"),
+                    );
+                    ui.selectable_value(
+                        font,
+                        WidgetFont::Large,
+                        t!("screen.custom_layout.font_large"),
+                    );
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label(t!("screen.custom_layout.align"));
+            ComboBox::from_id_salt("ScreenWidgetAlignSelect")
+                .selected_text(match align {
+                    WidgetAlign::Left => t!("screen.custom_layout.align_left"),
+                    WidgetAlign::Center => t!("screen.custom_layout.align_center"),
+                    WidgetAlign::Right => t!("screen.custom_layout.align_right"),
+                })
+                .width(75.0)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        align,
+                        WidgetAlign::Left,
+                        t!("screen.custom_layout.align_left"),
+                    );
+                    ui.selectable_value(
+                        align,
+                        WidgetAlign::Center,
+                        t!("screen.custom_layout.align_center"),
+                    );
+                    ui.selectable_value(
+                        align,
+                        WidgetAlign::Right,
+                        t!("screen.custom_layout.align_right"),
+                    );
+                });
+        });
+    }
+
+    fn draw_widget_stat_editor(ui: &mut Ui, stat: &mut u8, stat_names: &[String]) {
+        ui.horizontal(|ui| {
+            ui.label(t!("screen.custom_layout.stat_select"));
+            ComboBox::from_id_salt("ScreenWidgetStatSelect")
+                .selected_text(
+                    stat_names
+                        .get(*stat as usize)
+                        .cloned()
+                        .unwrap_or(t!("screen.custom_layout.stat_missing").into()),
+                )
+                .width(125.0)
+                .truncate()
+                .show_ui(ui, |ui| {
+                    for (i, n) in stat_names.iter().enumerate() {
+                        ui.selectable_value(stat, i as u8, n.as_str());
+                    }
+                })
+                .response
+                .on_hover_text_at_pointer(t!("help.screen.stat_select"));
+        });
+    }
+
+    fn draw_stat_graphs_editor(
+        ui: &mut Ui,
+        style: &mut GraphStyle,
+        history: &mut u8,
+        graphs: &mut StatGraphs,
+        stat_names: &[String],
+    ) {
+        ui.horizontal(|ui| {
+            ui.label(t!("screen.display_stats.graph_style"));
+            ComboBox::from_id_salt("ScreenGraphStyleSelect")
+                .selected_text(match style {
+                    GraphStyle::Sparkline => t!("screen.display_stats.graph_sparkline"),
+                    GraphStyle::Bars => t!("screen.display_stats.graph_bars"),
+                })
+                .width(100.0)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        style,
+                        GraphStyle::Sparkline,
+                        t!("screen.display_stats.graph_sparkline"),
+                    );
+                    ui.selectable_value(
+                        style,
+                        GraphStyle::Bars,
+                        t!("screen.display_stats.graph_bars"),
+                    );
+                });
+        });
+
+        ui.label(t!("screen.display_stats.graph_history"));
+        ui.add(
+            Slider::new(history, SCREEN_GRAPH_HISTORY_MIN..=SCREEN_GRAPH_HISTORY_MAX).suffix("s"),
+        );
+
+        for (i, graph) in graphs.iter_mut().enumerate() {
+            ui.push_id(("ScreenStatGraph", i), |ui| {
+                ui.label("");
+
+                let mut enabled = graph.is_some();
+                ui.horizontal(|ui| {
+                    ui.label(t!("screen.display_stats.graph", index = i + 1));
+                    ui.checkbox(&mut enabled, "");
+                });
+                match (enabled, graph.as_mut()) {
+                    (true, Some(g)) => {
+                        Self::draw_widget_stat_editor(ui, &mut g.stat, stat_names);
+                        Self::draw_widget_color_editor(
+                            ui,
+                            t!("screen.display_stats.graph_color"),
+                            &mut g.color,
+                        );
+                    }
+                    (true, None) => {
+                        *graph = Some(StatGraph {
+                            stat: 0,
+                            color: 0xFFFF,
+                        })
+                    }
+                    (false, _) => *graph = None,
+                }
+            });
+        }
+    }
+
+    fn draw_widget_editor(ui: &mut Ui, widget: &mut ScreenWidget, stat_names: &[String]) {
+        match widget {
+            ScreenWidget::Empty => {}
+            ScreenWidget::Label {
+                x,
+                y,
+                font,
+                align,
+                color,
+                text,
+            } => {
+                Self::draw_widget_position_editor(ui, x, y);
+                Self::draw_widget_font_editor(ui, font, align);
+
+                ui.horizontal(|ui| {
+                    ui.label(t!("screen.custom_layout.text"));
+                    let mut s = text.to_str().to_owned();
+                    if ui
+                        .add(
+                            TextEdit::singleline(&mut s)
+                                .char_limit(SCREEN_WIDGET_LABEL_LEN)
+                                .desired_width(125.0),
+                        )
+                        .changed()
+                    {
+                        *text = WidgetLabel::new(&s);
+                    }
+                });
+
+                Self::draw_widget_color_editor(ui, t!("screen.select_text_color"), color);
+            }
+            ScreenWidget::Stat {
+                x,
+                y,
+                font,
+                align,
+                color,
+                stat,
+                show_unit,
+            } => {
+                Self::draw_widget_position_editor(ui, x, y);
+                Self::draw_widget_font_editor(ui, font, align);
+                Self::draw_widget_stat_editor(ui, stat, stat_names);
+
+                ui.horizontal(|ui| {
+                    ui.label(t!("screen.custom_layout.show_unit"));
+                    ui.checkbox(show_unit, "");
+                });
+
+                Self::draw_widget_color_editor(ui, t!("screen.select_text_color"), color);
+            }
+            ScreenWidget::IconGrid {
+                x,
+                y,
+                scale,
+                spacing,
+            } => {
+                Self::draw_widget_position_editor(ui, x, y);
+                ui.label(t!("screen.custom_layout.scale"));
+                ui.add(Slider::new(scale, 1..=2));
+                ui.label(t!("screen.custom_layout.spacing"));
+                ui.add(Slider::new(spacing, 0..=16));
+            }
+            ScreenWidget::Clock {
+                x,
+                y,
+                font,
+                align,
+                color,
+                format,
+            } => {
+                Self::draw_widget_position_editor(ui, x, y);
+                Self::draw_widget_font_editor(ui, font, align);
+
+                ui.horizontal(|ui| {
+                    ui.label(t!("screen.custom_layout.clock_format"));
+                    ComboBox::from_id_salt("ScreenWidgetClockFormatSelect")
+                        .selected_text(clock_format_title(format))
+                        .width(125.0)
+                        .truncate()
+                        .show_ui(ui, |ui| {
+                            for f in ClockFormat::ALL {
+                                ui.selectable_value(format, f, clock_format_title(&f));
+                            }
+                        });
+                });
+
+                Self::draw_widget_color_editor(ui, t!("screen.select_text_color"), color);
+            }
+            ScreenWidget::ProgressBar {
+                x,
+                y,
+                w,
+                h,
+                color,
+                background_color,
+                stat,
+            } => {
+                Self::draw_widget_position_editor(ui, x, y);
+                ui.label(t!("screen.custom_layout.size"));
+                ui.add(Slider::new(w, 1..=SCREEN_W).prefix("W: "));
+                ui.add(Slider::new(h, 1..=SCREEN_H).prefix("H: "));
+                Self::draw_widget_stat_editor(ui, stat, stat_names);
+                Self::draw_widget_color_editor(ui, t!("screen.custom_layout.bar_color"), color);
+                Self::draw_widget_color_editor(
+                    ui,
+                    t!("screen.select_background_color"),
+                    background_color,
+                );
+            }
+            ScreenWidget::Image { x, y, slot, scale } => {
+                Self::draw_widget_position_editor(ui, x, y);
+                ui.horizontal(|ui| {
+                    ui.label(t!("screen.custom_layout.image_slot"));
+                    ComboBox::from_id_salt("ScreenWidgetImageSelect")
+                        .selected_text(format!("Key {}", *slot + 1))
+                        .width(75.0)
+                        .show_ui(ui, |ui| {
+                            for i in 0..12 {
+                                ui.selectable_value(slot, i, format!("Key {}", i + 1));
+                            }
+                        });
+                });
+                ui.label(t!("screen.custom_layout.scale"));
+                ui.add(Slider::new(scale, 1..=3));
+            }
+            ScreenWidget::ProfileName {
+                x,
+                y,
+                font,
+                align,
+                color,
+            } => {
+                Self::draw_widget_position_editor(ui, x, y);
+                Self::draw_widget_font_editor(ui, font, align);
+                Self::draw_widget_color_editor(ui, t!("screen.select_text_color"), color);
+            }
+        }
+    }
+
+    pub fn draw_idle_editor(ui: &mut Ui, idle: &mut IdleTimeout) {
+        let minutes = |v: f64, _: RangeInclusive<usize>| {
+            if v == 0.0 {
+                t!("idle.never").to_string()
+            } else {
+                t!("idle.minutes", n = v).to_string()
+            }
+        };
+
+        ui.label(t!("idle.dim_after"));
+        ui.add(Slider::new(&mut idle.dim_after, 0..=120).custom_formatter(minutes))
+            .on_hover_text_at_pointer(t!("help.idle.dim_after"));
+
+        ui.label(t!("idle.dim_brightness"));
+        ui.add(Slider::new(&mut idle.dim_brightness, 0..=100).suffix("%"))
+            .on_hover_text_at_pointer(t!("help.idle.dim_brightness"));
+
+        ui.label(t!("idle.sleep_after"));
+        ui.add(Slider::new(&mut idle.sleep_after, 0..=240).custom_formatter(minutes))
+            .on_hover_text_at_pointer(t!("help.idle.sleep_after"));
+    }
+
+    // Key icons as the keypad has them, at the frame animated ones are showing now
+    fn preview_icons(&self) -> [[u16; ICON_SIZE * ICON_SIZE]; 12] {
+        let mut icons = [[0u16; ICON_SIZE * ICON_SIZE]; 12];
+
+        let key_map = {
+            let c = self.config.blocking_lock();
+            c.profiles
+                .get(&c.current_profile)
+                .and_then(|d| d.get(&self.current_device))
+                .map(|p| p.key_map.clone())
+        };
+        let Some(key_map) = key_map else {
+            return icons;
+        };
+
+        let tick = chrono::Local::now().timestamp_millis() as u64 / (1000 / ICON_ANIMATION_FPS);
+        for (k, a) in &key_map {
+            let Some(icon) = icons.get_mut(k.slot() as usize) else {
+                continue;
+            };
+            let text = get_action_texts()
+                .get(&(self.current_device.clone(), *k))
+                .cloned();
+            let frames = get_icon_bytes(
+                a,
+                &self.current_device,
+                *k,
+                text.as_deref(),
+                &mut get_icon_cache(),
+            );
+            let frame = &frames[(tick % frames.len() as u64) as usize];
+            for (p, b) in icon.iter_mut().zip(frame.chunks_exact(2)) {
+                *p = u16::from_le_bytes([b[0], b[1]]);
+            }
+        }
+
+        icons
+    }
+
+    // Drawn by the same code the keypad draws its screen with, so what's here is what it shows
+    fn draw_custom_layout_preview(&mut self, ui: &mut Ui) {
+        let ScreenProfile::CustomLayout {
+            brightness,
+            background_color,
+            mut widgets,
+            idle,
+            screen_saver,
+        } = self.editing_screen
+        else {
+            return;
+        };
+
+        let size = vec2(SCREEN_W as f32, SCREEN_H as f32) * PREVIEW_SCALE;
+        let (rect, _) = ui.allocate_exact_size(size, Sense::empty());
+        let painter = ui.painter_at(rect);
+
+        let stats = self.system_stats.blocking_lock().clone();
+        let local_time = {
+            let now = chrono::Local::now();
+            DateTime::from_secs(now.timestamp() + now.offset().local_minus_utc() as i64)
+        };
+        let profile_name = self.config.blocking_lock().current_profile.clone();
+        let icons = self.preview_icons();
+
+        let mut screen = PreviewScreen::new(background_color);
+        let background: Bgr565 = RawU16::new(background_color).into();
+        let mut bounds = [Rectangle::zero(); SCREEN_LAYOUT_MAX_WIDGETS];
+
+        // Text and bars go down first, then icons and clocks over them, like on the keypad
+        for (i, w) in widgets.iter().enumerate() {
+            bounds[i] = match *w {
+                ScreenWidget::Label {
+                    x,
+                    y,
+                    font,
+                    align,
+                    color,
+                    text,
+                } => render::draw_text(
+                    &mut screen,
+                    text.to_str(),
+                    Point::new(x as i32, y as i32),
+                    widget_font(font),
+                    widget_text_style(align),
+                    RawU16::new(color).into(),
+                    background,
+                ),
+                ScreenWidget::Stat {
+                    x,
+                    y,
+                    font,
+                    align,
+                    color,
+                    stat,
+                    show_unit,
+                } => render::draw_stat(
+                    &mut screen,
+                    &stats,
+                    stat,
+                    Point::new(x as i32, y as i32),
+                    font,
+                    align,
+                    RawU16::new(color).into(),
+                    background,
+                    show_unit,
+                ),
+                ScreenWidget::ProgressBar {
+                    x,
+                    y,
+                    w,
+                    h,
+                    color,
+                    background_color,
+                    stat,
+                } => {
+                    let area = Rectangle::new(
+                        Point::new(x as i32, y as i32),
+                        Size::new(w as u32, h as u32),
+                    );
+                    render::draw_progress_bar(
+                        &mut screen,
+                        area,
+                        RawU16::new(color).into(),
+                        RawU16::new(background_color).into(),
+                        stats.get(stat).map(|s| s.fraction()).unwrap_or(0.0),
+                    );
+                    area
+                }
+                ScreenWidget::ProfileName {
+                    x,
+                    y,
+                    font,
+                    align,
+                    color,
+                } => render::draw_text(
+                    &mut screen,
+                    &profile_name,
+                    Point::new(x as i32, y as i32),
+                    widget_font(font),
+                    widget_text_style(align),
+                    RawU16::new(color).into(),
+                    background,
+                ),
+                ScreenWidget::Empty
+                | ScreenWidget::IconGrid { .. }
+                | ScreenWidget::Image { .. }
+                | ScreenWidget::Clock { .. } => continue,
+            };
+        }
+        for (i, w) in widgets.iter().enumerate() {
+            bounds[i] = match *w {
+                ScreenWidget::IconGrid {
+                    x,
+                    y,
+                    scale,
+                    spacing,
+                } => {
+                    let scale = scale.max(1) as usize;
+                    let size = 32 * scale + spacing as usize;
+                    for iy in 0..3 {
+                        for ix in 0..4 {
+                            render::draw_icon(
+                                &mut screen,
+                                &icons[iy * 4 + ix],
+                                1,
+                                x as usize + size * ix,
+                                y as usize + size * iy,
+                                scale,
+                                background,
+                            );
+                        }
+                    }
+                    Rectangle::new(
+                        Point::new(x as i32, y as i32),
+                        Size::new(
+                            (size * 3 + 32 * scale) as u32,
+                            (size * 2 + 32 * scale) as u32,
+                        ),
+                    )
+                }
+                ScreenWidget::Image { x, y, slot, scale } => {
+                    let scale = scale.max(1) as usize;
+                    if let Some(icon) = icons.get(slot as usize) {
+                        render::draw_icon(
+                            &mut screen,
+                            icon,
+                            1,
+                            x as usize,
+                            y as usize,
+                            scale,
+                            background,
+                        );
+                    }
+                    Rectangle::new(
+                        Point::new(x as i32, y as i32),
+                        Size::new_equal((32 * scale) as u32),
+                    )
+                }
+                ScreenWidget::Clock {
+                    x,
+                    y,
+                    font,
+                    align,
+                    color,
+                    format,
+                } => render::draw_text(
+                    &mut screen,
+                    local_time.format(format, &mut [0u8; 16]),
+                    Point::new(x as i32, y as i32),
+                    widget_font(font),
+                    widget_text_style(align),
+                    RawU16::new(color).into(),
+                    background,
+                ),
+                _ => continue,
+            };
+        }
+
+        let image = screen.image();
+        let texture = self.screen_preview.get_or_insert_with(|| {
+            ui.ctx()
+                .load_texture("ScreenPreview", image.clone(), TextureOptions::NEAREST)
+        });
+        texture.set(image, TextureOptions::NEAREST);
+        painter.image(
+            texture.id(),
+            rect,
+            Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+            Color32::WHITE,
+        );
+
+        for (i, w) in widgets.iter_mut().enumerate() {
+            if *w == ScreenWidget::Empty {
+                continue;
+            }
+            let bounds = Rect::from_min_size(
+                rect.min
+                    + vec2(bounds[i].top_left.x as f32, bounds[i].top_left.y as f32)
+                        * PREVIEW_SCALE,
+                vec2(bounds[i].size.width as f32, bounds[i].size.height as f32) * PREVIEW_SCALE,
+            );
+
+            // Widgets can be grabbed and dragged around the preview
+            let r = ui.interact(
+                bounds.intersect(rect),
+                ui.id().with(("ScreenWidgetPreview", i)),
+                Sense::click_and_drag(),
+            );
+            if r.clicked() || r.drag_started() {
+                self.editing_screen_widget = i;
+            }
+            if r.dragged() {
+                let (x, y) = w.position();
+                let d = r.drag_delta() / PREVIEW_SCALE;
+                let x = (x as f32 + d.x).round().clamp(0.0, (SCREEN_W - 1) as f32) as u16;
+                let y = (y as f32 + d.y).round().clamp(0.0, (SCREEN_H - 1) as f32) as u16;
+                w.set_position(x, y);
+            }
+
+            if i == self.editing_screen_widget {
+                painter.rect_stroke(
+                    bounds,
+                    0.0,
+                    Stroke::new(1.0, Color32::YELLOW),
+                    StrokeKind::Outside,
+                );
+            }
+        }
+
+        self.editing_screen = ScreenProfile::CustomLayout {
+            brightness,
+            background_color,
+            widgets,
+            idle,
+            screen_saver,
+        };
+    }
+
     pub fn draw_edit_screen(&mut self, ui: &mut Ui) {
         ui.label(t!("screen.title"));
         let screen_defaults = [
@@ -67,6 +769,11 @@ impl JukeBoxGui {
                 t!("screen.display_stats.title"),
                 t!("screen.display_stats.description"),
             ),
+            (
+                ScreenProfile::default_custom_layout(),
+                t!("screen.custom_layout.title"),
+                t!("screen.custom_layout.description"),
+            ),
         ];
 
         ui.horizontal(|ui| {
@@ -112,6 +819,8 @@ impl JukeBoxGui {
         );
         ui.label("");
 
+        let stat_names = get_system_stat_names(&self.config.blocking_lock().system_stats);
+
         ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
             ui.vertical(|ui| {
                 ScrollArea::vertical().max_height(164.0).show(ui, |ui| {
@@ -123,6 +832,8 @@ impl JukeBoxGui {
                             background_color,
                             text_color,
                             mut show_profile_name,
+                            idle,
+                            screen_saver,
                         } => {
                             ui.label(t!("screen.brightness"));
                             ui.add(Slider::new(&mut brightness, 0..=100));
@@ -153,6 +864,8 @@ impl JukeBoxGui {
                                 background_color,
                                 text_color,
                                 show_profile_name,
+                                idle,
+                                screen_saver,
                             };
                         }
                         ScreenProfile::DisplayStats {
@@ -160,6 +873,11 @@ impl JukeBoxGui {
                             background_color,
                             text_color,
                             mut show_profile_name,
+                            mut graph_style,
+                            mut graph_history,
+                            mut graphs,
+                            idle,
+                            screen_saver,
                         } => {
                             ui.label(t!("screen.brightness"));
                             ui.add(Slider::new(&mut brightness, 0..=100));
@@ -185,16 +903,148 @@ impl JukeBoxGui {
                                 ui.checkbox(&mut show_profile_name, "");
                             });
 
+                            ui.label("");
+
+                            Self::draw_stat_graphs_editor(
+                                ui,
+                                &mut graph_style,
+                                &mut graph_history,
+                                &mut graphs,
+                                &stat_names,
+                            );
+
                             self.editing_screen = ScreenProfile::DisplayStats {
                                 brightness,
                                 background_color,
                                 text_color,
                                 show_profile_name,
+                                graph_style,
+                                graph_history,
+                                graphs,
+                                idle,
+                                screen_saver,
                             };
                         }
+                        ScreenProfile::CustomLayout {
+                            mut brightness,
+                            background_color,
+                            mut widgets,
+                            idle,
+                            screen_saver,
+                        } => {
+                            ui.label(t!("screen.brightness"));
+                            ui.add(Slider::new(&mut brightness, 0..=100));
+
+                            ui.label("");
+
+                            ui.label(t!("screen.select_background_color"));
+                            let mut c = split_to_rgb565(background_color);
+                            Self::draw_rgb565_editor(ui, &mut c);
+                            let background_color = combine_to_rgb565(c.0, c.1, c.2);
+
+                            ui.label("");
+
+                            ui.horizontal(|ui| {
+                                ui.label(t!("screen.custom_layout.widgets"));
+
+                                let free_slot =
+                                    widgets.iter().position(|w| *w == ScreenWidget::Empty);
+                                ui.add_enabled_ui(free_slot.is_some(), |ui| {
+                                    ComboBox::from_id_salt("ScreenWidgetAdd")
+                                        .selected_text(t!("screen.custom_layout.add_widget"))
+                                        .width(100.0)
+                                        .show_ui(ui, |ui| {
+                                            for w in [
+                                                ScreenWidget::default_label(),
+                                                ScreenWidget::default_stat(),
+                                                ScreenWidget::default_icon_grid(),
+                                                ScreenWidget::default_clock(),
+                                                ScreenWidget::default_date(),
+                                                ScreenWidget::default_progress_bar(),
+                                                ScreenWidget::default_image(),
+                                                ScreenWidget::default_profile_name(),
+                                            ] {
+                                                if ui
+                                                    .selectable_label(false, widget_title(&w))
+                                                    .clicked()
+                                                {
+                                                    if let Some(i) = free_slot {
+                                                        widgets[i] = w;
+                                                        self.editing_screen_widget = i;
+                                                    }
+                                                }
+                                            }
+                                        })
+                                        .response
+                                        .on_hover_text_at_pointer(t!("help.screen.add_widget"));
+                                });
+                            });
+
+                            for (i, w) in widgets.iter_mut().enumerate() {
+                                if *w == ScreenWidget::Empty {
+                                    continue;
+                                }
+
+                                ui.horizontal(|ui| {
+                                    if ui
+                                        .selectable_label(
+                                            self.editing_screen_widget == i,
+                                            widget_title(w),
+                                        )
+                                        .clicked()
+                                    {
+                                        self.editing_screen_widget = i;
+                                    }
+                                    if ui
+                                        .button(RichText::new(phos::TRASH))
+                                        .on_hover_text_at_pointer(t!("help.screen.remove_widget"))
+                                        .clicked()
+                                    {
+                                        *w = ScreenWidget::Empty;
+                                    }
+                                });
+                            }
+
+                            if let Some(w) = widgets.get_mut(self.editing_screen_widget) {
+                                if *w != ScreenWidget::Empty {
+                                    ui.label("");
+                                    Self::draw_widget_editor(ui, w, &stat_names);
+                                }
+                            }
+
+                            self.editing_screen = ScreenProfile::CustomLayout {
+                                brightness,
+                                background_color,
+                                widgets,
+                                idle,
+                                screen_saver,
+                            };
+                        }
+                    }
+
+                    if let Some(idle) = self.editing_screen.idle_mut() {
+                        ui.label("");
+                        Self::draw_idle_editor(ui, idle);
+                    }
+                    if let Some(screen_saver) = self.editing_screen.screen_saver_mut() {
+                        ui.horizontal(|ui| {
+                            ui.label(t!("screen.screen_saver.title"));
+                            ComboBox::from_id_salt("ScreenSaverSelect")
+                                .selected_text(screen_saver_title(*screen_saver))
+                                .width(120.0)
+                                .show_ui(ui, |ui| {
+                                    for s in ScreenSaver::ALL {
+                                        ui.selectable_value(screen_saver, s, screen_saver_title(s));
+                                    }
+                                })
+                                .response
+                                .on_hover_text_at_pointer(t!("help.screen.screen_saver"));
+                        });
                     }
                 });
             });
+
+            self.draw_custom_layout_preview(ui);
         });
 
         // TODO: preview?
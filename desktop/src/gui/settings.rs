@@ -1,6 +1,17 @@
-use eframe::egui::{Align, Color32, Layout, RichText, Ui};
+use eframe::egui::{
+    Align, Button, Color32, ComboBox, DragValue, Layout, RichText, ScrollArea, TextEdit, Ui,
+};
 use egui_phosphor::regular as phos;
 use egui_theme_switch::global_theme_switch;
+use jukebox_util::stats::SYSTEM_STATS_MAX;
+
+use crate::{
+    actions::{discord, obs::tls_trust_ui},
+    config::TlsTrust,
+    mqtt,
+    secrets::{self, SecretError, SecretStoreKind},
+    system::{get_system_sensors, get_system_stat_names},
+};
 
 use super::gui::{GuiTab, JukeBoxGui};
 
@@ -80,6 +91,18 @@ impl JukeBoxGui {
             conf.save();
         }
 
+        ui.label("");
+
+        self.draw_settings_toasts(ui);
+
+        self.draw_settings_secrets(ui);
+
+        self.draw_settings_discord(ui);
+
+        self.draw_settings_mqtt(ui);
+
+        self.draw_settings_system_stats(ui);
+
         ui.with_layout(Layout::bottom_up(Align::RIGHT), |ui| {
             ui.columns_const(|[c1, c2]| {
                 c1.with_layout(Layout::left_to_right(Align::Max), |ui| {
@@ -96,4 +119,322 @@ impl JukeBoxGui {
             });
         });
     }
+
+    fn draw_settings_toasts(&mut self, ui: &mut Ui) {
+        let mut toasts = self.config.blocking_lock().toasts;
+
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.toasts.title"))
+                .on_hover_text_at_pointer(t!("help.settings.toasts.title"));
+            ui.checkbox(
+                &mut toasts.action_errors,
+                t!("settings.toasts.action_errors"),
+            )
+            .on_hover_text_at_pointer(t!("help.settings.toasts.action_errors"));
+            ui.checkbox(&mut toasts.obs, t!("settings.toasts.obs"))
+                .on_hover_text_at_pointer(t!("help.settings.toasts.obs"));
+            ui.checkbox(&mut toasts.discord, t!("settings.toasts.discord"))
+                .on_hover_text_at_pointer(t!("help.settings.toasts.discord"));
+            ui.checkbox(&mut toasts.desktop, t!("settings.toasts.desktop"))
+                .on_hover_text_at_pointer(t!("help.settings.toasts.desktop"));
+        });
+
+        let mut conf = self.config.blocking_lock();
+        if conf.toasts != toasts {
+            conf.toasts = toasts;
+            conf.save();
+        }
+    }
+
+    fn draw_settings_secrets(&mut self, ui: &mut Ui) {
+        let Some(kind) = secrets::kind() else {
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.secrets.title"))
+                .on_hover_text_at_pointer(t!("help.settings.secrets.title"));
+
+            match kind {
+                SecretStoreKind::SecretService => {
+                    ui.label(t!("settings.secrets.secret_service"));
+                }
+                SecretStoreKind::Memory => {
+                    ui.label(t!("settings.secrets.memory"));
+                }
+                SecretStoreKind::EncryptedFile if secrets::is_ready() => {
+                    ui.label(t!("settings.secrets.file"));
+                }
+                SecretStoreKind::EncryptedFile => {
+                    let (hint, help) = if secrets::has_secrets_file() {
+                        (
+                            t!("settings.secrets.passphrase"),
+                            t!("help.settings.secrets.unlock"),
+                        )
+                    } else {
+                        (
+                            t!("settings.secrets.new_passphrase"),
+                            t!("help.settings.secrets.create"),
+                        )
+                    };
+
+                    ui.add(
+                        TextEdit::singleline(&mut self.secrets_passphrase)
+                            .hint_text(hint)
+                            .password(true),
+                    );
+                    let unlock = ui
+                        .add_enabled(
+                            !self.secrets_passphrase.is_empty(),
+                            Button::new(t!("settings.secrets.unlock")),
+                        )
+                        .on_hover_text_at_pointer(help);
+                    if unlock.clicked() {
+                        match secrets::unlock(&self.secrets_passphrase) {
+                            Ok(s) => {
+                                let mut conf = self.config.blocking_lock();
+                                conf.fill_secrets(&s);
+                                conf.save();
+                                self.discord_app = conf.discord_app.clone();
+                                self.mqtt_broker = conf.mqtt_broker.clone();
+                                self.secrets_passphrase.clear();
+                                self.secrets_error = None;
+                            }
+                            Err(SecretError::WrongPassphrase) => {
+                                self.secrets_error =
+                                    Some(t!("settings.secrets.wrong_passphrase").into());
+                            }
+                            Err(e) => {
+                                log::error!("failed to unlock secrets: {:?}", e);
+                                self.secrets_error = Some(
+                                    t!("settings.secrets.err", error = format!("{:?}", e)).into(),
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        if let Some(e) = &self.secrets_error {
+            ui.label(RichText::new(e).color(Color32::from_rgb(255, 80, 80)));
+        }
+    }
+
+    fn draw_settings_discord(&mut self, ui: &mut Ui) {
+        let saved = self.config.blocking_lock().discord_app.clone();
+
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.discord.title"))
+                .on_hover_text_at_pointer(t!("help.settings.discord.title"));
+
+            ui.add(
+                TextEdit::singleline(&mut self.discord_app.client_id)
+                    .hint_text(t!("settings.discord.client_id"))
+                    .desired_width(170.0),
+            );
+
+            let mut secret = self.discord_app.client_secret.clone().unwrap_or_default();
+            ui.add(
+                TextEdit::singleline(&mut secret)
+                    .hint_text(t!("settings.discord.client_secret"))
+                    .password(true)
+                    .desired_width(170.0),
+            )
+            .on_hover_text_at_pointer(t!("help.settings.discord.client_secret"));
+            self.discord_app.client_secret = Some(secret).filter(|s| !s.is_empty());
+
+            if ui
+                .add_enabled(
+                    self.discord_app != saved,
+                    Button::new(t!("settings.discord.save")),
+                )
+                .on_hover_text_at_pointer(t!("help.settings.discord.save"))
+                .clicked()
+            {
+                let mut conf = self.config.blocking_lock();
+                conf.discord_app = self.discord_app.clone();
+                conf.save();
+                drop(conf);
+                // Tokens belong to the app they were made for, so the new one authorizes again
+                discord::reconnect(self.config.clone());
+            }
+        });
+    }
+
+    fn draw_settings_mqtt(&mut self, ui: &mut Ui) {
+        let saved = self.config.blocking_lock().mqtt_broker.clone();
+        // The first TLS connection pins a certificate, take it up instead of offering to undo it
+        if let (Some(TlsTrust::Pinned(None)), Some(TlsTrust::Pinned(Some(_)))) =
+            (&self.mqtt_broker.tls, &saved.tls)
+        {
+            self.mqtt_broker.tls = saved.tls.clone();
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.mqtt.title"))
+                .on_hover_text_at_pointer(t!("help.settings.mqtt.title"));
+
+            ui.add(
+                TextEdit::singleline(&mut self.mqtt_broker.host)
+                    .hint_text(t!("settings.mqtt.host"))
+                    .desired_width(170.0),
+            );
+            ui.add(DragValue::new(&mut self.mqtt_broker.port).range(1..=65535))
+                .on_hover_text_at_pointer(t!("help.settings.mqtt.port"));
+
+            let mut use_tls = self.mqtt_broker.tls.is_some();
+            if ui.checkbox(&mut use_tls, t!("settings.mqtt.tls")).changed() {
+                self.mqtt_broker.tls = use_tls.then_some(TlsTrust::SystemRoots);
+            }
+
+            let status = if saved.host.trim().is_empty() {
+                None
+            } else if mqtt::is_connected() {
+                Some((
+                    phos::PLUGS_CONNECTED,
+                    t!("settings.mqtt.connected").to_string(),
+                ))
+            } else {
+                Some((
+                    phos::PLUGS,
+                    mqtt::last_error().unwrap_or(t!("settings.mqtt.connecting").into()),
+                ))
+            };
+            if let Some((icon, text)) = status {
+                ui.label(icon).on_hover_text_at_pointer(text);
+            }
+        });
+
+        if let Some(trust) = &mut self.mqtt_broker.tls {
+            ui.horizontal(|ui| tls_trust_ui(ui, trust));
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.mqtt_broker.client_id)
+                    .hint_text(t!("settings.mqtt.client_id"))
+                    .desired_width(110.0),
+            );
+            ui.add(
+                TextEdit::singleline(&mut self.mqtt_broker.username)
+                    .hint_text(t!("settings.mqtt.username"))
+                    .desired_width(110.0),
+            );
+
+            let mut password = self.mqtt_broker.password.clone().unwrap_or_default();
+            ui.add(
+                TextEdit::singleline(&mut password)
+                    .hint_text(t!("settings.mqtt.password"))
+                    .password(true)
+                    .desired_width(110.0),
+            );
+            self.mqtt_broker.password = Some(password).filter(|p| !p.is_empty());
+
+            ui.add(
+                TextEdit::singleline(&mut self.mqtt_broker.key_event_topic)
+                    .hint_text(t!("settings.mqtt.key_event_topic"))
+                    .desired_width(130.0),
+            )
+            .on_hover_text_at_pointer(t!("help.settings.mqtt.key_event_topic"));
+
+            if ui
+                .add_enabled(
+                    self.mqtt_broker != saved,
+                    Button::new(t!("settings.mqtt.save")),
+                )
+                .on_hover_text_at_pointer(t!("help.settings.mqtt.save"))
+                .clicked()
+            {
+                let mut conf = self.config.blocking_lock();
+                conf.mqtt_broker = self.mqtt_broker.clone();
+                conf.save();
+                mqtt::reconnect();
+            }
+        });
+    }
+
+    fn draw_settings_system_stats(&mut self, ui: &mut Ui) {
+        let mut stats = self.config.blocking_lock().system_stats.clone();
+        let names = get_system_stat_names(&stats);
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.system_stats.title"));
+
+            ui.add_enabled_ui(stats.len() < SYSTEM_STATS_MAX, |ui| {
+                ComboBox::from_id_salt("SettingsSystemStatAdd")
+                    .selected_text(t!("settings.system_stats.add"))
+                    .width(250.0)
+                    .truncate()
+                    .show_ui(ui, |ui| {
+                        for (key, name) in get_system_sensors() {
+                            if stats.contains(&key) {
+                                continue;
+                            }
+                            if ui.selectable_label(false, name).clicked() {
+                                stats.push(key);
+                                changed = true;
+                            }
+                        }
+                    })
+                    .response
+                    .on_hover_text_at_pointer(t!("help.settings.system_stats.add"));
+            });
+        });
+
+        ScrollArea::vertical()
+            .id_salt("SettingsSystemStats")
+            .max_height(150.0)
+            .show(ui, |ui| {
+                let mut swap = None;
+                let mut remove = None;
+
+                for (i, name) in names.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add_enabled_ui(i > 0, |ui| {
+                            if ui
+                                .button(RichText::new(phos::ARROW_UP))
+                                .on_hover_text_at_pointer(t!("help.settings.system_stats.up"))
+                                .clicked()
+                            {
+                                swap = Some((i, i - 1));
+                            }
+                        });
+                        ui.add_enabled_ui(i + 1 < names.len(), |ui| {
+                            if ui
+                                .button(RichText::new(phos::ARROW_DOWN))
+                                .on_hover_text_at_pointer(t!("help.settings.system_stats.down"))
+                                .clicked()
+                            {
+                                swap = Some((i, i + 1));
+                            }
+                        });
+                        if ui
+                            .button(RichText::new(phos::TRASH))
+                            .on_hover_text_at_pointer(t!("help.settings.system_stats.remove"))
+                            .clicked()
+                        {
+                            remove = Some(i);
+                        }
+                        ui.label(format!("{}. {}", i + 1, name));
+                    });
+                }
+
+                if let Some((a, b)) = swap {
+                    stats.swap(a, b);
+                    changed = true;
+                }
+                if let Some(i) = remove {
+                    stats.remove(i);
+                    changed = true;
+                }
+            });
+
+        if changed {
+            let mut conf = self.config.blocking_lock();
+            conf.system_stats = stats;
+            conf.save();
+        }
+    }
 }
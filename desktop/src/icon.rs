@@ -0,0 +1,322 @@
+// Icon loading and conversion for keys shown on device screens
+
+use std::{fs::File, io::BufReader, path::Path};
+
+use anyhow::{bail, Result};
+use image::{
+    codecs::gif::GifDecoder,
+    imageops::{self, FilterType},
+    AnimationDecoder, RgbaImage,
+};
+use jukebox_util::screen::{
+    ICON_ANIMATION_FPS, ICON_ANIMATION_FRAMES_MAX, ICON_DATA_SIZE, ICON_SIZE,
+};
+
+use crate::config::{IconLabel, LabelPosition};
+
+pub type IconFrame = [u8; ICON_DATA_SIZE];
+pub type IconPixels = [u16; ICON_SIZE * ICON_SIZE];
+
+// A 16 bit BMP (BITMAPV4HEADER) with RGB565 bitfields, for a single 32x32 frame
+const BMP_HEADER_SIZE: usize = 0x7A;
+const BMP_HEADER: &[u8] = &[
+    66, 77, 122, 8, 0, 0, 0, 0, 0, 0, 122, 0, 0, 0, 108, 0, 0, 0, 32, 0, 0, 0, 32, 0, 0, 0, 1, 0,
+    16, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 248, 0, 0,
+    224, 7, 0, 0, 31, 0, 0, 0, 0, 0, 0, 0, 66, 71, 82, 115, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0,
+];
+
+/// Loads an image as a list of icon frames. Animated GIFs are resampled to the device's frame rate,
+/// everything else gives a single frame.
+pub fn load_icon_frames(path: &Path) -> Result<Vec<IconPixels>> {
+    let is_gif = path
+        .extension()
+        .map(|e| e.eq_ignore_ascii_case("gif"))
+        .unwrap_or(false);
+
+    let images = if is_gif {
+        load_gif_frames(path)?
+    } else {
+        vec![image::open(path)?.into_rgba8()]
+    };
+
+    Ok(images.iter().map(|i| dither_rgb565(&fit_icon(i))).collect())
+}
+
+fn load_gif_frames(path: &Path) -> Result<Vec<RgbaImage>> {
+    let decoder = GifDecoder::new(BufReader::new(File::open(path)?))?;
+
+    // Each frame with the time (in ms) it stops showing
+    let mut timeline = Vec::new();
+    let mut end = 0u64;
+    for f in decoder.into_frames().collect_frames()? {
+        let (n, d) = f.delay().numer_denom_ms();
+        // Browsers treat missing delays as 100ms, so we do too
+        let delay = if n == 0 || d == 0 {
+            100
+        } else {
+            (n / d).max(20)
+        };
+        end += delay as u64;
+        timeline.push((end, f.into_buffer()));
+    }
+
+    let Some((_, last)) = timeline.last() else {
+        bail!("gif has no frames");
+    };
+
+    let step = 1000 / ICON_ANIMATION_FPS;
+    let count = (end.div_ceil(step) as usize).clamp(1, ICON_ANIMATION_FRAMES_MAX);
+    Ok((0..count)
+        .map(|k| {
+            let at = k as u64 * step;
+            timeline
+                .iter()
+                .find(|(end, _)| at < *end)
+                .map(|(_, i)| i)
+                .unwrap_or(last)
+                .clone()
+        })
+        .collect())
+}
+
+/// Crops to the middle square and scales to icon size. Small pixel art is scaled up without blurring.
+fn fit_icon(image: &RgbaImage) -> RgbaImage {
+    let (w, h) = image.dimensions();
+    let s = w.min(h);
+    let cropped = imageops::crop_imm(image, (w - s) / 2, (h - s) / 2, s, s).to_image();
+
+    let filter = if s < ICON_SIZE as u32 {
+        FilterType::Nearest
+    } else {
+        FilterType::Lanczos3
+    };
+    imageops::resize(&cropped, ICON_SIZE as u32, ICON_SIZE as u32, filter)
+}
+
+/// Floyd-Steinberg dithering down to RGB565, with transparency blended onto black.
+/// Rows come out bottom up, like a BMP.
+fn dither_rgb565(image: &RgbaImage) -> IconPixels {
+    let n = ICON_SIZE;
+    let mut px: Vec<[f32; 3]> = image
+        .pixels()
+        .map(|p| {
+            let a = p[3] as f32 / 255.0;
+            [p[0] as f32 * a, p[1] as f32 * a, p[2] as f32 * a]
+        })
+        .collect();
+
+    let mut icon = [0u16; ICON_SIZE * ICON_SIZE];
+    for y in 0..n {
+        for x in 0..n {
+            let old = px[y * n + x];
+            let mut c = [0u16; 3];
+            let mut err = [0f32; 3];
+            for (ch, levels) in [31.0f32, 63.0, 31.0].into_iter().enumerate() {
+                let v = (old[ch].clamp(0.0, 255.0) / 255.0 * levels).round();
+                c[ch] = v as u16;
+                err[ch] = old[ch] - v / levels * 255.0;
+            }
+            icon[(n - 1 - y) * n + x] = (c[0] << 11) | (c[1] << 5) | c[2];
+
+            for (dx, dy, f) in [
+                (1isize, 0usize, 7.0 / 16.0),
+                (-1, 1, 3.0 / 16.0),
+                (0, 1, 5.0 / 16.0),
+                (1, 1, 1.0 / 16.0),
+            ] {
+                let nx = x as isize + dx;
+                if nx < 0 || nx >= n as isize || y + dy >= n {
+                    continue;
+                }
+                for (p, e) in px[(y + dy) * n + nx as usize].iter_mut().zip(err) {
+                    *p += e * f;
+                }
+            }
+        }
+    }
+
+    icon
+}
+
+/// A 16 bit BMP holding every frame stacked top to bottom, so it still opens as a normal image.
+pub fn encode_icon_bmp(frames: &[IconPixels]) -> Vec<u8> {
+    let size = BMP_HEADER_SIZE + ICON_DATA_SIZE * frames.len();
+    let height = (ICON_SIZE * frames.len()) as u32;
+
+    let mut data = BMP_HEADER.to_vec();
+    data[2..6].copy_from_slice(&(size as u32).to_le_bytes());
+    data[22..26].copy_from_slice(&height.to_le_bytes());
+
+    // BMPs are stored bottom up, so the last frame comes first
+    for f in frames.iter().rev() {
+        for p in f {
+            data.extend_from_slice(&p.to_le_bytes());
+        }
+    }
+
+    data
+}
+
+// Only our own format can be sent as is: 32 pixels wide, bottom up, RGB565 bitfields
+fn icon_bmp_frames(bytes: &[u8]) -> Option<(usize, usize)> {
+    let u16_at = |o: usize| Some(u16::from_le_bytes(bytes.get(o..o + 2)?.try_into().ok()?));
+    let u32_at = |o: usize| Some(u32::from_le_bytes(bytes.get(o..o + 4)?.try_into().ok()?));
+
+    if bytes.get(0..2)? != b"BM" {
+        return None;
+    }
+    let offset = u32_at(10)? as usize;
+    let width = u32_at(18)? as i32;
+    let height = u32_at(22)? as i32;
+    let bpp = u16_at(28)?;
+    let compression = u32_at(30)?;
+    let masks = (u32_at(54)?, u32_at(58)?, u32_at(62)?);
+
+    if width != ICON_SIZE as i32
+        || height <= 0
+        || height % ICON_SIZE as i32 != 0
+        || bpp != 16
+        || compression != 3
+        || masks != (0xF800, 0x07E0, 0x001F)
+    {
+        return None;
+    }
+
+    Some((offset, height as usize / ICON_SIZE))
+}
+
+/// How many frames an icon file holds, for showing just the first one.
+pub fn icon_frame_count(bytes: &[u8]) -> usize {
+    icon_bmp_frames(bytes).map(|(_, c)| c).unwrap_or(1)
+}
+
+/// Splits an icon file into frames ready to send to a device. Anything that isn't one of our own
+/// BMPs goes through the image crate first.
+pub fn decode_icon_frames(bytes: &[u8]) -> Result<Vec<IconFrame>> {
+    if let Some((offset, count)) = icon_bmp_frames(bytes) {
+        if let Some(data) = bytes.get(offset..offset + ICON_DATA_SIZE * count) {
+            return Ok(data
+                .chunks_exact(ICON_DATA_SIZE)
+                .rev()
+                .map(|c| c.try_into().unwrap())
+                .collect());
+        }
+    }
+
+    let image = image::load_from_memory(bytes)?.into_rgba8();
+    let icon = dither_rgb565(&fit_icon(&image));
+    let mut frame = [0u8; ICON_DATA_SIZE];
+    for (i, p) in icon.iter().enumerate() {
+        frame[i * 2..i * 2 + 2].copy_from_slice(&p.to_le_bytes());
+    }
+    Ok(vec![frame])
+}
+
+// A tiny 3x5 font for labels, one row per entry with the left pixel in the high bit
+fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b011, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+/// Draws a line of text across every frame of an icon, on a darkened band so it stays readable.
+/// Text that doesn't fit is cut off.
+pub fn compose_icon_label(frames: &mut [IconFrame], label: &IconLabel, text: &str) {
+    let scale = if label.large { 2 } else { 1 };
+    let advance = 4 * scale;
+    let band = 5 * scale + 2;
+
+    let chars: Vec<char> = text.chars().take((ICON_SIZE + scale) / advance).collect();
+    if chars.is_empty() {
+        return;
+    }
+    let width = chars.len() * advance - scale;
+    let x0 = (ICON_SIZE - width) / 2;
+    let y0 = match label.position {
+        LabelPosition::Top => 0,
+        LabelPosition::Middle => (ICON_SIZE - band) / 2,
+        LabelPosition::Bottom => ICON_SIZE - band,
+    };
+
+    // Frames are bottom up, like the BMPs they came from
+    let index = |x: usize, y: usize| ((ICON_SIZE - 1 - y) * ICON_SIZE + x) * 2;
+
+    for frame in frames.iter_mut() {
+        for y in y0..y0 + band {
+            for x in 0..ICON_SIZE {
+                let i = index(x, y);
+                let p = u16::from_le_bytes([frame[i], frame[i + 1]]);
+                frame[i..i + 2].copy_from_slice(&((p >> 1) & 0x7BEF).to_le_bytes());
+            }
+        }
+
+        for (n, c) in chars.iter().enumerate() {
+            for (row, bits) in glyph(*c).iter().enumerate() {
+                for col in 0..3 {
+                    if bits & (0b100 >> col) == 0 {
+                        continue;
+                    }
+                    for (dx, dy) in (0..scale).flat_map(|dx| (0..scale).map(move |dy| (dx, dy))) {
+                        let x = x0 + n * advance + col * scale + dx;
+                        let y = y0 + 1 + row * scale + dy;
+                        let i = index(x, y);
+                        frame[i..i + 2].copy_from_slice(&label.color.to_le_bytes());
+                    }
+                }
+            }
+        }
+    }
+}
@@ -12,14 +12,20 @@ extern crate rust_i18n;
 i18n!("locales", fallback = "en");
 
 mod actions;
+mod audio;
 mod config;
 mod firmware_update;
 mod gui;
+mod icon;
 mod input;
+mod mqtt;
+mod secrets;
 mod serial;
 mod software_update;
 mod splash;
 mod system;
+mod tls;
+mod toast;
 
 // static http client for various api calls
 // we also set the user agent to something specific to this software
@@ -69,10 +75,10 @@ fn main() -> anyhow::Result<()> {
         simple_logging::log_to_file(p, log::LevelFilter::Info).unwrap();
     }
 
-    // For OBS websocket TLS support, currently unused.
-    // rustls::crypto::aws_lc_rs::default_provider()
-    //     .install_default()
-    //     .expect("failed to install rustls crypto provider");
+    // For OBS websocket TLS support
+    rustls::crypto::aws_lc_rs::default_provider()
+        .install_default()
+        .expect("failed to install rustls crypto provider");
 
     // GUI launches all the necessary threads when started
     gui::gui::basic_gui();
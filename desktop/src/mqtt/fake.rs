@@ -0,0 +1,114 @@
+// A broker kept in memory, for trying out the MQTT actions without a real one. It listens on
+// localhost and speaks the same protocol, so the client is the same as with a real broker.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    net::Ipv4Addr,
+    sync::{Arc, Mutex},
+};
+
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+};
+
+use super::packet::{self, read_packet, topic_matches, Packet};
+
+struct FakeSession {
+    filters: Vec<String>,
+    tx: UnboundedSender<Vec<u8>>,
+}
+
+#[derive(Default)]
+struct FakeBroker {
+    sessions: HashMap<u64, FakeSession>,
+    retained: BTreeMap<String, Vec<u8>>, // Topic -> Payload
+}
+
+/// Starts the broker on a free port, which it returns
+pub async fn start_fake_broker() -> io::Result<u16> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+    let port = listener.local_addr()?.port();
+    let broker = Arc::new(Mutex::new(FakeBroker::default()));
+
+    tokio::spawn(async move {
+        let mut next_id = 0;
+        while let Ok((stream, _)) = listener.accept().await {
+            next_id += 1;
+            tokio::spawn(fake_session(stream, next_id, broker.clone()));
+        }
+    });
+
+    Ok(port)
+}
+
+async fn fake_session(stream: TcpStream, id: u64, broker: Arc<Mutex<FakeBroker>>) {
+    let (mut reader, mut writer) = stream.into_split();
+
+    // Other sessions publish to this one through here
+    let (tx, mut rx) = unbounded_channel::<Vec<u8>>();
+    tokio::spawn(async move {
+        while let Some(p) = rx.recv().await {
+            if writer.write_all(&p).await.is_err() {
+                break;
+            }
+        }
+    });
+    broker.lock().unwrap().sessions.insert(
+        id,
+        FakeSession {
+            filters: Vec::new(),
+            tx: tx.clone(),
+        },
+    );
+
+    while let Ok(p) = read_packet(&mut reader).await {
+        match p {
+            Packet::Connect { client_id } => {
+                log::debug!("mqtt: fake broker: {} connected", client_id);
+                let _ = tx.send(packet::connack(0));
+            }
+            Packet::Subscribe {
+                id: packet_id,
+                filters,
+            } => {
+                let mut broker = broker.lock().unwrap();
+                let _ = tx.send(packet::suback(packet_id, filters.len()));
+                for (topic, payload) in &broker.retained {
+                    if filters.iter().any(|f| topic_matches(f, topic)) {
+                        let _ = tx.send(packet::publish(topic, payload, true));
+                    }
+                }
+                if let Some(session) = broker.sessions.get_mut(&id) {
+                    session.filters.extend(filters);
+                }
+            }
+            Packet::Publish {
+                topic,
+                payload,
+                retain,
+            } => {
+                let mut broker = broker.lock().unwrap();
+                if retain && payload.is_empty() {
+                    broker.retained.remove(&topic);
+                } else if retain {
+                    broker.retained.insert(topic.clone(), payload.clone());
+                }
+                for session in broker.sessions.values() {
+                    if session.filters.iter().any(|f| topic_matches(f, &topic)) {
+                        let _ = session.tx.send(packet::publish(&topic, &payload, false));
+                    }
+                }
+            }
+            Packet::PingReq => {
+                let _ = tx.send(packet::pingresp());
+            }
+            Packet::Disconnect => break,
+            _ => {}
+        }
+    }
+
+    broker.lock().unwrap().sessions.remove(&id);
+}
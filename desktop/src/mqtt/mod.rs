@@ -0,0 +1,517 @@
+// MQTT, for home automation. A service task keeps a connection to the broker from the settings,
+// publishes for actions and key events, and keeps the last message on every topic an action
+// watches so key icons and LEDs can follow them.
+
+mod fake;
+mod packet;
+
+use std::{
+    collections::BTreeMap,
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    io::{split, AsyncRead, AsyncWrite, AsyncWriteExt, WriteHalf},
+    sync::{
+        mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        Mutex,
+    },
+    task::JoinHandle,
+    time::timeout,
+};
+
+use crate::{
+    actions::action::notify_icon_states_changed,
+    config::{JukeBoxConfig, MqttBroker, TlsTrust},
+    input::InputKey,
+    tls::{tcp_connect, tls_connect, TlsError},
+};
+
+use packet::{read_packet, topic_matches, Packet};
+
+// Set JUKEBOX_MQTT_BROKER to "fake" to use a broker kept in memory instead of the configured one
+const MQTT_BROKER_ENV: &str = "JUKEBOX_MQTT_BROKER";
+const MQTT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const MQTT_KEEP_ALIVE: Duration = Duration::from_secs(30);
+// How often the connection checks on the reader and pings, between requests
+const MQTT_IDLE_INTERVAL: Duration = Duration::from_secs(1);
+const RECONNECT_MIN_INTERVAL: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub enum MqttError {
+    NotSetUp,
+    NotConnected,
+    Network(TlsError),
+    Rejected(u8), // CONNACK return code
+    Lost(String),
+}
+impl MqttError {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::NotSetUp => t!("action.mqtt.err.not_set_up").into(),
+            Self::NotConnected => match last_error() {
+                Some(e) => t!("action.mqtt.err.not_connected_because", error = e).into(),
+                None => t!("action.mqtt.err.not_connected").into(),
+            },
+            Self::Network(TlsError::Timeout) => t!("action.mqtt.err.timeout").into(),
+            Self::Network(TlsError::Refused) => t!("action.mqtt.err.refused").into(),
+            Self::Network(TlsError::FingerprintChanged { expected, actual }) => t!(
+                "action.mqtt.err.fingerprint_changed",
+                expected = expected,
+                actual = actual
+            )
+            .into(),
+            Self::Network(
+                TlsError::Unreachable(e)
+                | TlsError::CaFile(e)
+                | TlsError::Untrusted(e)
+                | TlsError::Handshake(e),
+            ) => t!("action.mqtt.err.network", error = e).into(),
+            Self::Rejected(4 | 5) => t!("action.mqtt.err.login").into(),
+            Self::Rejected(code) => t!("action.mqtt.err.rejected", code = code).into(),
+            Self::Lost(e) => t!("action.mqtt.err.lost", error = e).into(),
+        }
+    }
+}
+
+enum MqttRequest {
+    Publish {
+        topic: String,
+        payload: Vec<u8>,
+        retain: bool,
+    },
+    Subscribe(String),
+    Reconnect, // The broker settings changed
+}
+
+static MQTT_TX: OnceLock<UnboundedSender<MqttRequest>> = OnceLock::new();
+static MQTT_CONNECTED: AtomicBool = AtomicBool::new(false);
+// Why the last connection attempt failed, for errors from actions while disconnected
+static MQTT_LAST_ERROR: std::sync::Mutex<Option<String>> = std::sync::Mutex::new(None);
+// Topic Filter -> Last message on it, for every filter an action watches. None until one comes in.
+static MQTT_TOPICS: std::sync::Mutex<BTreeMap<String, Option<String>>> =
+    std::sync::Mutex::new(BTreeMap::new());
+
+pub fn start_mqtt_service(config: Arc<Mutex<JukeBoxConfig>>) {
+    let (tx, rx) = unbounded_channel::<MqttRequest>();
+    if MQTT_TX.set(tx).is_err() {
+        return; // already running
+    }
+
+    tokio::runtime::Handle::current().spawn(async move { mqtt_task(config, rx).await });
+}
+
+pub fn is_connected() -> bool {
+    MQTT_CONNECTED.load(Ordering::Relaxed)
+}
+
+pub fn last_error() -> Option<String> {
+    MQTT_LAST_ERROR.lock().unwrap().clone()
+}
+
+/// Drops the connection and connects again with the saved broker settings
+pub fn reconnect() {
+    if let Some(tx) = MQTT_TX.get() {
+        let _ = tx.send(MqttRequest::Reconnect);
+    }
+}
+
+pub fn publish(topic: &str, payload: &[u8], retain: bool) -> Result<(), MqttError> {
+    let tx = MQTT_TX.get().ok_or(MqttError::NotConnected)?;
+    if !is_connected() {
+        return Err(MqttError::NotConnected);
+    }
+    tx.send(MqttRequest::Publish {
+        topic: topic.into(),
+        payload: payload.into(),
+        retain,
+    })
+    .map_err(|_| MqttError::NotConnected)
+}
+
+/// Tells the broker about a key going down or up, under `<topic>/<device UID>/<key>`. Does
+/// nothing when no topic is set, or while disconnected.
+pub fn publish_key_event(topic: &str, device_uid: &str, input_key: InputKey, pressed: bool) {
+    if topic.is_empty() || !is_connected() {
+        return;
+    }
+    let topic = format!(
+        "{}/{}/{}",
+        topic.trim_end_matches('/'),
+        device_uid,
+        input_key
+    );
+    let payload = if pressed { "pressed" } else { "released" };
+    let _ = publish(&topic, payload.as_bytes(), false);
+}
+
+/// The last message on a topic, or on any topic under a filter with wildcards. A topic is watched
+/// from the first time it's asked for, so this is None until the broker sends something for it.
+pub fn watch(filter: &str) -> Option<String> {
+    if filter.is_empty() {
+        return None;
+    }
+
+    let mut topics = MQTT_TOPICS.lock().unwrap();
+    if let Some(last) = topics.get(filter) {
+        return last.clone();
+    }
+    topics.insert(filter.into(), None);
+    if let Some(tx) = MQTT_TX.get() {
+        let _ = tx.send(MqttRequest::Subscribe(filter.into()));
+    }
+    None
+}
+
+// Keeps the message for every watched filter the topic falls under
+fn received(topic: &str, payload: &[u8]) {
+    let message = String::from_utf8_lossy(payload).to_string();
+    let mut changed = false;
+    for (filter, last) in MQTT_TOPICS.lock().unwrap().iter_mut() {
+        if topic_matches(filter, topic) && last.as_ref() != Some(&message) {
+            *last = Some(message.clone());
+            changed = true;
+        }
+    }
+    if changed {
+        notify_icon_states_changed();
+    }
+}
+
+trait MqttStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> MqttStream for T {}
+
+async fn write_packet<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    packet: &[u8],
+) -> Result<(), MqttError> {
+    writer
+        .write_all(packet)
+        .await
+        .map_err(|e| MqttError::Lost(e.to_string()))?;
+    // TLS holds on to writes until flushed
+    writer
+        .flush()
+        .await
+        .map_err(|e| MqttError::Lost(e.to_string()))
+}
+
+// Connects and logs in. A first TLS connection pins the certificate it sees.
+async fn open(
+    config: &Arc<Mutex<JukeBoxConfig>>,
+    broker: &MqttBroker,
+) -> Result<Box<dyn MqttStream>, MqttError> {
+    if broker.host.trim().is_empty() {
+        return Err(MqttError::NotSetUp);
+    }
+
+    let mut stream: Box<dyn MqttStream> = match &broker.tls {
+        None => Box::new(
+            tcp_connect(broker.host.trim(), broker.port, MQTT_CONNECT_TIMEOUT)
+                .await
+                .map_err(MqttError::Network)?,
+        ),
+        Some(trust) => {
+            let (stream, fingerprint) =
+                tls_connect(broker.host.trim(), broker.port, trust, MQTT_CONNECT_TIMEOUT)
+                    .await
+                    .map_err(MqttError::Network)?;
+            if *trust == TlsTrust::Pinned(None) {
+                let mut c = config.lock().await;
+                c.mqtt_broker.tls = Some(TlsTrust::Pinned(Some(fingerprint)));
+                c.save();
+            }
+            Box::new(stream)
+        }
+    };
+
+    let username = Some(broker.username.as_str()).filter(|u| !u.is_empty());
+    let connect = packet::connect(
+        &broker.client_id,
+        username,
+        broker.password.as_deref(),
+        MQTT_KEEP_ALIVE.as_secs() as u16,
+    );
+    write_packet(&mut stream, &connect).await?;
+
+    match timeout(MQTT_CONNECT_TIMEOUT, read_packet(&mut stream)).await {
+        Err(_) => Err(MqttError::Network(TlsError::Timeout)),
+        Ok(Err(e)) => Err(MqttError::Lost(e.to_string())),
+        Ok(Ok(Packet::ConnAck { code: 0 })) => Ok(stream),
+        Ok(Ok(Packet::ConnAck { code })) => Err(MqttError::Rejected(code)),
+        Ok(Ok(p)) => Err(MqttError::Lost(format!("expected CONNACK, got {:?}", p))),
+    }
+}
+
+// Serves requests over an open connection. Ok when asked to reconnect, Err once it's lost.
+async fn run_connection(
+    stream: Box<dyn MqttStream>,
+    rx: &mut UnboundedReceiver<MqttRequest>,
+) -> Result<(), MqttError> {
+    let (mut reader, mut writer) = split(stream);
+
+    let last_seen = Arc::new(std::sync::Mutex::new(Instant::now()));
+    let reader_last_seen = last_seen.clone();
+    let mut read_task = tokio::spawn(async move {
+        loop {
+            let p = match read_packet(&mut reader).await {
+                Ok(p) => p,
+                Err(e) => return e,
+            };
+            *reader_last_seen.lock().unwrap() = Instant::now();
+            if let Packet::Publish { topic, payload, .. } = p {
+                received(&topic, &payload);
+            }
+        }
+    });
+
+    let res = serve_requests(&mut writer, rx, &mut read_task, &last_seen).await;
+
+    read_task.abort();
+    res
+}
+
+async fn serve_requests(
+    writer: &mut WriteHalf<Box<dyn MqttStream>>,
+    rx: &mut UnboundedReceiver<MqttRequest>,
+    read_task: &mut JoinHandle<io::Error>,
+    last_seen: &std::sync::Mutex<Instant>,
+) -> Result<(), MqttError> {
+    // Packet ids can't be 0
+    let mut packet_id: u16 = 0;
+    let mut next_id = || {
+        packet_id = packet_id % u16::MAX + 1;
+        packet_id
+    };
+
+    // Everything watched before this connection, later ones come in as requests
+    let filters: Vec<_> = MQTT_TOPICS.lock().unwrap().keys().cloned().collect();
+    for filter in filters {
+        write_packet(writer, &packet::subscribe(next_id(), &filter)).await?;
+    }
+
+    let mut last_ping = Instant::now();
+    loop {
+        if read_task.is_finished() {
+            return Err(match read_task.await {
+                Ok(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    MqttError::Lost(t!("action.mqtt.err.closed").into())
+                }
+                Ok(e) => MqttError::Lost(e.to_string()),
+                Err(e) => MqttError::Lost(e.to_string()),
+            });
+        }
+        if last_seen.lock().unwrap().elapsed() > MQTT_KEEP_ALIVE * 2 {
+            return Err(MqttError::Lost(t!("action.mqtt.err.no_answer").into()));
+        }
+
+        let packet = match timeout(MQTT_IDLE_INTERVAL, rx.recv()).await {
+            Ok(Some(MqttRequest::Publish {
+                topic,
+                payload,
+                retain,
+            })) => packet::publish(&topic, &payload, retain),
+            Ok(Some(MqttRequest::Subscribe(filter))) => packet::subscribe(next_id(), &filter),
+            Ok(Some(MqttRequest::Reconnect)) | Ok(None) => {
+                let _ = write_packet(writer, &packet::disconnect()).await;
+                return Ok(());
+            }
+            Err(_) if last_ping.elapsed() >= MQTT_KEEP_ALIVE / 2 => {
+                last_ping = Instant::now();
+                packet::pingreq()
+            }
+            Err(_) => continue,
+        };
+        write_packet(writer, &packet).await?;
+    }
+}
+
+// Drops requests that can't be served while disconnected, until one asks to reconnect. Watched
+// topics are subscribed to again on connecting, so nothing is lost there.
+async fn wait_for_reconnect(rx: &mut UnboundedReceiver<MqttRequest>) {
+    while let Some(r) = rx.recv().await {
+        if let MqttRequest::Reconnect = r {
+            return;
+        }
+    }
+    std::future::pending().await
+}
+
+async fn mqtt_task(config: Arc<Mutex<JukeBoxConfig>>, mut rx: UnboundedReceiver<MqttRequest>) {
+    let fake = std::env::var(MQTT_BROKER_ENV).as_deref() == Ok("fake");
+    let mut fake_port = None;
+    let mut backoff = RECONNECT_MIN_INTERVAL;
+
+    loop {
+        let mut broker = config.lock().await.mqtt_broker.clone();
+        if fake {
+            if fake_port.is_none() {
+                match fake::start_fake_broker().await {
+                    Ok(p) => fake_port = Some(p),
+                    Err(e) => log::warn!("mqtt: failed to start the fake broker: {}", e),
+                }
+            }
+            broker.host = "127.0.0.1".into();
+            broker.port = fake_port.unwrap_or(broker.port);
+            broker.tls = None;
+        }
+
+        let err = match open(&config, &broker).await {
+            Ok(stream) => {
+                log::info!("mqtt: connected to {}:{}", broker.host, broker.port);
+                backoff = RECONNECT_MIN_INTERVAL;
+                *MQTT_LAST_ERROR.lock().unwrap() = None;
+                MQTT_CONNECTED.store(true, Ordering::Relaxed);
+                notify_icon_states_changed();
+
+                let res = run_connection(stream, &mut rx).await;
+
+                MQTT_CONNECTED.store(false, Ordering::Relaxed);
+                notify_icon_states_changed();
+                match res {
+                    Ok(()) => continue,
+                    Err(e) => e,
+                }
+            }
+            Err(MqttError::NotSetUp) => {
+                wait_for_reconnect(&mut rx).await;
+                continue;
+            }
+            Err(e) => e,
+        };
+
+        log::warn!(
+            "mqtt: disconnected from {}:{}, retrying in {:?}: {:?}",
+            broker.host,
+            broker.port,
+            backoff,
+            err
+        );
+        *MQTT_LAST_ERROR.lock().unwrap() = Some(err.describe());
+
+        // Changed settings get tried right away
+        let _ = timeout(backoff, wait_for_reconnect(&mut rx)).await;
+        backoff = (backoff * 2).min(RECONNECT_MAX_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpStream;
+
+    use super::*;
+
+    async fn next_packet(stream: &mut TcpStream) -> Packet {
+        timeout(Duration::from_secs(5), read_packet(stream))
+            .await
+            .expect("the broker went quiet")
+            .unwrap()
+    }
+
+    async fn next_publish(stream: &mut TcpStream) -> (String, Vec<u8>) {
+        loop {
+            if let Packet::Publish { topic, payload, .. } = next_packet(stream).await {
+                return (topic, payload);
+            }
+        }
+    }
+
+    async fn wait_until(what: &str, mut f: impl FnMut() -> bool) {
+        for _ in 0..100 {
+            if f() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("timed out waiting until {}", what);
+    }
+
+    #[test]
+    fn service_reaches_the_fake_broker() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let port = fake::start_fake_broker().await.unwrap();
+            let config = JukeBoxConfig {
+                mqtt_broker: MqttBroker {
+                    host: "127.0.0.1".into(),
+                    port,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            start_mqtt_service(Arc::new(Mutex::new(config)));
+
+            // Another client, to see what reaches the broker
+            let mut observer = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+            write_packet(&mut observer, &packet::connect("observer", None, None, 30))
+                .await
+                .unwrap();
+            assert_eq!(
+                next_packet(&mut observer).await,
+                Packet::ConnAck { code: 0 }
+            );
+            write_packet(&mut observer, &packet::subscribe(1, "jukebox/#"))
+                .await
+                .unwrap();
+            assert_eq!(next_packet(&mut observer).await, Packet::SubAck { id: 1 });
+
+            wait_until("connected", is_connected).await;
+
+            publish("jukebox/light/set", b"ON", false).unwrap();
+            assert_eq!(
+                next_publish(&mut observer).await,
+                ("jukebox/light/set".to_string(), b"ON".to_vec())
+            );
+
+            publish_key_event("jukebox/keys/", "JB-1234", InputKey::KeySwitch3, true);
+            publish_key_event("jukebox/keys/", "JB-1234", InputKey::KeySwitch3, false);
+            assert_eq!(
+                next_publish(&mut observer).await,
+                (
+                    "jukebox/keys/JB-1234/KeySwitch3".to_string(),
+                    b"pressed".to_vec()
+                )
+            );
+            assert_eq!(
+                next_publish(&mut observer).await,
+                (
+                    "jukebox/keys/JB-1234/KeySwitch3".to_string(),
+                    b"released".to_vec()
+                )
+            );
+
+            // Watching subscribes, and a retained state comes in whenever that happens
+            assert_eq!(watch("jukebox/+/state"), None);
+            write_packet(
+                &mut observer,
+                &packet::publish("jukebox/desk/state", b"OFF", true),
+            )
+            .await
+            .unwrap();
+            wait_until("the retained state came in", || {
+                watch("jukebox/+/state").as_deref() == Some("OFF")
+            })
+            .await;
+
+            write_packet(
+                &mut observer,
+                &packet::publish("jukebox/desk/state", b"ON", false),
+            )
+            .await
+            .unwrap();
+            wait_until("the new state came in", || {
+                watch("jukebox/+/state").as_deref() == Some("ON")
+            })
+            .await;
+        });
+    }
+}
@@ -0,0 +1,356 @@
+// Just enough of MQTT 3.1.1 for what JukeBox does with it: QoS 0 publishes and subscriptions.
+// The client and the stand-in broker both use these.
+
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+// Bigger packets are refused instead of buffered, nothing keys care about comes close
+const MAX_PACKET_SIZE: usize = 1 << 20;
+
+const CONNECT: u8 = 1;
+const CONNACK: u8 = 2;
+const PUBLISH: u8 = 3;
+const SUBSCRIBE: u8 = 8;
+const SUBACK: u8 = 9;
+const PINGREQ: u8 = 12;
+const PINGRESP: u8 = 13;
+const DISCONNECT: u8 = 14;
+
+#[derive(Debug, PartialEq)]
+pub enum Packet {
+    Connect {
+        client_id: String,
+    },
+    ConnAck {
+        code: u8, // 0 when accepted
+    },
+    Publish {
+        topic: String,
+        payload: Vec<u8>,
+        retain: bool,
+    },
+    Subscribe {
+        id: u16,
+        filters: Vec<String>,
+    },
+    SubAck {
+        id: u16,
+    },
+    PingReq,
+    PingResp,
+    Disconnect,
+    Other(u8), // Packet type we have no use for
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn with_header(header: u8, body: Vec<u8>) -> Vec<u8> {
+    let mut packet = vec![header];
+    let mut len = body.len();
+    loop {
+        let mut b = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            b |= 0x80;
+        }
+        packet.push(b);
+        if len == 0 {
+            break;
+        }
+    }
+    packet.extend(body);
+    packet
+}
+
+fn push_str(body: &mut Vec<u8>, s: &[u8]) {
+    body.extend((s.len() as u16).to_be_bytes());
+    body.extend(s);
+}
+
+/// Starts a clean session. A password is only sent along with a username, as the spec wants.
+pub fn connect(
+    client_id: &str,
+    username: Option<&str>,
+    password: Option<&str>,
+    keep_alive: u16,
+) -> Vec<u8> {
+    let password = username.and(password);
+
+    let mut body = Vec::new();
+    push_str(&mut body, b"MQTT");
+    body.push(4); // protocol level, 3.1.1
+    let mut flags = 0x02; // clean session
+    if username.is_some() {
+        flags |= 0x80;
+    }
+    if password.is_some() {
+        flags |= 0x40;
+    }
+    body.push(flags);
+    body.extend(keep_alive.to_be_bytes());
+    push_str(&mut body, client_id.as_bytes());
+    if let Some(username) = username {
+        push_str(&mut body, username.as_bytes());
+    }
+    if let Some(password) = password {
+        push_str(&mut body, password.as_bytes());
+    }
+    with_header(CONNECT << 4, body)
+}
+
+pub fn connack(code: u8) -> Vec<u8> {
+    with_header(CONNACK << 4, vec![0, code])
+}
+
+pub fn publish(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let mut body = Vec::new();
+    push_str(&mut body, topic.as_bytes());
+    body.extend(payload);
+    with_header((PUBLISH << 4) | retain as u8, body)
+}
+
+pub fn subscribe(id: u16, filter: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend(id.to_be_bytes());
+    push_str(&mut body, filter.as_bytes());
+    body.push(0); // QoS 0
+    with_header((SUBSCRIBE << 4) | 0x02, body)
+}
+
+pub fn suback(id: u16, count: usize) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend(id.to_be_bytes());
+    body.extend(std::iter::repeat_n(0, count)); // granted QoS 0 for each
+    with_header(SUBACK << 4, body)
+}
+
+pub fn pingreq() -> Vec<u8> {
+    with_header(PINGREQ << 4, Vec::new())
+}
+
+pub fn pingresp() -> Vec<u8> {
+    with_header(PINGRESP << 4, Vec::new())
+}
+
+pub fn disconnect() -> Vec<u8> {
+    with_header(DISCONNECT << 4, Vec::new())
+}
+
+// Reads fields off a packet's body, failing on anything cut short
+struct Fields<'a>(&'a [u8]);
+impl<'a> Fields<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(invalid("packet cut short"));
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| invalid("string isn't UTF-8"))
+    }
+}
+
+pub async fn read_packet<R: AsyncRead + Unpin>(r: &mut R) -> io::Result<Packet> {
+    let header = r.read_u8().await?;
+
+    let mut len = 0;
+    for i in 0..4 {
+        let b = r.read_u8().await?;
+        len |= ((b & 0x7F) as usize) << (7 * i);
+        if b & 0x80 == 0 {
+            break;
+        }
+        if i == 3 {
+            return Err(invalid("remaining length too long"));
+        }
+    }
+    if len > MAX_PACKET_SIZE {
+        return Err(invalid("packet too large"));
+    }
+
+    let mut body = vec![0; len];
+    r.read_exact(&mut body).await?;
+    let mut fields = Fields(&body);
+
+    Ok(match header >> 4 {
+        CONNECT => {
+            fields.string()?; // protocol name
+            fields.u8()?; // protocol level
+            fields.u8()?; // flags
+            fields.u16()?; // keep alive
+            Packet::Connect {
+                client_id: fields.string()?,
+            }
+        }
+        CONNACK => {
+            fields.u8()?; // session present
+            Packet::ConnAck { code: fields.u8()? }
+        }
+        PUBLISH => {
+            let topic = fields.string()?;
+            if (header >> 1) & 0x03 > 0 {
+                fields.u16()?; // packet id, only there above QoS 0
+            }
+            Packet::Publish {
+                topic,
+                payload: fields.0.to_vec(),
+                retain: header & 0x01 != 0,
+            }
+        }
+        SUBSCRIBE => {
+            let id = fields.u16()?;
+            let mut filters = Vec::new();
+            while !fields.0.is_empty() {
+                filters.push(fields.string()?);
+                fields.u8()?; // requested QoS
+            }
+            Packet::Subscribe { id, filters }
+        }
+        SUBACK => Packet::SubAck { id: fields.u16()? },
+        PINGREQ => Packet::PingReq,
+        PINGRESP => Packet::PingResp,
+        DISCONNECT => Packet::Disconnect,
+        kind => Packet::Other(kind),
+    })
+}
+
+/// Whether a topic falls under a subscription filter, with its + and # wildcards
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    let mut levels = topic.split('/');
+    for f in filter.split('/') {
+        match (f, levels.next()) {
+            ("#", _) => return true,
+            ("+", Some(_)) => {}
+            (f, Some(t)) if f == t => {}
+            _ => return false,
+        }
+    }
+    levels.next().is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8]) -> io::Result<Packet> {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(read_packet(&mut &bytes[..]))
+    }
+
+    #[test]
+    fn remaining_length() {
+        for (len, encoded) in [
+            (0, &[0x00][..]),
+            (127, &[0x7F]),
+            (128, &[0x80, 0x01]),
+            (16383, &[0xFF, 0x7F]),
+            (16384, &[0x80, 0x80, 0x01]),
+        ] {
+            let packet = with_header(PUBLISH << 4, vec![0; len]);
+            assert_eq!(&packet[1..1 + encoded.len()], encoded, "length {}", len);
+            assert_eq!(packet.len(), 1 + encoded.len() + len);
+        }
+    }
+
+    #[test]
+    fn publish_round_trip() {
+        // Topic length and the topic take 2 + 5 bytes of the body
+        for len in [127, 128, 16383] {
+            let payload = vec![0xA5; len - 7];
+            let decoded = decode(&publish("a/b/c", &payload, len == 128)).unwrap();
+            assert_eq!(
+                decoded,
+                Packet::Publish {
+                    topic: "a/b/c".into(),
+                    payload,
+                    retain: len == 128,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn connect_round_trip() {
+        let packet = connect("jukebox", Some("user"), Some("hunter2"), 30);
+        assert_eq!(packet[9], 0xC2); // username, password and clean session
+        assert_eq!(&packet[10..12], &30u16.to_be_bytes());
+        assert_eq!(
+            decode(&packet).unwrap(),
+            Packet::Connect {
+                client_id: "jukebox".into()
+            }
+        );
+
+        // No password without a username
+        let packet = connect("jukebox", None, Some("hunter2"), 30);
+        assert_eq!(packet[9], 0x02);
+        assert_eq!(packet.len(), 2 + 10 + 2 + 7);
+
+        assert_eq!(decode(&connack(5)).unwrap(), Packet::ConnAck { code: 5 });
+    }
+
+    #[test]
+    fn subscribe_round_trip() {
+        let packet = subscribe(7, "home/+/state");
+        assert_eq!(packet[0], 0x82);
+        assert_eq!(
+            decode(&packet).unwrap(),
+            Packet::Subscribe {
+                id: 7,
+                filters: vec!["home/+/state".into()],
+            }
+        );
+        assert_eq!(decode(&suback(7, 1)).unwrap(), Packet::SubAck { id: 7 });
+    }
+
+    #[test]
+    fn bodiless_packets() {
+        assert_eq!(decode(&pingreq()).unwrap(), Packet::PingReq);
+        assert_eq!(decode(&pingresp()).unwrap(), Packet::PingResp);
+        assert_eq!(decode(&disconnect()).unwrap(), Packet::Disconnect);
+        assert_eq!(decode(&[0x40, 0x02, 0x00, 0x01]).unwrap(), Packet::Other(4));
+    }
+
+    #[test]
+    fn bad_packets() {
+        let packet = publish("a/b", b"payload", false);
+        for cut in 0..packet.len() {
+            assert!(decode(&packet[..cut]).is_err());
+        }
+
+        // The body says the topic is longer than the body
+        assert!(decode(&[0x30, 0x03, 0x00, 0x05, b'a']).is_err());
+        assert!(decode(&[0x30, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]).is_err());
+        // 2 MiB, over the limit
+        assert!(decode(&[0x30, 0x80, 0x80, 0x80, 0x01]).is_err());
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(topic_matches("home/desk/light", "home/desk/light"));
+        assert!(topic_matches("home/+/light", "home/desk/light"));
+        assert!(topic_matches("home/#", "home/desk/light"));
+        assert!(topic_matches("#", "home"));
+        assert!(!topic_matches("home/+", "home/desk/light"));
+        assert!(!topic_matches("home/desk/light", "home/desk"));
+        assert!(!topic_matches("home/+/light", "home/desk/fan"));
+    }
+}
@@ -0,0 +1,691 @@
+// Credentials (OBS passwords, Discord tokens) are kept out of the config file. They go to the
+// Secret Service on Linux, or to a file encrypted with a passphrase the user picks. Until either is
+// usable they stay in the config as before, so nothing is lost.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    future::Future,
+    io::{Read, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Sender},
+        OnceLock,
+    },
+};
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use tokio::{
+    runtime::{Handle, RuntimeFlavor},
+    sync::oneshot,
+    task::block_in_place,
+};
+
+use crate::config::JukeBoxConfig;
+
+// Set JUKEBOX_SECRET_STORE to "memory" or "file" to skip the Secret Service
+const SECRET_STORE_ENV: &str = "JUKEBOX_SECRET_STORE";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SecretStoreKind {
+    SecretService,
+    EncryptedFile,
+    Memory,
+}
+
+#[derive(Debug)]
+pub enum SecretError {
+    Unavailable(String),
+    Locked,
+    WrongPassphrase,
+    Io(String),
+}
+
+trait SecretBackend: Send {
+    fn kind(&self) -> SecretStoreKind;
+    fn load(&mut self) -> Result<HashMap<String, String>, SecretError>;
+    fn set(&mut self, key: &str, value: &str) -> Result<(), SecretError>;
+    fn delete(&mut self, key: &str) -> Result<(), SecretError>;
+    fn unlock(&mut self, _passphrase: &str) -> Result<(), SecretError> {
+        Ok(())
+    }
+}
+
+// Keeps secrets for this run only. Stands in for a real store when testing.
+#[derive(Default)]
+struct MemoryBackend {
+    secrets: HashMap<String, String>,
+}
+impl SecretBackend for MemoryBackend {
+    fn kind(&self) -> SecretStoreKind {
+        SecretStoreKind::Memory
+    }
+
+    fn load(&mut self) -> Result<HashMap<String, String>, SecretError> {
+        Ok(self.secrets.clone())
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), SecretError> {
+        self.secrets.insert(key.into(), value.into());
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &str) -> Result<(), SecretError> {
+        self.secrets.remove(key);
+        Ok(())
+    }
+}
+
+// File layout: magic, salt, nonce, then the encrypted JSON map of secrets
+const FILE_MAGIC: &[u8; 8] = b"JBSECRT1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+struct FileBackend {
+    path: PathBuf,
+    key: Option<(Key, [u8; SALT_LEN])>, // None until the passphrase is given
+    secrets: HashMap<String, String>,
+}
+impl FileBackend {
+    fn get_path() -> PathBuf {
+        let mut p = JukeBoxConfig::get_dir();
+        p.push("secrets.bin");
+        p
+    }
+
+    fn new() -> Self {
+        Self {
+            path: Self::get_path(),
+            key: None,
+            secrets: HashMap::new(),
+        }
+    }
+
+    fn derive_key(passphrase: &str, salt: &[u8; SALT_LEN]) -> Result<Key, SecretError> {
+        let mut key = Key::default();
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| SecretError::Io(e.to_string()))?;
+        Ok(key)
+    }
+
+    fn write(&self) -> Result<(), SecretError> {
+        let Some((key, salt)) = &self.key else {
+            return Err(SecretError::Locked);
+        };
+
+        let plain =
+            serde_json::to_vec(&self.secrets).map_err(|e| SecretError::Io(e.to_string()))?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let data = ChaCha20Poly1305::new(key)
+            .encrypt(&nonce, plain.as_slice())
+            .map_err(|e| SecretError::Io(e.to_string()))?;
+
+        let mut f = File::create(&self.path).map_err(|e| SecretError::Io(e.to_string()))?;
+        f.write_all(FILE_MAGIC)
+            .and_then(|_| f.write_all(salt))
+            .and_then(|_| f.write_all(&nonce))
+            .and_then(|_| f.write_all(&data))
+            .map_err(|e| SecretError::Io(e.to_string()))
+    }
+}
+impl SecretBackend for FileBackend {
+    fn kind(&self) -> SecretStoreKind {
+        SecretStoreKind::EncryptedFile
+    }
+
+    fn load(&mut self) -> Result<HashMap<String, String>, SecretError> {
+        if self.key.is_none() {
+            return Err(SecretError::Locked);
+        }
+        Ok(self.secrets.clone())
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), SecretError> {
+        self.secrets.insert(key.into(), value.into());
+        self.write()
+    }
+
+    fn delete(&mut self, key: &str) -> Result<(), SecretError> {
+        self.secrets.remove(key);
+        self.write()
+    }
+
+    // Opens the existing file, or starts a new one with this passphrase
+    fn unlock(&mut self, passphrase: &str) -> Result<(), SecretError> {
+        let mut data = Vec::new();
+        match File::open(&self.path) {
+            Ok(mut f) => {
+                f.read_to_end(&mut data)
+                    .map_err(|e| SecretError::Io(e.to_string()))?;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut salt = [0u8; SALT_LEN];
+                OsRng.fill_bytes(&mut salt);
+                self.key = Some((Self::derive_key(passphrase, &salt)?, salt));
+                self.secrets.clear();
+                return self.write();
+            }
+            Err(e) => return Err(SecretError::Io(e.to_string())),
+        }
+
+        let header = FILE_MAGIC.len() + SALT_LEN + NONCE_LEN;
+        if data.len() < header || &data[..FILE_MAGIC.len()] != FILE_MAGIC {
+            return Err(SecretError::Io("not a JukeBox secrets file".into()));
+        }
+        let salt: [u8; SALT_LEN] = data[FILE_MAGIC.len()..FILE_MAGIC.len() + SALT_LEN]
+            .try_into()
+            .unwrap();
+        let nonce = Nonce::from_slice(&data[FILE_MAGIC.len() + SALT_LEN..header]);
+
+        let key = Self::derive_key(passphrase, &salt)?;
+        let plain = ChaCha20Poly1305::new(&key)
+            .decrypt(nonce, &data[header..])
+            .map_err(|_| SecretError::WrongPassphrase)?;
+        self.secrets =
+            serde_json::from_slice(&plain).map_err(|e| SecretError::Io(e.to_string()))?;
+        self.key = Some((key, salt));
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod secret_service {
+    // The freedesktop Secret Service, as provided by GNOME Keyring, KWallet and KeePassXC
+
+    use std::collections::HashMap;
+
+    use futures::StreamExt;
+    use tokio::runtime::Runtime;
+    use zbus::{
+        zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value},
+        Connection, Proxy,
+    };
+
+    use super::{SecretBackend, SecretError, SecretStoreKind};
+
+    const DEST: &str = "org.freedesktop.secrets";
+    const SERVICE_PATH: &str = "/org/freedesktop/secrets";
+    const DEFAULT_COLLECTION: &str = "/org/freedesktop/secrets/aliases/default";
+    const APPLICATION: &str = "JukeBoxDesktop";
+
+    // (session, parameters, value, content type)
+    type Secret = (OwnedObjectPath, Vec<u8>, Vec<u8>, String);
+
+    fn err(e: zbus::Error) -> SecretError {
+        SecretError::Unavailable(e.to_string())
+    }
+
+    pub struct SecretServiceBackend {
+        rt: Runtime, // Only ever used from the secrets thread, outside of the main runtime
+        conn: Connection,
+        session: OwnedObjectPath,
+    }
+    impl SecretServiceBackend {
+        pub fn connect() -> Result<Self, SecretError> {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| SecretError::Unavailable(e.to_string()))?;
+
+            let (conn, session) = rt.block_on(async {
+                let conn = Connection::session().await.map_err(err)?;
+                let service = Self::proxy(&conn, SERVICE_PATH, "Service").await?;
+                // Secrets cross the session bus unencrypted. Only processes running as the user
+                // can reach that bus, and any of them could ask the service for the same items,
+                // so encrypting them on the way adds no protection.
+                let (_, session): (OwnedValue, OwnedObjectPath) = service
+                    .call("OpenSession", &("plain", Value::from("")))
+                    .await
+                    .map_err(err)?;
+                Ok::<_, SecretError>((conn, session))
+            })?;
+
+            Ok(Self { rt, conn, session })
+        }
+
+        async fn proxy<'a>(
+            conn: &Connection,
+            path: &'a str,
+            interface: &str,
+        ) -> Result<Proxy<'a>, SecretError> {
+            Proxy::new(
+                conn,
+                DEST,
+                path,
+                format!("org.freedesktop.Secret.{}", interface),
+            )
+            .await
+            .map_err(err)
+        }
+
+        // Unlocking can ask the user for their keyring password, this waits for them to answer
+        async fn unlock(
+            conn: &Connection,
+            objects: Vec<OwnedObjectPath>,
+        ) -> Result<(), SecretError> {
+            if objects.is_empty() {
+                return Ok(());
+            }
+
+            let service = Self::proxy(conn, SERVICE_PATH, "Service").await?;
+            let (_, prompt): (Vec<OwnedObjectPath>, OwnedObjectPath) =
+                service.call("Unlock", &(objects,)).await.map_err(err)?;
+            Self::prompt(conn, prompt).await
+        }
+
+        async fn prompt(conn: &Connection, prompt: OwnedObjectPath) -> Result<(), SecretError> {
+            if prompt.as_str() == "/" {
+                return Ok(());
+            }
+
+            let prompt = Self::proxy(conn, prompt.as_str(), "Prompt").await?;
+            let mut completed = prompt.receive_signal("Completed").await.map_err(err)?;
+            let _: () = prompt.call("Prompt", &("",)).await.map_err(err)?;
+
+            let Some(msg) = completed.next().await else {
+                return Err(SecretError::Locked);
+            };
+            let (dismissed, _): (bool, OwnedValue) = msg.body().deserialize().map_err(err)?;
+            if dismissed {
+                Err(SecretError::Locked)
+            } else {
+                Ok(())
+            }
+        }
+
+        async fn search(
+            conn: &Connection,
+            key: Option<&str>,
+        ) -> Result<Vec<OwnedObjectPath>, SecretError> {
+            let mut attributes = HashMap::from([("application", APPLICATION)]);
+            if let Some(key) = key {
+                attributes.insert("key", key);
+            }
+
+            let service = Self::proxy(conn, SERVICE_PATH, "Service").await?;
+            let (mut unlocked, locked): (Vec<OwnedObjectPath>, Vec<OwnedObjectPath>) = service
+                .call("SearchItems", &(attributes,))
+                .await
+                .map_err(err)?;
+            Self::unlock(conn, locked.clone()).await?;
+            unlocked.extend(locked);
+
+            Ok(unlocked)
+        }
+    }
+    impl SecretBackend for SecretServiceBackend {
+        fn kind(&self) -> SecretStoreKind {
+            SecretStoreKind::SecretService
+        }
+
+        fn load(&mut self) -> Result<HashMap<String, String>, SecretError> {
+            let conn = &self.conn;
+            let session = &self.session;
+            self.rt.block_on(async {
+                let items = Self::search(conn, None).await?;
+                let service = Self::proxy(conn, SERVICE_PATH, "Service").await?;
+                let secrets: HashMap<OwnedObjectPath, Secret> = service
+                    .call("GetSecrets", &(items, session))
+                    .await
+                    .map_err(err)?;
+
+                let mut res = HashMap::new();
+                for (path, (_, _, value, _)) in secrets {
+                    let item = Self::proxy(conn, path.as_str(), "Item").await?;
+                    let attributes: HashMap<String, String> =
+                        item.get_property("Attributes").await.map_err(err)?;
+                    let (Some(key), Ok(value)) = (attributes.get("key"), String::from_utf8(value))
+                    else {
+                        continue;
+                    };
+                    res.insert(key.clone(), value);
+                }
+
+                Ok(res)
+            })
+        }
+
+        fn set(&mut self, key: &str, value: &str) -> Result<(), SecretError> {
+            let conn = &self.conn;
+            let session = &self.session;
+            self.rt.block_on(async {
+                let collection = ObjectPath::try_from(DEFAULT_COLLECTION).unwrap();
+                Self::unlock(conn, vec![collection.into()]).await?;
+
+                let attributes = HashMap::from([("application", APPLICATION), ("key", key)]);
+                let properties = HashMap::from([
+                    (
+                        "org.freedesktop.Secret.Item.Label",
+                        Value::from(format!("JukeBox Desktop ({})", key)),
+                    ),
+                    (
+                        "org.freedesktop.Secret.Item.Attributes",
+                        Value::from(attributes),
+                    ),
+                ]);
+                let secret: Secret = (
+                    session.clone(),
+                    Vec::new(),
+                    value.as_bytes().to_vec(),
+                    "text/plain".into(),
+                );
+
+                let collection = Self::proxy(conn, DEFAULT_COLLECTION, "Collection").await?;
+                let (_, prompt): (OwnedObjectPath, OwnedObjectPath) = collection
+                    .call("CreateItem", &(properties, secret, true))
+                    .await
+                    .map_err(err)?;
+                Self::prompt(conn, prompt).await
+            })
+        }
+
+        fn delete(&mut self, key: &str) -> Result<(), SecretError> {
+            let conn = &self.conn;
+            self.rt.block_on(async {
+                for path in Self::search(conn, Some(key)).await? {
+                    let item = Self::proxy(conn, path.as_str(), "Item").await?;
+                    let prompt: OwnedObjectPath = item.call("Delete", &()).await.map_err(err)?;
+                    Self::prompt(conn, prompt).await?;
+                }
+                Ok(())
+            })
+        }
+    }
+}
+
+struct SecretStore {
+    backend: Box<dyn SecretBackend>,
+    stored: HashMap<String, String>, // What the backend holds, as of the last load or write
+    loaded: bool, // Writes only happen after a load, so a bad start can't wipe the store
+}
+impl SecretStore {
+    fn new() -> Self {
+        let backend: Box<dyn SecretBackend> = match std::env::var(SECRET_STORE_ENV).as_deref() {
+            Ok("memory") => Box::new(MemoryBackend::default()),
+            Ok("file") => Box::new(FileBackend::new()),
+            _ => Self::system_backend(),
+        };
+        log::info!("secret store: {:?}", backend.kind());
+        let _ = SECRETS_KIND.set(backend.kind());
+
+        Self {
+            backend,
+            stored: HashMap::new(),
+            loaded: false,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn system_backend() -> Box<dyn SecretBackend> {
+        match secret_service::SecretServiceBackend::connect() {
+            Ok(b) => Box::new(b),
+            Err(e) => {
+                log::warn!("secret service unavailable, using encrypted file: {:?}", e);
+                Box::new(FileBackend::new())
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn system_backend() -> Box<dyn SecretBackend> {
+        Box::new(FileBackend::new())
+    }
+
+    fn load(&mut self) -> Result<HashMap<String, String>, SecretError> {
+        let secrets = self.backend.load()?;
+        self.stored = secrets.clone();
+        self.loaded = true;
+        Ok(secrets)
+    }
+
+    // Brings the backend in line with `secrets`, touching only what changed
+    fn store(&mut self, secrets: BTreeMap<String, String>) -> Result<(), SecretError> {
+        if !self.loaded {
+            return Err(SecretError::Locked);
+        }
+
+        for (key, value) in &secrets {
+            if self.stored.get(key) != Some(value) {
+                self.backend.set(key, value)?;
+                self.stored.insert(key.clone(), value.clone());
+            }
+        }
+
+        let removed: Vec<_> = self
+            .stored
+            .keys()
+            .filter(|k| !secrets.contains_key(*k))
+            .cloned()
+            .collect();
+        for key in removed {
+            self.backend.delete(&key)?;
+            self.stored.remove(&key);
+        }
+
+        Ok(())
+    }
+}
+
+type SecretJob = Box<dyn FnOnce(&mut SecretStore) + Send>;
+
+static SECRETS_TX: OnceLock<Sender<SecretJob>> = OnceLock::new();
+static SECRETS_KIND: OnceLock<SecretStoreKind> = OnceLock::new();
+// Credentials are safely in the store, so the config file leaves them out
+static SECRETS_READY: AtomicBool = AtomicBool::new(false);
+
+// Stores can block (D-Bus calls, key derivation), so they get a thread of their own instead of
+// holding up whichever async task happens to save the config
+async fn with_store<R: Send + 'static>(
+    f: impl FnOnce(&mut SecretStore) -> R + Send + 'static,
+) -> Result<R, SecretError> {
+    let tx = SECRETS_TX.get_or_init(|| {
+        let (tx, rx) = channel::<SecretJob>();
+        std::thread::spawn(move || {
+            let mut store = SecretStore::new();
+            while let Ok(job) = rx.recv() {
+                job(&mut store);
+            }
+        });
+        tx
+    });
+
+    let stopped = || SecretError::Unavailable("the secret store thread stopped".into());
+    let (res_tx, res_rx) = oneshot::channel();
+    tx.send(Box::new(move |store| {
+        let _ = res_tx.send(f(store));
+    }))
+    .map_err(|_| stopped())?;
+    res_rx.await.map_err(|_| stopped())
+}
+
+// Loading and saving the config isn't async, and happens on the runtime's threads too. Waiting on
+// one of those hands its other tasks off first.
+fn wait<R>(f: impl Future<Output = R>) -> R {
+    match Handle::try_current() {
+        Ok(h) if h.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            block_in_place(|| h.block_on(f))
+        }
+        _ => futures::executor::block_on(f),
+    }
+}
+
+/// Loads everything saved in the store. None when there's no usable store yet.
+pub fn load() -> Option<HashMap<String, String>> {
+    match wait(with_store(|s| s.load())).and_then(|r| r) {
+        Ok(secrets) => {
+            SECRETS_READY.store(true, Ordering::Relaxed);
+            Some(secrets)
+        }
+        Err(e) => {
+            log::warn!("credentials will be kept in the config file: {:?}", e);
+            None
+        }
+    }
+}
+
+/// Opens the encrypted file with the user's passphrase, creating it on first use.
+pub fn unlock(passphrase: &str) -> Result<HashMap<String, String>, SecretError> {
+    let passphrase = passphrase.to_string();
+    let secrets = wait(with_store(move |s| {
+        s.backend.unlock(&passphrase)?;
+        s.load()
+    }))??;
+    SECRETS_READY.store(true, Ordering::Relaxed);
+    Ok(secrets)
+}
+
+/// Saves the credentials, and forgets any that aren't in `secrets` anymore.
+pub fn store(secrets: BTreeMap<String, String>) {
+    if !SECRETS_READY.load(Ordering::Relaxed) {
+        return;
+    }
+
+    if let Err(e) = wait(with_store(move |s| s.store(secrets))).and_then(|r| r) {
+        log::error!(
+            "failed to save credentials, keeping them in the config file: {:?}",
+            e
+        );
+        SECRETS_READY.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Which store is in use. None before the first load.
+pub fn kind() -> Option<SecretStoreKind> {
+    SECRETS_KIND.get().copied()
+}
+
+/// True once credentials are being kept in the store, instead of the config file.
+pub fn is_ready() -> bool {
+    SECRETS_READY.load(Ordering::Relaxed)
+}
+
+/// True when an encrypted file was made before, so a passphrase entered now has to match it.
+pub fn has_secrets_file() -> bool {
+    FileBackend::get_path().exists()
+}
+
+// For `skip_serializing_if` on credential fields in the config
+pub fn is_stored<T>(_: &T) -> bool {
+    is_ready()
+}
+
+// Tests saving a config share the one store, and each save drops what that config doesn't have
+#[cfg(test)]
+pub static TEST_STORE: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An encrypted file backend in a folder of its own, gone when the test ends
+    struct TempFile(FileBackend);
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let mut path = std::env::temp_dir();
+            path.push(format!("jukebox-secrets-{}-{}", std::process::id(), name));
+            std::fs::create_dir_all(&path).unwrap();
+            path.push("secrets.bin");
+            let _ = std::fs::remove_file(&path);
+            Self(Self::open(&path))
+        }
+
+        fn open(path: &std::path::Path) -> FileBackend {
+            FileBackend {
+                path: path.to_path_buf(),
+                key: None,
+                secrets: HashMap::new(),
+            }
+        }
+
+        fn reopen(&self) -> FileBackend {
+            Self::open(&self.0.path)
+        }
+    }
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(self.0.path.parent().unwrap());
+        }
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let mut file = TempFile::new("round-trip");
+        assert!(matches!(file.0.load(), Err(SecretError::Locked)));
+        assert!(matches!(
+            file.0.set("obs/Main", "x"),
+            Err(SecretError::Locked)
+        ));
+
+        file.0.unlock("correct horse").unwrap();
+        file.0.set("obs/Main", "hunter2").unwrap();
+        file.0.set("mqtt", "swordfish").unwrap();
+        file.0.delete("mqtt").unwrap();
+
+        let mut b = file.reopen();
+        b.unlock("correct horse").unwrap();
+        assert_eq!(
+            b.load().unwrap(),
+            HashMap::from([("obs/Main".to_string(), "hunter2".to_string())])
+        );
+
+        // Nothing readable ends up in the file
+        let data = std::fs::read(&b.path).unwrap();
+        assert!(data.starts_with(FILE_MAGIC));
+        assert!(!data.windows(7).any(|w| w == b"hunter2"));
+    }
+
+    #[test]
+    fn file_wrong_passphrase() {
+        let mut file = TempFile::new("wrong-passphrase");
+        file.0.unlock("correct horse").unwrap();
+        file.0.set("mqtt", "swordfish").unwrap();
+
+        let mut b = file.reopen();
+        assert!(matches!(
+            b.unlock("battery staple"),
+            Err(SecretError::WrongPassphrase)
+        ));
+        assert!(matches!(b.load(), Err(SecretError::Locked)));
+
+        // A wrong guess leaves the file as it was
+        b.unlock("correct horse").unwrap();
+        assert_eq!(b.load().unwrap()["mqtt"], "swordfish");
+    }
+
+    #[test]
+    fn file_corrupted() {
+        let mut file = TempFile::new("corrupted");
+        file.0.unlock("correct horse").unwrap();
+        file.0.set("mqtt", "swordfish").unwrap();
+        let data = std::fs::read(&file.0.path).unwrap();
+
+        // A flipped bit anywhere past the header fails the authentication tag
+        let mut flipped = data.clone();
+        *flipped.last_mut().unwrap() ^= 0x01;
+        std::fs::write(&file.0.path, &flipped).unwrap();
+        assert!(matches!(
+            file.reopen().unlock("correct horse"),
+            Err(SecretError::WrongPassphrase)
+        ));
+
+        let header = FILE_MAGIC.len() + SALT_LEN + NONCE_LEN;
+        for broken in [
+            &data[..header - 1],
+            &data[..3],
+            b"not a secrets file at all",
+        ] {
+            std::fs::write(&file.0.path, broken).unwrap();
+            assert!(matches!(
+                file.reopen().unlock("correct horse"),
+                Err(SecretError::Io(_))
+            ));
+        }
+    }
+}
@@ -3,7 +3,10 @@
 
 use crate::actions::types::ActionMap;
 use crate::config::{DeviceConfig, DeviceInfo, JukeBoxConfig};
+use crate::icon::IconFrame;
 use crate::input::InputKey;
+use crate::system::HOST_STATE;
+use crate::toast::{show_device_toast, ToastSource, TOAST_COLOR_WARNING};
 
 use std::cmp::min;
 use std::collections::{HashMap, HashSet};
@@ -14,6 +17,7 @@ use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, bail, Context, Result};
 use jukebox_util::{
+    icon::{icon_hash, icon_rle_encode},
     input::InputEvent,
     peripheral::{
         DeviceType, KeyInputs, KnobInputs, PedalInputs, IDENT_KEY_INPUT, IDENT_KNOB_INPUT,
@@ -21,11 +25,14 @@ use jukebox_util::{
     },
     protocol::{
         decode_packet_size, encode_packet_size, Command, RSP_ACK, RSP_DISCONNECTED,
-        RSP_INPUT_HEADER, RSP_LINK_DELIMITER, RSP_LINK_HEADER, RSP_UNKNOWN,
+        RSP_FRAME_TIMING_HEADER, RSP_INPUT_HEADER, RSP_LINK_DELIMITER, RSP_LINK_HEADER,
+        RSP_NOT_CACHED, RSP_NO_ROOM, RSP_UNKNOWN,
     },
     rgb::RgbProfile,
-    screen::{ProfileName, ScreenProfile, PROFILE_NAME_CHAR_LEN},
+    screen::{FrameTiming, ProfileName, ScreenProfile, ICON_DATA_SIZE, PROFILE_NAME_CHAR_LEN},
     stats::SystemStats,
+    time::TimeSync,
+    toast::{Toast, ToastIcon},
 };
 use serialport::SerialPort;
 use tokio::{
@@ -52,9 +59,10 @@ pub enum SerialCommand {
     // SetMouseInput(u8, MouseEvent),
     // SetGamepadInput(u8, [u8; 6]),
     SetRgbMode(RgbProfile),
-    SetScrIcon(u8, [u8; 32 * 32 * 2]),
+    SetScrIcon(u8, Vec<IconFrame>),
     SetScrMode(ScreenProfile),
     SetProfileName(String),
+    ShowToast(Toast),
     Update,
     Disconnect,
 }
@@ -316,15 +324,133 @@ async fn transmit_set_rgb_mode(f: &mut Serial, rgb_profile: RgbProfile) -> Resul
     send_expect(f, &cmd, &[RSP_ACK]).await
 }
 
-async fn transmit_set_scr_icon(
+async fn transmit_set_scr_icon(f: &mut Serial, slot: u8, icon_data: IconFrame) -> Result<()> {
+    let mut cmd = vec![Command::SetScrIcon.into(), slot];
+    cmd.extend_from_slice(&icon_data);
+
+    send_expect(f, &cmd, &[RSP_ACK]).await
+}
+
+// Some(true) if the device had the icon cached and is showing it now.
+// None if the device doesn't know the command, older firmware has no cache.
+async fn transmit_set_scr_icon_cached(f: &mut Serial, slot: u8, hash: u32) -> Result<Option<bool>> {
+    let mut cmd = vec![Command::SetScrIconCached.into(), slot];
+    cmd.extend_from_slice(&hash.to_le_bytes());
+
+    send_bytes(f, &cmd)
+        .await
+        .context("failed to send cached icon")?;
+    let resp = get_serial_string(f)
+        .await
+        .context("expected cached icon response")?;
+
+    match resp.get(0) {
+        Some(&RSP_ACK) => Ok(Some(true)),
+        Some(&RSP_NOT_CACHED) => Ok(Some(false)),
+        Some(&RSP_UNKNOWN) => Ok(None),
+        _ => {
+            send_negative_ack(f).await?;
+            bail!("failed to set cached icon (got {:?})", resp);
+        }
+    }
+}
+
+async fn transmit_set_scr_icon_rle(f: &mut Serial, slot: u8, icon_data: &[u8]) -> Result<()> {
+    let mut cmd = vec![Command::SetScrIconRle.into(), slot];
+    cmd.extend_from_slice(icon_data);
+
+    send_expect(f, &cmd, &[RSP_ACK]).await
+}
+
+// Tries the device's icon cache first, then a compressed upload, then the icon as is
+async fn transmit_still_icon(
     f: &mut Serial,
     slot: u8,
-    icon_data: [u8; 32 * 32 * 2],
+    icon_data: IconFrame,
+    icon_cache: &mut bool,
 ) -> Result<()> {
-    let mut cmd = vec![Command::SetScrIcon.into(), slot];
+    if *icon_cache {
+        match transmit_set_scr_icon_cached(f, slot, icon_hash(&icon_data)).await? {
+            Some(true) => return Ok(()),
+            Some(false) => {
+                let mut rle = [0u8; ICON_DATA_SIZE];
+                if let Some(n) = icon_rle_encode(&icon_data, &mut rle) {
+                    return transmit_set_scr_icon_rle(f, slot, &rle[..n]).await;
+                }
+            }
+            None => *icon_cache = false,
+        }
+    }
+
+    transmit_set_scr_icon(f, slot, icon_data).await
+}
+
+// Some(true) if the device took the frame. Some(false) if it has no room left to animate the
+// icon, it shows the first frame instead. None if the device doesn't know the command, older
+// firmware can't animate icons.
+async fn transmit_set_scr_icon_frame(
+    f: &mut Serial,
+    slot: u8,
+    index: u8,
+    count: u8,
+    icon_data: IconFrame,
+) -> Result<Option<bool>> {
+    let mut cmd = vec![Command::SetScrIconFrame.into(), slot, index, count];
     cmd.extend_from_slice(&icon_data);
 
-    send_expect(f, &cmd, &[RSP_ACK]).await
+    send_bytes(f, &cmd)
+        .await
+        .context("failed to send icon frame")?;
+    let resp = get_serial_string(f)
+        .await
+        .context("expected icon frame response")?;
+
+    match resp.get(0) {
+        Some(&RSP_ACK) => Ok(Some(true)),
+        Some(&RSP_NO_ROOM) => Ok(Some(false)),
+        Some(&RSP_UNKNOWN) => Ok(None),
+        _ => {
+            send_negative_ack(f).await?;
+            bail!("failed to set icon frame (got {:?})", resp);
+        }
+    }
+}
+
+// Sends every frame of an animated icon, or just the first one when the device can't animate it
+async fn transmit_animated_icon(
+    f: &mut Serial,
+    device_uid: &str,
+    slot: u8,
+    icon_data: Vec<IconFrame>,
+    icon_cache: &mut bool,
+) -> Result<()> {
+    let count = icon_data.len() as u8;
+    for (i, frame) in icon_data.iter().enumerate() {
+        match transmit_set_scr_icon_frame(f, slot, i as u8, count, *frame).await? {
+            Some(true) => {}
+            Some(false) => {
+                log::warn!(
+                    "{} has no room to animate the icon on key {}",
+                    device_uid,
+                    slot
+                );
+                show_device_toast(
+                    ToastSource::ActionError,
+                    device_uid,
+                    Toast::new(
+                        &t!("toast.icon_no_room.title"),
+                        &t!("toast.icon_no_room.body", key = slot + 1),
+                        ToastIcon::Key(slot),
+                        0,
+                        TOAST_COLOR_WARNING,
+                    ),
+                );
+                return Ok(());
+            }
+            None => return transmit_still_icon(f, slot, icon_data[0], icon_cache).await,
+        }
+    }
+    Ok(())
 }
 
 async fn transmit_set_screen_mode(f: &mut Serial, screen_profile: ScreenProfile) -> Result<()> {
@@ -351,6 +477,68 @@ async fn transmit_set_system_stats(f: &mut Serial, system_stats: SystemStats) ->
     send_expect(f, &cmd, &[RSP_ACK]).await
 }
 
+async fn transmit_set_time(f: &mut Serial) -> Result<()> {
+    let now = chrono::Local::now();
+    let time = TimeSync {
+        epoch_secs: now.timestamp().max(0) as u64,
+        utc_offset_secs: now.offset().local_minus_utc(),
+    };
+
+    let mut cmd = vec![Command::SetTime.into()];
+    cmd.extend_from_slice(&time.encode());
+
+    send_bytes(f, &cmd).await.context("failed to send time")?;
+    let resp = get_serial_string(f)
+        .await
+        .context("expected set time response")?;
+
+    // Older firmware keeps no clock, so it has nothing to set
+    match resp.get(0) {
+        Some(&RSP_ACK) | Some(&RSP_UNKNOWN) => Ok(()),
+        _ => {
+            send_negative_ack(f).await?;
+            bail!("failed to set time (got {:?})", resp);
+        }
+    }
+}
+
+// None if the firmware is too old to report frame timings
+async fn transmit_get_frame_timing(f: &mut Serial) -> Result<Option<FrameTiming>> {
+    send_cmd(f, Command::GetFrameTiming.into())
+        .await
+        .context("failed to send get frame timing")?;
+    let resp = get_serial_string(f)
+        .await
+        .context("expected frame timing")?;
+
+    match resp.get(0) {
+        Some(&RSP_FRAME_TIMING_HEADER) => Ok(Some(FrameTiming::decode(&resp[1..]))),
+        Some(&RSP_UNKNOWN) => Ok(None),
+        _ => {
+            send_negative_ack(f).await?;
+            bail!("failed to get frame timing (got {:?})", resp);
+        }
+    }
+}
+
+async fn transmit_set_host_state(f: &mut Serial, state: u8) -> Result<()> {
+    send_bytes(f, &[Command::SetHostState.into(), state])
+        .await
+        .context("failed to send host state")?;
+    let resp = get_serial_string(f)
+        .await
+        .context("expected host state response")?;
+
+    // Older firmware doesn't know about it, which is fine
+    match resp.get(0) {
+        Some(&RSP_ACK) | Some(&RSP_UNKNOWN) => Ok(()),
+        _ => {
+            send_negative_ack(f).await?;
+            bail!("failed to set host state (got {:?})", resp);
+        }
+    }
+}
+
 async fn transmit_identify_signal(f: &mut Serial) -> Result<()> {
     send_expect(f, &[Command::Identify.into()], &[RSP_ACK]).await
 }
@@ -395,6 +583,25 @@ pub fn serial_get_device(connected_uids: &HashSet<String>) -> Result<Serial> {
         .context("failed to open serial port")?)
 }
 
+async fn transmit_show_toast(f: &mut Serial, toast: Toast) -> Result<()> {
+    let mut cmd = vec![Command::ShowToast.into()];
+    cmd.extend_from_slice(&toast.encode());
+
+    send_bytes(f, &cmd).await.context("failed to send toast")?;
+    let resp = get_serial_string(f)
+        .await
+        .context("expected toast response")?;
+
+    // Older firmware can't show toasts, they just get dropped
+    match resp.get(0) {
+        Some(&RSP_ACK) | Some(&RSP_UNKNOWN) => Ok(()),
+        _ => {
+            send_negative_ack(f).await?;
+            bail!("failed to show toast (got {:?})", resp);
+        }
+    }
+}
+
 pub async fn serial_loop(
     f: &mut Serial,
     sg_tx: UnboundedSender<SerialEvent>,
@@ -410,6 +617,16 @@ pub async fn serial_loop(
         .checked_add(Duration::from_millis(50))
         .unwrap();
     let mut sys_stats_tick = Instant::now().checked_add(Duration::from_secs(1)).unwrap();
+    // Sync right away, then every so often to catch clock drift and timezone changes
+    let mut time_sync_tick = Instant::now();
+    // Screen render timings are only asked for when someone is reading the debug log
+    let mut frame_timing_tick = Instant::now();
+    let mut frame_timing = log::log_enabled!(log::Level::Debug);
+    let mut host_state = None;
+
+    // What each key is showing, so unchanged icons aren't sent again
+    let mut icon_hashes: HashMap<u8, u32> = HashMap::new();
+    let mut icon_cache = true;
 
     'forv: loop {
         let now = Instant::now();
@@ -442,6 +659,25 @@ pub async fn serial_loop(
             transmit_set_system_stats(f, stats).await?;
         }
 
+        if device_type == DeviceType::KeyPad && now >= time_sync_tick {
+            time_sync_tick = Instant::now().checked_add(Duration::from_secs(60)).unwrap();
+            transmit_set_time(f).await?;
+        }
+
+        let state = HOST_STATE.load(std::sync::atomic::Ordering::Relaxed);
+        if device_type == DeviceType::KeyPad && host_state != Some(state) {
+            host_state = Some(state);
+            transmit_set_host_state(f, state).await?;
+        }
+
+        if device_type == DeviceType::KeyPad && frame_timing && now >= frame_timing_tick {
+            frame_timing_tick = Instant::now().checked_add(Duration::from_secs(10)).unwrap();
+            match transmit_get_frame_timing(f).await? {
+                Some(t) => log::debug!("{} frame timing: {:?}", device_uid, t),
+                None => frame_timing = false,
+            }
+        }
+
         while let Ok(cmd) = s_cmd_rx.try_recv() {
             match cmd {
                 SerialCommand::Identify => {
@@ -457,7 +693,24 @@ pub async fn serial_loop(
                 }
                 SerialCommand::SetScrIcon(slot, icon_data) => {
                     if device_type == DeviceType::KeyPad {
-                        transmit_set_scr_icon(f, slot, icon_data).await?;
+                        let hash = icon_hash(&icon_data.concat());
+                        if icon_hashes.get(&slot) == Some(&hash) {
+                            continue;
+                        }
+
+                        if icon_data.len() == 1 {
+                            transmit_still_icon(f, slot, icon_data[0], &mut icon_cache).await?;
+                        } else {
+                            transmit_animated_icon(
+                                f,
+                                &device_uid,
+                                slot,
+                                icon_data,
+                                &mut icon_cache,
+                            )
+                            .await?;
+                        }
+                        icon_hashes.insert(slot, hash);
                     }
                 }
                 SerialCommand::SetScrMode(screen_profile) => {
@@ -470,6 +723,11 @@ pub async fn serial_loop(
                         transmit_set_profile_name(f, profile_name).await?;
                     }
                 }
+                SerialCommand::ShowToast(toast) => {
+                    if device_type == DeviceType::KeyPad {
+                        transmit_show_toast(f, toast).await?;
+                    }
+                }
                 SerialCommand::Update => {
                     transmit_update_signal(f).await?;
                     sr_tx
@@ -1,350 +1,755 @@
 // System stats monitoring for displaying on certain jukebox devices
 
-use std::{collections::VecDeque, sync::Arc, thread::sleep};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, OnceLock,
+    },
+    thread::sleep,
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
-use jukebox_util::{smallstr::SmallStr, stats::SystemStats};
-use sysinfo::{Components, MemoryRefreshKind, System, MINIMUM_CPU_UPDATE_INTERVAL};
+use jukebox_util::{
+    idle::{HOST_IDLE, HOST_LOCKED},
+    smallstr::SmallStr,
+    stats::{Stat, SystemStats, SYSTEM_STATS_MAX},
+};
+use sysinfo::{
+    Components, Disks, MemoryRefreshKind, Networks, System, MINIMUM_CPU_UPDATE_INTERVAL,
+};
 use tokio::sync::Mutex;
 
 use nvml_wrapper::{enum_wrappers::device::TemperatureSensor, Nvml};
 #[cfg(feature = "amd_gpu")]
 use rocm_smi_lib::{RocmSmi, RsmiTemperatureMetric, RsmiTemperatureType};
-
-#[allow(dead_code)]
-#[derive(Debug, Clone, Copy)]
-enum GpuPreference {
-    Nvidia,
-    Amd,
-    None,
+#[cfg(target_os = "windows")]
+use windows::{
+    core::PWSTR,
+    Win32::{
+        System::{
+            RemoteDesktop::{
+                WTSFreeMemory, WTSQuerySessionInformationW, WTSSessionInfoEx, WTSINFOEXW,
+                WTS_CURRENT_SERVER_HANDLE, WTS_CURRENT_SESSION, WTS_SESSIONSTATE_LOCK,
+            },
+            SystemInformation::GetTickCount,
+        },
+        UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO},
+    },
+};
+#[cfg(target_os = "linux")]
+use zbus::{proxy::CacheProperties, zvariant::OwnedObjectPath, Connection, Proxy};
+
+use crate::config::JukeBoxConfig;
+
+// The stats shown before the user picks their own, matching the old fixed stats screen
+pub const DEFAULT_SYSTEM_STATS: [&str; 6] = [
+    "cpu.usage",
+    "cpu.temperature",
+    "memory.used",
+    "gpu.0.usage",
+    "gpu.0.temperature",
+    "gpu.0.vram",
+];
+
+// Every sensor found on the last poll, as (key, name), so the settings page can offer them
+pub static SYSTEM_SENSORS: OnceLock<Mutex<Vec<(String, String)>>> = OnceLock::new();
+
+// Whether the session is locked or idle, as HOST_* flags, for devices to wind down with
+pub static HOST_STATE: AtomicU8 = AtomicU8::new(0);
+const HOST_STATE_POLL: Duration = Duration::from_secs(5);
+#[cfg(target_os = "windows")]
+const HOST_IDLE_SECS: u32 = 5 * 60;
+
+pub fn get_system_sensors() -> Vec<(String, String)> {
+    SYSTEM_SENSORS
+        .get_or_init(|| Mutex::new(Vec::new()))
+        .blocking_lock()
+        .clone()
 }
 
-#[derive(Debug, Clone, Copy)]
-struct StatReport {
-    pub cpu_usage: f32,
-    pub cpu_temperature: f32,
-    pub memory_used: u64,
-    pub memory_total: u64,
-
-    pub gpu_usage: f32,
-    pub gpu_temperature: f32,
-    pub vram_used: u64,
-    pub vram_total: u64,
+// Readable names for the selected sensor keys, falling back to the key for sensors not found
+pub fn get_system_stat_names(keys: &[String]) -> Vec<String> {
+    let sensors = get_system_sensors();
+    keys.iter()
+        .map(|k| {
+            sensors
+                .iter()
+                .find(|(key, _)| key == k)
+                .map(|(_, name)| name.clone())
+                .unwrap_or(k.clone())
+        })
+        .collect()
 }
-impl StatReport {
-    pub fn avg(reports: VecDeque<Self>) -> Self {
-        let count = reports.len();
-        let mut cpu_usage = 0.0;
-        let mut cpu_temperature = 0.0;
-        let mut memory_used = 0;
-        let mut memory_total = 0;
-        let mut gpu_usage = 0.0;
-        let mut gpu_temperature = 0.0;
-        let mut vram_used = 0;
-        let mut vram_total = 0;
-
-        for r in reports {
-            cpu_usage += r.cpu_usage;
-            cpu_temperature += r.cpu_temperature;
-            memory_used += r.memory_used;
-            memory_total = r.memory_total;
-            gpu_usage += r.gpu_usage;
-            gpu_temperature += r.gpu_temperature;
-            vram_used += r.vram_used;
-            vram_total = r.vram_total;
-        }
 
-        StatReport {
-            cpu_usage: cpu_usage / (count as f32),
-            cpu_temperature: cpu_temperature / (count as f32),
-            memory_used: ((memory_used as f64) / (count as f64)) as u64,
-            memory_total: memory_total,
-            gpu_usage: gpu_usage / (count as f32),
-            gpu_temperature: gpu_temperature / (count as f32),
-            vram_used: ((vram_used as f64) / (count as f64)) as u64,
-            vram_total: vram_total,
+const GIGABYTE: f32 = 1024.0 * 1024.0 * 1024.0;
+const MEGABYTE: f32 = 1024.0 * 1024.0;
+
+#[derive(Debug, Clone)]
+struct Sensor {
+    pub key: String,
+    pub name: String,  // shown in the app
+    pub label: String, // shown on the device
+    pub value: f32,
+    pub unit: &'static str,
+    pub min: f32,
+    pub max: f32,
+}
+impl Sensor {
+    fn new(key: String, name: String, label: String, value: f32, unit: &'static str) -> Self {
+        Self {
+            key,
+            name,
+            label,
+            value,
+            unit,
+            min: 0.0,
+            max: 100.0,
         }
     }
 
-    fn format_memory(memory_used: u64, memory_total: u64) -> (String, String, String) {
-        // TODO: support TB memory
-        let memory_used = format!(
-            "{: >5.1}",
-            (memory_used as f64) / (1024.0 * 1024.0 * 1024.0)
-        );
-        let memory_total = format!(
-            "{: >5.1}",
-            (memory_total as f64) / (1024.0 * 1024.0 * 1024.0)
-        );
-        (memory_used, memory_total, "GB".into())
+    // a max of zero means there's no fixed range, so it scales to the highest value seen
+    fn with_max(mut self, max: f32) -> Self {
+        self.max = max;
+        self
     }
 
-    pub fn get_cpu_name(cpu_vendor: String, cpu_brand: String) -> String {
-        log::info!("cpu info: {} {}", cpu_vendor, cpu_brand);
-        let mut cpu_name = match cpu_vendor.as_str() {
-            "GenuineIntel" => {
-                // TODO
-                cpu_brand
-                    .replace("Intel(R)", "Intel")
-                    .replace("Core(TM) ", "")
-                    .replace("Processor ", "")
-                    .replace("1st Gen ", "")
-                    .replace("2nd Gen ", "")
-                    .replace("3rd Gen ", "")
-                    .replace("4th Gen ", "")
-                    .replace("5th Gen ", "")
-                    .replace("6th Gen ", "")
-                    .replace("7th Gen ", "")
-                    .replace("8th Gen ", "")
-                    .replace("9th Gen ", "")
-                    .replace("10th Gen ", "")
-                    .replace("11th Gen ", "")
-                    .replace("12th Gen ", "")
-                    .trim()
-                    .to_string()
-            }
-            "AuthenticAMD" => {
-                // TODO
-                cpu_brand
-                    .replace("Ryzen 3", "R3")
-                    .replace("Ryzen 5", "R5")
-                    .replace("Ryzen 7", "R7")
-                    .replace("Ryzen 9", "R9")
-                    .replace("1-Core ", "")
-                    .replace("2-Core ", "")
-                    .replace("4-Core ", "")
-                    .replace("6-Core ", "")
-                    .replace("8-Core ", "")
-                    .replace("12-Core ", "")
-                    .replace("16-Core ", "")
-                    .replace("24-Core ", "")
-                    .replace("32-Core ", "")
-                    .replace("Processor", "")
-                    .trim()
-                    .to_string()
-            }
-            _ => cpu_brand,
-        };
-
-        cpu_name.truncate(20);
+    fn to_stat(&self, id: u8, value: f32, max: f32) -> Stat {
+        Stat {
+            id,
+            label: SmallStr::from_str(truncate_str(&self.label, 20)),
+            value,
+            unit: SmallStr::from_str(self.unit),
+            min: self.min,
+            max,
+        }
+    }
+}
 
-        cpu_name
+fn truncate_str(s: &str, max: usize) -> &str {
+    let mut end = 0;
+    for (i, c) in s.char_indices() {
+        if i + c.len_utf8() > max {
+            break;
+        }
+        end = i + c.len_utf8();
     }
+    &s[..end]
+}
 
-    pub fn to_system_stats(self, cpu_name: String, gpu_info: String) -> SystemStats {
-        let cpu_usage = format!("{: >5.1}", self.cpu_usage);
-        let (memory_used, memory_total, memory_unit) =
-            Self::format_memory(self.memory_used, self.memory_total);
+fn get_cpu_name(cpu_vendor: String, cpu_brand: String) -> String {
+    log::info!("cpu info: {} {}", cpu_vendor, cpu_brand);
+    let mut cpu_name = match cpu_vendor.as_str() {
+        "GenuineIntel" => {
+            // TODO
+            cpu_brand
+                .replace("Intel(R)", "Intel")
+                .replace("Core(TM) ", "")
+                .replace("Processor ", "")
+                .replace("1st Gen ", "")
+                .replace("2nd Gen ", "")
+                .replace("3rd Gen ", "")
+                .replace("4th Gen ", "")
+                .replace("5th Gen ", "")
+                .replace("6th Gen ", "")
+                .replace("7th Gen ", "")
+                .replace("8th Gen ", "")
+                .replace("9th Gen ", "")
+                .replace("10th Gen ", "")
+                .replace("11th Gen ", "")
+                .replace("12th Gen ", "")
+                .trim()
+                .to_string()
+        }
+        "AuthenticAMD" => {
+            // TODO
+            cpu_brand
+                .replace("Ryzen 3", "R3")
+                .replace("Ryzen 5", "R5")
+                .replace("Ryzen 7", "R7")
+                .replace("Ryzen 9", "R9")
+                .replace("1-Core ", "")
+                .replace("2-Core ", "")
+                .replace("4-Core ", "")
+                .replace("6-Core ", "")
+                .replace("8-Core ", "")
+                .replace("12-Core ", "")
+                .replace("16-Core ", "")
+                .replace("24-Core ", "")
+                .replace("32-Core ", "")
+                .replace("Processor", "")
+                .trim()
+                .to_string()
+        }
+        _ => cpu_brand,
+    };
 
-        // sysinfo crate does not provide temperature on windows machines currently
-        // TODO: when it eventually does, we can remove this
-        let cpu_temperature = if cfg!(target_os = "windows") {
-            String::from("  N/A")
-        } else {
-            format!("{: >5.1}", self.cpu_temperature)
-        };
+    cpu_name.truncate(20);
 
-        let mut gpu_name = gpu_info.replace("GeForce", "").trim().to_string();
-        gpu_name.truncate(20);
-
-        let gpu_usage = format!("{: >5.1}", self.gpu_usage);
-        let gpu_temperature = format!("{: >5.1}", self.gpu_temperature);
-        let (vram_used, vram_total, vram_unit) =
-            Self::format_memory(self.vram_used, self.vram_total);
-
-        SystemStats {
-            cpu_name: SmallStr::from_str(&cpu_name),
-            cpu_usage: SmallStr::from_str(&cpu_usage),
-            cpu_temperature: SmallStr::from_str(&cpu_temperature),
-            memory_used: SmallStr::from_str(&memory_used),
-            memory_total: SmallStr::from_str(&memory_total),
-            memory_unit: SmallStr::from_str(&memory_unit),
-            gpu_name: SmallStr::from_str(&gpu_name),
-            gpu_usage: SmallStr::from_str(&gpu_usage),
-            gpu_temperature: SmallStr::from_str(&gpu_temperature),
-            vram_used: SmallStr::from_str(&vram_used),
-            vram_total: SmallStr::from_str(&vram_total),
-            vram_unit: SmallStr::from_str(&vram_unit),
-        }
-    }
+    cpu_name
 }
 
-pub fn system_task(system_stats: Arc<Mutex<SystemStats>>) -> Result<()> {
-    let mut sys = System::new();
-    let mut cmps = Components::new_with_refreshed_list();
-    log::info!("components:");
-    for c in &cmps {
-        log::info!("\t{c:?}");
-    }
+fn is_cpu_temperature_label(label: &str) -> bool {
+    // k10temp for AMD, coretemp for Intel
+    label == "Tctl" || label.ends_with(" Tctl") || label.ends_with("Package id 0")
+}
 
-    // heuristic to figure out which gpu stats to get
-    // we can reasonably assume a user won't have both (probably) and the same count (hopefully)
-    // if they do, then they probably don't have any discrete gpu installed (for sanity's sake)
-    // TODO: enable amd gpu support when cross compiling the rocm lib is possible
+struct SensorPoller {
+    sys: System,
+    cmps: Components,
+    disks: Disks,
+    networks: Networks,
+    nvml: Option<Nvml>,
     #[cfg(feature = "amd_gpu")]
-    let (gpu_preference, nvml, mut rocm) = {
-        let nvml = Nvml::init();
-        let mut rocm = RocmSmi::init();
-        let gpu_preference = match (&nvml, rocm.as_mut()) {
-            // if we find both nvidia and amd support, we default to nvidia because its more popular.
-            // this may need to change in the future.
-            (Ok(_), Ok(_)) => GpuPreference::Nvidia,
-            (Ok(_), Err(_)) => GpuPreference::Nvidia,
-            (Err(_), Ok(_)) => GpuPreference::Amd,
-            (Err(_), Err(_)) => GpuPreference::None,
-        };
-        log::info!("gpu preference: {:?}", gpu_preference);
+    rocm: Option<RocmSmi>,
 
-        (gpu_preference, nvml, rocm)
-    };
-    #[cfg(not(feature = "amd_gpu"))]
-    let (gpu_preference, nvml) = {
-        let nvml = Nvml::init();
-        let gpu_preference = match &nvml {
-            Ok(_) => GpuPreference::Nvidia,
-            Err(_) => GpuPreference::None,
-        };
-        log::info!("gpu preference: {:?}", gpu_preference);
+    cpu_name: Option<String>,
+    last_poll: Instant,
+}
+impl SensorPoller {
+    fn new() -> Self {
+        let cmps = Components::new_with_refreshed_list();
+        log::info!("components:");
+        for c in &cmps {
+            log::info!("\t{c:?}");
+        }
 
-        (gpu_preference, nvml)
-    };
+        // TODO: enable amd gpu support when cross compiling the rocm lib is possible
+        let nvml = Nvml::init().ok();
+        log::info!("nvidia gpu support: {}", nvml.is_some());
+        #[cfg(feature = "amd_gpu")]
+        let rocm = RocmSmi::init().ok();
+        #[cfg(feature = "amd_gpu")]
+        log::info!("amd gpu support: {}", rocm.is_some());
+
+        Self {
+            sys: System::new(),
+            cmps,
+            disks: Disks::new_with_refreshed_list(),
+            networks: Networks::new_with_refreshed_list(),
+            nvml,
+            #[cfg(feature = "amd_gpu")]
+            rocm,
+
+            cpu_name: None,
+            last_poll: Instant::now(),
+        }
+    }
 
-    let mut cpu_info: Option<String> = None;
-    let mut stat_reports: VecDeque<StatReport> = VecDeque::new();
+    fn poll(&mut self) -> Vec<Sensor> {
+        let mut sensors = Vec::new();
+
+        let now = Instant::now();
+        let elapsed = (now - self.last_poll).as_secs_f32().max(0.001);
+        self.last_poll = now;
+
+        self.poll_cpu(&mut sensors);
+        self.poll_memory(&mut sensors);
+        self.poll_components(&mut sensors);
+        self.poll_gpus(&mut sensors);
+        self.poll_disks(&mut sensors, elapsed);
+        self.poll_networks(&mut sensors, elapsed);
+        #[cfg(target_os = "linux")]
+        {
+            poll_linux_fans(&mut sensors);
+            poll_linux_batteries(&mut sensors);
+        }
 
-    loop {
-        // Get CPU info
-        sys.refresh_cpu_all();
+        sensors
+    }
+
+    fn poll_cpu(&mut self, sensors: &mut Vec<Sensor>) {
+        self.sys.refresh_cpu_all();
         let mut cpu_usage = 0.0;
-        // let mut cpu_temperature = 0;
         let mut cpu_count = 0;
 
-        for cpu in sys.cpus() {
+        for (i, cpu) in self.sys.cpus().iter().enumerate() {
             // I'm not aware of any generally-available system that has hot swappable CPUs.
             // But if it exists and can run JukeBox Desktop, I'd love to meet it.
-            if cpu_info.is_none() {
-                cpu_info = Some(StatReport::get_cpu_name(
-                    cpu.vendor_id().into(),
-                    cpu.brand().into(),
-                ));
-                log::info!("formatted cpu name: {:?}", cpu_info);
+            if self.cpu_name.is_none() {
+                self.cpu_name = Some(get_cpu_name(cpu.vendor_id().into(), cpu.brand().into()));
+                log::info!("formatted cpu name: {:?}", self.cpu_name);
             }
 
-            cpu_usage += cpu.cpu_usage();
+            sensors.push(Sensor::new(
+                format!("cpu.core.{}", i),
+                format!("CPU Core {} Usage", i),
+                format!("Core {}", i),
+                cpu.cpu_usage(),
+                "%",
+            ));
 
+            cpu_usage += cpu.cpu_usage();
             cpu_count += 1;
         }
 
-        let cpu_usage = cpu_usage / (cpu_count as f32);
+        if cpu_count > 0 {
+            let cpu_name = self.cpu_name.clone().unwrap_or_default();
+            sensors.push(Sensor::new(
+                "cpu.usage".into(),
+                format!("CPU Usage ({})", cpu_name),
+                cpu_name,
+                cpu_usage / (cpu_count as f32),
+                "%",
+            ));
+        }
+    }
 
-        let mut cpu_temperature = 0.0;
-        cmps.refresh(false);
-        for c in &cmps {
-            // TODO: find more cpu temp labels
-            match c.label() {
-                "Tctl" | "k10temp Tctl" => {
-                    cpu_temperature = c.temperature().unwrap();
-                }
-                _ => (),
+    fn poll_memory(&mut self, sensors: &mut Vec<Sensor>) {
+        self.sys
+            .refresh_memory_specifics(MemoryRefreshKind::nothing().with_ram().with_swap());
+
+        let memory_total = self.sys.total_memory() as f32 / GIGABYTE;
+        sensors.push(
+            Sensor::new(
+                "memory.used".into(),
+                "Memory Used".into(),
+                "RAM".into(),
+                self.sys.used_memory() as f32 / GIGABYTE,
+                "GB",
+            )
+            .with_max(memory_total),
+        );
+
+        let swap_total = self.sys.total_swap() as f32 / GIGABYTE;
+        if swap_total > 0.0 {
+            sensors.push(
+                Sensor::new(
+                    "swap.used".into(),
+                    "Swap Used".into(),
+                    "Swap".into(),
+                    self.sys.used_swap() as f32 / GIGABYTE,
+                    "GB",
+                )
+                .with_max(swap_total),
+            );
+        }
+    }
+
+    fn poll_components(&mut self, sensors: &mut Vec<Sensor>) {
+        // sysinfo crate does not provide temperature on windows machines currently
+        self.cmps.refresh(false);
+        let mut cpu_temperature = None;
+
+        for c in &self.cmps {
+            let Some(temperature) = c.temperature() else {
+                continue;
+            };
+            let max = c.critical().or(c.max()).unwrap_or(100.0).max(1.0);
+
+            if cpu_temperature.is_none() && is_cpu_temperature_label(c.label()) {
+                cpu_temperature = Some((temperature, max));
             }
+
+            sensors.push(
+                Sensor::new(
+                    format!("component.{}", c.label()),
+                    format!("Temperature ({})", c.label()),
+                    c.label().into(),
+                    temperature,
+                    "°C",
+                )
+                .with_max(max),
+            );
         }
 
-        // Get Memory Info
-        sys.refresh_memory_specifics(MemoryRefreshKind::nothing().with_ram());
-        let memory_used = sys.used_memory();
-        let memory_total = sys.total_memory();
+        if let Some((temperature, max)) = cpu_temperature {
+            sensors.push(
+                Sensor::new(
+                    "cpu.temperature".into(),
+                    "CPU Temperature".into(),
+                    "CPU Temp".into(),
+                    temperature,
+                    "°C",
+                )
+                .with_max(max),
+            );
+        }
+    }
 
-        // Get GPU Info
-        #[allow(unused_mut)]
-        let (mut gpu_info, mut gpu_usage, mut gpu_temperature, mut vram_used, mut vram_total) =
-            (String::from("Unknown GPU"), 0.0f32, 0.0f32, 0u64, 0u64);
+    fn push_gpu(
+        sensors: &mut Vec<Sensor>,
+        i: usize,
+        name: String,
+        usage: Option<f32>,
+        temperature: Option<f32>,
+        vram: Option<(u64, u64)>,
+    ) {
+        let name = name.replace("GeForce", "").trim().to_string();
+
+        if let Some(usage) = usage {
+            sensors.push(Sensor::new(
+                format!("gpu.{}.usage", i),
+                format!("GPU {} Usage ({})", i, name),
+                name.clone(),
+                usage,
+                "%",
+            ));
+        }
+        if let Some(temperature) = temperature {
+            sensors.push(Sensor::new(
+                format!("gpu.{}.temperature", i),
+                format!("GPU {} Temperature ({})", i, name),
+                format!("GPU{} Temp", i),
+                temperature,
+                "°C",
+            ));
+        }
+        if let Some((used, total)) = vram {
+            sensors.push(
+                Sensor::new(
+                    format!("gpu.{}.vram", i),
+                    format!("GPU {} VRAM Used ({})", i, name),
+                    "VRAM".into(),
+                    used as f32 / GIGABYTE,
+                    "GB",
+                )
+                .with_max(total as f32 / GIGABYTE),
+            );
+        }
+    }
 
-        #[cfg(feature = "amd_gpu")]
-        match gpu_preference {
-            GpuPreference::Nvidia => {
-                if let Ok(n) = &nvml {
-                    if let Ok(d) = n.device_by_index(0) {
-                        if let Ok(name) = d.name() {
-                            gpu_info = name;
-                        }
-                        if let Ok(rates) = d.utilization_rates() {
-                            gpu_usage = rates.gpu as f32;
-                        }
-                        if let Ok(temp) = d.temperature(TemperatureSensor::Gpu) {
-                            gpu_temperature = temp as f32;
-                        }
-                        if let Ok(memory) = d.memory_info() {
-                            vram_used = memory.used;
-                            vram_total = memory.total;
-                        }
-                    }
+    fn poll_gpus(&mut self, sensors: &mut Vec<Sensor>) {
+        // GPUs are numbered across vendors, nvidia first
+        let mut gpu_index = 0;
+
+        if let Some(n) = &self.nvml {
+            for i in 0..n.device_count().unwrap_or(0) {
+                if let Ok(d) = n.device_by_index(i) {
+                    Self::push_gpu(
+                        sensors,
+                        gpu_index,
+                        d.name().unwrap_or("Unknown GPU".into()),
+                        d.utilization_rates().ok().map(|r| r.gpu as f32),
+                        d.temperature(TemperatureSensor::Gpu).ok().map(|t| t as f32),
+                        d.memory_info().ok().map(|m| (m.used, m.total)),
+                    );
+                    gpu_index += 1;
                 }
             }
-            GpuPreference::Amd => {
-                if let Ok(r) = &mut rocm {
-                    if let Ok(name) = r.get_device_identifiers(0) {
-                        gpu_info = name.name.unwrap();
-                    }
-                    if let Ok(busy) = r.get_device_busy_percent(0) {
-                        gpu_usage = busy as f32;
-                    }
-                    if let Ok(temp) = r.get_device_temperature_metric(
-                        0,
+        }
+
+        #[cfg(feature = "amd_gpu")]
+        if let Some(r) = &mut self.rocm {
+            for i in 0..r.get_device_count() {
+                let name = r
+                    .get_device_identifiers(i)
+                    .ok()
+                    .and_then(|d| d.name)
+                    .unwrap_or("Unknown GPU".into());
+                let usage = r.get_device_busy_percent(i).ok().map(|b| b as f32);
+                let temperature = r
+                    .get_device_temperature_metric(
+                        i,
                         RsmiTemperatureType::Junction,
                         RsmiTemperatureMetric::Current,
-                    ) {
-                        gpu_temperature = temp as f32;
-                    }
-                    if let Ok(memory) = r.get_device_memory_data(0) {
-                        vram_used = memory.vram_used;
-                        vram_total = memory.vram_total;
-                    }
-                }
+                    )
+                    .ok()
+                    .map(|t| t as f32);
+                let vram = r
+                    .get_device_memory_data(i)
+                    .ok()
+                    .map(|m| (m.vram_used, m.vram_total));
+                Self::push_gpu(sensors, gpu_index, name, usage, temperature, vram);
+                gpu_index += 1;
             }
-            GpuPreference::None => {}
         }
-        #[cfg(not(feature = "amd_gpu"))]
-        match gpu_preference {
-            GpuPreference::Nvidia => {
-                if let Ok(n) = &nvml {
-                    if let Ok(d) = n.device_by_index(0) {
-                        if let Ok(name) = d.name() {
-                            gpu_info = name;
-                        }
-                        if let Ok(rates) = d.utilization_rates() {
-                            gpu_usage = rates.gpu as f32;
-                        }
-                        if let Ok(temp) = d.temperature(TemperatureSensor::Gpu) {
-                            gpu_temperature = temp as f32;
-                        }
-                        if let Ok(memory) = d.memory_info() {
-                            vram_used = memory.used;
-                            vram_total = memory.total;
-                        }
-                    }
-                }
+    }
+
+    fn poll_disks(&mut self, sensors: &mut Vec<Sensor>, elapsed: f32) {
+        self.disks.refresh(true);
+
+        for d in self.disks.list() {
+            let name = d.name().to_string_lossy();
+            let name = name.trim_start_matches("/dev/");
+            let usage = d.usage();
+
+            sensors.push(
+                Sensor::new(
+                    format!("disk.{}.read", name),
+                    format!("Disk Read ({})", name),
+                    format!("{} Read", name),
+                    usage.read_bytes as f32 / MEGABYTE / elapsed,
+                    "MB/s",
+                )
+                .with_max(0.0),
+            );
+            sensors.push(
+                Sensor::new(
+                    format!("disk.{}.write", name),
+                    format!("Disk Write ({})", name),
+                    format!("{} Write", name),
+                    usage.written_bytes as f32 / MEGABYTE / elapsed,
+                    "MB/s",
+                )
+                .with_max(0.0),
+            );
+        }
+    }
+
+    fn poll_networks(&mut self, sensors: &mut Vec<Sensor>, elapsed: f32) {
+        self.networks.refresh(true);
+
+        for (name, data) in &self.networks {
+            sensors.push(
+                Sensor::new(
+                    format!("net.{}.rx", name),
+                    format!("Network Download ({})", name),
+                    format!("{} Down", name),
+                    data.received() as f32 / MEGABYTE / elapsed,
+                    "MB/s",
+                )
+                .with_max(0.0),
+            );
+            sensors.push(
+                Sensor::new(
+                    format!("net.{}.tx", name),
+                    format!("Network Upload ({})", name),
+                    format!("{} Up", name),
+                    data.transmitted() as f32 / MEGABYTE / elapsed,
+                    "MB/s",
+                )
+                .with_max(0.0),
+            );
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_sysfs(path: &std::path::Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn poll_linux_fans(sensors: &mut Vec<Sensor>) {
+    let Ok(hwmons) = std::fs::read_dir("/sys/class/hwmon") else {
+        return;
+    };
+
+    for hwmon in hwmons.flatten() {
+        let path = hwmon.path();
+        let hwmon_name = read_sysfs(&path.join("name")).unwrap_or_default();
+        let Ok(files) = std::fs::read_dir(&path) else {
+            continue;
+        };
+
+        for f in files.flatten() {
+            let file_name = f.file_name().to_string_lossy().to_string();
+            let Some(fan) = file_name.strip_suffix("_input") else {
+                continue;
+            };
+            if !fan.starts_with("fan") {
+                continue;
+            }
+
+            let Some(rpm) = read_sysfs(&f.path()).and_then(|s| s.parse::<f32>().ok()) else {
+                continue;
+            };
+            let label = read_sysfs(&path.join(format!("{}_label", fan))).unwrap_or(fan.into());
+            let max = read_sysfs(&path.join(format!("{}_max", fan)))
+                .and_then(|s| s.parse::<f32>().ok())
+                .unwrap_or(0.0);
+
+            sensors.push(
+                Sensor::new(
+                    format!("fan.{}.{}", hwmon_name, fan),
+                    format!("Fan Speed ({} {})", hwmon_name, label),
+                    label,
+                    rpm,
+                    "RPM",
+                )
+                .with_max(max),
+            );
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn poll_linux_batteries(sensors: &mut Vec<Sensor>) {
+    let Ok(supplies) = std::fs::read_dir("/sys/class/power_supply") else {
+        return;
+    };
+
+    for supply in supplies.flatten() {
+        let path = supply.path();
+        if read_sysfs(&path.join("type")).as_deref() != Some("Battery") {
+            continue;
+        }
+
+        let name = supply.file_name().to_string_lossy().to_string();
+        if let Some(capacity) =
+            read_sysfs(&path.join("capacity")).and_then(|s| s.parse::<f32>().ok())
+        {
+            sensors.push(Sensor::new(
+                format!("battery.{}", name),
+                format!("Battery ({})", name),
+                "Battery".into(),
+                capacity,
+                "%",
+            ));
+        }
+    }
+}
+
+// Logind's session of ours, None when there's no logind to ask
+#[cfg(target_os = "linux")]
+static LOGIN_SESSION: OnceLock<Option<Proxy<'static>>> = OnceLock::new();
+
+#[cfg(target_os = "linux")]
+async fn get_login_session() -> zbus::Result<Proxy<'static>> {
+    const LOGIN: &str = "org.freedesktop.login1";
+
+    let conn = Connection::system().await?;
+    let manager = Proxy::new(
+        &conn,
+        LOGIN,
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )
+    .await?;
+    let session = std::env::var("XDG_SESSION_ID").unwrap_or("auto".into());
+    let path: OwnedObjectPath = manager.call("GetSession", &(session,)).await?;
+
+    // Both hints are asked for on every poll anyway, there's no need to follow their changes
+    zbus::proxy::Builder::new(&conn)
+        .destination(LOGIN)?
+        .path(path)?
+        .interface("org.freedesktop.login1.Session")?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await
+}
+
+// Logind tracks both for us, with the idle time the desktop environment is set to
+#[cfg(target_os = "linux")]
+fn get_host_state() -> u8 {
+    let rt = tokio::runtime::Handle::current();
+    let session = LOGIN_SESSION.get_or_init(|| {
+        rt.block_on(get_login_session())
+            .inspect_err(|e| log::warn!("failed to find login session: {}", e))
+            .ok()
+    });
+    let Some(session) = session else {
+        return 0;
+    };
+
+    rt.block_on(async {
+        let mut state = 0;
+        if let Ok(true) = session.get_property("LockedHint").await {
+            state |= HOST_LOCKED;
+        }
+        if let Ok(true) = session.get_property("IdleHint").await {
+            state |= HOST_IDLE;
+        }
+        state
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn get_host_state() -> u8 {
+    let mut state = 0;
+
+    // Windows keeps the lock state with the session
+    unsafe {
+        let mut info = PWSTR::null();
+        let mut len = 0;
+        if WTSQuerySessionInformationW(
+            Some(WTS_CURRENT_SERVER_HANDLE),
+            WTS_CURRENT_SESSION,
+            WTSSessionInfoEx,
+            &mut info,
+            &mut len,
+        )
+        .is_ok()
+        {
+            let info = info.0 as *const WTSINFOEXW;
+            if (*info).Level == 1
+                && (*info).Data.WTSInfoExLevel1.SessionFlags == WTS_SESSIONSTATE_LOCK as i32
+            {
+                state |= HOST_LOCKED;
             }
-            _ => {}
         }
+        if !info.is_null() {
+            WTSFreeMemory(info.0 as _);
+        }
+    }
+
+    let mut info = LASTINPUTINFO {
+        cbSize: size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    if unsafe { GetLastInputInfo(&mut info) }.as_bool() {
+        let idle_ms = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+        if idle_ms / 1000 >= HOST_IDLE_SECS {
+            state |= HOST_IDLE;
+        }
+    }
+
+    state
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn get_host_state() -> u8 {
+    0
+}
+
+pub fn system_task(
+    config: Arc<Mutex<JukeBoxConfig>>,
+    system_stats: Arc<Mutex<SystemStats>>,
+) -> Result<()> {
+    let mut poller = SensorPoller::new();
+
+    // we take an average of the past few samples of each stat to put in the mutex
+    let mut history: HashMap<String, VecDeque<f32>> = HashMap::new();
+    // rates (like disk and network) have no real maximum, so we scale them to the highest we've seen
+    let mut peaks: HashMap<String, f32> = HashMap::new();
+    let mut host_state_tick = Instant::now();
+
+    loop {
+        if Instant::now() >= host_state_tick {
+            host_state_tick = Instant::now() + HOST_STATE_POLL;
+            HOST_STATE.store(get_host_state(), Ordering::Relaxed);
+        }
+
+        let sensors = poller.poll();
+
+        *SYSTEM_SENSORS
+            .get_or_init(|| Mutex::new(Vec::new()))
+            .blocking_lock() = sensors
+            .iter()
+            .map(|s| (s.key.clone(), s.name.clone()))
+            .collect();
+
+        let selected = config.blocking_lock().system_stats.clone();
+        let mut stats = SystemStats::default();
+        for (id, key) in selected.iter().take(SYSTEM_STATS_MAX).enumerate() {
+            let Some(sensor) = sensors.iter().find(|s| &s.key == key) else {
+                continue;
+            };
+
+            let h = history.entry(key.clone()).or_default();
+            h.push_back(sensor.value);
+            if h.len() > 20 {
+                let _ = h.pop_front();
+            }
+            let value = h.iter().sum::<f32>() / (h.len() as f32);
+
+            let max = if sensor.max > sensor.min {
+                sensor.max
+            } else {
+                let peak = peaks.entry(key.clone()).or_insert(1.0);
+                *peak = peak.max(value);
+                *peak
+            };
 
-        // push report, we take an average of the past few samples to put in the mutex
-        stat_reports.push_back(StatReport {
-            cpu_usage,
-            cpu_temperature,
-            memory_used,
-            memory_total,
-
-            gpu_usage,
-            gpu_temperature,
-            vram_used,
-            vram_total,
-        });
-        if stat_reports.len() > 20 {
-            let _ = stat_reports.pop_front();
+            stats.push(sensor.to_stat(id as u8, value, max));
         }
+        history.retain(|k, _| selected.contains(k));
 
-        *system_stats.blocking_lock() = StatReport::avg(stat_reports.clone())
-            .to_system_stats(cpu_info.clone().unwrap(), gpu_info);
+        *system_stats.blocking_lock() = stats;
 
         sleep(MINIMUM_CPU_UPDATE_INTERVAL);
     }
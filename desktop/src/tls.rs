@@ -0,0 +1,281 @@
+// TLS for connections made by libraries that can't be handed our own certificate checks (like the
+// OBS websocket client). Those talk plain TCP to a relay on localhost, and the relay does TLS to
+// the real host, trusting the system roots, a custom CA or a pinned certificate fingerprint.
+
+use std::{
+    io,
+    net::Ipv4Addr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rustls::{
+    client::{
+        danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        WebPkiServerVerifier,
+    },
+    crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider},
+    pki_types::{pem::PemObject, CertificateDer, ServerName, UnixTime},
+    ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use sha2::{Digest, Sha256};
+use tokio::{
+    io::copy_bidirectional,
+    net::{TcpListener, TcpStream},
+    time::timeout,
+};
+use tokio_rustls::{client::TlsStream, TlsConnector};
+
+use crate::config::TlsTrust;
+
+#[derive(Debug)]
+pub enum TlsError {
+    Timeout,
+    Refused,
+    Unreachable(String),
+    CaFile(String),
+    Untrusted(String),
+    FingerprintChanged { expected: String, actual: String },
+    Handshake(String),
+}
+
+// SHA-256 of the certificate, as colon separated hex like browsers show it
+pub fn fingerprint(cert: &CertificateDer<'_>) -> String {
+    Sha256::digest(cert.as_ref())
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+// Checks certificates like `inner` does, or only against `pinned` when there's no inner verifier.
+// Remembers the fingerprint it was shown either way, so callers can pin it or report it.
+#[derive(Debug)]
+struct RecordingVerifier {
+    inner: Option<Arc<WebPkiServerVerifier>>,
+    pinned: Option<String>,
+    provider: Arc<CryptoProvider>,
+    seen: Arc<Mutex<Option<String>>>,
+}
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let fingerprint = fingerprint(end_entity);
+        *self.seen.lock().unwrap() = Some(fingerprint.clone());
+
+        if let Some(inner) = &self.inner {
+            return inner.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                ocsp_response,
+                now,
+            );
+        }
+
+        match &self.pinned {
+            Some(p) if !p.eq_ignore_ascii_case(&fingerprint) => Err(rustls::Error::General(
+                "certificate fingerprint changed".into(),
+            )),
+            _ => Ok(ServerCertVerified::assertion()), // nothing pinned yet, trust on first use
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+fn crypto_provider() -> Arc<CryptoProvider> {
+    CryptoProvider::get_default()
+        .cloned()
+        .unwrap_or_else(|| Arc::new(rustls::crypto::aws_lc_rs::default_provider()))
+}
+
+fn client_config(
+    trust: &TlsTrust,
+    seen: Arc<Mutex<Option<String>>>,
+) -> Result<ClientConfig, TlsError> {
+    let provider = crypto_provider();
+
+    let roots = match trust {
+        TlsTrust::SystemRoots => Some(RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        }),
+        TlsTrust::CustomCa(path) => {
+            let mut roots = RootCertStore::empty();
+            let certs =
+                CertificateDer::pem_file_iter(path).map_err(|e| TlsError::CaFile(e.to_string()))?;
+            for cert in certs {
+                let cert = cert.map_err(|e| TlsError::CaFile(e.to_string()))?;
+                roots
+                    .add(cert)
+                    .map_err(|e| TlsError::CaFile(e.to_string()))?;
+            }
+            Some(roots)
+        }
+        TlsTrust::Pinned(_) => None,
+    };
+
+    let inner = match roots {
+        Some(roots) => Some(
+            WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                .build()
+                .map_err(|e| TlsError::CaFile(e.to_string()))?,
+        ),
+        None => None,
+    };
+
+    let pinned = match trust {
+        TlsTrust::Pinned(p) => p.clone(),
+        _ => None,
+    };
+
+    let verifier = RecordingVerifier {
+        inner,
+        pinned,
+        provider: provider.clone(),
+        seen,
+    };
+
+    Ok(ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| TlsError::Handshake(e.to_string()))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth())
+}
+
+/// Opens a plain TCP connection, sorting out the usual ways it can fail.
+pub async fn tcp_connect(host: &str, port: u16, wait: Duration) -> Result<TcpStream, TlsError> {
+    match timeout(wait, TcpStream::connect((host, port))).await {
+        Err(_) => Err(TlsError::Timeout),
+        Ok(Err(e)) if e.kind() == io::ErrorKind::ConnectionRefused => Err(TlsError::Refused),
+        Ok(Err(e)) => Err(TlsError::Unreachable(e.to_string())),
+        Ok(Ok(s)) => Ok(s),
+    }
+}
+
+/// Connects with TLS, returning the stream and the fingerprint of the server's certificate.
+pub async fn tls_connect(
+    host: &str,
+    port: u16,
+    trust: &TlsTrust,
+    wait: Duration,
+) -> Result<(TlsStream<TcpStream>, String), TlsError> {
+    let seen = Arc::new(Mutex::new(None));
+    let config = client_config(trust, seen.clone())?;
+    let server_name =
+        ServerName::try_from(host.to_string()).map_err(|e| TlsError::Unreachable(e.to_string()))?;
+
+    let tcp = tcp_connect(host, port, wait).await?;
+    let res = timeout(
+        wait,
+        TlsConnector::from(Arc::new(config)).connect(server_name, tcp),
+    )
+    .await
+    .map_err(|_| TlsError::Timeout)?;
+
+    let seen = seen.lock().unwrap().clone();
+    match res {
+        Ok(stream) => Ok((stream, seen.unwrap_or_default())),
+        Err(e) => {
+            if let (TlsTrust::Pinned(Some(expected)), Some(actual)) = (trust, seen) {
+                if !expected.eq_ignore_ascii_case(&actual) {
+                    return Err(TlsError::FingerprintChanged {
+                        expected: expected.clone(),
+                        actual,
+                    });
+                }
+            }
+
+            let untrusted = e
+                .get_ref()
+                .and_then(|e| e.downcast_ref::<rustls::Error>())
+                .is_some_and(|e| matches!(e, rustls::Error::InvalidCertificate(_)));
+            if untrusted {
+                Err(TlsError::Untrusted(e.to_string()))
+            } else {
+                Err(TlsError::Handshake(e.to_string()))
+            }
+        }
+    }
+}
+
+pub struct TlsRelay {
+    pub port: u16,           // on 127.0.0.1
+    pub fingerprint: String, // of the remote's certificate
+}
+
+/// Connects to the remote with TLS and passes a single local connection through to it. The TLS
+/// connection is made up front, so certificate problems come back from here and not as a vague
+/// failure from whatever connects to the relay.
+pub async fn start_relay(
+    host: &str,
+    port: u16,
+    trust: &TlsTrust,
+    wait: Duration,
+) -> Result<TlsRelay, TlsError> {
+    let (mut remote, fingerprint) = tls_connect(host, port, trust, wait).await?;
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .await
+        .map_err(|e| TlsError::Unreachable(e.to_string()))?;
+    let local_port = listener
+        .local_addr()
+        .map_err(|e| TlsError::Unreachable(e.to_string()))?
+        .port();
+
+    tokio::spawn(async move {
+        // Whoever asked for the relay should connect right away, don't hang around otherwise
+        let Ok(Ok((mut local, _))) = timeout(wait * 2, listener.accept()).await else {
+            return;
+        };
+        drop(listener);
+
+        if let Err(e) = copy_bidirectional(&mut local, &mut remote).await {
+            log::debug!("tls relay closed: {}", e);
+        }
+    });
+
+    Ok(TlsRelay {
+        port: local_port,
+        fingerprint,
+    })
+}
@@ -0,0 +1,164 @@
+// Short messages shown on top of keypad screens, raised by actions, integrations and the desktop
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+};
+
+use jukebox_util::{
+    color::rgb888_to_rgb565,
+    toast::{Toast, ToastIcon},
+};
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedSender},
+    Mutex,
+};
+
+use crate::{actions::types::ActionError, config::JukeBoxConfig, serial::SerialCommand};
+
+pub const TOAST_COLOR_INFO: u16 = rgb888_to_rgb565(80, 160, 255);
+pub const TOAST_COLOR_WARNING: u16 = rgb888_to_rgb565(255, 200, 100);
+pub const TOAST_COLOR_ERROR: u16 = rgb888_to_rgb565(255, 80, 80);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToastSource {
+    ActionError,
+    Obs,
+    Discord,
+    Desktop,
+    Command, // Output of shell commands, asked for by the action itself
+}
+
+struct ToastRequest {
+    source: ToastSource,
+    device_uid: Option<String>, // None goes to every keypad
+    toast: Toast,
+}
+
+static TOAST_TX: OnceLock<UnboundedSender<ToastRequest>> = OnceLock::new();
+
+/// Queues a toast for every connected keypad. Does nothing before the toast task is running.
+pub fn show_toast(source: ToastSource, toast: Toast) {
+    send_toast(source, None, toast);
+}
+
+/// Queues a toast for a single device.
+pub fn show_device_toast(source: ToastSource, device_uid: &str, toast: Toast) {
+    send_toast(source, Some(device_uid.to_string()), toast);
+}
+
+fn send_toast(source: ToastSource, device_uid: Option<String>, toast: Toast) {
+    if let Some(tx) = TOAST_TX.get() {
+        let _ = tx.send(ToastRequest {
+            source,
+            device_uid,
+            toast,
+        });
+    }
+}
+
+pub fn show_action_error_toast(error: &ActionError) {
+    let toast = Toast::new(
+        &t!("toast.action_error.title"),
+        &error.msg,
+        ToastIcon::Error,
+        6,
+        TOAST_COLOR_ERROR,
+    );
+    match &error.device_uid {
+        Some(device_uid) => show_device_toast(ToastSource::ActionError, device_uid, toast),
+        None => show_toast(ToastSource::ActionError, toast),
+    }
+}
+
+pub async fn toast_task(
+    config: Arc<Mutex<JukeBoxConfig>>,
+    scmd_txs: Arc<Mutex<HashMap<String, UnboundedSender<SerialCommand>>>>,
+) {
+    let (toast_tx, mut toast_rx) = unbounded_channel::<ToastRequest>();
+    TOAST_TX.set(toast_tx).expect("failed to set TOAST_TX");
+
+    #[cfg(target_os = "linux")]
+    tokio::spawn(async {
+        if let Err(e) = desktop_notification_listener().await {
+            log::warn!("desktop notifications will not be shown as toasts: {:?}", e);
+        }
+    });
+
+    while let Some(req) = toast_rx.recv().await {
+        let enabled = {
+            let toasts = config.lock().await.toasts;
+            match req.source {
+                ToastSource::ActionError => toasts.action_errors,
+                ToastSource::Obs => toasts.obs,
+                ToastSource::Discord => toasts.discord,
+                ToastSource::Desktop => toasts.desktop,
+                ToastSource::Command => true,
+            }
+        };
+        if !enabled {
+            continue;
+        }
+
+        // Devices without a screen skip it themselves
+        for (device_uid, tx) in scmd_txs.lock().await.iter() {
+            if req.device_uid.as_ref().is_some_and(|d| d != device_uid) {
+                continue;
+            }
+            let _ = tx.send(SerialCommand::ShowToast(req.toast.clone()));
+        }
+    }
+}
+
+// Watches the session bus for notifications sent by other applications
+#[cfg(target_os = "linux")]
+async fn desktop_notification_listener() -> anyhow::Result<()> {
+    use futures::TryStreamExt;
+    use zbus::{fdo::MonitoringProxy, message::Type, zvariant::OwnedValue, MatchRule};
+
+    let conn = zbus::Connection::session().await?;
+    let rule = MatchRule::builder()
+        .msg_type(Type::MethodCall)
+        .interface("org.freedesktop.Notifications")?
+        .member("Notify")?
+        .build();
+    MonitoringProxy::new(&conn)
+        .await?
+        .become_monitor(&[rule], 0)
+        .await?;
+
+    let mut stream = zbus::MessageStream::from(&conn);
+    while let Some(msg) = stream.try_next().await? {
+        let header = msg.header();
+        if header.member().map(|m| m.as_str()) != Some("Notify") {
+            continue;
+        }
+
+        // app_name, replaces_id, app_icon, summary, body, actions, hints, expire_timeout
+        let body = msg.body();
+        let Ok((app_name, _, _, summary, text, _, _, _)) = body.deserialize::<(
+            String,
+            u32,
+            String,
+            String,
+            String,
+            Vec<String>,
+            HashMap<String, OwnedValue>,
+            i32,
+        )>() else {
+            continue;
+        };
+
+        let title = if summary.is_empty() {
+            app_name
+        } else {
+            summary
+        };
+        show_toast(
+            ToastSource::Desktop,
+            Toast::new(&title, &text, ToastIcon::Info, 0, TOAST_COLOR_INFO),
+        );
+    }
+
+    Ok(())
+}
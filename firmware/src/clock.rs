@@ -0,0 +1,24 @@
+//! Clock
+//!
+//! Tick tock, the host sets the time and we count from there.
+
+use embassy_sync::mutex::Mutex;
+use embassy_time::Instant;
+use jukebox_util::time::{DateTime, TimeSync};
+
+use crate::util::ClockSyncMutex;
+
+pub static CLOCK_SYNC: ClockSyncMutex = Mutex::new(None);
+
+pub async fn set_time(time: TimeSync) {
+    *CLOCK_SYNC.lock().await = Some((time, Instant::now()));
+}
+
+/// Local wall time, if the host has ever told us what time it is.
+pub async fn get_local_time() -> Option<DateTime> {
+    let sync = *CLOCK_SYNC.lock().await;
+    sync.map(|(time, synced_at)| {
+        let elapsed = (Instant::now() - synced_at).as_secs() as i64;
+        DateTime::from_secs(time.local_secs() + elapsed)
+    })
+}
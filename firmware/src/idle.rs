@@ -0,0 +1,53 @@
+//! Idle
+//!
+//! Lights out when nobody's around, any key brings them back.
+
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use embassy_time::Instant;
+use jukebox_util::idle::{HOST_IDLE, HOST_LOCKED, IdleLevel, IdleTimeout};
+
+// Uptime in seconds of the last key press
+static LAST_ACTIVITY: AtomicU32 = AtomicU32::new(0);
+static HOST_IS_LOCKED: AtomicBool = AtomicBool::new(false);
+static HOST_IS_IDLE: AtomicBool = AtomicBool::new(false);
+
+// Set by the peripherals themselves, a key press while any are asleep only wakes them
+pub static SCREEN_ASLEEP: AtomicBool = AtomicBool::new(false);
+pub static RGB_ASLEEP: AtomicBool = AtomicBool::new(false);
+
+fn uptime_secs() -> u32 {
+    Instant::now().as_secs() as u32
+}
+
+pub fn asleep() -> bool {
+    SCREEN_ASLEEP.load(Ordering::Relaxed) || RGB_ASLEEP.load(Ordering::Relaxed)
+}
+
+/// Starts the idle timers over, local activity outranks whatever the host last told us.
+pub fn wake() {
+    LAST_ACTIVITY.store(uptime_secs(), Ordering::Relaxed);
+    HOST_IS_LOCKED.store(false, Ordering::Relaxed);
+    HOST_IS_IDLE.store(false, Ordering::Relaxed);
+}
+
+pub fn set_host_state(state: u8) {
+    // The host coming back counts as activity
+    if state == 0 {
+        wake();
+        return;
+    }
+
+    HOST_IS_LOCKED.store(state & HOST_LOCKED != 0, Ordering::Relaxed);
+    HOST_IS_IDLE.store(state & HOST_IDLE != 0, Ordering::Relaxed);
+}
+
+pub fn idle_level(timeout: &IdleTimeout) -> IdleLevel {
+    let idle_secs = if HOST_IS_LOCKED.load(Ordering::Relaxed) {
+        u64::MAX
+    } else {
+        uptime_secs().wrapping_sub(LAST_ACTIVITY.load(Ordering::Relaxed)) as u64
+    };
+
+    timeout.level(idle_secs, HOST_IS_IDLE.load(Ordering::Relaxed))
+}
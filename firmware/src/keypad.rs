@@ -11,11 +11,16 @@ use embassy_rp::gpio::{Input, Output};
 use embassy_time::{Duration, Instant};
 use jukebox_util::peripheral::JBInputs;
 
+use crate::idle::{asleep, wake};
+
 static KEYPAD_KEYS: [AtomicBool; 12] = [const { AtomicBool::new(false) }; 12];
+// Keys that woke the device up, hidden until they're let go so they don't fire anything
+static KEYPAD_WAKE_KEYS: [AtomicBool; 12] = [const { AtomicBool::new(false) }; 12];
 pub fn get_raw_inputs() -> [bool; 16] {
     let mut inputs = [false; 16];
     KEYPAD_KEYS.iter().enumerate().for_each(|(i, k)| {
-        inputs[i] = k.load(core::sync::atomic::Ordering::Relaxed);
+        inputs[i] = k.load(core::sync::atomic::Ordering::Relaxed)
+            && !KEYPAD_WAKE_KEYS[i].load(core::sync::atomic::Ordering::Relaxed);
     });
     inputs
 }
@@ -57,10 +62,19 @@ impl KeypadMod {
 
                 for col in 0..4 {
                     let i = row * 4 + col;
-                    KEYPAD_KEYS[i].store(
-                        self.col_pins[col].is_high(),
-                        core::sync::atomic::Ordering::Relaxed,
-                    );
+                    let pressed = self.col_pins[col].is_high();
+                    let was_pressed = KEYPAD_KEYS[i].load(core::sync::atomic::Ordering::Relaxed);
+
+                    if pressed {
+                        if !was_pressed && asleep() {
+                            KEYPAD_WAKE_KEYS[i].store(true, core::sync::atomic::Ordering::Relaxed);
+                        }
+                        wake();
+                    } else {
+                        KEYPAD_WAKE_KEYS[i].store(false, core::sync::atomic::Ordering::Relaxed);
+                    }
+
+                    KEYPAD_KEYS[i].store(pressed, core::sync::atomic::Ordering::Relaxed);
                 }
 
                 self.row_pins[row].set_low();
@@ -5,8 +5,10 @@
 #![no_std]
 #![no_main]
 
+mod clock;
 mod eeprom;
 mod identify;
+mod idle;
 mod keypad;
 mod rgb;
 mod screen;
@@ -13,9 +13,13 @@ use embassy_rp::{
 };
 use embassy_sync::mutex::Mutex;
 use embassy_time::{Duration, Instant};
-use jukebox_util::rgb::{RgbProfile, rgb_brightness};
+use jukebox_util::{
+    idle::IdleLevel,
+    rgb::{RgbProfile, rgb_brightness},
+};
 
 use crate::{
+    idle::{RGB_ASLEEP, idle_level},
     usb::usb_suspended,
     util::{DefaultRgbProfileMutex, Irqs, RgbProfileMutex},
 };
@@ -81,10 +85,17 @@ impl RgbMod {
 
             let profile = RGB_PROFILE.lock().await.clone();
 
+            let idle = profile.idle();
+            let level = idle_level(&idle);
+            RGB_ASLEEP.store(
+                level == IdleLevel::Asleep,
+                core::sync::atomic::Ordering::Relaxed,
+            );
+
             if usb_suspended() {
                 self.set_brightness_target(0);
             } else {
-                self.set_brightness_target(profile.brightness());
+                self.set_brightness_target(idle.brightness(level, profile.brightness()));
             }
 
             let b = self.brightness as u8;
@@ -4,7 +4,7 @@
 
 use defmt::*;
 
-use embassy_futures::yield_now;
+use embassy_futures::{join::join, yield_now};
 use embassy_rp::{
     Peri,
     dma::Channel,
@@ -20,25 +20,39 @@ use embassy_sync::mutex::Mutex;
 use embassy_time::{Duration, Instant};
 use embedded_graphics::prelude::*;
 use embedded_graphics::{
-    pixelcolor::{Bgr565, Gray4, raw::RawU16},
-    primitives::{PrimitiveStyle, Rectangle},
-    text::{Alignment, Baseline, Text, TextStyle, TextStyleBuilder},
+    pixelcolor::{Bgr565, raw::RawU16},
+    primitives::{Circle, Line, PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyle, TextStyleBuilder, renderer::TextRenderer},
 };
 use embedded_graphics_framebuf::{FrameBuf, backends::FrameBufferBackend};
 use jukebox_util::{
-    screen::{ProfileName, ScreenProfile},
-    stats::SystemStats,
+    idle::IdleLevel,
+    render::{
+        self, CENTER_TEXT_STYLE, FONT1, FONT2, LEFT_TEXT_STYLE, envelope, widget_font,
+        widget_text_style,
+    },
+    screen::{
+        FrameTiming, GraphStyle, ICON_ANIMATION_FPS, ProfileName, SCREEN_LAYOUT_MAX_WIDGETS,
+        ScreenProfile, ScreenSaver, ScreenWidget, StatGraph, WidgetAlign, WidgetFont,
+    },
+    stats::{StatHistory, SystemStats},
+    time::ClockFormat,
+    toast::{Toast, ToastIcon},
 };
-use mplusfonts::{BitmapFont, mplus, style::BitmapFontStyleBuilder};
+use mplusfonts::style::BitmapFontStyleBuilder;
+use ringbuffer::{ConstGenericRingBuffer, RingBuffer};
 
 use crate::{
+    clock::get_local_time,
+    idle::{SCREEN_ASLEEP, idle_level},
     keypad::get_raw_inputs,
     serial::SERIAL_CONNECTED,
     uid::get_uid,
     usb::usb_suspended,
     util::{
-        DefaultScreenProfileMutex, Irqs, ScreenIconsMutex, ScreenProfileMutex,
-        ScreenProfileNameMutex, ScreenSystemStatsMutex,
+        DefaultScreenProfileMutex, Irqs, ScreenFrameTimingMutex, ScreenIconCacheMutex,
+        ScreenIconFramesMutex, ScreenIconsMutex, ScreenProfileMutex, ScreenProfileNameMutex,
+        ScreenSystemStatsMutex, ScreenToastsMutex,
     },
 };
 
@@ -240,9 +254,30 @@ impl St7789_8080 {
     }
 
     pub async fn push_framebuffer(&mut self, fb: &'static [u16]) {
+        self.push_window(SCREEN_AREA, fb).await;
+    }
+
+    /// Sends pixels for one area of the screen, `data` being its rows back to back.
+    pub async fn push_window(&mut self, area: Rectangle, data: &[u16]) {
+        let x = area.top_left.x as u16;
+        let y = area.top_left.y as u16;
+        let w = area.size.width as u16;
+        let h = area.size.height as u16;
+
+        self.write_cmd(&[0x002A, x, x + w - 1]).await; // CASET: column addresses
+        self.write_cmd(&[0x002B, y, y + h - 1]).await; // RASET: row addresses
         self.write_cmd(&[0x002C]).await;
         self.set_dc_cs(true, false).await;
-        self.sm.tx().dma_push(&mut self.dma, fb, true).await;
+        self.sm.tx().dma_push(&mut self.dma, data, true).await;
+    }
+
+    async fn push_staged(&mut self, staged: &Staged) {
+        let staging = unsafe { &*core::ptr::addr_of!(STAGING) };
+        for (area, offset) in &staged.areas[..staged.count] {
+            let len = (area.size.width * area.size.height) as usize;
+            self.push_window(*area, &staging[*offset..*offset + len])
+                .await;
+        }
     }
 }
 
@@ -255,10 +290,134 @@ pub static SCREEN_PROFILE_NAME: ScreenProfileNameMutex =
 pub static SCREEN_SYSTEM_STATS: ScreenSystemStatsMutex =
     Mutex::new((false, SystemStats::default()));
 pub static SCREEN_ICONS: ScreenIconsMutex = Mutex::new([[0u16; 32 * 32]; 12]);
+pub static SCREEN_ICON_FRAMES: ScreenIconFramesMutex = Mutex::new(IconFrames::default());
+pub static SCREEN_ICON_CACHE: ScreenIconCacheMutex = Mutex::new(IconCache::default());
+pub static SCREEN_FRAME_TIMING: ScreenFrameTimingMutex = Mutex::new(FrameTiming::default());
+pub static SCREEN_TOASTS: ScreenToastsMutex = Mutex::new(ConstGenericRingBuffer::new());
+
+// Frames after the first for animated icons, shared between all the keys.
+// The first frame of every icon stays in SCREEN_ICONS, so still icons cost nothing extra.
+const ICON_FRAME_POOL: usize = 48;
+
+pub struct IconFrames {
+    frames: [[u16; 32 * 32]; ICON_FRAME_POOL],
+    slots: [(usize, usize); 12], // (start, count) in frames
+    used: usize,
+}
+impl IconFrames {
+    pub const fn default() -> Self {
+        Self {
+            frames: [[0u16; 32 * 32]; ICON_FRAME_POOL],
+            slots: [(0, 0); 12],
+            used: 0,
+        }
+    }
+
+    pub fn clear_all(&mut self) {
+        self.slots = [(0, 0); 12];
+        self.used = 0;
+    }
+
+    /// Frees a slot's frames, moving the frames after it down so the pool stays packed.
+    pub fn clear(&mut self, slot: usize) {
+        let Some((start, count)) = self.slots.get(slot).copied() else {
+            return;
+        };
+        if count == 0 {
+            return;
+        }
+
+        self.frames.copy_within(start + count..self.used, start);
+        for s in self.slots.iter_mut() {
+            if s.0 > start {
+                s.0 -= count;
+            }
+        }
+        self.slots[slot] = (0, 0);
+        self.used -= count;
+    }
+
+    /// Makes room for a slot's extra frames. If the pool is full the icon just won't animate.
+    pub fn reserve(&mut self, slot: usize, count: usize) -> bool {
+        self.clear(slot);
+        if slot >= self.slots.len() || self.used + count > ICON_FRAME_POOL {
+            return false;
+        }
+
+        self.slots[slot] = (self.used, count);
+        self.used += count;
+        true
+    }
+
+    /// Frame 0 is not kept here, use SCREEN_ICONS for it.
+    pub fn frame_mut(&mut self, slot: usize, index: usize) -> Option<&mut [u16; 32 * 32]> {
+        let (start, count) = *self.slots.get(slot)?;
+        if index == 0 || index > count {
+            return None;
+        }
+        Some(&mut self.frames[start + index - 1])
+    }
+
+    /// The frame to show for a slot at the given animation tick, or None for the first frame.
+    pub fn frame(&self, slot: usize, tick: u64) -> Option<&[u16; 32 * 32]> {
+        let (start, count) = *self.slots.get(slot)?;
+        let index = (tick % (count as u64 + 1)) as usize;
+        if index == 0 {
+            None
+        } else {
+            Some(&self.frames[start + index - 1])
+        }
+    }
+}
+
+// Recently sent icons by content hash, so switching back to a profile doesn't resend them.
+// Kept across reconnects, an icon's hash only ever points at the same pixels.
+const ICON_CACHE_SIZE: usize = 24;
+
+pub struct IconCache {
+    icons: [[u16; 32 * 32]; ICON_CACHE_SIZE],
+    hashes: [Option<u32>; ICON_CACHE_SIZE],
+    last_used: [u32; ICON_CACHE_SIZE],
+    clock: u32,
+}
+impl IconCache {
+    pub const fn default() -> Self {
+        Self {
+            icons: [[0u16; 32 * 32]; ICON_CACHE_SIZE],
+            hashes: [None; ICON_CACHE_SIZE],
+            last_used: [0; ICON_CACHE_SIZE],
+            clock: 0,
+        }
+    }
+
+    pub fn get(&mut self, hash: u32) -> Option<&[u16; 32 * 32]> {
+        let i = self.hashes.iter().position(|h| *h == Some(hash))?;
+        self.clock = self.clock.wrapping_add(1);
+        self.last_used[i] = self.clock;
+        Some(&self.icons[i])
+    }
+
+    /// Stores an icon over an empty or the least recently used entry.
+    pub fn insert(&mut self, hash: u32, icon: &[u16; 32 * 32]) {
+        if self.get(hash).is_some() {
+            return;
+        }
+
+        let i = unwrap!(
+            (0..ICON_CACHE_SIZE).min_by_key(|i| (self.hashes[*i].is_some(), self.last_used[*i]))
+        );
+        self.clock = self.clock.wrapping_add(1);
+        self.icons[i] = *icon;
+        self.hashes[i] = Some(hash);
+        self.last_used[i] = self.clock;
+    }
+}
 
 const POLL_TIME: Duration = Duration::from_millis(50);
 pub const SCR_W: usize = 320;
 pub const SCR_H: usize = 240;
+const SCREEN_AREA: Rectangle =
+    Rectangle::new(Point::new(0, 0), Size::new(SCR_W as u32, SCR_H as u32));
 static mut FBDATA: [u16; SCR_W * SCR_H] = [0x0; SCR_W * SCR_H];
 struct FBBackEnd {
     t: &'static mut [u16; SCR_W * SCR_H],
@@ -280,80 +439,269 @@ impl FrameBufferBackend for FBBackEnd {
     }
 }
 
-static FONT1: BitmapFont<'static, Gray4, 1> = mplus!(
-    code(100),
-    500,
-    16,
-    false,
-    1,
-    4,
-    '0'..='9',
-    'A'..='Z',
-    'a'..'z',
-    [" ", "-", ".", "%", "°", "/", ":"]
-);
-static FONT2: BitmapFont<'static, Gray4, 1> = mplus!(
-    code(100),
-    500,
-    32,
-    false,
-    1,
-    4,
-    '0'..='9',
-    'A'..='Z',
-    'a'..'z',
-    [" ", "-", ".", "%", "°", "/", ":"]
-);
-const LEFT_TEXT_STYLE: TextStyle = TextStyleBuilder::new()
+// Areas of the framebuffer drawn over since the last push
+const DIRTY_RECTS_MAX: usize = 16;
+
+#[derive(Clone, Copy)]
+struct DirtyRects {
+    rects: [Rectangle; DIRTY_RECTS_MAX],
+    count: usize,
+}
+impl DirtyRects {
+    const fn new() -> Self {
+        Self {
+            rects: [Rectangle::zero(); DIRTY_RECTS_MAX],
+            count: 0,
+        }
+    }
+
+    fn add(&mut self, area: Rectangle) {
+        let mut area = area.intersection(&SCREEN_AREA);
+        if area.is_zero_sized() {
+            return;
+        }
+
+        // Overlapping areas are merged so no pixel gets sent twice
+        let mut i = 0;
+        while i < self.count {
+            if self.rects[i].intersection(&area).is_zero_sized() {
+                i += 1;
+                continue;
+            }
+            area = envelope(&self.rects[i], &area);
+            self.count -= 1;
+            self.rects[i] = self.rects[self.count];
+            i = 0;
+        }
+
+        // Too many to keep track of, fall back to one area covering all of them
+        if self.count == DIRTY_RECTS_MAX {
+            for r in &self.rects {
+                area = envelope(r, &area);
+            }
+            self.count = 0;
+        }
+
+        self.rects[self.count] = area;
+        self.count += 1;
+    }
+
+    fn all(&mut self) {
+        self.rects[0] = SCREEN_AREA;
+        self.count = 1;
+    }
+
+    fn take(&mut self) -> Self {
+        let d = *self;
+        self.count = 0;
+        d
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Rectangle> {
+        self.rects[..self.count].iter()
+    }
+
+    fn intersects(&self, area: &Rectangle) -> bool {
+        self.iter().any(|r| !r.intersection(area).is_zero_sized())
+    }
+}
+
+// Dirty areas get copied out of the framebuffer so they can be sent while the next frame is drawn.
+// A second whole framebuffer won't fit in RAM next to the icons, so big updates go out in bands.
+const STAGING_LEN: usize = SCR_W * 48;
+const STAGED_MAX: usize = 24;
+static mut STAGING: [u16; STAGING_LEN] = [0x0; STAGING_LEN];
+
+#[derive(Clone, Copy)]
+struct Staged {
+    areas: [(Rectangle, usize); STAGED_MAX], // (area, offset in STAGING)
+    count: usize,
+    used: usize,
+}
+impl Staged {
+    const fn new() -> Self {
+        Self {
+            areas: [(Rectangle::zero(), 0); STAGED_MAX],
+            count: 0,
+            used: 0,
+        }
+    }
+
+    /// Copies an area of the framebuffer in, or false if there's no room left.
+    /// Only call this while nothing is being pushed from the staging buffer.
+    /// `area` is where it goes on the screen, `shift` how far the framebuffer is moved there.
+    fn stage(&mut self, fb: &[u16], area: Rectangle, shift: Point) -> bool {
+        let x = area.top_left.x as usize;
+        let y = area.top_left.y as usize;
+        let w = area.size.width as usize;
+        let h = area.size.height as usize;
+        if self.count == STAGED_MAX || self.used + w * h > STAGING_LEN {
+            return false;
+        }
+
+        let staging = unsafe { &mut *core::ptr::addr_of_mut!(STAGING) };
+        for row in 0..h {
+            let dst = self.used + row * w;
+            if shift == Point::zero() {
+                let src = (y + row) * SCR_W + x;
+                staging[dst..dst + w].copy_from_slice(&fb[src..src + w]);
+                continue;
+            }
+
+            // Shifted in from off screen, the edges get stretched out to fill the gap
+            let sy = ((y + row) as i32 - shift.y).clamp(0, SCR_H as i32 - 1) as usize;
+            for col in 0..w {
+                let sx = ((x + col) as i32 - shift.x).clamp(0, SCR_W as i32 - 1) as usize;
+                staging[dst + col] = fb[sy * SCR_W + sx];
+            }
+        }
+
+        self.areas[self.count] = (area, self.used);
+        self.count += 1;
+        self.used += w * h;
+        true
+    }
+}
+
+// Offsets the screen cycles through with the pixel shift screen saver, one a minute
+const PIXEL_SHIFTS: [Point; 8] = [
+    Point::new(2, 0),
+    Point::new(2, 2),
+    Point::new(0, 2),
+    Point::new(-2, 2),
+    Point::new(-2, 0),
+    Point::new(-2, -2),
+    Point::new(0, -2),
+    Point::new(2, -2),
+];
+
+const SAVER_TEXT_STYLE: TextStyle = TextStyleBuilder::new()
     .alignment(Alignment::Left)
-    .baseline(Baseline::Middle)
-    .build();
-const CENTER_TEXT_STYLE: TextStyle = TextStyleBuilder::new()
-    .alignment(Alignment::Center)
-    .baseline(Baseline::Middle)
-    .build();
-const RIGHT_TEXT_STYLE: TextStyle = TextStyleBuilder::new()
-    .alignment(Alignment::Right)
-    .baseline(Baseline::Middle)
+    .baseline(Baseline::Top)
     .build();
 
-struct ScreenMod {
-    scr: St7789_8080,
+// Toasts waiting their turn, past this the oldest ones get dropped
+pub const TOAST_QUEUE_LEN: usize = 4;
+const TOAST_AREA: Rectangle = Rectangle::new(Point::new(8, 8), Size::new(304, 84));
+const TOAST_BACKGROUND: Bgr565 = Bgr565::new(4, 8, 4);
+const TOAST_LINE_CHARS: usize = 30;
+const TOAST_BODY_LINES: usize = 3;
+
+/// Splits text into lines that fit in a toast, breaking at spaces and newlines where it can.
+/// Whatever doesn't fit in the last line is cut off.
+fn wrap_toast_body(text: &str) -> [&str; TOAST_BODY_LINES] {
+    let mut lines = [""; TOAST_BODY_LINES];
+    let mut rest = text.trim();
+
+    for line in lines.iter_mut() {
+        let mut limit = TOAST_LINE_CHARS.min(rest.len());
+        while !rest.is_char_boundary(limit) {
+            limit -= 1;
+        }
+
+        let end = if let Some(newline) = rest[..limit].find('\n') {
+            newline
+        } else if rest.len() <= TOAST_LINE_CHARS {
+            rest.len()
+        } else {
+            rest[..limit]
+                .rfind(' ')
+                .filter(|space| *space > 0)
+                .unwrap_or(limit)
+        };
 
+        *line = &rest[..end];
+        rest = rest[end..].trim_start();
+        if rest.is_empty() {
+            break;
+        }
+    }
+
+    lines
+}
+
+// Cheap check for whether an icon tile needs drawing again
+fn icon_fingerprint(icon: &[u16]) -> u32 {
+    icon.iter()
+        .fold(0x811C9DC5, |h, p| (h ^ *p as u32).wrapping_mul(0x01000193))
+}
+
+// The stats screen fills the top of the screen first, then the spaces beside the icons.
+// With graphs shown, the stats only get one row at the top and the graphs go under them.
+const STATS_TOP_CELLS_MAX: usize = 6;
+const STATS_TOP_CELLS_MAX_WITH_GRAPHS: usize = 3;
+const STATS_SIDE_CELLS: [(i32, i32); 4] = [(0, 130), (232, 130), (0, 185), (232, 185)];
+const STATS_GRAPH_H: u32 = 50;
+
+fn stats_cell(i: usize, count: usize, top_max: usize, top_h: u32) -> Rectangle {
+    let top_count = count.min(top_max);
+
+    if i < top_count {
+        let (cols, rows) = if top_count <= 3 {
+            (top_count, 1)
+        } else {
+            (top_count.div_ceil(2), 2)
+        };
+        let w = SCR_W as u32 / cols as u32;
+        let h = top_h / rows;
+        Rectangle::new(
+            Point::new(
+                ((i % cols) as u32 * w) as i32,
+                ((i / cols) as u32 * h) as i32,
+            ),
+            Size::new(w, h),
+        )
+    } else {
+        let (x, y) = STATS_SIDE_CELLS[i - top_count];
+        Rectangle::new(Point::new(x, y), Size::new(88, 55))
+    }
+}
+
+struct ScreenMod {
     _fb_dma: FbDma,
     fb: FrameBuf<Bgr565, FBBackEnd>,
 
     screen_profile: ScreenProfile,
     screen_profile_name: ProfileName,
     screen_system_stats: SystemStats,
+    stat_history: StatHistory,
 
     keys_status: [u8; 12],
 
+    // Areas the clock widgets were last drawn in, cleared before each redraw
+    clock_bboxes: [Option<Rectangle>; SCREEN_LAYOUT_MAX_WIDGETS],
+    clock_texts: [Option<[u8; 16]>; SCREEN_LAYOUT_MAX_WIDGETS],
+
+    // What each icon tile was last drawn with, (fingerprint, key status) by widget and slot
+    tiles: [[Option<(u32, u8)>; 12]; SCREEN_LAYOUT_MAX_WIDGETS],
+    tile_areas: [[Option<Rectangle>; 12]; SCREEN_LAYOUT_MAX_WIDGETS],
+    dirty: DirtyRects,
+
+    // What draw_pre_tick clears before drawing again: where its widgets were last drawn, and
+    // whatever else got left behind, like old toasts and the last layout's icons
+    stale: DirtyRects,
+    // The color the whole screen was last cleared to, None once something else covered it
+    background: Option<u16>,
+
+    // The screen saver showing, if any, and where it last drew
+    saver: Option<ScreenSaver>,
+    saver_bbox: Option<Rectangle>,
+    logo_pos: Point,
+    logo_vel: Point,
+    shift: Point,
+
+    toast: Option<Toast>,
+    toast_end: Instant,
+    toast_shown: bool,
+
     brightness: f32,
     brightness_target: f32,
 
     poll_time: Instant,
 }
 impl ScreenMod {
-    async fn new(
-        pio: ScrPio,
-        dma: ScrDma,
-        data: ScrDataPins,
-        clk: ScrClkPin,
-        rd: ScrRdPin,
-        cs: ScrCsPin,
-        dc: ScrDcPin,
-        bl: ScrBlPin,
-        rst: ScrRstPin,
-        fb_dma: FbDma,
-    ) -> Self {
-        let mut scr = St7789_8080::new(pio, dma, data, clk, rd, cs, dc, bl, rst);
-
-        scr.init(SCR_W as u16, SCR_H as u16).await;
-
+    async fn new(fb_dma: FbDma) -> Self {
         let mut s = Self {
-            scr,
-
             _fb_dma: fb_dma,
             fb: FrameBuf::new(
                 FBBackEnd {
@@ -366,96 +714,184 @@ impl ScreenMod {
             screen_profile: ScreenProfile::default_profile(),
             screen_profile_name: ProfileName::default(),
             screen_system_stats: SystemStats::default(),
+            stat_history: StatHistory::default(),
 
             keys_status: [1; 12],
 
+            clock_bboxes: [None; SCREEN_LAYOUT_MAX_WIDGETS],
+            clock_texts: [None; SCREEN_LAYOUT_MAX_WIDGETS],
+
+            tiles: [[None; 12]; SCREEN_LAYOUT_MAX_WIDGETS],
+            tile_areas: [[None; 12]; SCREEN_LAYOUT_MAX_WIDGETS],
+            dirty: DirtyRects::new(),
+
+            stale: DirtyRects::new(),
+            background: None,
+
+            saver: None,
+            saver_bbox: None,
+            logo_pos: Point::new(40, 40),
+            logo_vel: Point::new(2, 1),
+            shift: Point::zero(),
+
+            toast: None,
+            toast_end: Instant::now(),
+            toast_shown: false,
+
             brightness: 0f32,
             brightness_target: 0f32,
 
             poll_time: unwrap!(Instant::now().checked_add(POLL_TIME)),
         };
 
-        // black out the screen
-        s.scr
-            .push_framebuffer(unsafe { &mut *core::ptr::addr_of_mut!(FBDATA) })
-            .await;
-
         s.draw_pre_tick().await;
 
         s
     }
 
-    fn put_pixel(&mut self, color: Bgr565, x: usize, y: usize) {
-        if x >= SCR_W || y >= SCR_H {
+    fn draw_icon(&mut self, icon: &[u16], key: u8, x: usize, y: usize, s: usize) {
+        let background_color = RawU16::new(self.screen_profile.background_color()).into();
+        render::draw_icon(&mut self.fb, icon, key, x, y, s, background_color);
+    }
+
+    /// Draws an icon tile, unless it's unchanged since the last time it was drawn.
+    fn draw_tile(
+        &mut self,
+        widget: usize,
+        slot: usize,
+        icon: &[u16],
+        key: u8,
+        x: usize,
+        y: usize,
+        s: usize,
+    ) {
+        let state = Some((icon_fingerprint(icon), key));
+        if self.tiles[widget][slot] == state {
             return;
         }
+        self.tiles[widget][slot] = state;
 
-        self.fb.set_color_at(Point::new(x as i32, y as i32), color);
+        let area = Rectangle::new(
+            Point::new(x as i32, y as i32),
+            Size::new_equal((32 * s) as u32),
+        );
+        self.draw_icon(icon, key, x, y, s);
+        self.tile_areas[widget][slot] = Some(area);
+        self.dirty.add(area);
     }
 
-    fn rectangle(&mut self, color: Bgr565, x: usize, y: usize, w: usize, h: usize) {
-        for h in 0..h {
-            for w in 0..w {
-                self.put_pixel(color, x + w, y + h);
-            }
-        }
+    /// Marks an area draw_pre_tick drew on, so it's pushed now and cleared before the next one.
+    fn add_static(&mut self, area: Rectangle) {
+        self.stale.add(area);
+        self.dirty.add(area);
     }
 
-    fn rounded_rect(&mut self, color: Bgr565, x: usize, y: usize, s: usize, r: usize) {
-        self.rectangle(color, x, y, 2 * r, 2 * r);
-        self.rectangle(color, x, y, 4 * r, 1 * r);
-        self.rectangle(color, x, y, 1 * r, 4 * r);
-
-        self.rectangle(color, x, y + s - 2 * r, 2 * r, 2 * r);
-        self.rectangle(color, x, y + s - 2 * r + 1 * r, 4 * r, 1 * r);
-        self.rectangle(color, x, y + s - 2 * r - 2 * r, 1 * r, 4 * r);
-
-        self.rectangle(color, x + s - 2 * r, y, 2 * r, 2 * r);
-        self.rectangle(color, x + s - 2 * r - 2 * r, y, 4 * r, 1 * r);
-        self.rectangle(color, x + s - 2 * r + 1 * r, y, 1 * r, 4 * r);
+    fn draw_static_text<S: TextRenderer<Color = Bgr565>>(&mut self, text: Text<'_, S>) {
+        let _ = text.draw(&mut self.fb);
+        self.add_static(text.bounding_box());
+    }
 
-        self.rectangle(color, x + s - 2 * r, y + s - 2 * r, 2 * r, 2 * r);
-        self.rectangle(
-            color,
-            x + s - 2 * r - 2 * r,
-            y + s - 2 * r + 1 * r,
-            4 * r,
-            1 * r,
-        );
-        self.rectangle(
+    fn draw_stat(
+        &mut self,
+        id: u8,
+        pos: Point,
+        font: WidgetFont,
+        align: WidgetAlign,
+        color: Bgr565,
+        background_color: Bgr565,
+        show_unit: bool,
+    ) {
+        let bbox = render::draw_stat(
+            &mut self.fb,
+            &self.screen_system_stats,
+            id,
+            pos,
+            font,
+            align,
             color,
-            x + s - 2 * r + 1 * r,
-            y + s - 2 * r - 2 * r,
-            1 * r,
-            4 * r,
+            background_color,
+            show_unit,
         );
+        self.add_static(bbox);
     }
 
-    fn draw_icon(&mut self, icon: &[u16], key: u8, x: usize, y: usize, s: usize) {
-        // icon drawing
-        let mut h = 0;
-        while h < 32 {
-            let mut w = 0;
-            while w < 32 {
-                let c = RawU16::new(icon[32 * (31 - w) + (31 - h)]).into();
-                self.rectangle(c, 32 * s - h * s + x - s, w * s + y, s, s);
-                w += 1;
+    fn draw_graph(
+        &mut self,
+        graph: StatGraph,
+        style: GraphStyle,
+        history: usize,
+        area: Rectangle,
+        background_color: Bgr565,
+    ) {
+        let color: Bgr565 = RawU16::new(graph.color).into();
+        self.add_static(area);
+        let history = history.max(2);
+        let bottom = area.top_left.y + area.size.height as i32 - 1;
+        let right = area.top_left.x + area.size.width as i32 - 1;
+        let h = (area.size.height - 1) as f32;
+        let step = area.size.width as f32 / history as f32;
+
+        // Newest sample on the right, so a short history grows in from the right edge
+        let n = history.min(self.stat_history.sample_count());
+        let mut last: Option<Point> = None;
+        for (i, f) in self.stat_history.samples(graph.stat, n).enumerate() {
+            let x = right - ((n - 1 - i) as f32 * step) as i32;
+            let y = bottom - (f * h) as i32;
+            match style {
+                GraphStyle::Sparkline => {
+                    let p = Point::new(x, y);
+                    if let Some(l) = last {
+                        let _ = Line::new(l, p)
+                            .into_styled(PrimitiveStyle::with_stroke(color, 1))
+                            .draw(&mut self.fb);
+                    }
+                    last = Some(p);
+                }
+                GraphStyle::Bars => {
+                    let w = (step as u32).max(1);
+                    let _ = Rectangle::new(
+                        Point::new(x - w as i32 + 1, y),
+                        Size::new(w, (bottom - y + 1) as u32),
+                    )
+                    .into_styled(PrimitiveStyle::with_fill(color))
+                    .draw(&mut self.fb);
+                }
             }
-            h += 1;
         }
 
-        // rounded corners
-        let bgc = RawU16::new(self.screen_profile.background_color()).into();
-
-        if key > 0 {
-            self.rounded_rect(bgc, x, y, 32 * s, key as usize);
-        } else {
-            self.rounded_rect(bgc, x, y, 32 * s, 1);
+        // Baseline, and the stat's name in the corner
+        let _ = Line::new(
+            Point::new(area.top_left.x, bottom),
+            Point::new(right, bottom),
+        )
+        .into_styled(PrimitiveStyle::with_stroke(color, 1))
+        .draw(&mut self.fb);
+        if let Some(label) = self
+            .screen_system_stats
+            .get(graph.stat)
+            .map(|s| s.label.clone())
+        {
+            let style = BitmapFontStyleBuilder::new()
+                .text_color(color)
+                .background_color(background_color)
+                .font(&FONT1)
+                .build();
+            self.draw_static_text(Text::with_text_style(
+                label.to_str(),
+                area.top_left,
+                style,
+                TextStyleBuilder::new()
+                    .alignment(Alignment::Left)
+                    .baseline(Baseline::Top)
+                    .build(),
+            ));
         }
     }
 
     async fn draw_post_tick(&mut self) {
         let i = SCREEN_ICONS.lock().await.clone();
+        let frames = SCREEN_ICON_FRAMES.lock().await;
+        let tick = Instant::now().as_millis() / (1000 / ICON_ANIMATION_FPS);
 
         match self.screen_profile {
             ScreenProfile::Off => {}
@@ -463,8 +899,10 @@ impl ScreenMod {
                 for y in 0..3 {
                     for x in 0..4 {
                         let idx = y * 4 + x;
-                        self.draw_icon(
-                            &i[idx],
+                        self.draw_tile(
+                            0,
+                            idx,
+                            frames.frame(idx, tick).unwrap_or(&i[idx]),
                             self.keys_status[idx],
                             23 + (64 + 6) * x,
                             4 + (64 + 6) * y,
@@ -477,8 +915,10 @@ impl ScreenMod {
                 for y in 0..3 {
                     for x in 0..4 {
                         let idx = y * 4 + x;
-                        self.draw_icon(
-                            &i[idx],
+                        self.draw_tile(
+                            0,
+                            idx,
+                            frames.frame(idx, tick).unwrap_or(&i[idx]),
                             self.keys_status[idx],
                             90 + (32 + 4) * x,
                             130 + (32 + 4) * y,
@@ -487,31 +927,174 @@ impl ScreenMod {
                     }
                 }
             }
+            ScreenProfile::CustomLayout { widgets, .. } => {
+                for (wi, w) in widgets.into_iter().enumerate() {
+                    match w {
+                        ScreenWidget::IconGrid {
+                            x,
+                            y,
+                            scale,
+                            spacing,
+                        } => {
+                            let scale = scale.max(1) as usize;
+                            let size = 32 * scale + spacing as usize;
+                            for iy in 0..3 {
+                                for ix in 0..4 {
+                                    let idx = iy * 4 + ix;
+                                    self.draw_tile(
+                                        wi,
+                                        idx,
+                                        frames.frame(idx, tick).unwrap_or(&i[idx]),
+                                        self.keys_status[idx],
+                                        x as usize + size * ix,
+                                        y as usize + size * iy,
+                                        scale,
+                                    );
+                                }
+                            }
+                        }
+                        ScreenWidget::Image { x, y, slot, scale } => {
+                            // Images are drawn without key press feedback
+                            if let Some(icon) = i.get(slot as usize) {
+                                self.draw_tile(
+                                    wi,
+                                    slot as usize,
+                                    frames.frame(slot as usize, tick).unwrap_or(icon),
+                                    1,
+                                    x as usize,
+                                    y as usize,
+                                    scale.max(1) as usize,
+                                );
+                            }
+                        }
+                        ScreenWidget::Clock {
+                            x,
+                            y,
+                            font,
+                            align,
+                            color,
+                            format,
+                        } => {
+                            let background_color: Bgr565 =
+                                RawU16::new(self.screen_profile.background_color()).into();
+
+                            // Until the host syncs the time we have nothing to show
+                            let mut buf = [0u8; 16];
+                            let time = get_local_time().await;
+                            let time = match &time {
+                                Some(t) => t.format(format, &mut buf),
+                                None => "--:--",
+                            };
+
+                            // Most frames the text is the same, so there's nothing to redraw
+                            let mut shown = [0u8; 16];
+                            let len = time.len().min(shown.len());
+                            shown[..len].copy_from_slice(&time.as_bytes()[..len]);
+                            if self.clock_texts[wi] == Some(shown) {
+                                continue;
+                            }
+                            self.clock_texts[wi] = Some(shown);
+
+                            if let Some(bbox) = self.clock_bboxes[wi].take() {
+                                let _ = bbox
+                                    .into_styled(PrimitiveStyle::with_fill(background_color))
+                                    .draw(&mut self.fb);
+                                self.dirty.add(bbox);
+                            }
+
+                            let bbox = render::draw_text(
+                                &mut self.fb,
+                                time,
+                                Point::new(x as i32, y as i32),
+                                widget_font(font),
+                                widget_text_style(align),
+                                RawU16::new(color).into(),
+                                background_color,
+                            );
+                            self.clock_bboxes[wi] = Some(bbox);
+                            self.dirty.add(bbox);
+                        }
+                        _ => {}
+                    }
+                }
+            }
         }
     }
 
+    /// Drops the icons and clocks of the old layout, so the next draw_pre_tick clears them.
+    fn clear_layout(&mut self) {
+        for area in self.tile_areas.iter_mut().flatten() {
+            if let Some(area) = area.take() {
+                self.stale.add(area);
+            }
+        }
+        for bbox in self.clock_bboxes.iter_mut() {
+            if let Some(bbox) = bbox.take() {
+                self.stale.add(bbox);
+            }
+        }
+        self.tiles = [[None; 12]; SCREEN_LAYOUT_MAX_WIDGETS];
+        self.clock_texts = [None; SCREEN_LAYOUT_MAX_WIDGETS];
+    }
+
     async fn draw_pre_tick(&mut self) {
         let text_color: Bgr565 = RawU16::new(self.screen_profile.text_color()).into();
         let background_color: Bgr565 = RawU16::new(self.screen_profile.background_color()).into();
         let show_profile_name = self.screen_profile.show_profile_name();
+        let profile_name = self.screen_profile_name.clone();
+
+        let background = self.screen_profile.background_color();
+        if self.background != Some(background) {
+            // Nothing on screen is worth keeping, clear all of it
+            // TODO: We actually can't do the background color with a DMA right now because of embassy limitations...
+            // unsafe {
+            //     write_repeated(
+            //         self.fb_dma.reborrow(),
+            //         &mut *core::ptr::addr_of_mut!(FBDATA[0]),
+            //         SCR_W * SCR_H,
+            //         embassy_rp::pac::dma::vals::TreqSel::PERMANENT,
+            //     )
+            //     .await;
+            // };
+            let _ = SCREEN_AREA
+                .into_styled(PrimitiveStyle::with_fill(background_color))
+                .draw(&mut self.fb);
+            self.dirty.all();
+            self.background = Some(background);
+            self.stale = DirtyRects::new();
+            self.tiles = [[None; 12]; SCREEN_LAYOUT_MAX_WIDGETS];
+            self.tile_areas = [[None; 12]; SCREEN_LAYOUT_MAX_WIDGETS];
+            self.clock_bboxes = [None; SCREEN_LAYOUT_MAX_WIDGETS];
+            self.clock_texts = [None; SCREEN_LAYOUT_MAX_WIDGETS];
+        } else {
+            // Only what was drawn last time gets cleared, everything else stays as it is
+            let stale = self.stale.take();
+            for area in stale.iter() {
+                let _ = area
+                    .into_styled(PrimitiveStyle::with_fill(background_color))
+                    .draw(&mut self.fb);
+                self.dirty.add(*area);
+            }
 
-        // Clear the screen to the background color using a DMA
-        // TODO: We actually can't do the background color right now because of embassy limitations...
-        // unsafe {
-        //     write_repeated(
-        //         self.fb_dma.reborrow(),
-        //         &mut *core::ptr::addr_of_mut!(FBDATA[0]),
-        //         SCR_W * SCR_H,
-        //         embassy_rp::pac::dma::vals::TreqSel::PERMANENT,
-        //     )
-        //     .await;
-        // };
-        // TODO: dont do this.
-        let _ = Rectangle::new(Point::new(0, 0), Size::new(SCR_W as u32, SCR_H as u32))
-            .into_styled(PrimitiveStyle::with_fill(
-                RawU16::new(self.screen_profile.background_color()).into(),
-            ))
-            .draw(&mut self.fb);
+            // Icons and clocks caught in a cleared area are drawn again in draw_post_tick
+            for (tiles, areas) in self.tiles.iter_mut().zip(self.tile_areas.iter()) {
+                for (tile, area) in tiles.iter_mut().zip(areas.iter()) {
+                    if area.is_some_and(|a| stale.intersects(&a)) {
+                        *tile = None;
+                    }
+                }
+            }
+            for (text, bbox) in self
+                .clock_texts
+                .iter_mut()
+                .zip(self.clock_bboxes.iter_mut())
+            {
+                if bbox.is_some_and(|b| stale.intersects(&b)) {
+                    *text = None;
+                    *bbox = None;
+                }
+            }
+        }
 
         let font1_style = BitmapFontStyleBuilder::new()
             .text_color(text_color)
@@ -526,218 +1109,194 @@ impl ScreenMod {
 
                 if serial_connected {
                     if show_profile_name {
-                        let _ = Text::with_text_style(
-                            self.screen_profile_name.to_str(),
+                        self.draw_static_text(Text::with_text_style(
+                            profile_name.to_str(),
                             Point::new(160 - 1, 224),
                             font1_style.clone(),
                             CENTER_TEXT_STYLE,
-                        )
-                        .draw(&mut self.fb);
+                        ));
                     }
                 } else {
-                    let _ = Text::with_text_style(
+                    self.draw_static_text(Text::with_text_style(
                         get_uid(),
                         Point::new(160 - 1, 224),
                         font1_style.clone(),
                         CENTER_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
+                    ));
 
-                    let _ = Text::with_text_style(
+                    self.draw_static_text(Text::with_text_style(
                         env!("CARGO_PKG_VERSION"),
                         Point::new(255, 224),
                         font1_style.clone(),
                         LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
+                    ));
                 }
             }
-            ScreenProfile::DisplayStats { .. } => {
-                let font2_style = BitmapFontStyleBuilder::new()
-                    .text_color(text_color)
-                    .background_color(background_color)
-                    .font(&FONT2)
-                    .build();
-
-                // Left side
-                {
-                    // Memory Total
-                    let _ = Text::with_text_style(
-                        self.screen_system_stats.memory_total.to_str(),
-                        Point::new(24, 167 + 60),
-                        font1_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-                    let _ = Text::with_text_style(
-                        self.screen_system_stats.memory_unit.to_str(),
-                        Point::new(66, 167 + 60),
-                        font1_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-                    let _ = Text::with_text_style(
-                        " /",
-                        Point::new(4, 167 + 60),
-                        font1_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-
-                    // Memory Used
-                    let _ = Text::with_text_style(
-                        self.screen_system_stats.memory_used.to_str(),
-                        Point::new(4, 142 + 60),
-                        font2_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-
-                    let _ = Text::with_text_style(
-                        "RAM:",
-                        Point::new(45, 167 + 10),
+            ScreenProfile::DisplayStats {
+                graph_style,
+                graph_history,
+                graphs,
+                ..
+            } => {
+                // Leave room for the profile name between the stats and the icons
+                let top_h = if show_profile_name { 106 } else { 126 };
+                let graph_count = graphs.iter().flatten().count();
+                let (top_max, cells_h) = if graph_count > 0 {
+                    (STATS_TOP_CELLS_MAX_WITH_GRAPHS, top_h - STATS_GRAPH_H)
+                } else {
+                    (STATS_TOP_CELLS_MAX, top_h)
+                };
+                let count =
+                    (self.screen_system_stats.count as usize).min(top_max + STATS_SIDE_CELLS.len());
+
+                for i in 0..count {
+                    let stat = self.screen_system_stats.stats[i].clone();
+                    let cell = stats_cell(i, count, top_max, cells_h);
+
+                    let center = cell.top_left.x + cell.size.width as i32 / 2;
+                    self.draw_static_text(Text::with_text_style(
+                        stat.label.to_str(),
+                        Point::new(center, cell.top_left.y + 10),
                         font1_style.clone(),
                         CENTER_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-
-                    // CPU Temperature
-                    let _ = Text::with_text_style(
-                        self.screen_system_stats.cpu_temperature.to_str(),
-                        Point::new(26, 82),
-                        font2_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-                    let _ = Text::with_text_style(
-                        "°C",
-                        Point::new(108, 86),
-                        font1_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-
-                    // CPU Usage
-                    let _ = Text::with_text_style(
-                        self.screen_system_stats.cpu_usage.to_str(),
-                        Point::new(26, 42),
-                        font2_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-                    let _ = Text::with_text_style(
-                        "%",
-                        Point::new(108, 46),
-                        font1_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-
-                    // CPU name
-                    let _ = Text::with_text_style(
-                        self.screen_system_stats.cpu_name.to_str(),
-                        Point::new(3, 10),
-                        font1_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
+                    ));
+
+                    // Narrow cells can't fit the big font
+                    let font = if cell.size.width >= 100 {
+                        WidgetFont::Large
+                    } else {
+                        WidgetFont::Small
+                    };
+                    self.draw_stat(
+                        stat.id,
+                        Point::new(center, cell.top_left.y + 36),
+                        font,
+                        WidgetAlign::Center,
+                        text_color,
+                        background_color,
+                        true,
+                    );
                 }
 
-                // Right side
-                {
-                    // VRAM Total
-                    let _ = Text::with_text_style(
-                        self.screen_system_stats.vram_total.to_str(),
-                        Point::new(230 + 24, 167 + 60),
-                        font1_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-                    let _ = Text::with_text_style(
-                        self.screen_system_stats.vram_unit.to_str(),
-                        Point::new(230 + 66, 167 + 60),
-                        font1_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-                    let _ = Text::with_text_style(
-                        " /",
-                        Point::new(230 + 4, 167 + 60),
-                        font1_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-
-                    // VRAM Used
-                    let _ = Text::with_text_style(
-                        self.screen_system_stats.vram_used.to_str(),
-                        Point::new(230 + 4, 142 + 60),
-                        font2_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-
-                    let _ = Text::with_text_style(
-                        "VRAM:",
-                        Point::new(230 + 45, 167 + 10),
-                        font1_style.clone(),
-                        CENTER_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-
-                    // GPU Temperature
-                    let _ = Text::with_text_style(
-                        self.screen_system_stats.gpu_temperature.to_str(),
-                        Point::new(160 + 26, 82),
-                        font2_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-                    let _ = Text::with_text_style(
-                        "°C",
-                        Point::new(160 + 108, 86),
-                        font1_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-
-                    // GPU Usage
-                    let _ = Text::with_text_style(
-                        self.screen_system_stats.gpu_usage.to_str(),
-                        Point::new(160 + 26, 42),
-                        font2_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-                    let _ = Text::with_text_style(
-                        "%",
-                        Point::new(160 + 108, 46),
-                        font1_style.clone(),
-                        LEFT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
-
-                    // GPU name
-                    let _ = Text::with_text_style(
-                        self.screen_system_stats.gpu_name.to_str(),
-                        Point::new(320 - 2 - 2, 10),
-                        font1_style.clone(),
-                        RIGHT_TEXT_STYLE,
-                    )
-                    .draw(&mut self.fb);
+                let graph_w = SCR_W as u32 / graph_count.max(1) as u32;
+                for (i, graph) in graphs.iter().flatten().enumerate() {
+                    let area = Rectangle::new(
+                        Point::new((i as u32 * graph_w) as i32 + 4, cells_h as i32),
+                        Size::new(graph_w - 8, STATS_GRAPH_H - 4),
+                    );
+                    self.draw_graph(
+                        *graph,
+                        graph_style,
+                        graph_history as usize,
+                        area,
+                        background_color,
+                    );
                 }
 
                 if show_profile_name {
-                    let _ = Text::with_text_style(
-                        self.screen_profile_name.to_str(),
+                    self.draw_static_text(Text::with_text_style(
+                        profile_name.to_str(),
                         Point::new(160 - 1, 116),
                         font1_style.clone(),
                         TextStyleBuilder::new()
                             .alignment(Alignment::Center)
                             .baseline(Baseline::Middle)
                             .build(),
-                    )
-                    .draw(&mut self.fb);
+                    ));
+                }
+            }
+            ScreenProfile::CustomLayout { widgets, .. } => {
+                for w in widgets {
+                    match w {
+                        ScreenWidget::Label {
+                            x,
+                            y,
+                            font,
+                            align,
+                            color,
+                            text,
+                        } => {
+                            let bbox = render::draw_text(
+                                &mut self.fb,
+                                text.to_str(),
+                                Point::new(x as i32, y as i32),
+                                widget_font(font),
+                                widget_text_style(align),
+                                RawU16::new(color).into(),
+                                background_color,
+                            );
+                            self.add_static(bbox);
+                        }
+                        ScreenWidget::Stat {
+                            x,
+                            y,
+                            font,
+                            align,
+                            color,
+                            stat,
+                            show_unit,
+                        } => {
+                            self.draw_stat(
+                                stat,
+                                Point::new(x as i32, y as i32),
+                                font,
+                                align,
+                                RawU16::new(color).into(),
+                                background_color,
+                                show_unit,
+                            );
+                        }
+                        ScreenWidget::ProgressBar {
+                            x,
+                            y,
+                            w,
+                            h,
+                            color,
+                            background_color,
+                            stat,
+                        } => {
+                            let fraction = self
+                                .screen_system_stats
+                                .get(stat)
+                                .map(|s| s.fraction())
+                                .unwrap_or(0.0);
+                            let area = Rectangle::new(
+                                Point::new(x as i32, y as i32),
+                                Size::new(w as u32, h as u32),
+                            );
+                            render::draw_progress_bar(
+                                &mut self.fb,
+                                area,
+                                RawU16::new(color).into(),
+                                RawU16::new(background_color).into(),
+                                fraction,
+                            );
+                            self.add_static(area);
+                        }
+                        ScreenWidget::ProfileName {
+                            x,
+                            y,
+                            font,
+                            align,
+                            color,
+                        } => {
+                            let bbox = render::draw_text(
+                                &mut self.fb,
+                                profile_name.to_str(),
+                                Point::new(x as i32, y as i32),
+                                widget_font(font),
+                                widget_text_style(align),
+                                RawU16::new(color).into(),
+                                background_color,
+                            );
+                            self.add_static(bbox);
+                        }
+                        // Drawn every frame in draw_post_tick
+                        ScreenWidget::Empty
+                        | ScreenWidget::IconGrid { .. }
+                        | ScreenWidget::Image { .. }
+                        | ScreenWidget::Clock { .. } => {}
+                    }
                 }
             }
         }
@@ -748,6 +1307,9 @@ impl ScreenMod {
         {
             let mut screen_profile = SCREEN_PROFILE.lock().await;
             if screen_profile.0 {
+                if self.screen_profile != screen_profile.1 {
+                    self.clear_layout();
+                }
                 self.screen_profile = screen_profile.1.clone();
                 screen_profile.0 = false;
                 changed = true;
@@ -765,6 +1327,7 @@ impl ScreenMod {
             let mut screen_system_stats = SCREEN_SYSTEM_STATS.lock().await;
             if screen_system_stats.0 {
                 self.screen_system_stats = screen_system_stats.1.clone();
+                self.stat_history.push(&self.screen_system_stats);
                 screen_system_stats.0 = false;
                 changed = true;
             }
@@ -772,7 +1335,7 @@ impl ScreenMod {
         changed
     }
 
-    fn update_brightness(&mut self) {
+    fn update_brightness(&mut self) -> u8 {
         let diff = self.brightness_target - self.brightness;
         if diff.abs() < 1.0 {
             self.brightness = self.brightness_target;
@@ -780,17 +1343,274 @@ impl ScreenMod {
             self.brightness += diff / 5000.0;
         }
 
-        self.scr.set_backlight(self.brightness as u8);
+        self.brightness as u8
     }
 
     fn set_brightness_target(&mut self, new_brightness: u8) {
         self.brightness_target = new_brightness as f32;
     }
 
-    async fn task(mut self) -> ! {
+    async fn draw_saver(&mut self, saver: ScreenSaver) {
+        if let Some(bbox) = self.saver_bbox.take() {
+            let _ = bbox
+                .into_styled(PrimitiveStyle::with_fill(Bgr565::BLACK))
+                .draw(&mut self.fb);
+            self.dirty.add(bbox);
+        }
+
+        let style = BitmapFontStyleBuilder::new()
+            .text_color(RawU16::new(self.screen_profile.text_color()).into())
+            .background_color(Bgr565::BLACK)
+            .font(&FONT2)
+            .build();
+
+        let mut buf = [0u8; 16];
+        let text = match saver {
+            ScreenSaver::Clock => {
+                let time = get_local_time().await;
+                let time = match &time {
+                    Some(t) => t.format(ClockFormat::Time24, &mut buf),
+                    None => "--:--",
+                };
+
+                // Jumps somewhere new every minute so no pixels stay lit for long
+                let step = (Instant::now().as_secs() / 60) as i32;
+                let pos = Point::new(20 + step * 37 % 200, 20 + step * 53 % 170);
+                Text::with_text_style(time, pos, style, SAVER_TEXT_STYLE)
+            }
+            ScreenSaver::BouncingLogo => {
+                let text = Text::with_text_style(
+                    "JukeBox",
+                    self.logo_pos + self.logo_vel,
+                    style,
+                    SAVER_TEXT_STYLE,
+                );
+                let bbox = text.bounding_box();
+                let end = bbox.top_left + bbox.size;
+                if bbox.top_left.x <= 0 || end.x >= SCR_W as i32 {
+                    self.logo_vel.x = -self.logo_vel.x;
+                }
+                if bbox.top_left.y <= 0 || end.y >= SCR_H as i32 {
+                    self.logo_vel.y = -self.logo_vel.y;
+                }
+                self.logo_pos = text.position;
+                text
+            }
+            ScreenSaver::Blank | ScreenSaver::PixelShift => return,
+        };
+
+        let _ = text.draw(&mut self.fb);
+        self.saver_bbox = Some(text.bounding_box());
+        self.dirty.add(text.bounding_box());
+    }
+
+    /// Moves on to the next queued toast once the current one is done.
+    /// Returns true when the screen needs a redraw to clear the old one.
+    async fn update_toast(&mut self) -> bool {
+        let expired = self.toast.is_some() && Instant::now() >= self.toast_end;
+        if expired {
+            self.toast = None;
+        }
+
+        if self.toast.is_none() {
+            if let Some(toast) = SCREEN_TOASTS.lock().await.dequeue() {
+                self.toast_end =
+                    unwrap!(Instant::now().checked_add(Duration::from_secs(toast.duration_secs())));
+                self.toast = Some(toast);
+                self.toast_shown = false;
+            }
+        }
+
+        // The next toast covers the same area, so it doesn't need clearing
+        let cleared = expired && self.toast.is_none();
+        if cleared {
+            self.stale.add(TOAST_AREA);
+        }
+        cleared
+    }
+
+    /// Draws the current toast on top of everything, unless nothing under it changed.
+    async fn draw_toast(&mut self) {
+        let Some(toast) = self.toast.clone() else {
+            return;
+        };
+        if self.toast_shown && !self.dirty.intersects(&TOAST_AREA) {
+            return;
+        }
+        self.toast_shown = true;
+
+        let color: Bgr565 = RawU16::new(toast.color).into();
+        let _ = TOAST_AREA
+            .into_styled(PrimitiveStyle::with_fill(TOAST_BACKGROUND))
+            .draw(&mut self.fb);
+        let _ = TOAST_AREA
+            .into_styled(PrimitiveStyle::with_stroke(color, 2))
+            .draw(&mut self.fb);
+
+        let icon_pos = TOAST_AREA.top_left + Point::new(8, 8);
+        let glyph = match toast.icon {
+            ToastIcon::None => None,
+            ToastIcon::Info => Some(("i", Bgr565::BLUE, Bgr565::WHITE)),
+            ToastIcon::Warning => Some(("!", Bgr565::YELLOW, Bgr565::BLACK)),
+            ToastIcon::Error => Some(("X", Bgr565::RED, Bgr565::WHITE)),
+            ToastIcon::Key(k) => {
+                if let Some(icon) = SCREEN_ICONS.lock().await.get(k as usize) {
+                    self.draw_icon(icon, 0, icon_pos.x as usize, icon_pos.y as usize, 1);
+                }
+                None
+            }
+        };
+        if let Some((glyph, fill, text)) = glyph {
+            let _ = Circle::new(icon_pos, 32)
+                .into_styled(PrimitiveStyle::with_fill(fill))
+                .draw(&mut self.fb);
+            let style = BitmapFontStyleBuilder::new()
+                .text_color(text)
+                .background_color(fill)
+                .font(&FONT1)
+                .build();
+            let _ = Text::with_text_style(
+                glyph,
+                icon_pos + Point::new(16, 16),
+                style,
+                CENTER_TEXT_STYLE,
+            )
+            .draw(&mut self.fb);
+        }
+
+        let text_x = match toast.icon {
+            ToastIcon::None => TOAST_AREA.top_left.x + 8,
+            _ => TOAST_AREA.top_left.x + 48,
+        };
+        let title_style = BitmapFontStyleBuilder::new()
+            .text_color(color)
+            .background_color(TOAST_BACKGROUND)
+            .font(&FONT1)
+            .build();
+        let body_style = BitmapFontStyleBuilder::new()
+            .text_color(Bgr565::WHITE)
+            .background_color(TOAST_BACKGROUND)
+            .font(&FONT1)
+            .build();
+
+        let mut y = TOAST_AREA.top_left.y + 14;
+        let _ = Text::with_text_style(
+            toast.title.to_str(),
+            Point::new(text_x, y),
+            title_style,
+            LEFT_TEXT_STYLE,
+        )
+        .draw(&mut self.fb);
+        for line in wrap_toast_body(toast.body.to_str()) {
+            y += 18;
+            let _ = Text::with_text_style(line, Point::new(text_x, y), body_style, LEFT_TEXT_STYLE)
+                .draw(&mut self.fb);
+        }
+
+        self.dirty.add(TOAST_AREA);
+    }
+
+    async fn draw_frame(&mut self, level: IdleLevel) {
+        let changed = self.update_components().await;
+
+        let saver = match level {
+            IdleLevel::Asleep => Some(self.screen_profile.screen_saver()),
+            _ => None,
+        };
+        let saver_changed = saver != self.saver;
+        self.saver = saver;
+
+        // Only the pixel shift keeps the usual screen around
+        let shift = match saver {
+            Some(ScreenSaver::PixelShift) => {
+                PIXEL_SHIFTS[(Instant::now().as_secs() / 60) as usize % PIXEL_SHIFTS.len()]
+            }
+            _ => Point::zero(),
+        };
+        if shift != self.shift {
+            self.shift = shift;
+            self.dirty.all();
+        }
+
+        match saver {
+            // The backlight's off, nothing to draw
+            Some(ScreenSaver::Blank) => return,
+            Some(ScreenSaver::Clock) | Some(ScreenSaver::BouncingLogo) => {
+                if changed || saver_changed {
+                    let _ = SCREEN_AREA
+                        .into_styled(PrimitiveStyle::with_fill(Bgr565::BLACK))
+                        .draw(&mut self.fb);
+                    self.dirty.all();
+                    self.saver_bbox = None;
+                    self.background = None;
+                }
+                self.draw_saver(saver.unwrap()).await;
+                return;
+            }
+            None | Some(ScreenSaver::PixelShift) => {
+                // Toasts wait out the other screen savers
+                let toast_cleared = self.update_toast().await;
+                if changed || saver_changed || toast_cleared {
+                    self.draw_pre_tick().await;
+                }
+            }
+        }
+
+        let keys = get_raw_inputs();
+        for i in 0..12 {
+            if keys[i] {
+                self.keys_status[i] = 4;
+            } else if self.keys_status[i] > 1 {
+                self.keys_status[i] -= 1;
+            }
+        }
+
+        self.draw_post_tick().await;
+        self.draw_toast().await;
+    }
+
+    /// Copies the dirty areas out to be pushed, sending some now if they don't all fit.
+    async fn stage_dirty(&mut self, scr: &mut St7789_8080) -> (Staged, u32) {
+        let fb = unsafe { &*core::ptr::addr_of!(FBDATA) };
+        let mut staged = Staged::new();
+        let mut pixels = 0;
+
+        for area in self.dirty.take().iter() {
+            let area = area.translate(self.shift).intersection(&SCREEN_AREA);
+            if area.is_zero_sized() {
+                continue;
+            }
+            pixels += area.size.width * area.size.height;
+
+            let w = area.size.width as usize;
+            let bottom = area.top_left.y + area.size.height as i32;
+            let mut y = area.top_left.y;
+            while y < bottom {
+                let rows = ((STAGING_LEN - staged.used) / w).min((bottom - y) as usize);
+                let band = Rectangle::new(
+                    Point::new(area.top_left.x, y),
+                    Size::new(w as u32, rows as u32),
+                );
+                if rows == 0 || !staged.stage(fb, band, self.shift) {
+                    // Out of room, send what we have and start over
+                    scr.push_staged(&staged).await;
+                    staged = Staged::new();
+                    continue;
+                }
+                y += rows as i32;
+            }
+        }
+
+        (staged, pixels)
+    }
+
+    async fn task(mut self, mut scr: St7789_8080) -> ! {
+        let mut staged = Staged::new();
+        let mut timing = FrameTiming::default();
+
         loop {
             // Update brightness
-            self.update_brightness();
+            scr.set_backlight(self.update_brightness());
 
             // Refresh Rate timer
             let now = Instant::now();
@@ -801,59 +1621,64 @@ impl ScreenMod {
                 continue;
             }
 
-            let draw_pre_tick_time = if self.update_components().await {
-                let draw_pre_tick_start = Instant::now();
-                self.draw_pre_tick().await;
-                let draw_pre_tick_end = Instant::now();
-                (draw_pre_tick_end - draw_pre_tick_start).as_micros()
-            } else {
-                0
-            };
-
-            // We only push frames on refresh rate
+            // We only draw frames on refresh rate
             if self.poll_time > now {
                 yield_now().await;
                 // TODO: sleep?
                 continue;
             }
 
-            let keys = get_raw_inputs();
-            for i in 0..12 {
-                if keys[i] {
-                    self.keys_status[i] = 4;
-                } else if self.keys_status[i] > 1 {
-                    self.keys_status[i] -= 1;
-                }
-            }
+            let idle = self.screen_profile.idle();
+            let level = idle_level(&idle);
+            SCREEN_ASLEEP.store(
+                level == IdleLevel::Asleep,
+                core::sync::atomic::Ordering::Relaxed,
+            );
+
+            // The last frame goes out to the screen while this one is drawn
+            let (push_time, render_time) = join(
+                async {
+                    let push_start = Instant::now();
+                    scr.push_staged(&staged).await;
+                    (Instant::now() - push_start).as_micros()
+                },
+                async {
+                    let render_start = Instant::now();
+                    self.draw_frame(level).await;
+                    (Instant::now() - render_start).as_micros()
+                },
+            )
+            .await;
+
+            let (next, pixels) = self.stage_dirty(&mut scr).await;
+            staged = next;
 
-            let draw_post_tick_time = {
-                let draw_post_tick_start = Instant::now();
-                self.draw_post_tick().await;
-                let draw_post_tick_end = Instant::now();
-                (draw_post_tick_end - draw_post_tick_start).as_micros()
-            };
-
-            let frame_push_time = {
-                let frame_push_start = Instant::now();
-                // in an ideal world we would double buffer and work on the next frame while transferring the current frame
-                self.scr
-                    .push_framebuffer(unsafe { &mut *core::ptr::addr_of_mut!(FBDATA) })
-                    .await;
-                self.set_brightness_target(self.screen_profile.brightness());
-                let frame_push_end = Instant::now();
-                (frame_push_end - frame_push_start).as_micros()
-            };
-
-            let total_time = draw_pre_tick_time + draw_post_tick_time + frame_push_time;
-
-            info!(
-                "(pre, post, push, total): ({}, {}, {}, {}) us",
-                draw_pre_tick_time, draw_post_tick_time, frame_push_time, total_time
+            // Screen savers show at the dimmed brightness
+            let brightness = self.screen_profile.brightness();
+            self.set_brightness_target(match self.saver {
+                Some(ScreenSaver::Blank) => 0,
+                Some(_) => idle.brightness(IdleLevel::Dimmed, brightness),
+                None => idle.brightness(level, brightness),
+            });
+
+            let total_time = (Instant::now() - now).as_micros();
+
+            debug!(
+                "(render, push, total): ({}, {}, {}) us, {} px",
+                render_time, push_time, total_time, pixels
             );
             if total_time >= POLL_TIME.as_micros() {
                 warn!("!!! FRAME OVERTIME !!! -- {} us", total_time);
+                timing.overtime_frames += 1;
             }
 
+            timing.render_us = render_time as u32;
+            timing.push_us = push_time as u32;
+            timing.frame_us = total_time as u32;
+            timing.max_frame_us = timing.max_frame_us.max(total_time as u32);
+            timing.pushed_pixels = pixels;
+            *SCREEN_FRAME_TIMING.lock().await = timing;
+
             self.poll_time = unwrap!(now.checked_add(POLL_TIME));
         }
     }
@@ -872,8 +1697,12 @@ pub async fn screen_task(
     rst: ScrRstPin,
     fb_dma: FbDma,
 ) -> ! {
-    ScreenMod::new(pio, dma, data, clk, rd, cs, dc, bl, rst, fb_dma)
-        .await
-        .task()
+    let mut scr = St7789_8080::new(pio, dma, data, clk, rd, cs, dc, bl, rst);
+    scr.init(SCR_W as u16, SCR_H as u16).await;
+
+    // black out the screen
+    scr.push_framebuffer(unsafe { &mut *core::ptr::addr_of_mut!(FBDATA) })
         .await;
+
+    ScreenMod::new(fb_dma).await.task(scr).await;
 }
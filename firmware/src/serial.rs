@@ -10,29 +10,35 @@ use embassy_futures::yield_now;
 use embassy_sync::{blocking_mutex::raw::ThreadModeRawMutex, pipe::Pipe};
 use embassy_time::{Duration, Instant};
 use jukebox_util::{
+    icon::{icon_hash, icon_rle_decode},
     input::InputEvent,
     peripheral::{
         IDENT_KEY_INPUT, IDENT_KNOB_INPUT, IDENT_PEDAL_INPUT, IDENT_UNKNOWN_INPUT, JBInputs,
     },
     protocol::{
-        Command, MAX_PACKET_SIZE, RSP_FULL_ACK, RSP_FULL_DISCONNECTED, RSP_FULL_KB_INPUT_HEADER,
-        RSP_FULL_KP_INPUT_HEADER, RSP_FULL_PP_INPUT_HEADER, RSP_FULL_UNKNOWN, RSP_LINK_DELIMITER,
-        RSP_LINK_HEADER, decode_packet_size,
+        Command, MAX_PACKET_SIZE, RSP_FULL_ACK, RSP_FULL_DISCONNECTED,
+        RSP_FULL_FRAME_TIMING_HEADER, RSP_FULL_KB_INPUT_HEADER, RSP_FULL_KP_INPUT_HEADER,
+        RSP_FULL_NO_ROOM, RSP_FULL_NOT_CACHED, RSP_FULL_PP_INPUT_HEADER, RSP_FULL_UNKNOWN,
+        RSP_LINK_DELIMITER, RSP_LINK_HEADER, decode_packet_size,
     },
     rgb::RgbProfile,
-    screen::ScreenProfile,
+    screen::{ICON_DATA_SIZE, ScreenProfile},
     smallstr::SmallStr,
     stats::SystemStats,
+    time::TimeSync,
+    toast::Toast,
 };
 use ringbuffer::{ConstGenericRingBuffer, RingBuffer};
 
 use crate::{
+    clock::set_time,
     identify::start_identify,
+    idle::set_host_state,
     keypad::get_inputs,
     rgb::{DEFAULT_RGB_PROFILE, RGB_PROFILE},
     screen::{
-        DEFAULT_SCREEN_PROFILE, SCREEN_ICONS, SCREEN_PROFILE, SCREEN_PROFILE_NAME,
-        SCREEN_SYSTEM_STATS,
+        DEFAULT_SCREEN_PROFILE, SCREEN_FRAME_TIMING, SCREEN_ICON_CACHE, SCREEN_ICON_FRAMES,
+        SCREEN_ICONS, SCREEN_PROFILE, SCREEN_PROFILE_NAME, SCREEN_SYSTEM_STATS, SCREEN_TOASTS,
     },
     uid::get_uid,
     usb::{DEFAULT_INPUT_EVENTS, INPUT_EVENTS},
@@ -47,6 +53,28 @@ pub static SERIAL_CONNECTED: AtomicBool = AtomicBool::new(false);
 
 const KEEPALIVE_TIME: Duration = Duration::from_secs(1);
 
+// Icons come in as little endian RGB565
+fn copy_icon(dst: &mut [u16; 32 * 32], src: &[u8]) {
+    let mut i = 0;
+    while i < 32 * 32 {
+        dst[i] = ((src[i * 2 + 1] as u16) << 8) | (src[i * 2] as u16);
+        i += 1;
+    }
+}
+
+// Shows a still icon on a key and remembers it for later
+async fn set_still_icon(slot: usize, src: &[u8]) {
+    if let Some(scr_icon) = SCREEN_ICONS.lock().await.get_mut(slot) {
+        copy_icon(scr_icon, src);
+        SCREEN_ICON_CACHE
+            .lock()
+            .await
+            .insert(icon_hash(src), scr_icon);
+    }
+    // A still icon replaces any animation the key had
+    SCREEN_ICON_FRAMES.lock().await.clear(slot);
+}
+
 struct SerialMod {
     buf: InternalBuf,
     connected: bool,
@@ -122,6 +150,8 @@ impl SerialMod {
         *INPUT_EVENTS.lock().await = DEFAULT_INPUT_EVENTS.lock().await.1.clone();
         *RGB_PROFILE.lock().await = DEFAULT_RGB_PROFILE.lock().await.1.clone();
         *SCREEN_PROFILE.lock().await = (true, DEFAULT_SCREEN_PROFILE.lock().await.1.clone());
+        set_host_state(0);
+        SCREEN_TOASTS.lock().await.clear();
     }
 
     async fn start_update(&mut self) -> bool {
@@ -230,21 +260,87 @@ impl SerialMod {
                         true
                     }
                     Command::SetScrIcon => {
-                        let mut icons = SCREEN_ICONS.lock().await;
-                        let slot = data[0];
-                        let new_icon = &data[1..32 * 32 * 2 + 1];
-
-                        let scr_icon = &mut icons[slot as usize];
-                        let mut i = 0;
-                        while i < 32 * 32 {
-                            scr_icon[i] =
-                                ((new_icon[i * 2 + 1] as u16) << 8) | (new_icon[i * 2] as u16);
-                            i += 1;
-                        }
+                        set_still_icon(data[0] as usize, &data[1..ICON_DATA_SIZE + 1]).await;
+                        SERIAL_TO_USB.write_all(RSP_FULL_ACK).await;
+                        true
+                    }
+                    Command::SetScrIconCached => {
+                        let slot = data[0] as usize;
+                        let hash = u32::from_le_bytes(unwrap!(data[1..5].try_into()));
+
+                        let found = match SCREEN_ICON_CACHE.lock().await.get(hash) {
+                            Some(cached) => {
+                                if let Some(scr_icon) = SCREEN_ICONS.lock().await.get_mut(slot) {
+                                    *scr_icon = *cached;
+                                }
+                                true
+                            }
+                            None => false,
+                        };
 
+                        if found {
+                            SCREEN_ICON_FRAMES.lock().await.clear(slot);
+                            SERIAL_TO_USB.write_all(RSP_FULL_ACK).await;
+                        } else {
+                            SERIAL_TO_USB.write_all(RSP_FULL_NOT_CACHED).await;
+                        }
+                        true
+                    }
+                    Command::SetScrIconRle => {
+                        let mut icon = [0u8; ICON_DATA_SIZE];
+                        if icon_rle_decode(&data[1..], &mut icon).is_some() {
+                            set_still_icon(data[0] as usize, &icon).await;
+                            SERIAL_TO_USB.write_all(RSP_FULL_ACK).await;
+                            true
+                        } else {
+                            error!("failed to decode icon {}", data[0]);
+                            unknown().await
+                        }
+                    }
+                    Command::GetFrameTiming => {
+                        let timing = *SCREEN_FRAME_TIMING.lock().await;
+                        SERIAL_TO_USB.write_all(RSP_FULL_FRAME_TIMING_HEADER).await;
+                        SERIAL_TO_USB.write_all(&timing.encode()).await;
+                        true
+                    }
+                    Command::SetHostState => {
+                        set_host_state(data[0]);
                         SERIAL_TO_USB.write_all(RSP_FULL_ACK).await;
                         true
                     }
+                    Command::ShowToast => {
+                        // A full queue drops the oldest toast
+                        SCREEN_TOASTS.lock().await.enqueue(Toast::decode(&data));
+                        SERIAL_TO_USB.write_all(RSP_FULL_ACK).await;
+                        true
+                    }
+                    Command::SetScrIconFrame => {
+                        let slot = data[0] as usize;
+                        let index = data[1] as usize;
+                        let count = data[2] as usize;
+                        let new_icon = &data[3..ICON_DATA_SIZE + 3];
+
+                        // The first frame starts a new animation, the rest follow in order.
+                        // Without room for the rest, the first frame stays up as a still icon.
+                        let mut rsp = RSP_FULL_ACK;
+                        if index == 0 {
+                            if let Some(scr_icon) = SCREEN_ICONS.lock().await.get_mut(slot) {
+                                copy_icon(scr_icon, new_icon);
+                            }
+                            let mut frames = SCREEN_ICON_FRAMES.lock().await;
+                            if !frames.reserve(slot, count.saturating_sub(1)) {
+                                warn!("no room to animate icon {}", slot);
+                                rsp = RSP_FULL_NO_ROOM;
+                            }
+                        } else if let Some(frame) =
+                            SCREEN_ICON_FRAMES.lock().await.frame_mut(slot, index)
+                        {
+                            copy_icon(frame, new_icon);
+                        }
+
+                        SERIAL_TO_USB.write_all(rsp).await;
+                        true
+                    }
                     Command::SetScrMode => {
                         *SCREEN_PROFILE.lock().await = (true, ScreenProfile::decode(&data));
                         SERIAL_TO_USB.write_all(RSP_FULL_ACK).await;
@@ -255,6 +351,11 @@ impl SerialMod {
                         SERIAL_TO_USB.write_all(RSP_FULL_ACK).await;
                         true
                     }
+                    Command::SetTime => {
+                        set_time(TimeSync::decode(&data)).await;
+                        SERIAL_TO_USB.write_all(RSP_FULL_ACK).await;
+                        true
+                    }
                     Command::SetProfileName => {
                         *SCREEN_PROFILE_NAME.lock().await = (true, SmallStr::decode(&data));
                         SERIAL_TO_USB.write_all(RSP_FULL_ACK).await;
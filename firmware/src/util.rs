@@ -15,15 +15,22 @@ use jukebox_util::{
     input::InputEvent,
     peripheral::JBInputs,
     rgb::RgbProfile,
-    screen::{ProfileName, ScreenProfile},
+    screen::{FrameTiming, ProfileName, ScreenProfile},
     stats::SystemStats,
+    time::TimeSync,
+    toast::Toast,
 };
+use ringbuffer::ConstGenericRingBuffer;
 use usbd_human_interface_device::{
     device::{keyboard::NKROBootKeyboardReport, mouse::WheelMouseReport},
     page::Keyboard,
 };
 
-use crate::{keypad::get_inputs, screen::SCREEN_ICONS, usb::INPUT_EVENTS};
+use crate::{
+    keypad::get_inputs,
+    screen::{IconCache, IconFrames, SCREEN_ICON_FRAMES, SCREEN_ICONS, TOAST_QUEUE_LEN},
+    usb::INPUT_EVENTS,
+};
 
 pub fn bootsel() {
     // TODO: make peripherals go dark before rebooting.
@@ -50,6 +57,12 @@ pub type DefaultScreenProfileMutex = Mutex<SpinlockRawMutex<7>, (bool, ScreenPro
 pub type ScreenProfileNameMutex = Mutex<SpinlockRawMutex<8>, (bool, ProfileName)>;
 pub type ScreenSystemStatsMutex = Mutex<SpinlockRawMutex<9>, (bool, SystemStats)>;
 pub type ScreenIconsMutex = Mutex<SpinlockRawMutex<10>, [[u16; 32 * 32]; 12]>;
+pub type ClockSyncMutex = Mutex<SpinlockRawMutex<11>, Option<(TimeSync, Instant)>>;
+pub type ScreenIconFramesMutex = Mutex<SpinlockRawMutex<12>, IconFrames>;
+pub type ScreenIconCacheMutex = Mutex<SpinlockRawMutex<13>, IconCache>;
+pub type ScreenFrameTimingMutex = Mutex<SpinlockRawMutex<14>, FrameTiming>;
+pub type ScreenToastsMutex =
+    Mutex<SpinlockRawMutex<15>, ConstGenericRingBuffer<Toast, TOAST_QUEUE_LEN>>;
 
 pub async fn get_keyboard_events() -> NKROBootKeyboardReport {
     let mut keys = [Keyboard::NoEventIndicated; 16 * 6];
@@ -198,4 +211,6 @@ pub async fn reset_icons() {
         }
         i += 1;
     }
+
+    SCREEN_ICON_FRAMES.lock().await.clear_all();
 }
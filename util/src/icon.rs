@@ -0,0 +1,157 @@
+// Icon hashing and compression, to keep icon uploads small
+
+// Runs of the same pixel are common in icons, so packets are either:
+// - 0x80 | (n - 2), then one pixel repeated n times (2..=129)
+// - n - 1, then n pixels as is (1..=128)
+const RLE_RUN: u8 = 0x80;
+const RLE_RUN_MAX: usize = 129;
+const RLE_LITERAL_MAX: usize = 128;
+
+/// FNV-1a, over the little endian RGB565 bytes of an icon.
+pub fn icon_hash(data: &[u8]) -> u32 {
+    let mut hash = 0x811C9DC5u32;
+    for b in data {
+        hash ^= *b as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    hash
+}
+
+/// Compresses icon data into `out`, or None if it doesn't fit (so it's better sent as is).
+pub fn icon_rle_encode(data: &[u8], out: &mut [u8]) -> Option<usize> {
+    let px = |i: usize| [data[i * 2], data[i * 2 + 1]];
+    let n = data.len() / 2;
+
+    let mut o = 0;
+    let mut push = |b: &[u8]| -> Option<()> {
+        out.get_mut(o..o + b.len())?.copy_from_slice(b);
+        o += b.len();
+        Some(())
+    };
+
+    let mut i = 0;
+    while i < n {
+        let mut run = 1;
+        while i + run < n && run < RLE_RUN_MAX && px(i + run) == px(i) {
+            run += 1;
+        }
+
+        if run >= 2 {
+            push(&[RLE_RUN | (run - 2) as u8])?;
+            push(&px(i))?;
+            i += run;
+        } else {
+            // Take pixels as is until the next run starts
+            let start = i;
+            while i < n && i - start < RLE_LITERAL_MAX && !(i + 1 < n && px(i) == px(i + 1)) {
+                i += 1;
+            }
+            push(&[(i - start - 1) as u8])?;
+            push(&data[start * 2..i * 2])?;
+        }
+    }
+
+    Some(o)
+}
+
+/// Fills `dst` from compressed data, or None if the data runs out or overflows.
+pub fn icon_rle_decode(src: &[u8], dst: &mut [u8]) -> Option<()> {
+    let mut s = 0;
+    let mut d = 0;
+    while d < dst.len() {
+        let h = *src.get(s)?;
+        s += 1;
+
+        if h & RLE_RUN != 0 {
+            let count = (h & !RLE_RUN) as usize + 2;
+            let p = src.get(s..s + 2)?;
+            s += 2;
+            for _ in 0..count {
+                dst.get_mut(d..d + 2)?.copy_from_slice(p);
+                d += 2;
+            }
+        } else {
+            let len = (h as usize + 1) * 2;
+            dst.get_mut(d..d + len)?
+                .copy_from_slice(src.get(s..s + len)?);
+            s += len;
+            d += len;
+        }
+    }
+
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(data: &[u8]) -> usize {
+        let mut packed = [0u8; 4096];
+        let len = icon_rle_encode(data, &mut packed).unwrap();
+
+        let mut unpacked = [0u8; 2048];
+        icon_rle_decode(&packed[..len], &mut unpacked[..data.len()]).unwrap();
+        assert_eq!(&unpacked[..data.len()], data);
+        len
+    }
+
+    #[test]
+    fn rle_round_trip() {
+        let mut data = [0u8; 64];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = if i < 20 { 0xAB } else { (i * 7) as u8 };
+        }
+        round_trip(&data);
+        round_trip(&[]);
+        round_trip(&[1, 2]);
+    }
+
+    #[test]
+    fn rle_splits_long_runs_and_literals() {
+        // 300 of the same pixel takes three runs: 129 + 129 + 42
+        let same = [0x5Au8; 600];
+        assert_eq!(round_trip(&same), 3 * 3);
+
+        // 200 different pixels take two literals: 128 + 72
+        let mut different = [0u8; 400];
+        for (i, p) in different.chunks_mut(2).enumerate() {
+            p.copy_from_slice(&(i as u16).to_le_bytes());
+        }
+        assert_eq!(round_trip(&different), 2 + 400);
+    }
+
+    #[test]
+    fn rle_encode_reports_overflow() {
+        let mut different = [0u8; 64];
+        for (i, b) in different.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let mut small = [0u8; 32];
+        assert_eq!(icon_rle_encode(&different, &mut small), None);
+    }
+
+    #[test]
+    fn rle_decode_rejects_truncated_data() {
+        let data = [0x11u8; 40];
+        let mut packed = [0u8; 64];
+        let len = icon_rle_encode(&data, &mut packed).unwrap();
+
+        let mut unpacked = [0u8; 40];
+        for cut in 0..len {
+            assert_eq!(icon_rle_decode(&packed[..cut], &mut unpacked), None);
+        }
+
+        // A literal claiming more pixels than are left
+        assert_eq!(icon_rle_decode(&[3, 1, 2], &mut unpacked), None);
+        // Decoding past the end of the icon
+        assert_eq!(icon_rle_decode(&[RLE_RUN | 127, 1, 2], &mut unpacked[..4]), None);
+    }
+
+    #[test]
+    fn hash_is_fnv1a() {
+        assert_eq!(icon_hash(b""), 0x811C9DC5);
+        assert_eq!(icon_hash(b"a"), 0xE40C292C);
+        assert_eq!(icon_hash(b"foobar"), 0xBF9CF968);
+    }
+}
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+// Hints from the host, so the device can wind down along with it
+pub const HOST_LOCKED: u8 = 0x01;
+pub const HOST_IDLE: u8 = 0x02;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum IdleLevel {
+    Active,
+    Dimmed,
+    Asleep,
+}
+
+// How long a peripheral waits without key presses before winding down
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct IdleTimeout {
+    pub dim_after: u8,      // Minutes, 0 is never
+    pub dim_brightness: u8, // Percent of the profile's brightness
+    pub sleep_after: u8,    // Minutes, 0 is never
+}
+impl IdleTimeout {
+    pub const fn never() -> Self {
+        Self {
+            dim_after: 0,
+            dim_brightness: 30,
+            sleep_after: 0,
+        }
+    }
+
+    /// Where a peripheral should be, `idle_secs` after the last key press.
+    /// An idle host dims right away, a locked host should pass u64::MAX.
+    pub fn level(&self, idle_secs: u64, host_idle: bool) -> IdleLevel {
+        if self.sleep_after > 0 && idle_secs >= self.sleep_after as u64 * 60 {
+            IdleLevel::Asleep
+        } else if self.dim_after > 0 && (host_idle || idle_secs >= self.dim_after as u64 * 60) {
+            IdleLevel::Dimmed
+        } else {
+            IdleLevel::Active
+        }
+    }
+
+    pub fn brightness(&self, level: IdleLevel, brightness: u8) -> u8 {
+        match level {
+            IdleLevel::Active => brightness,
+            IdleLevel::Dimmed => {
+                (brightness as u32 * self.dim_brightness.min(100) as u32 / 100) as u8
+            }
+            IdleLevel::Asleep => 0,
+        }
+    }
+}
+
+// Older configs don't have idle timeouts, so they never dim, and new profiles start out the same
+pub const fn default_idle_timeout() -> IdleTimeout {
+    IdleTimeout::never()
+}
@@ -1,10 +1,16 @@
 #![no_std]
 
 pub mod color;
+pub mod icon;
+pub mod idle;
 pub mod input;
 pub mod peripheral;
 pub mod protocol;
+#[cfg(feature = "render")]
+pub mod render;
 pub mod rgb;
 pub mod screen;
 pub mod smallstr;
 pub mod stats;
+pub mod time;
+pub mod toast;
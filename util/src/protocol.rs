@@ -11,7 +11,14 @@ const CMD_SET_RGB_MODE: u8 = b'\x45';
 const CMD_SET_SCR_MODE: u8 = b'\x46';
 const CMD_SET_SCR_ICON: u8 = b'\x47';
 const CMD_SET_PROFILE_NAME: u8 = b'\x48';
+const CMD_SET_SCR_ICON_FRAME: u8 = b'\x49';
 const CMD_SET_SYSTEM_STATS: u8 = b'\x4A';
+const CMD_SET_TIME: u8 = b'\x4B';
+const CMD_SET_SCR_ICON_CACHED: u8 = b'\x4C';
+const CMD_SET_SCR_ICON_RLE: u8 = b'\x4D';
+const CMD_GET_FRAME_TIMING: u8 = b'\x4E';
+const CMD_SET_HOST_STATE: u8 = b'\x4F';
+const CMD_SHOW_TOAST: u8 = b'\x50';
 const CMD_SET_DEFAULT_INPUT_EVENT: u8 = b'\x52';
 const CMD_SET_DEFAULT_RGB_MODE: u8 = b'\x55';
 const CMD_SET_DEFAULT_SCR_MODE: u8 = b'\x56';
@@ -23,16 +30,22 @@ const CMD_UNKNOWN: u8 = b'?';
 
 pub const RSP_LINK_HEADER: u8 = b'\x01';
 pub const RSP_LINK_DELIMITER: u8 = b'\x02';
+pub const RSP_NOT_CACHED: u8 = b'\x03';
 pub const RSP_ACK: u8 = b'\x06';
 pub const RSP_INPUT_HEADER: u8 = b'!';
+pub const RSP_FRAME_TIMING_HEADER: u8 = b'%';
 pub const RSP_UNKNOWN: u8 = b'?';
 pub const RSP_DISCONNECTED: u8 = b'\x04';
+pub const RSP_NO_ROOM: u8 = b'\x05';
 pub const RSP_FULL_KB_INPUT_HEADER: &[u8] = &[b'0', b'0', b'4', RSP_INPUT_HEADER];
 pub const RSP_FULL_KP_INPUT_HEADER: &[u8] = &[b'0', b'0', b'3', RSP_INPUT_HEADER];
 pub const RSP_FULL_PP_INPUT_HEADER: &[u8] = &[b'0', b'0', b'3', RSP_INPUT_HEADER];
 pub const RSP_FULL_ACK: &[u8] = &[b'0', b'0', b'1', RSP_ACK];
 pub const RSP_FULL_UNKNOWN: &[u8] = &[b'0', b'0', b'1', RSP_UNKNOWN];
 pub const RSP_FULL_DISCONNECTED: &[u8] = &[b'0', b'0', b'1', RSP_DISCONNECTED];
+pub const RSP_FULL_NOT_CACHED: &[u8] = &[b'0', b'0', b'1', RSP_NOT_CACHED];
+pub const RSP_FULL_NO_ROOM: &[u8] = &[b'0', b'0', b'1', RSP_NO_ROOM];
+pub const RSP_FULL_FRAME_TIMING_HEADER: &[u8] = &[b'0', b'2', b'1', RSP_FRAME_TIMING_HEADER];
 
 #[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 #[repr(u8)]
@@ -47,7 +60,14 @@ pub enum Command {
     SetScrMode = CMD_SET_SCR_MODE,
     SetScrIcon = CMD_SET_SCR_ICON,
     SetProfileName = CMD_SET_PROFILE_NAME,
+    SetScrIconFrame = CMD_SET_SCR_ICON_FRAME,
     SetSystemStats = CMD_SET_SYSTEM_STATS,
+    SetTime = CMD_SET_TIME,
+    SetScrIconCached = CMD_SET_SCR_ICON_CACHED,
+    SetScrIconRle = CMD_SET_SCR_ICON_RLE,
+    GetFrameTiming = CMD_GET_FRAME_TIMING,
+    SetHostState = CMD_SET_HOST_STATE,
+    ShowToast = CMD_SHOW_TOAST,
 
     SetDefaultInputEvent = CMD_SET_DEFAULT_INPUT_EVENT,
     SetDefaultRgbMode = CMD_SET_DEFAULT_RGB_MODE,
@@ -69,7 +89,14 @@ impl From<u8> for Command {
             CMD_SET_SCR_ICON => Self::SetScrIcon,
             CMD_SET_SCR_MODE => Self::SetScrMode,
             CMD_SET_PROFILE_NAME => Self::SetProfileName,
+            CMD_SET_SCR_ICON_FRAME => Self::SetScrIconFrame,
             CMD_SET_SYSTEM_STATS => Self::SetSystemStats,
+            CMD_SET_TIME => Self::SetTime,
+            CMD_SET_SCR_ICON_CACHED => Self::SetScrIconCached,
+            CMD_SET_SCR_ICON_RLE => Self::SetScrIconRle,
+            CMD_GET_FRAME_TIMING => Self::GetFrameTiming,
+            CMD_SET_HOST_STATE => Self::SetHostState,
+            CMD_SHOW_TOAST => Self::ShowToast,
             CMD_IDENTIFY => Self::Identify,
             CMD_UPDATE => Self::Update,
             CMD_DISCONNECT => Self::Disconnect,
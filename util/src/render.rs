@@ -0,0 +1,251 @@
+use embedded_graphics::{
+    pixelcolor::{raw::RawU16, Bgr565, Gray4},
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyle, TextStyleBuilder},
+};
+use mplusfonts::{mplus, style::BitmapFontStyleBuilder, BitmapFont};
+
+use crate::{
+    screen::{WidgetAlign, WidgetFont},
+    stats::SystemStats,
+};
+
+// Drawing shared by the keypad's screen and the desktop's preview of it, so both come out the same
+// down to the pixel. Colors are RGB565 as the host sends them, the screen takes them as they are.
+
+pub static FONT1: BitmapFont<'static, Gray4, 1> = mplus!(
+    code(100),
+    500,
+    16,
+    false,
+    1,
+    4,
+    '0'..='9',
+    'A'..='Z',
+    'a'..'z',
+    [" ", "-", ".", "%", "°", "/", ":", "!", "?", ",", "'"]
+);
+pub static FONT2: BitmapFont<'static, Gray4, 1> = mplus!(
+    code(100),
+    500,
+    32,
+    false,
+    1,
+    4,
+    '0'..='9',
+    'A'..='Z',
+    'a'..'z',
+    [" ", "-", ".", "%", "°", "/", ":"]
+);
+pub const LEFT_TEXT_STYLE: TextStyle = TextStyleBuilder::new()
+    .alignment(Alignment::Left)
+    .baseline(Baseline::Middle)
+    .build();
+pub const CENTER_TEXT_STYLE: TextStyle = TextStyleBuilder::new()
+    .alignment(Alignment::Center)
+    .baseline(Baseline::Middle)
+    .build();
+pub const RIGHT_TEXT_STYLE: TextStyle = TextStyleBuilder::new()
+    .alignment(Alignment::Right)
+    .baseline(Baseline::Middle)
+    .build();
+
+pub fn widget_font(font: WidgetFont) -> &'static BitmapFont<'static, Gray4, 1> {
+    match font {
+        WidgetFont::Small => &FONT1,
+        WidgetFont::Large => &FONT2,
+    }
+}
+
+pub fn widget_text_style(align: WidgetAlign) -> TextStyle {
+    match align {
+        WidgetAlign::Left => LEFT_TEXT_STYLE,
+        WidgetAlign::Center => CENTER_TEXT_STYLE,
+        WidgetAlign::Right => RIGHT_TEXT_STYLE,
+    }
+}
+
+fn rectangle<D: DrawTarget<Color = Bgr565>>(
+    target: &mut D,
+    color: Bgr565,
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+) {
+    let area = Rectangle::new(Point::new(x as i32, y as i32), Size::new(w as u32, h as u32));
+    let _ = target.fill_solid(&area.intersection(&target.bounding_box()), color);
+}
+
+fn rounded_rect<D: DrawTarget<Color = Bgr565>>(
+    target: &mut D,
+    color: Bgr565,
+    x: usize,
+    y: usize,
+    s: usize,
+    r: usize,
+) {
+    rectangle(target, color, x, y, 2 * r, 2 * r);
+    rectangle(target, color, x, y, 4 * r, 1 * r);
+    rectangle(target, color, x, y, 1 * r, 4 * r);
+
+    rectangle(target, color, x, y + s - 2 * r, 2 * r, 2 * r);
+    rectangle(target, color, x, y + s - 2 * r + 1 * r, 4 * r, 1 * r);
+    rectangle(target, color, x, y + s - 2 * r - 2 * r, 1 * r, 4 * r);
+
+    rectangle(target, color, x + s - 2 * r, y, 2 * r, 2 * r);
+    rectangle(target, color, x + s - 2 * r - 2 * r, y, 4 * r, 1 * r);
+    rectangle(target, color, x + s - 2 * r + 1 * r, y, 1 * r, 4 * r);
+
+    rectangle(target, color, x + s - 2 * r, y + s - 2 * r, 2 * r, 2 * r);
+    rectangle(
+        target,
+        color,
+        x + s - 2 * r - 2 * r,
+        y + s - 2 * r + 1 * r,
+        4 * r,
+        1 * r,
+    );
+    rectangle(
+        target,
+        color,
+        x + s - 2 * r + 1 * r,
+        y + s - 2 * r - 2 * r,
+        1 * r,
+        4 * r,
+    );
+}
+
+/// Draws a 32x32 icon at `s` times its size. `key` is the key's press feedback, it rounds the
+/// corners off more while the key is held.
+pub fn draw_icon<D: DrawTarget<Color = Bgr565>>(
+    target: &mut D,
+    icon: &[u16],
+    key: u8,
+    x: usize,
+    y: usize,
+    s: usize,
+    background_color: Bgr565,
+) {
+    // icon drawing
+    let mut h = 0;
+    while h < 32 {
+        let mut w = 0;
+        while w < 32 {
+            let c = RawU16::new(icon[32 * (31 - w) + (31 - h)]).into();
+            rectangle(target, c, 32 * s - h * s + x - s, w * s + y, s, s);
+            w += 1;
+        }
+        h += 1;
+    }
+
+    // rounded corners
+    if key > 0 {
+        rounded_rect(target, background_color, x, y, 32 * s, key as usize);
+    } else {
+        rounded_rect(target, background_color, x, y, 32 * s, 1);
+    }
+}
+
+/// Draws a line of text, returning the area it covers.
+pub fn draw_text<D: DrawTarget<Color = Bgr565>>(
+    target: &mut D,
+    text: &str,
+    pos: Point,
+    font: &'static BitmapFont<'static, Gray4, 1>,
+    style: TextStyle,
+    color: Bgr565,
+    background_color: Bgr565,
+) -> Rectangle {
+    let character_style = BitmapFontStyleBuilder::new()
+        .text_color(color)
+        .background_color(background_color)
+        .font(font)
+        .build();
+    let text = Text::with_text_style(text, pos, character_style, style);
+    let _ = text.draw(target);
+    text.bounding_box()
+}
+
+/// Draws a stat's value, and its unit after it, returning the area they cover.
+pub fn draw_stat<D: DrawTarget<Color = Bgr565>>(
+    target: &mut D,
+    stats: &SystemStats,
+    id: u8,
+    pos: Point,
+    font: WidgetFont,
+    align: WidgetAlign,
+    color: Bgr565,
+    background_color: Bgr565,
+    show_unit: bool,
+) -> Rectangle {
+    let mut buf = [0u8; 12];
+    let (value, unit) = match stats.get(id) {
+        Some(s) => (s.format_value(&mut buf), s.unit.to_str()),
+        None => ("N/A", ""),
+    };
+
+    // Units always trail the value in the small font, so we center just the value
+    let bbox = draw_text(
+        target,
+        value,
+        pos,
+        widget_font(font),
+        widget_text_style(align),
+        color,
+        background_color,
+    );
+    if !show_unit {
+        return bbox;
+    }
+
+    let offset = match font {
+        WidgetFont::Small => 0,
+        WidgetFont::Large => 4,
+    };
+    let unit_bbox = draw_text(
+        target,
+        unit,
+        Point::new(bbox.top_left.x + bbox.size.width as i32 + 2, pos.y + offset),
+        &FONT1,
+        LEFT_TEXT_STYLE,
+        color,
+        background_color,
+    );
+    envelope(&bbox, &unit_bbox)
+}
+
+/// Draws a bar filled from the left by `fraction`, 0 to 1.
+pub fn draw_progress_bar<D: DrawTarget<Color = Bgr565>>(
+    target: &mut D,
+    area: Rectangle,
+    color: Bgr565,
+    background_color: Bgr565,
+    fraction: f32,
+) {
+    let _ = area
+        .into_styled(PrimitiveStyle::with_fill(background_color))
+        .draw(target);
+    let fill = (area.size.width as f32 * fraction) as u32;
+    let _ = Rectangle::new(area.top_left, Size::new(fill, area.size.height))
+        .into_styled(PrimitiveStyle::with_fill(color))
+        .draw(target);
+}
+
+/// The smallest rectangle covering both. Empty ones don't count.
+pub fn envelope(a: &Rectangle, b: &Rectangle) -> Rectangle {
+    if a.is_zero_sized() {
+        return *b;
+    }
+    if b.is_zero_sized() {
+        return *a;
+    }
+
+    let a_end = a.top_left + a.size;
+    let b_end = b.top_left + b.size;
+    Rectangle::with_corners(
+        a.top_left.component_min(b.top_left),
+        a_end.component_max(b_end) - Point::new(1, 1),
+    )
+}
@@ -1,7 +1,10 @@
 use rgb::RGB8;
 use serde::{Deserialize, Serialize};
 
-use crate::color::hsv2rgb;
+use crate::{
+    color::hsv2rgb,
+    idle::{default_idle_timeout, IdleTimeout},
+};
 
 pub const RGB_PROFILE_SIZE: usize = 64;
 
@@ -24,10 +27,14 @@ pub enum RgbProfile {
     StaticSolid {
         brightness: u8,
         color: (u8, u8, u8),
+        #[serde(default = "default_idle_timeout")]
+        idle: IdleTimeout,
     },
     StaticPerKey {
         brightness: u8,
         colors: [(u8, u8, u8); RGB_STATIC_PER_KEY_COUNT],
+        #[serde(default = "default_idle_timeout")]
+        idle: IdleTimeout,
     },
     Wave {
         brightness: u8,
@@ -36,6 +43,8 @@ pub enum RgbProfile {
         speed_y: i8,
         color_count: u8,
         colors: [(u8, u8, u8); RGB_WAVE_COLOR_COUNT_MAX],
+        #[serde(default = "default_idle_timeout")]
+        idle: IdleTimeout,
     },
     Breathe {
         brightness: u8,
@@ -43,12 +52,16 @@ pub enum RgbProfile {
         trans_time: u8,
         color_count: u8,
         colors: [(u8, u8, u8); RGB_BREATHE_COLOR_COUNT_MAX],
+        #[serde(default = "default_idle_timeout")]
+        idle: IdleTimeout,
     },
     RainbowSolid {
         brightness: u8,
         speed: i8,
         saturation: u8,
         value: u8,
+        #[serde(default = "default_idle_timeout")]
+        idle: IdleTimeout,
     },
     RainbowWave {
         brightness: u8,
@@ -57,19 +70,47 @@ pub enum RgbProfile {
         speed_y: i8,
         saturation: u8,
         value: u8,
+        #[serde(default = "default_idle_timeout")]
+        idle: IdleTimeout,
     },
 }
 impl RgbProfile {
+    pub fn idle(&self) -> IdleTimeout {
+        match self {
+            Self::Off => IdleTimeout::never(),
+            Self::StaticSolid { idle, .. }
+            | Self::StaticPerKey { idle, .. }
+            | Self::Wave { idle, .. }
+            | Self::Breathe { idle, .. }
+            | Self::RainbowSolid { idle, .. }
+            | Self::RainbowWave { idle, .. } => *idle,
+        }
+    }
+
+    pub fn idle_mut(&mut self) -> Option<&mut IdleTimeout> {
+        match self {
+            Self::Off => None,
+            Self::StaticSolid { idle, .. }
+            | Self::StaticPerKey { idle, .. }
+            | Self::Wave { idle, .. }
+            | Self::Breathe { idle, .. }
+            | Self::RainbowSolid { idle, .. }
+            | Self::RainbowWave { idle, .. } => Some(idle),
+        }
+    }
+
     pub fn get_type(&self) -> u8 {
         match self {
             Self::Off => RGB_PROFILE_OFF,
             Self::StaticSolid {
                 brightness: _,
                 color: _,
+                idle: _,
             } => RGB_PROFILE_STATIC_SOLID,
             Self::StaticPerKey {
                 brightness: _,
                 colors: _,
+                idle: _,
             } => RGB_PROFILE_STATIC_PER_KEY,
             Self::Wave {
                 brightness: _,
@@ -78,6 +119,7 @@ impl RgbProfile {
                 speed_y: _,
                 color_count: _,
                 colors: _,
+                idle: _,
             } => RGB_PROFILE_WAVE,
             Self::Breathe {
                 brightness: _,
@@ -85,12 +127,14 @@ impl RgbProfile {
                 trans_time: _,
                 color_count: _,
                 colors: _,
+                idle: _,
             } => RGB_PROFILE_BREATHE,
             Self::RainbowSolid {
                 brightness: _,
                 speed: _,
                 saturation: _,
                 value: _,
+                idle: _,
             } => RGB_PROFILE_RAINBOW_SOLID,
             Self::RainbowWave {
                 brightness: _,
@@ -99,6 +143,7 @@ impl RgbProfile {
                 speed_y: _,
                 saturation: _,
                 value: _,
+                idle: _,
             } => RGB_PROFILE_RAINBOW_WAVE,
         }
     }
@@ -109,10 +154,12 @@ impl RgbProfile {
             Self::StaticSolid {
                 brightness,
                 color: _,
+                idle: _,
             } => *brightness,
             Self::StaticPerKey {
                 brightness,
                 colors: _,
+                idle: _,
             } => *brightness,
             Self::Wave {
                 brightness,
@@ -121,6 +168,7 @@ impl RgbProfile {
                 speed_y: _,
                 color_count: _,
                 colors: _,
+                idle: _,
             } => *brightness,
             Self::Breathe {
                 brightness,
@@ -128,12 +176,14 @@ impl RgbProfile {
                 trans_time: _,
                 color_count: _,
                 colors: _,
+                idle: _,
             } => *brightness,
             Self::RainbowSolid {
                 brightness,
                 speed: _,
                 saturation: _,
                 value: _,
+                idle: _,
             } => *brightness,
             Self::RainbowWave {
                 brightness,
@@ -142,6 +192,7 @@ impl RgbProfile {
                 speed_y: _,
                 saturation: _,
                 value: _,
+                idle: _,
             } => *brightness,
         }
     }
@@ -164,6 +215,7 @@ impl RgbProfile {
             Self::StaticSolid {
                 brightness: _,
                 color,
+                idle: _,
             } => {
                 for led in buffer.iter_mut() {
                     *led = *color;
@@ -172,6 +224,7 @@ impl RgbProfile {
             Self::StaticPerKey {
                 brightness: _,
                 colors,
+                idle: _,
             } => {
                 for (i, led) in buffer.iter_mut().enumerate() {
                     *led = colors[i];
@@ -184,6 +237,7 @@ impl RgbProfile {
                 speed_y,
                 color_count,
                 colors,
+                idle: _,
             } => {
                 let color_count = *color_count as usize;
                 let n = color_count as f32;
@@ -218,6 +272,7 @@ impl RgbProfile {
                 trans_time,
                 color_count,
                 colors,
+                idle: _,
             } => {
                 let color_count = *color_count as usize;
                 let h = (*hold_time as u64) * 100_000;
@@ -252,6 +307,7 @@ impl RgbProfile {
                 speed,
                 saturation,
                 value,
+                idle: _,
             } => {
                 let t = t as f32;
                 let s = *speed as f32;
@@ -270,6 +326,7 @@ impl RgbProfile {
                 speed_y,
                 saturation,
                 value,
+                idle: _,
             } => {
                 let t = t as f32;
                 let s = *speed as f32;
@@ -319,6 +376,7 @@ impl RgbProfile {
                 (0, 0, 0),
                 (0, 0, 0),
             ],
+            idle: IdleTimeout::never(),
         }
     }
 
@@ -330,6 +388,7 @@ impl RgbProfile {
         RgbProfile::StaticSolid {
             brightness: 25,
             color: (255, 200, 100),
+            idle: IdleTimeout::never(),
         }
     }
 
@@ -350,6 +409,7 @@ impl RgbProfile {
                 (255, 200, 100),
                 (100, 155, 255),
             ],
+            idle: IdleTimeout::never(),
         }
     }
 
@@ -378,6 +438,7 @@ impl RgbProfile {
                 (0, 0, 0),
                 (0, 0, 0),
             ],
+            idle: IdleTimeout::never(),
         }
     }
 
@@ -405,6 +466,7 @@ impl RgbProfile {
                 (0, 0, 0),
                 (0, 0, 0),
             ],
+            idle: IdleTimeout::never(),
         }
     }
 
@@ -414,6 +476,7 @@ impl RgbProfile {
             speed: 30,
             saturation: 100,
             value: 100,
+            idle: IdleTimeout::never(),
         }
     }
 
@@ -425,6 +488,7 @@ impl RgbProfile {
             speed_y: 30,
             saturation: 100,
             value: 100,
+            idle: IdleTimeout::never(),
         }
     }
 }
@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize};
 
-use crate::smallstr::SmallStr;
+use crate::{
+    idle::{default_idle_timeout, IdleTimeout},
+    smallstr::SmallStr,
+    stats::STAT_HISTORY_LEN,
+    time::ClockFormat,
+};
 
 pub const PROFILE_NAME_CODE_POINT_LEN: usize = 18;
 pub const PROFILE_NAME_CHAR_LEN: usize = PROFILE_NAME_CODE_POINT_LEN * 4;
@@ -8,9 +13,322 @@ pub type ProfileName = SmallStr<{ PROFILE_NAME_CHAR_LEN + 1 }>;
 
 pub const SCREEN_PROFILE_SIZE: usize = 256;
 
+pub const ICON_SIZE: usize = 32;
+pub const ICON_DATA_SIZE: usize = ICON_SIZE * ICON_SIZE * 2;
+// Animated icons play back at a fixed rate, the desktop resamples them to match
+pub const ICON_ANIMATION_FPS: u64 = 10;
+pub const ICON_ANIMATION_FRAMES_MAX: usize = 24;
+
+pub const FRAME_TIMING_SIZE: usize = 32;
+
 pub const SCREEN_PROFILE_OFF: u8 = 0;
 pub const SCREEN_PROFILE_DISPLAY_KEYS: u8 = 1;
 pub const SCREEN_PROFILE_DISPLAY_STATS: u8 = 2;
+pub const SCREEN_PROFILE_CUSTOM_LAYOUT: u8 = 3;
+
+// A custom layout has to fit in the screen profile alongside everything else, so we keep the widget
+// count and label length small. Worst case a widget takes ~25 bytes when postcard encoded.
+pub const SCREEN_LAYOUT_MAX_WIDGETS: usize = 8;
+pub const SCREEN_WIDGET_LABEL_LEN: usize = 12;
+
+// Graphs on the stats screen, drawn from history kept on the device
+pub const SCREEN_STAT_GRAPHS_MAX: usize = 3;
+pub const SCREEN_GRAPH_HISTORY_MIN: u8 = 10;
+pub const SCREEN_GRAPH_HISTORY_MAX: u8 = STAT_HISTORY_LEN as u8;
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WidgetFont {
+    Small, // 16px
+    Large, // 32px
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WidgetAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WidgetLabel {
+    pub len: u8,
+    pub text: [u8; SCREEN_WIDGET_LABEL_LEN],
+}
+impl WidgetLabel {
+    pub const fn default() -> Self {
+        Self {
+            len: 0,
+            text: [0u8; SCREEN_WIDGET_LABEL_LEN],
+        }
+    }
+
+    /// Truncates the string to fit, without splitting a character in half.
+    pub fn new(s: &str) -> Self {
+        let mut len = 0;
+        for (i, c) in s.char_indices() {
+            if i + c.len_utf8() > SCREEN_WIDGET_LABEL_LEN {
+                break;
+            }
+            len = i + c.len_utf8();
+        }
+
+        let mut text = [0u8; SCREEN_WIDGET_LABEL_LEN];
+        text[..len].copy_from_slice(&s.as_bytes()[..len]);
+
+        Self {
+            len: len as u8,
+            text,
+        }
+    }
+
+    pub fn to_str(&self) -> &str {
+        let len = (self.len as usize).min(SCREEN_WIDGET_LABEL_LEN);
+        core::str::from_utf8(&self.text[..len]).unwrap_or("")
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ScreenWidget {
+    Empty,
+    Label {
+        x: u16,
+        y: u16,
+        font: WidgetFont,
+        align: WidgetAlign,
+        color: u16,
+        text: WidgetLabel,
+    },
+    Stat {
+        x: u16,
+        y: u16,
+        font: WidgetFont,
+        align: WidgetAlign,
+        color: u16,
+        stat: u8, // Stat ID
+        show_unit: bool,
+    },
+    IconGrid {
+        x: u16,
+        y: u16,
+        scale: u8,
+        spacing: u8,
+    },
+    Clock {
+        x: u16,
+        y: u16,
+        font: WidgetFont,
+        align: WidgetAlign,
+        color: u16,
+        format: ClockFormat,
+    },
+    ProgressBar {
+        x: u16,
+        y: u16,
+        w: u16,
+        h: u16,
+        color: u16,
+        background_color: u16,
+        stat: u8, // Stat ID
+    },
+    Image {
+        x: u16,
+        y: u16,
+        slot: u8,
+        scale: u8,
+    },
+    ProfileName {
+        x: u16,
+        y: u16,
+        font: WidgetFont,
+        align: WidgetAlign,
+        color: u16,
+    },
+}
+impl ScreenWidget {
+    pub fn position(&self) -> (u16, u16) {
+        match self {
+            Self::Empty => (0, 0),
+            Self::Label { x, y, .. } => (*x, *y),
+            Self::Stat { x, y, .. } => (*x, *y),
+            Self::IconGrid { x, y, .. } => (*x, *y),
+            Self::Clock { x, y, .. } => (*x, *y),
+            Self::ProgressBar { x, y, .. } => (*x, *y),
+            Self::Image { x, y, .. } => (*x, *y),
+            Self::ProfileName { x, y, .. } => (*x, *y),
+        }
+    }
+
+    pub fn set_position(&mut self, new_x: u16, new_y: u16) {
+        match self {
+            Self::Empty => {}
+            Self::Label { x, y, .. }
+            | Self::Stat { x, y, .. }
+            | Self::IconGrid { x, y, .. }
+            | Self::Clock { x, y, .. }
+            | Self::ProgressBar { x, y, .. }
+            | Self::Image { x, y, .. }
+            | Self::ProfileName { x, y, .. } => {
+                *x = new_x;
+                *y = new_y;
+            }
+        }
+    }
+
+    pub const fn default_label() -> Self {
+        Self::Label {
+            x: 160,
+            y: 120,
+            font: WidgetFont::Small,
+            align: WidgetAlign::Center,
+            color: 0xFFFF,
+            text: WidgetLabel::default(),
+        }
+    }
+
+    pub const fn default_stat() -> Self {
+        Self::Stat {
+            x: 160,
+            y: 120,
+            font: WidgetFont::Large,
+            align: WidgetAlign::Center,
+            color: 0xFFFF,
+            stat: 0,
+            show_unit: true,
+        }
+    }
+
+    pub const fn default_icon_grid() -> Self {
+        Self::IconGrid {
+            x: 23,
+            y: 4,
+            scale: 2,
+            spacing: 6,
+        }
+    }
+
+    pub const fn default_clock() -> Self {
+        Self::Clock {
+            x: 160,
+            y: 100,
+            font: WidgetFont::Large,
+            align: WidgetAlign::Center,
+            color: 0xFFFF,
+            format: ClockFormat::Time24,
+        }
+    }
+
+    pub const fn default_date() -> Self {
+        Self::Clock {
+            x: 160,
+            y: 140,
+            font: WidgetFont::Small,
+            align: WidgetAlign::Center,
+            color: 0xFFFF,
+            format: ClockFormat::DateLong,
+        }
+    }
+
+    pub const fn default_progress_bar() -> Self {
+        Self::ProgressBar {
+            x: 60,
+            y: 110,
+            w: 200,
+            h: 20,
+            color: 0x07E0,
+            background_color: 0x0000,
+            stat: 0,
+        }
+    }
+
+    pub const fn default_image() -> Self {
+        Self::Image {
+            x: 144,
+            y: 104,
+            slot: 0,
+            scale: 1,
+        }
+    }
+
+    pub const fn default_profile_name() -> Self {
+        Self::ProfileName {
+            x: 160 - 1,
+            y: 224,
+            font: WidgetFont::Small,
+            align: WidgetAlign::Center,
+            color: 0xFFFF,
+        }
+    }
+}
+
+pub type ScreenWidgets = [ScreenWidget; SCREEN_LAYOUT_MAX_WIDGETS];
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum GraphStyle {
+    Sparkline,
+    Bars,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct StatGraph {
+    pub stat: u8, // Stat ID
+    pub color: u16,
+}
+
+pub type StatGraphs = [Option<StatGraph>; SCREEN_STAT_GRAPHS_MAX];
+
+const fn default_graph_style() -> GraphStyle {
+    GraphStyle::Sparkline
+}
+
+const fn default_graph_history() -> u8 {
+    60
+}
+
+// CPU usage, memory used and GPU usage, with the desktop's default stat list
+const fn default_stat_graphs() -> StatGraphs {
+    [
+        Some(StatGraph {
+            stat: 0,
+            color: 0x07E0,
+        }),
+        Some(StatGraph {
+            stat: 2,
+            color: 0xFD20,
+        }),
+        Some(StatGraph {
+            stat: 3,
+            color: 0x5D1F,
+        }),
+    ]
+}
+
+// What the screen shows once it's been idle long enough to sleep
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ScreenSaver {
+    Blank,
+    Clock,
+    BouncingLogo,
+    PixelShift, // The usual screen, nudged around every minute
+}
+impl ScreenSaver {
+    pub const ALL: [ScreenSaver; 4] = [
+        Self::Blank,
+        Self::Clock,
+        Self::BouncingLogo,
+        Self::PixelShift,
+    ];
+}
+
+const fn default_screen_saver() -> ScreenSaver {
+    ScreenSaver::Blank
+}
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -21,12 +339,36 @@ pub enum ScreenProfile {
         background_color: u16,
         text_color: u16,
         show_profile_name: bool,
+        #[serde(default = "default_idle_timeout")]
+        idle: IdleTimeout,
+        #[serde(default = "default_screen_saver")]
+        screen_saver: ScreenSaver,
     },
     DisplayStats {
         brightness: u8,
         background_color: u16,
         text_color: u16,
         show_profile_name: bool,
+        // Older configs don't have graphs, so they get the defaults
+        #[serde(default = "default_graph_style")]
+        graph_style: GraphStyle,
+        #[serde(default = "default_graph_history")]
+        graph_history: u8, // Seconds
+        #[serde(default = "default_stat_graphs")]
+        graphs: StatGraphs,
+        #[serde(default = "default_idle_timeout")]
+        idle: IdleTimeout,
+        #[serde(default = "default_screen_saver")]
+        screen_saver: ScreenSaver,
+    },
+    CustomLayout {
+        brightness: u8,
+        background_color: u16,
+        widgets: ScreenWidgets,
+        #[serde(default = "default_idle_timeout")]
+        idle: IdleTimeout,
+        #[serde(default = "default_screen_saver")]
+        screen_saver: ScreenSaver,
     },
 }
 impl ScreenProfile {
@@ -38,13 +380,27 @@ impl ScreenProfile {
                 background_color: _,
                 text_color: _,
                 show_profile_name: _,
+                idle: _,
+                screen_saver: _,
             } => SCREEN_PROFILE_DISPLAY_KEYS,
             Self::DisplayStats {
                 brightness: _,
                 background_color: _,
                 text_color: _,
                 show_profile_name: _,
+                graph_style: _,
+                graph_history: _,
+                graphs: _,
+                idle: _,
+                screen_saver: _,
             } => SCREEN_PROFILE_DISPLAY_STATS,
+            Self::CustomLayout {
+                brightness: _,
+                background_color: _,
+                widgets: _,
+                idle: _,
+                screen_saver: _,
+            } => SCREEN_PROFILE_CUSTOM_LAYOUT,
         }
     }
 
@@ -56,12 +412,26 @@ impl ScreenProfile {
                 background_color: _,
                 text_color: _,
                 show_profile_name: _,
+                idle: _,
+                screen_saver: _,
             } => *brightness,
             Self::DisplayStats {
                 brightness,
                 background_color: _,
                 text_color: _,
                 show_profile_name: _,
+                graph_style: _,
+                graph_history: _,
+                graphs: _,
+                idle: _,
+                screen_saver: _,
+            } => *brightness,
+            Self::CustomLayout {
+                brightness,
+                background_color: _,
+                widgets: _,
+                idle: _,
+                screen_saver: _,
             } => *brightness,
         }
     }
@@ -74,12 +444,26 @@ impl ScreenProfile {
                 background_color,
                 text_color: _,
                 show_profile_name: _,
+                idle: _,
+                screen_saver: _,
             } => *background_color,
             Self::DisplayStats {
                 brightness: _,
                 background_color,
                 text_color: _,
                 show_profile_name: _,
+                graph_style: _,
+                graph_history: _,
+                graphs: _,
+                idle: _,
+                screen_saver: _,
+            } => *background_color,
+            Self::CustomLayout {
+                brightness: _,
+                background_color,
+                widgets: _,
+                idle: _,
+                screen_saver: _,
             } => *background_color,
         }
     }
@@ -92,13 +476,22 @@ impl ScreenProfile {
                 background_color: _,
                 text_color,
                 show_profile_name: _,
+                idle: _,
+                screen_saver: _,
             } => *text_color,
             Self::DisplayStats {
                 brightness: _,
                 background_color: _,
                 text_color,
                 show_profile_name: _,
+                graph_style: _,
+                graph_history: _,
+                graphs: _,
+                idle: _,
+                screen_saver: _,
             } => *text_color,
+            // Each widget carries its own color
+            Self::CustomLayout { .. } => 0xFFFF,
         }
     }
 
@@ -111,6 +504,57 @@ impl ScreenProfile {
             ScreenProfile::DisplayStats {
                 show_profile_name, ..
             } => *show_profile_name,
+            ScreenProfile::CustomLayout { .. } => false,
+        }
+    }
+
+    pub fn idle(&self) -> IdleTimeout {
+        match self {
+            Self::Off => IdleTimeout::never(),
+            Self::DisplayKeys { idle, .. }
+            | Self::DisplayStats { idle, .. }
+            | Self::CustomLayout { idle, .. } => *idle,
+        }
+    }
+
+    pub fn idle_mut(&mut self) -> Option<&mut IdleTimeout> {
+        match self {
+            Self::Off => None,
+            Self::DisplayKeys { idle, .. }
+            | Self::DisplayStats { idle, .. }
+            | Self::CustomLayout { idle, .. } => Some(idle),
+        }
+    }
+
+    pub fn screen_saver(&self) -> ScreenSaver {
+        match self {
+            Self::Off => ScreenSaver::Blank,
+            Self::DisplayKeys { screen_saver, .. }
+            | Self::DisplayStats { screen_saver, .. }
+            | Self::CustomLayout { screen_saver, .. } => *screen_saver,
+        }
+    }
+
+    pub fn screen_saver_mut(&mut self) -> Option<&mut ScreenSaver> {
+        match self {
+            Self::Off => None,
+            Self::DisplayKeys { screen_saver, .. }
+            | Self::DisplayStats { screen_saver, .. }
+            | Self::CustomLayout { screen_saver, .. } => Some(screen_saver),
+        }
+    }
+
+    pub fn graphs(&self) -> &[Option<StatGraph>] {
+        match self {
+            ScreenProfile::DisplayStats { graphs, .. } => graphs,
+            _ => &[],
+        }
+    }
+
+    pub fn widgets(&self) -> &[ScreenWidget] {
+        match self {
+            ScreenProfile::CustomLayout { widgets, .. } => widgets,
+            _ => &[],
         }
     }
 
@@ -134,6 +578,8 @@ impl ScreenProfile {
             background_color: 0x01B3,
             text_color: 0xFFFF,
             show_profile_name: true,
+            idle: IdleTimeout::never(),
+            screen_saver: ScreenSaver::Clock,
         }
     }
 
@@ -143,6 +589,59 @@ impl ScreenProfile {
             background_color: 0x01B3,
             text_color: 0xFFFF,
             show_profile_name: true,
+            graph_style: default_graph_style(),
+            graph_history: default_graph_history(),
+            graphs: default_stat_graphs(),
+            idle: IdleTimeout::never(),
+            screen_saver: ScreenSaver::Clock,
         }
     }
+
+    pub const fn default_custom_layout() -> Self {
+        let mut widgets = [ScreenWidget::Empty; SCREEN_LAYOUT_MAX_WIDGETS];
+        widgets[0] = ScreenWidget::default_icon_grid();
+        widgets[1] = ScreenWidget::default_profile_name();
+
+        Self::CustomLayout {
+            brightness: 100,
+            background_color: 0x01B3,
+            widgets,
+            idle: IdleTimeout::never(),
+            screen_saver: ScreenSaver::Clock,
+        }
+    }
+}
+
+// Screen render timings, for debugging. All times are in microseconds.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FrameTiming {
+    pub render_us: u32, // Drawing into the framebuffer
+    pub push_us: u32,   // Sending the last frame, overlaps with render
+    pub frame_us: u32,
+    pub max_frame_us: u32,
+    pub overtime_frames: u32,
+    pub pushed_pixels: u32,
+}
+impl FrameTiming {
+    pub const fn default() -> Self {
+        Self {
+            render_us: 0,
+            push_us: 0,
+            frame_us: 0,
+            max_frame_us: 0,
+            overtime_frames: 0,
+            pushed_pixels: 0,
+        }
+    }
+
+    pub fn encode(self) -> [u8; FRAME_TIMING_SIZE] {
+        let mut data = [0u8; FRAME_TIMING_SIZE];
+        let _ = postcard::to_slice(&self, &mut data).unwrap();
+        data
+    }
+
+    pub fn decode(data: &[u8]) -> Self {
+        postcard::from_bytes(data).unwrap()
+    }
 }
@@ -1,4 +1,6 @@
-use core::ptr::addr_of;
+use core::{fmt, ptr::addr_of};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -59,3 +61,51 @@ impl<const N: usize> SmallStr<N> {
         N
     }
 }
+
+// Serialized as a plain string, so it can be used in postcard encoded structures.
+impl<const N: usize> Serialize for SmallStr<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.to_str())
+    }
+}
+
+struct SmallStrVisitor<const N: usize>;
+impl<const N: usize> de::Visitor<'_> for SmallStrVisitor<N> {
+    type Value = SmallStr<N>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a string of at most {} bytes", N)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        if v.len() > N {
+            return Err(E::invalid_length(v.len(), &self));
+        }
+
+        Ok(SmallStr::from_str(v))
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for SmallStr<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(SmallStrVisitor::<N>)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_accepts_up_to_capacity() {
+        let mut buf = [0u8; 16];
+
+        let full = postcard::to_slice("abcdefgh", &mut buf).unwrap();
+        let s: SmallStr<8> = postcard::from_bytes(full).unwrap();
+        assert_eq!(s.to_str(), "abcdefgh");
+        assert_eq!(s, SmallStr::<8>::from_str("abcdefgh"));
+
+        let over = postcard::to_slice("abcdefghi", &mut buf).unwrap();
+        assert!(postcard::from_bytes::<SmallStr<8>>(over).is_err());
+    }
+}
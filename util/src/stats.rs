@@ -1,92 +1,227 @@
+use core::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
 use crate::smallstr::SmallStr;
 
 // We assume each string here only uses ASCII characters and thus each character can fit in a single byte.
+// Exception being the degree symbol in "°C", which is why the unit has room for 4 bytes.
+
+pub const SYSTEM_STATS_MAX: usize = 24;
+pub const SYSTEM_STATS_SIZE: usize = 1024;
+// The host sends stats about once a second, so this is roughly two minutes of history
+pub const STAT_HISTORY_LEN: usize = 120;
+
+pub type StatLabel = SmallStr<{ 20 + 1 }>;
+pub type StatUnit = SmallStr<{ 4 + 1 }>;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct SystemStats {
-    pub cpu_name: SmallStr<{ 20 + 1 }>,
-    pub cpu_usage: SmallStr<{ 5 + 1 }>,
-    pub cpu_temperature: SmallStr<{ 5 + 1 }>,
+pub struct Stat {
+    // Chosen by the host, stays the same even when other stats are missing from the list
+    pub id: u8,
+    pub label: StatLabel,
+    pub value: f32,
+    pub unit: StatUnit,
+    pub min: f32,
+    pub max: f32,
+}
+impl Stat {
+    pub const fn default() -> Self {
+        Self {
+            id: 0,
+            label: SmallStr::default(),
+            value: 0.0,
+            unit: SmallStr::default(),
+            min: 0.0,
+            max: 0.0,
+        }
+    }
+
+    /// How "full" this stat is, from 0.0 to 1.0. Used by progress bars and graphs.
+    pub fn fraction(&self) -> f32 {
+        let range = self.max - self.min;
+        if range > 0.0 {
+            ((self.value - self.min) / range).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    pub fn format_value<'a>(&self, buf: &'a mut [u8; 12]) -> &'a str {
+        let mut w = BufWriter { buf, len: 0 };
 
-    pub memory_used: SmallStr<{ 5 + 1 }>,
-    pub memory_total: SmallStr<{ 5 + 1 }>,
-    pub memory_unit: SmallStr<{ 2 + 1 }>,
+        // Big numbers (like fan RPM) don't need the decimal
+        let _ = if self.value.abs() >= 1000.0 {
+            write!(w, "{:.0}", self.value)
+        } else {
+            write!(w, "{:.1}", self.value)
+        };
 
-    pub gpu_name: SmallStr<{ 20 + 1 }>,
-    pub gpu_usage: SmallStr<{ 5 + 1 }>,
-    pub gpu_temperature: SmallStr<{ 5 + 1 }>,
+        let len = w.len;
+        core::str::from_utf8(&buf[..len]).unwrap_or("")
+    }
+}
 
-    pub vram_used: SmallStr<{ 5 + 1 }>,
-    pub vram_total: SmallStr<{ 5 + 1 }>,
-    pub vram_unit: SmallStr<{ 2 + 1 }>,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SystemStats {
+    pub count: u8,
+    pub stats: [Stat; SYSTEM_STATS_MAX],
 }
 impl SystemStats {
     pub const fn default() -> Self {
+        const EMPTY: Stat = Stat::default();
         Self {
-            cpu_name: SmallStr::default(),
-            cpu_usage: SmallStr::default(),
-            cpu_temperature: SmallStr::default(),
+            count: 0,
+            stats: [EMPTY; SYSTEM_STATS_MAX],
+        }
+    }
 
-            memory_used: SmallStr::default(),
-            memory_total: SmallStr::default(),
-            memory_unit: SmallStr::default(),
+    pub fn push(&mut self, stat: Stat) -> bool {
+        if (self.count as usize) >= SYSTEM_STATS_MAX {
+            return false;
+        }
 
-            gpu_name: SmallStr::default(),
-            gpu_usage: SmallStr::default(),
-            gpu_temperature: SmallStr::default(),
+        self.stats[self.count as usize] = stat;
+        self.count += 1;
+        true
+    }
 
-            vram_used: SmallStr::default(),
-            vram_total: SmallStr::default(),
-            vram_unit: SmallStr::default(),
-        }
+    pub fn as_slice(&self) -> &[Stat] {
+        &self.stats[..(self.count as usize).min(SYSTEM_STATS_MAX)]
     }
 
-    pub fn encode(self) -> [u8; 100] {
-        let mut data = [0u8; 100];
-
-        let d = [
-            &self.cpu_name.encode()[..],
-            &self.cpu_usage.encode()[..],
-            &self.cpu_temperature.encode()[..],
-            &self.memory_used.encode()[..],
-            &self.memory_total.encode()[..],
-            &self.memory_unit.encode()[..],
-            &self.gpu_name.encode()[..],
-            &self.gpu_usage.encode()[..],
-            &self.gpu_temperature.encode()[..],
-            &self.vram_used.encode()[..],
-            &self.vram_total.encode()[..],
-            &self.vram_unit.encode()[..],
-        ];
-
-        let mut size = 0usize;
-        for d in d {
-            let len = d.len();
-            data[size..size + len].copy_from_slice(d);
-            size += len;
-        }
+    pub fn get(&self, id: u8) -> Option<&Stat> {
+        self.as_slice().iter().find(|s| s.id == id)
+    }
 
+    pub fn encode(self) -> [u8; SYSTEM_STATS_SIZE] {
+        let mut data = [0u8; SYSTEM_STATS_SIZE];
+        let _ = postcard::to_slice(&self, &mut data).unwrap();
         data
     }
 
     pub fn decode(data: &[u8]) -> Self {
-        let mut s = Self::default();
-
-        // TODO: this sucks
-        s.cpu_name = SmallStr::decode(&data[..21]);
-        s.cpu_usage = SmallStr::decode(&data[21..27]);
-        s.cpu_temperature = SmallStr::decode(&data[27..33]);
-        s.memory_used = SmallStr::decode(&data[33..39]);
-        s.memory_total = SmallStr::decode(&data[39..45]);
-        s.memory_unit = SmallStr::decode(&data[45..48]);
-        s.gpu_name = SmallStr::decode(&data[48..69]);
-        s.gpu_usage = SmallStr::decode(&data[69..75]);
-        s.gpu_temperature = SmallStr::decode(&data[75..81]);
-        s.vram_used = SmallStr::decode(&data[81..87]);
-        s.vram_total = SmallStr::decode(&data[87..93]);
-        s.vram_unit = SmallStr::decode(&data[93..]);
-
-        s
+        postcard::from_bytes(data).unwrap()
+    }
+}
+
+/// Recent samples of every stat, indexed by stat ID. Samples are kept as fractions of the stat's
+/// range (see `Stat::fraction`) squeezed into a byte, so the whole thing stays a few KB.
+pub struct StatHistory {
+    samples: [[u8; STAT_HISTORY_LEN]; SYSTEM_STATS_MAX],
+    head: usize,  // where the next sample goes
+    count: usize, // how many samples have been pushed, up to STAT_HISTORY_LEN
+}
+impl StatHistory {
+    pub const fn default() -> Self {
+        Self {
+            samples: [[0u8; STAT_HISTORY_LEN]; SYSTEM_STATS_MAX],
+            head: 0,
+            count: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.count = 0;
+    }
+
+    /// Records one sample for every stat. Stats missing from the list are recorded as empty.
+    pub fn push(&mut self, stats: &SystemStats) {
+        for row in self.samples.iter_mut() {
+            row[self.head] = 0;
+        }
+        for stat in stats.as_slice() {
+            if let Some(row) = self.samples.get_mut(stat.id as usize) {
+                row[self.head] = (stat.fraction() * 255.0) as u8;
+            }
+        }
+
+        self.head = (self.head + 1) % STAT_HISTORY_LEN;
+        self.count = (self.count + 1).min(STAT_HISTORY_LEN);
+    }
+
+    pub fn sample_count(&self) -> usize {
+        self.count
+    }
+
+    /// The last `n` samples of a stat from 0.0 to 1.0, oldest first.
+    pub fn samples(&self, id: u8, n: usize) -> impl Iterator<Item = f32> + '_ {
+        let n = n.min(self.count);
+        let row = self.samples.get(id as usize);
+        let start = self.head + STAT_HISTORY_LEN - n;
+        (0..n).filter_map(move |i| row.map(|r| r[(start + i) % STAT_HISTORY_LEN] as f32 / 255.0))
+    }
+}
+
+struct BufWriter<'a> {
+    buf: &'a mut [u8; 12],
+    len: usize,
+}
+impl Write for BufWriter<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(core::fmt::Error);
+        }
+
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats_with(id: u8, value: f32) -> SystemStats {
+        let mut stats = SystemStats::default();
+        stats.push(Stat {
+            id,
+            value,
+            max: 200.0,
+            ..Stat::default()
+        });
+        stats
+    }
+
+    fn stored(value: f32) -> f32 {
+        ((value / 200.0) * 255.0) as u8 as f32 / 255.0
+    }
+
+    #[test]
+    fn history_wraps_around() {
+        let mut history = StatHistory::default();
+        for i in 0..STAT_HISTORY_LEN + 10 {
+            history.push(&stats_with(3, i as f32));
+        }
+        assert_eq!(history.sample_count(), STAT_HISTORY_LEN);
+
+        // The first ten samples were overwritten, the rest come back oldest first
+        let samples: [f32; STAT_HISTORY_LEN] = core::array::from_fn(|i| stored((i + 10) as f32));
+        assert!(history.samples(3, STAT_HISTORY_LEN).eq(samples));
+        assert!(history.samples(3, 1000).eq(samples));
+        assert!(history.samples(3, 2).eq([stored(128.0), stored(129.0)]));
+    }
+
+    #[test]
+    fn history_before_filling_up() {
+        let mut history = StatHistory::default();
+        assert_eq!(history.samples(3, 10).count(), 0);
+
+        history.push(&stats_with(3, 100.0));
+        history.push(&stats_with(5, 100.0));
+        assert_eq!(history.sample_count(), 2);
+        // A stat missing from a push is recorded as empty
+        assert!(history.samples(3, 10).eq([stored(100.0), 0.0]));
+        assert!(history.samples(5, 10).eq([0.0, stored(100.0)]));
+        // Out of range IDs have no history at all
+        assert_eq!(history.samples(200, 10).count(), 0);
+
+        history.clear();
+        assert_eq!(history.sample_count(), 0);
     }
 }
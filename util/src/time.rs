@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+
+pub const TIME_SYNC_SIZE: usize = 16;
+
+// Wall time sent by the host, the device counts forward from it using its own uptime
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TimeSync {
+    pub epoch_secs: u64,
+    pub utc_offset_secs: i32,
+}
+impl TimeSync {
+    pub fn encode(self) -> [u8; TIME_SYNC_SIZE] {
+        let mut data = [0u8; TIME_SYNC_SIZE];
+        let _ = postcard::to_slice(&self, &mut data).unwrap();
+        data
+    }
+
+    pub fn decode(data: &[u8]) -> Self {
+        postcard::from_bytes(data).unwrap()
+    }
+
+    pub fn local_secs(&self) -> i64 {
+        self.epoch_secs as i64 + self.utc_offset_secs as i64
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ClockFormat {
+    Time24,        // 14:05
+    Time24Seconds, // 14:05:09
+    Time12,        // 2:05 PM
+    Time12Seconds, // 2:05:09 PM
+    DateIso,       // 2026-10-18
+    DateDmy,       // 18/10/2026
+    DateMdy,       // 10/18/2026
+    DateLong,      // Sun 18 Oct
+}
+impl ClockFormat {
+    pub const ALL: [ClockFormat; 8] = [
+        Self::Time24,
+        Self::Time24Seconds,
+        Self::Time12,
+        Self::Time12Seconds,
+        Self::DateIso,
+        Self::DateDmy,
+        Self::DateMdy,
+        Self::DateLong,
+    ];
+}
+
+const WEEKDAY_NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DateTime {
+    pub year: i32,
+    pub month: u8, // 1-12
+    pub day: u8,   // 1-31
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub weekday: u8, // 0 is Sunday
+}
+impl DateTime {
+    pub fn from_secs(secs: i64) -> Self {
+        let days = secs.div_euclid(86400);
+        let rem = secs.rem_euclid(86400);
+
+        // Howard Hinnant's civil_from_days
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Self {
+            year: year as i32,
+            month: month as u8,
+            day: day as u8,
+            hour: (rem / 3600) as u8,
+            minute: ((rem / 60) % 60) as u8,
+            second: (rem % 60) as u8,
+            weekday: (days + 4).rem_euclid(7) as u8, // 1970-01-01 was a Thursday
+        }
+    }
+
+    /// Writes the formatted date/time into the buffer, returning the used part as a string.
+    pub fn format<'a>(&self, format: ClockFormat, buf: &'a mut [u8; 16]) -> &'a str {
+        let mut w = Writer { buf, len: 0 };
+
+        match format {
+            ClockFormat::Time24 | ClockFormat::Time24Seconds => {
+                w.num(self.hour as u32, 2);
+                w.str(":");
+                w.num(self.minute as u32, 2);
+                if format == ClockFormat::Time24Seconds {
+                    w.str(":");
+                    w.num(self.second as u32, 2);
+                }
+            }
+            ClockFormat::Time12 | ClockFormat::Time12Seconds => {
+                let hour = match self.hour % 12 {
+                    0 => 12,
+                    h => h,
+                };
+                w.num(hour as u32, 1);
+                w.str(":");
+                w.num(self.minute as u32, 2);
+                if format == ClockFormat::Time12Seconds {
+                    w.str(":");
+                    w.num(self.second as u32, 2);
+                }
+                w.str(if self.hour < 12 { " AM" } else { " PM" });
+            }
+            ClockFormat::DateIso => {
+                w.num(self.year as u32, 4);
+                w.str("-");
+                w.num(self.month as u32, 2);
+                w.str("-");
+                w.num(self.day as u32, 2);
+            }
+            ClockFormat::DateDmy => {
+                w.num(self.day as u32, 2);
+                w.str("/");
+                w.num(self.month as u32, 2);
+                w.str("/");
+                w.num(self.year as u32, 4);
+            }
+            ClockFormat::DateMdy => {
+                w.num(self.month as u32, 2);
+                w.str("/");
+                w.num(self.day as u32, 2);
+                w.str("/");
+                w.num(self.year as u32, 4);
+            }
+            ClockFormat::DateLong => {
+                w.str(WEEKDAY_NAMES[self.weekday as usize % 7]);
+                w.str(" ");
+                w.num(self.day as u32, 1);
+                w.str(" ");
+                w.str(MONTH_NAMES[(self.month as usize + 11) % 12]);
+            }
+        }
+
+        let len = w.len;
+        core::str::from_utf8(&buf[..len]).unwrap_or("")
+    }
+}
+
+struct Writer<'a> {
+    buf: &'a mut [u8; 16],
+    len: usize,
+}
+impl Writer<'_> {
+    fn str(&mut self, s: &str) {
+        for b in s.bytes() {
+            if self.len < self.buf.len() {
+                self.buf[self.len] = b;
+                self.len += 1;
+            }
+        }
+    }
+
+    // Writes a number, zero padded to at least `width` digits
+    fn num(&mut self, mut n: u32, width: usize) {
+        let mut digits = [0u8; 10];
+        let mut count = 0;
+        while n > 0 || count < width.max(1) {
+            digits[count] = b'0' + (n % 10) as u8;
+            n /= 10;
+            count += 1;
+        }
+        while count > 0 {
+            count -= 1;
+            if self.len < self.buf.len() {
+                self.buf[self.len] = digits[count];
+                self.len += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(
+        year: i32,
+        month: u8,
+        day: u8,
+        hour: u8,
+        minute: u8,
+        second: u8,
+        weekday: u8,
+    ) -> DateTime {
+        DateTime {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            weekday,
+        }
+    }
+
+    #[test]
+    fn from_secs_at_epoch() {
+        assert_eq!(DateTime::from_secs(0), date(1970, 1, 1, 0, 0, 0, 4));
+        assert_eq!(DateTime::from_secs(86399), date(1970, 1, 1, 23, 59, 59, 4));
+    }
+
+    #[test]
+    fn from_secs_on_leap_days() {
+        assert_eq!(
+            DateTime::from_secs(951_782_400),
+            date(2000, 2, 29, 0, 0, 0, 2)
+        );
+        assert_eq!(
+            DateTime::from_secs(1_709_208_000),
+            date(2024, 2, 29, 12, 0, 0, 4)
+        );
+        assert_eq!(
+            DateTime::from_secs(1_709_251_200),
+            date(2024, 3, 1, 0, 0, 0, 5)
+        );
+        // 2100 isn't a leap year
+        assert_eq!(
+            DateTime::from_secs(4_107_542_400),
+            date(2100, 3, 1, 0, 0, 0, 1)
+        );
+    }
+
+    #[test]
+    fn from_secs_with_negative_offset() {
+        let time = TimeSync {
+            epoch_secs: 0,
+            utc_offset_secs: -5 * 3600,
+        };
+        assert_eq!(time.local_secs(), -18000);
+        assert_eq!(
+            DateTime::from_secs(time.local_secs()),
+            date(1969, 12, 31, 19, 0, 0, 3)
+        );
+    }
+
+    #[test]
+    fn format_twelve_hour_clock() {
+        let mut buf = [0u8; 16];
+        let midnight = DateTime::from_secs(0);
+        assert_eq!(midnight.format(ClockFormat::Time12, &mut buf), "12:00 AM");
+        let evening = DateTime::from_secs(-18000);
+        assert_eq!(
+            evening.format(ClockFormat::DateLong, &mut buf),
+            "Wed 31 Dec"
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+
+use crate::smallstr::SmallStr;
+
+// Same as the stats, we assume mostly ASCII here. Longer text gets cut off by the host.
+
+pub const TOAST_SIZE: usize = 160;
+pub const TOAST_TITLE_LEN: usize = 24;
+pub const TOAST_BODY_LEN: usize = 96;
+// How long a toast stays up when the host doesn't say
+pub const TOAST_DEFAULT_DURATION: u8 = 4;
+
+pub type ToastTitle = SmallStr<{ TOAST_TITLE_LEN + 1 }>;
+pub type ToastBody = SmallStr<{ TOAST_BODY_LEN + 1 }>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ToastIcon {
+    None,
+    Info,
+    Warning,
+    Error,
+    Key(u8), // Reuses the icon currently on one of the keys
+}
+
+// A short message shown on top of the screen for a few seconds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Toast {
+    pub title: ToastTitle,
+    pub body: ToastBody,
+    pub icon: ToastIcon,
+    pub duration: u8, // Seconds, 0 is the default
+    pub color: u16,   // RGB565, used for the border and title
+}
+impl Toast {
+    pub const fn default() -> Self {
+        Self {
+            title: SmallStr::default(),
+            body: SmallStr::default(),
+            icon: ToastIcon::None,
+            duration: 0,
+            color: 0xFFFF,
+        }
+    }
+
+    /// Builds a toast, cutting the title and body down to size if needed.
+    pub fn new(title: &str, body: &str, icon: ToastIcon, duration: u8, color: u16) -> Self {
+        Self {
+            title: SmallStr::from_str(truncate(title, TOAST_TITLE_LEN)),
+            body: SmallStr::from_str(truncate(body, TOAST_BODY_LEN)),
+            icon,
+            duration,
+            color,
+        }
+    }
+
+    pub fn duration_secs(&self) -> u64 {
+        match self.duration {
+            0 => TOAST_DEFAULT_DURATION as u64,
+            d => d as u64,
+        }
+    }
+
+    pub fn encode(self) -> [u8; TOAST_SIZE] {
+        let mut data = [0u8; TOAST_SIZE];
+        let _ = postcard::to_slice(&self, &mut data).unwrap();
+        data
+    }
+
+    pub fn decode(data: &[u8]) -> Self {
+        postcard::from_bytes(data).unwrap()
+    }
+}
+
+// Cuts a string down to at most `len` bytes without splitting a character
+fn truncate(s: &str, len: usize) -> &str {
+    if s.len() <= len {
+        return s;
+    }
+
+    let mut end = len;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}